@@ -0,0 +1,345 @@
+//! Derive macros for TimeSteward boilerplate.
+//!
+//! These are re-exported from `time_steward`; the generated code refers to items by their paths in that crate.
+
+extern crate proc_macro;
+
+use proc_macro::TokenStream;
+use proc_macro2::Span;
+use quote::quote;
+use syn::spanned::Spanned;
+use syn::{
+  parse_macro_input, parse_quote, AttributeArgs, DeriveInput, Error, GenericParam, ImplItem,
+  ItemImpl, Lit, Meta, NestedMeta,
+};
+
+/// Hashes a path string into a PersistentTypeId.
+///
+/// IDs generated this way end up in saved files, so this function must never change. It is 64-bit FNV-1a followed by the splitmix64 finalizer, which spreads the bits of similar paths apart.
+fn hash_path(path: &str) -> u64 {
+  let mut hash: u64 = 0xcbf29ce484222325;
+  for byte in path.bytes() {
+    hash ^= u64::from(byte);
+    hash = hash.wrapping_mul(0x100000001b3);
+  }
+  hash = (hash ^ (hash >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+  hash = (hash ^ (hash >> 27)).wrapping_mul(0x94d049bb133111eb);
+  hash ^ (hash >> 31)
+}
+
+fn persistent_id(input: &DeriveInput) -> Result<u64, Error> {
+  let mut result = None;
+  for attribute in &input.attrs {
+    if !attribute.path.is_ident("persistent_id") {
+      continue;
+    }
+    if result.is_some() {
+      return Err(Error::new(attribute.span(), "duplicate #[persistent_id] attribute"));
+    }
+    let usage = "expected #[persistent_id(0x...)] or #[persistent_id(path = \"...\")]";
+    let nested = match attribute.parse_meta()? {
+      Meta::List(list) => list.nested,
+      other => return Err(Error::new(other.span(), usage)),
+    };
+    if nested.len() != 1 {
+      return Err(Error::new(nested.span(), usage));
+    }
+    result = Some(match &nested[0] {
+      NestedMeta::Lit(Lit::Int(id)) => id.base10_parse::<u64>()?,
+      NestedMeta::Meta(Meta::NameValue(name_value)) if name_value.path.is_ident("path") => {
+        match &name_value.lit {
+          Lit::Str(path) => hash_path(&path.value()),
+          other => return Err(Error::new(other.span(), usage)),
+        }
+      }
+      other => return Err(Error::new(other.span(), usage)),
+    });
+  }
+  result.ok_or_else(|| {
+    Error::new(
+      Span::call_site(),
+      "deriving PersistentlyIdentifiedType requires a #[persistent_id(0x...)] or #[persistent_id(path = \"...\")] attribute",
+    )
+  })
+}
+
+/// Implements `PersistentlyIdentifiedType`.
+///
+/// The ID is either given explicitly, as in `#[persistent_id(0x08c4b60ad5d0ed08)]`, or hashed from a path string, as in `#[persistent_id(path = "bouncy_circles::RelationshipChange")]`. The path doesn't have to match the actual module path – it only needs to stay the same as long as you want to load old saves, so it's fine to keep the old string after moving the type.
+///
+/// For generic types, each type parameter must also be a PersistentlyIdentifiedType, and its ID is mixed into the result, so that different instantiations get different IDs.
+#[proc_macro_derive(PersistentlyIdentifiedType, attributes(persistent_id))]
+pub fn derive_persistently_identified_type(input: TokenStream) -> TokenStream {
+  let mut input = parse_macro_input!(input as DeriveInput);
+  let id = match persistent_id(&input) {
+    Ok(id) => id,
+    Err(error) => return error.to_compile_error().into(),
+  };
+
+  let mut parameter_ids = Vec::new();
+  for (index, parameter) in input.generics.params.iter().enumerate() {
+    if let GenericParam::Type(parameter) = parameter {
+      let name = &parameter.ident;
+      let rotation = (index as u32 + 1) % 64;
+      parameter_ids.push(quote! {
+        ^ <#name as ::time_steward::type_utils::PersistentlyIdentifiedType>::ID.0.rotate_left(#rotation)
+      });
+    }
+  }
+  let type_parameters: Vec<_> = input.generics.type_params().map(|parameter| parameter.ident.clone()).collect();
+  {
+    let where_clause = input.generics.make_where_clause();
+    for name in type_parameters {
+      where_clause
+        .predicates
+        .push(parse_quote!(#name: ::time_steward::type_utils::PersistentlyIdentifiedType));
+    }
+  }
+
+  let name = &input.ident;
+  let (impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
+  (quote! {
+    impl #impl_generics ::time_steward::type_utils::PersistentlyIdentifiedType for #name #type_generics #where_clause {
+      const ID: ::time_steward::type_utils::PersistentTypeId = ::time_steward::type_utils::PersistentTypeId(#id #(#parameter_ids)*);
+    }
+  })
+  .into()
+}
+
+/// Implements `type_utils::list_of_types::Listable` for a data type, registering it as serializable data.
+///
+/// Add `#[listable(default)]` if the type implements Default and should be loadable as a stub in partial snapshots.
+#[proc_macro_derive(Listable, attributes(listable))]
+pub fn derive_listable(input: TokenStream) -> TokenStream {
+  let mut input = parse_macro_input!(input as DeriveInput);
+  let mut with_default = false;
+  for attribute in &input.attrs {
+    if !attribute.path.is_ident("listable") {
+      continue;
+    }
+    let usage = "the only #[listable] option is `default`";
+    match attribute.parse_meta() {
+      Ok(Meta::List(ref list)) if list.nested.len() == 1 => match &list.nested[0] {
+        NestedMeta::Meta(Meta::Path(path)) if path.is_ident("default") => with_default = true,
+        other => return Error::new(other.span(), usage).to_compile_error().into(),
+      },
+      Ok(other) => return Error::new(other.span(), usage).to_compile_error().into(),
+      Err(error) => return error.to_compile_error().into(),
+    }
+  }
+
+  let name = &input.ident;
+  let self_type = {
+    let (_, type_generics, _) = input.generics.split_for_impl();
+    quote!(#name #type_generics)
+  };
+  let type_parameters: Vec<_> = input.generics.type_params().map(|parameter| parameter.ident.clone()).collect();
+  {
+    let where_clause = input.generics.make_where_clause();
+    for name in type_parameters {
+      where_clause.predicates.push(parse_quote!(#name: 'static));
+    }
+    where_clause
+      .predicates
+      .push(parse_quote!(#self_type: ::time_steward::type_utils::list_of_types::SerializableData));
+  }
+  let registration = if with_default {
+    quote!(registrar.data_with_default::<Self>())
+  } else {
+    quote!(registrar.data::<Self>())
+  };
+  let (impl_generics, _, where_clause) = input.generics.split_for_impl();
+  (quote! {
+    impl #impl_generics ::time_steward::type_utils::list_of_types::Listable for #self_type #where_clause {
+      fn register<ListableRegistrar: ::time_steward::type_utils::list_of_types::Registrar>(registrar: &mut ListableRegistrar) {
+        #registration;
+      }
+    }
+  })
+  .into()
+}
+
+/// Fills in the routine parts of an `impl Event for ...` block.
+///
+/// The block must define `type Steward` and `fn execute`. If it doesn't define `type ExecutionData`, it defaults to `()`.
+///
+/// Every event must either define `fn undo`, or be declared `#[event(not_undoable)]`. A non-undoable event gets an `undo` that panics with the event's name and time, which is much easier to track down than a bare `unimplemented!()`. (Events can only be undone when an earlier change invalidates them, so simulations where that never happens to a particular event type can safely skip writing its undo.)
+///
+/// The generated `undo` names `FutureCleanupAccessor`, so the steward's `FutureCleanupAccessor` must be in scope, as it would be for a handwritten one.
+///
+/// This also implements `Listable` for the event type, so that it can be listed in `Basics::Types`.
+#[proc_macro_attribute]
+pub fn event(arguments: TokenStream, item: TokenStream) -> TokenStream {
+  let arguments = parse_macro_input!(arguments as AttributeArgs);
+  let mut item = parse_macro_input!(item as ItemImpl);
+  match event_impl(arguments, &mut item) {
+    Ok(()) => {
+      let listable = event_listable_impl(&item);
+      (quote! {#item #listable}).into()
+    }
+    Err(error) => error.to_compile_error().into(),
+  }
+}
+
+fn event_listable_impl(item: &ItemImpl) -> proc_macro2::TokenStream {
+  let (impl_generics, _, where_clause) = item.generics.split_for_impl();
+  let self_type = &item.self_ty;
+  let event_trait = &item.trait_.as_ref().expect("checked by event_impl").1;
+  quote! {
+    impl #impl_generics ::time_steward::type_utils::list_of_types::Listable for #self_type #where_clause {
+      fn register<ListableRegistrar: ::time_steward::type_utils::list_of_types::Registrar>(registrar: &mut ListableRegistrar) {
+        <Self as #event_trait>::register_event(registrar);
+      }
+    }
+  }
+}
+
+fn event_impl(arguments: AttributeArgs, item: &mut ItemImpl) -> Result<(), Error> {
+  let mut not_undoable = false;
+  for argument in arguments {
+    match argument {
+      NestedMeta::Meta(Meta::Path(ref path)) if path.is_ident("not_undoable") => not_undoable = true,
+      other => return Err(Error::new(other.span(), "the only #[event] option is `not_undoable`")),
+    }
+  }
+  if item.trait_.is_none() {
+    return Err(Error::new(item.span(), "#[event] must be applied to an `impl Event for ...` block"));
+  }
+
+  let mut has_steward = false;
+  let mut has_execution_data = false;
+  let mut has_execute = false;
+  let mut undo_span = None;
+  for impl_item in &item.items {
+    match impl_item {
+      ImplItem::Type(item) if item.ident == "Steward" => has_steward = true,
+      ImplItem::Type(item) if item.ident == "ExecutionData" => has_execution_data = true,
+      ImplItem::Method(method) if method.sig.ident == "execute" => has_execute = true,
+      ImplItem::Method(method) if method.sig.ident == "undo" => undo_span = Some(method.sig.ident.span()),
+      _ => {}
+    }
+  }
+  if !has_steward {
+    return Err(Error::new(item.span(), "an event must define `type Steward`"));
+  }
+  if !has_execute {
+    return Err(Error::new(item.span(), "an event must define `fn execute`"));
+  }
+  match (undo_span, not_undoable) {
+    (Some(span), true) => {
+      return Err(Error::new(span, "this event is declared #[event(not_undoable)], but defines undo anyway"));
+    }
+    (None, false) => {
+      return Err(Error::new(
+        item.span(),
+        "an event must define `fn undo`, or be declared #[event(not_undoable)]",
+      ));
+    }
+    _ => {}
+  }
+
+  if !has_execution_data {
+    item.items.push(parse_quote! {
+      type ExecutionData = ();
+    });
+  }
+  if not_undoable {
+    let self_type = &item.self_ty;
+    let name = quote!(#self_type).to_string();
+    item.items.push(parse_quote! {
+      fn undo<A: FutureCleanupAccessor<Steward = Self::Steward>>(
+        &self,
+        accessor: &mut A,
+        _: Self::ExecutionData,
+      ) {
+        panic!(
+          "{} was declared #[event(not_undoable)], but it was undone at {:?}. Something earlier than it must have changed one of its inputs; if that's legitimate, implement undo for it.",
+          #name,
+          accessor.extended_now()
+        );
+      }
+    });
+  }
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn hash_path_is_stable() {
+    // saved files depend on these, so they must never change
+    assert_eq!(hash_path(""), 0xf52a15e9a9b5e89b);
+    assert_eq!(
+      hash_path("bouncy_circles::RelationshipChange"),
+      0x6f46ccc806081e66
+    );
+    assert_eq!(hash_path("time_steward::Example"), 0xd7043d7a3d5a88ef);
+  }
+
+  #[test]
+  fn persistent_id_forms() {
+    let explicit: DeriveInput = parse_quote! {
+      #[persistent_id(0x08c4b60ad5d0ed08)]
+      struct Example;
+    };
+    assert_eq!(persistent_id(&explicit).unwrap(), 0x08c4b60ad5d0ed08);
+    let hashed: DeriveInput = parse_quote! {
+      #[persistent_id(path = "time_steward::Example")]
+      struct Example;
+    };
+    assert_eq!(
+      persistent_id(&hashed).unwrap(),
+      hash_path("time_steward::Example")
+    );
+  }
+
+  fn persistent_id_error(input: DeriveInput) -> String {
+    persistent_id(&input).unwrap_err().to_string()
+  }
+
+  #[test]
+  fn persistent_id_errors() {
+    assert!(persistent_id_error(parse_quote! { struct Example; })
+      .starts_with("deriving PersistentlyIdentifiedType requires"));
+
+    let usage = "expected #[persistent_id(0x...)] or #[persistent_id(path = \"...\")]";
+    assert_eq!(
+      persistent_id_error(parse_quote! {
+        #[persistent_id]
+        struct Example;
+      }),
+      usage
+    );
+    assert_eq!(
+      persistent_id_error(parse_quote! {
+        #[persistent_id("0x08c4b60ad5d0ed08")]
+        struct Example;
+      }),
+      usage
+    );
+    assert_eq!(
+      persistent_id_error(parse_quote! {
+        #[persistent_id(name = "time_steward::Example")]
+        struct Example;
+      }),
+      usage
+    );
+    assert_eq!(
+      persistent_id_error(parse_quote! {
+        #[persistent_id(1, 2)]
+        struct Example;
+      }),
+      usage
+    );
+    assert_eq!(
+      persistent_id_error(parse_quote! {
+        #[persistent_id(1)]
+        #[persistent_id(2)]
+        struct Example;
+      }),
+      "duplicate #[persistent_id] attribute"
+    );
+  }
+}
@@ -1,4 +1,3 @@
-#![feature(nll, try_trait, specialization)]
 
 #[macro_use]
 extern crate failure;
@@ -40,12 +40,6 @@ pub enum Error<T: Integer> {
 #[derive(Copy, Clone, Debug)]
 pub struct OverflowError;
 
-use std::option::NoneError;
-impl From<NoneError> for OverflowError {
-  fn from(_: NoneError) -> OverflowError {
-    OverflowError
-  }
-}
 impl<T: Integer> From<Error<T>> for OverflowError {
   fn from(_: Error<T>) -> OverflowError {
     OverflowError
@@ -218,7 +212,7 @@ pub fn evaluate_at_fractional_input_check<
     + max(precision_shift_increment, input_shift);
   for coefficient in coefficients.iter().skip(1).rev() {
     let coefficient: T = (*coefficient).into();
-    overflow_checked_shl(coefficient, precision_shift)?;
+    overflow_checked_shl(coefficient, precision_shift).ok_or(OverflowError)?;
     precision_shift -= precision_shift_increment;
   }
 
@@ -479,7 +473,9 @@ pub fn compute_derivative<Coefficient: Integer, T: Integer + Signed + From<Coeff
     .zip(results.iter_mut())
   {
     let coefficient: T = (*coefficient).into();
-    *result = coefficient.checked_mul(&T::from_usize(power).unwrap())?;
+    *result = coefficient
+      .checked_mul(&T::from_usize(power).unwrap())
+      .ok_or(OverflowError)?;
   }
   Ok(())
 }
@@ -498,7 +494,9 @@ pub fn compute_nth_derivative<Coefficient: Integer, T: Integer + Signed + From<C
   {
     let coefficient: T = (*coefficient).into();
     let factor = (power + 1 - which_derivative..power + 1).product::<usize>();
-    *result = coefficient.checked_mul(&T::from_usize(factor).unwrap())?;
+    *result = coefficient
+      .checked_mul(&T::from_usize(factor).unwrap())
+      .ok_or(OverflowError)?;
   }
   Ok(())
 }
@@ -521,7 +519,9 @@ pub fn compute_nth_taylor_coefficient_function<
   {
     let coefficient: T = (*coefficient).into();
     let factor = (power + 1 - which_derivative..power + 1).product::<usize>() / factorial;
-    *result = coefficient.checked_mul(&T::from_usize(factor).unwrap())?;
+    *result = coefficient
+      .checked_mul(&T::from_usize(factor).unwrap())
+      .ok_or(OverflowError)?;
   }
   Ok(())
 }
@@ -550,8 +550,10 @@ pub fn add_product_into<Coefficient: Integer, T: Integer + Signed + From<Coeffic
     for (second_power, second_coefficient) in second.iter().enumerate() {
       let second_coefficient: T = (*second_coefficient).into();
       let destination = &mut destination[first_power + second_power];
-      *destination =
-        destination.checked_add(&first_coefficient.checked_mul(&second_coefficient)?)?;
+      *destination = first_coefficient
+        .checked_mul(&second_coefficient)
+        .and_then(|product| destination.checked_add(&product))
+        .ok_or(OverflowError)?;
     }
   }
   Ok(())
@@ -605,8 +607,11 @@ pub fn set_nth_taylor_coefficient_at_fractional_input<
       input_numerator,
       input_shift,
     )?;
-    let change_size = target_value.checked_sub(&current_value)?;
-    coefficients[index] = coefficients[index].checked_add(&Coefficient::from(change_size)?)?;
+    coefficients[index] = target_value
+      .checked_sub(&current_value)
+      .and_then(|change_size| Coefficient::from(change_size))
+      .and_then(|change_size| coefficients[index].checked_add(&change_size))
+      .ok_or(OverflowError)?;
   }
   Ok(())
 }
@@ -111,7 +111,7 @@ pub trait SetNthTaylorCoefficientAtFractionalInput<WorkingType>: PolynomialBase
   input: WorkingType,
   input_shift: u32,
   target_value: Self::Coefficient,
-) -> Result<(), ::std::option::NoneError>;
+) -> Option<()>;
 }
 
 pub trait Polynomial<Coefficient: DoubleSizedSignedInteger>:
@@ -303,7 +303,7 @@ impl <Coefficient: DoubleSizedSignedInteger> SetNthTaylorCoefficientAtFractional
   input: DoubleSized<Coefficient>,
   input_shift: u32,
   target_value: Coefficient,
-) -> Result<(), ::std::option::NoneError> {
+) -> Option<()> {
   let mut target_values: ::smallvec::SmallVec<[DoubleSized<Coefficient>; 8]> =
     ::smallvec::SmallVec::with_capacity(which_derivative + 1);
   let bounds = self.all_taylor_coefficients_bounds(input, input_shift, 0u32)?;
@@ -323,7 +323,7 @@ impl <Coefficient: DoubleSizedSignedInteger> SetNthTaylorCoefficientAtFractional
     self.as_mut_slice()[index] =
       self.as_slice()[index].checked_add(&change_size.try_into().ok()?)?;
   }
-  Ok(())
+  Some(())
 }
 }
 
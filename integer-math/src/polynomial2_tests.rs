@@ -409,14 +409,3 @@ $(
     uniform3,
     polynomial_tests_32,
   );
-
-  /*#[test]
-  fn print_record_data() {
-     use std::io::Write;
-     let mut data: Vec<Vec<RangeSearchRecordHack>> = serde_json::from_reader (::std::fs::File::open("/n/pfft/range_search_data.json").unwrap()).unwrap();
-     data.sort_by_key(|datum|datum.len());
-     let percentiles:Vec<usize> = (0..=100).map(|percent| data[percent*(data.len()-1)/100].len()).collect();
-     write!(::std::io::stderr(), "Number of searches: {:?}", data.len());
-     write!(::std::io::stderr(), "Sizes: {:?}", percentiles);
-     write!(::std::io::stderr(), "Worst: {}", serde_json::to_string_pretty(data.last().unwrap()).unwrap());
-  }*/
@@ -152,36 +152,6 @@ pub fn coefficient_bounds_on_tail<
 }
 
 
-
-//pub struct
-/*
-type HackP = ValueWithPrecision<[[i64; 2]; 5]>;
-
-#[derive(Copy, Clone, Serialize, Deserialize, Debug)]
-pub enum RangeSearchRecordHack {
-  Interval { endpoints: [PolynomialBasedAtInput<HackP, FractionalInput<i64>>; 2], bounds: [[i64;2];5] },
-  ToEnd { endpoint: PolynomialBasedAtInput<HackP, FractionalInput<i64>>, bounds: [[i64;2];5] },
-}
-
-trait RangeSearchRecorderHack<I,P> {
-  type Func: Fn(FractionalInput<i64>) -> Option<HackP>;
-  fn range_search_record_hack<G: FnOnce(&RangeSearch<Self::Func, i64, HackP>)> (&self, g:G);
-}
-
-impl<F: Fn(FractionalInput<I>) -> Option<P>, I, P> RangeSearchRecorderHack<I,P> for RangeSearch<F,I,P> {
-  default type Func = F;
-  default fn range_search_record_hack<G: FnOnce(&RangeSearch<Self::Func, i64, HackP>)> (&self, _g:G) {}
-}
-
-impl<F: Fn(FractionalInput<i64>) -> Option<HackP>> RangeSearchRecorderHack<i64, HackP> for RangeSearch<F, i64, HackP> {
-  type Func = F;
-  #[allow (unused_variables)]
-  fn range_search_record_hack<G: FnOnce(&RangeSearch<Self::Func, i64, HackP>)> (&self, g:G) {
-    //(g)(self);
-  }
-}*/
-
-
 pub trait RangeSearch {
   type Input: Integer;
   type IntegerValue: Debug;
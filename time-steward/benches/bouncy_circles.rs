@@ -1,7 +1,5 @@
-#![feature (test)]
-#![feature (macro_vis_matcher)]
-
-extern crate test;
+#[macro_use]
+extern crate bencher;
 #[macro_use]
 extern crate time_steward;
 
@@ -18,10 +16,12 @@ extern crate serde;
 extern crate serde_derive;
 
 
-use test::Bencher;
+use bencher::Bencher;
 
 use time_steward::{DeterministicRandomId};
-use time_steward::{PersistentTypeId, ListedType, PersistentlyIdentifiedType, DataTimelineCellTrait, Basics as BasicsTrait};
+use time_steward::{DataTimelineCellTrait, Basics as BasicsTrait};
+use time_steward::type_utils::{PersistentTypeId, PersistentlyIdentifiedType};
+use time_steward::type_utils::list_of_types::ListedType;
 //use time_steward::stewards::{simple_full as steward_module};
 use steward_module::{TimeSteward, ConstructibleTimeSteward, Event, DataTimelineCell, EventAccessor, FutureCleanupAccessor, SnapshotAccessor, simple_timeline};
 use simple_timeline::{SimpleTimeline, GetVarying};
@@ -29,10 +29,9 @@ use simple_timeline::{SimpleTimeline, GetVarying};
 #[path = "../dev-shared/bouncy_circles.rs"] mod bouncy_circles;
 use bouncy_circles::*;
 
-#[bench]
 fn bouncy_circles_straightforward(bencher: &mut Bencher) {
   bencher.iter(|| {
-    let mut steward: Steward = Steward::from_globals (make_globals());
+    let mut steward: Steward = Steward::from_globals (make_globals()).unwrap();
     steward.insert_fiat_event(0, DeterministicRandomId::new(&0), Initialize {}).unwrap();
     for index in 0..1000 {
       let time = 10*SECOND*index/1000;
@@ -43,10 +42,9 @@ fn bouncy_circles_straightforward(bencher: &mut Bencher) {
 }
 
 
-#[bench]
 fn bouncy_circles_disturbed (bencher: &mut Bencher) {
   bencher.iter(|| {
-    let mut steward: Steward = Steward::from_globals (make_globals());
+    let mut steward: Steward = Steward::from_globals (make_globals()).unwrap();
     steward.insert_fiat_event(0, DeterministicRandomId::new(&0), Initialize {}).unwrap();
     for index in 1..10 {
       steward.insert_fiat_event (index*SECOND, DeterministicRandomId::new (& index), Disturb{ coordinates: [ARENA_SIZE/3,ARENA_SIZE/3]}).unwrap();
@@ -72,3 +70,6 @@ fn bouncy_circles_disturbed_retroactive (bencher: &mut Bencher) {
   })
 }
 */
+
+benchmark_group!(benches, bouncy_circles_straightforward, bouncy_circles_disturbed);
+benchmark_main!(benches);
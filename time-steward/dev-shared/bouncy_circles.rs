@@ -3,17 +3,17 @@ use time_steward::support::trajectories;
 use nalgebra::Vector2;
 //use time_steward::support::rounding_error_tolerant_math::right_shift_round_up;
 //use time_steward::support::integer_math::polynomial::RootSearchResult;
-use std::marker::PhantomData;
 
-use time_steward::{DeterministicRandomId};
+use time_steward::{DeterministicRandomId, event, Listable};
 use time_steward::{DataHandleTrait, DataTimelineCellTrait, QueryResult, Basics as BasicsTrait};
 use time_steward::type_utils::{PersistentTypeId, PersistentlyIdentifiedType};
 use time_steward::type_utils::list_of_types::{ListedType};
 pub use time_steward::stewards::{simple_full as steward_module};
-use crate::steward_module::{TimeSteward, Event, DataHandle, DataTimelineCell, Accessor, EventAccessor, FutureCleanupAccessor, bbox_collision_detection_2d as collisions};
-use crate::simple_timeline::{SimpleTimeline, query, tracking_query, tracking_query_ref, set, destroy};
-use self::collisions::{BoundingBox, NumDimensions, Detector};
+use crate::steward_module::{TimeSteward, Event, DataHandle, DataTimelineCell, Accessor, EventAccessor, FutureCleanupAccessor, bbox_collision_detection as collisions, contact_prediction};
+use crate::simple_timeline::{SimpleTimeline, query, tracking_query, tracking_query_ref, set, unset};
+use self::collisions::{BoundingBox, Detector};
 use self::collisions::simple_grid::{SimpleGridDetector};
+use self::contact_prediction::{Contact, contact_data, set_contact_data};
 
 use rand::Rng;
 use boolinator::Boolinator;
@@ -37,20 +37,19 @@ macro_rules! define_event {
   (
     $visibility: vis struct $Struct: ident {$($contents:tt)*},
     PersistentTypeId($id: expr),
-    fn execute $($execute:tt)*
+    fn execute $execute_arguments: tt $execute_body: block
+    fn undo $undo_arguments: tt $undo_body: block
   ) => {
 #[derive (Clone, PartialEq, Eq, Serialize, Deserialize, Debug)]
 $visibility struct $Struct {$($contents)*}
 impl PersistentlyIdentifiedType for $Struct {
   const ID: PersistentTypeId = PersistentTypeId($id);
 }
+#[event]
 impl Event for $Struct {
   type Steward = Steward;
-  type ExecutionData = ();
-  fn execute <Accessor: EventAccessor <Steward = Self::Steward>> $($execute)*
-  fn undo <Accessor: FutureCleanupAccessor <Steward = Self::Steward>> (&self, _accessor: &mut Accessor, _: ()) {
-    unimplemented!()
-  }
+  fn execute <Accessor: EventAccessor <Steward = Self::Steward>> $execute_arguments $execute_body
+  fn undo <Accessor: FutureCleanupAccessor <Steward = Self::Steward>> $undo_arguments $undo_body
 }
   }
 }
@@ -66,21 +65,14 @@ fn modify_trajectory<A: EventAccessor <Steward = Steward>, F: FnOnce(&mut Circle
   });
   trajectory_changed (accessor, circle);
 }
-fn modify_trajectories<A: EventAccessor <Steward = Steward>, F: FnOnce(&mut RelationshipVarying, (&mut CircleVarying, &mut CircleVarying))>(accessor: &A, relationship: &RelationshipHandle, f: F) {
-  modify (accessor, & relationship.varying, | relationship_varying | {
-    modify (accessor, & relationship.circles.0.varying, | circle_0 | {
-      modify (accessor, & relationship.circles.1.varying, | circle_1 | {
-        (f)(relationship_varying, (circle_0, circle_1));
-      });
-    });
-  });
-  trajectory_changed (accessor, & relationship.circles.0);
-  trajectory_changed (accessor, & relationship.circles.1);
+/// Undoes the current event's changes to a circle, and to its contacts from before and after the event (including ones the event created or destroyed).
+fn unset_circle<A: FutureCleanupAccessor <Steward = Steward>>(accessor: &A, circle: &CircleHandle) {
+  contact_prediction::unset_contacts (accessor, &Contacts, circle);
+  unset (accessor, & circle.varying);
+  contact_prediction::unset_contacts (accessor, &Contacts, circle);
 }
 fn trajectory_changed <A: EventAccessor <Steward = Steward>>(accessor: &A, circle: &CircleHandle) {
-  for relationship in query (accessor, & circle.varying).relationships.iter() {
-    update_relationship_change_prediction (accessor, relationship);
-  }
+  contact_prediction::trajectory_changed (accessor, &Contacts, circle);
   update_boundary_change_prediction (accessor, circle);
   SimpleGridDetector::changed_course(accessor, &query(accessor, &accessor.globals().detector), circle);
 }
@@ -92,7 +84,7 @@ pub struct Basics {}
 impl BasicsTrait for Basics {
   type Time = Time;
   type Globals = Globals;
-  type Types = (ListedType <RelationshipChange>, ListedType <BoundaryChange>, ListedType <Initialize>, ListedType <Disturb>, collisions::simple_grid::Types <Space>);
+  type Types = (ListedType <Circle>, contact_prediction::Types <Contacts>, ListedType <BoundaryChange>, ListedType <Initialize>, ListedType <Disturb>, collisions::simple_grid::Types <Space, 2>);
 }
 
 pub type Steward = steward_module::Steward <Basics>;
@@ -101,10 +93,10 @@ pub type Steward = steward_module::Steward <Basics>;
 #[derive (Clone, PartialEq, Eq, Serialize, Deserialize, Debug)]
 pub struct Globals {
   pub circles: Vec<CircleHandle>,
-  pub detector: DataTimelineCell <SimpleTimeline <DataHandle <SimpleGridDetector<Space>>, Steward>>,
+  pub detector: DataTimelineCell <SimpleTimeline <DataHandle <SimpleGridDetector<Space, 2>>, Steward>>,
 }
 
-#[derive (Clone, PartialEq, Eq, Serialize, Deserialize, Debug)]
+#[derive (Clone, PartialEq, Eq, Serialize, Deserialize, Debug, Listable)]
 pub struct Circle {
   pub index: usize,
   pub radius: SpaceCoordinate,
@@ -113,30 +105,17 @@ pub struct Circle {
 #[derive (Clone, PartialEq, Eq, Serialize, Deserialize, Debug)]
 pub struct CircleVarying {
   pub position: QuadraticTrajectory,
-  pub relationships: Vec<RelationshipHandle>,
+  pub contacts: Vec<ContactHandle>,
   pub boundary_induced_acceleration: Option <Vector2<SpaceCoordinate>>,
   pub next_boundary_change: Option <<Steward as TimeSteward>::EventHandle>,
-  pub collision_data: Option<collisions::simple_grid::DetectorDataPerObject<Space>>,
+  pub collision_data: Option<collisions::simple_grid::DetectorDataPerObject<Space, 2>>,
 }
 impl PersistentlyIdentifiedType for Circle {
   const ID: PersistentTypeId = PersistentTypeId(0xd711cc7240c71607);
 }
 type CircleHandle = DataHandle <Circle>;
 
-#[derive (Clone, PartialEq, Eq, Serialize, Deserialize, Debug)]
-pub struct Relationship {
-  pub circles: (CircleHandle, CircleHandle),
-  pub varying: DataTimelineCell <SimpleTimeline <RelationshipVarying, Steward>>,
-}
-#[derive (Clone, PartialEq, Eq, Serialize, Deserialize, Debug)]
-pub struct RelationshipVarying {
-  pub induced_acceleration: Option <Vector2<SpaceCoordinate>>,
-  pub next_change: Option <<Steward as TimeSteward>::EventHandle>,
-}
-impl PersistentlyIdentifiedType for Relationship {
-  const ID: PersistentTypeId = PersistentTypeId(0xa1010b5e80c3465a);
-}
-type RelationshipHandle = DataHandle <Relationship>;
+pub type ContactHandle = DataHandle <Contact <Contacts>>;
 
 
 fn to_collision_space (coordinate: SpaceCoordinate)->collisions::Coordinate {
@@ -151,13 +130,12 @@ pub struct Space;
 impl PersistentlyIdentifiedType for Space {
   const ID: PersistentTypeId = PersistentTypeId(0x879511343e48addd);
 }
-impl collisions::Space for Space {
+impl collisions::Space<2> for Space {
   type Steward = Steward;
   type Object = Circle;
-  type DetectorDataPerObject = collisions::simple_grid::DetectorDataPerObject<Self>;
+  type DetectorDataPerObject = collisions::simple_grid::DetectorDataPerObject<Self, 2>;
   type UniqueId = usize;
-  
-  const DIMENSIONS: NumDimensions = 2;
+  type WorldCoordinate = i64;
 
   // An Object generally has to store some opaque data for the collision detector.
   // It would normally include a DataHandle to a tree node.
@@ -168,11 +146,11 @@ impl collisions::Space for Space {
   fn set_detector_data<A: EventAccessor <Steward = Self::Steward>>(&self, accessor: &A, object: &DataHandle<Self::Object>, data: Option<Self::DetectorDataPerObject>) {
     modify (accessor, &object.varying, | varying | varying.collision_data = data);
   }
-  fn unique_id<A: EventAccessor <Steward = Self::Steward>>(&self, _accessor: &A, object: &DataHandle<Self::Object>)->Self::UniqueId {
+  fn unique_id<A: Accessor <Steward = Self::Steward>>(&self, _accessor: &A, object: &DataHandle<Self::Object>)->Self::UniqueId {
     object.index
   }
 
-  fn current_bounding_box<A: EventAccessor <Steward = Self::Steward>>(&self, accessor: &A, object: &DataHandle<Self::Object>)->BoundingBox <Self> {
+  fn current_bounding_box<A: EventAccessor <Steward = Self::Steward>>(&self, accessor: &A, object: &DataHandle<Self::Object>)->BoundingBox <2> {
     let varying = tracking_query (accessor, & object.varying);
     let center = varying.position.value(*accessor.now(), STATIC_TIME_SHIFT).unwrap();
     let effective_radius = object.radius + 16; // just correcting for leeway in next_time_possibly_outside_bounds 
@@ -181,10 +159,9 @@ impl collisions::Space for Space {
         [to_collision_space (center [0] - effective_radius), to_collision_space (center [0] + effective_radius)],
         [to_collision_space (center [1] - effective_radius), to_collision_space (center [1] + effective_radius)],
       ],
-      _marker: PhantomData
     }
   }
-  fn when_escapes<A: EventAccessor <Steward = Self::Steward>>(&self, accessor: &A, object: &DataHandle<Self::Object>, bounds: BoundingBox <Self>)->Option<<<Self::Steward as TimeSteward>::Basics as BasicsTrait>::Time> {
+  fn when_escapes<A: EventAccessor <Steward = Self::Steward>>(&self, accessor: &A, object: &DataHandle<Self::Object>, bounds: BoundingBox <2>)->Option<<<Self::Steward as TimeSteward>::Basics as BasicsTrait>::Time> {
     let varying = tracking_query (accessor, & object.varying);
     varying.position.next_time_possibly_outside_bounds ([*accessor.now(), Time::max_value()], STATIC_TIME_SHIFT, [
       Vector2::new (from_collision_space (bounds.bounds [0] [0]) + object.radius, from_collision_space (bounds.bounds [1] [0]) + object.radius),
@@ -193,33 +170,15 @@ impl collisions::Space for Space {
   }
   
   fn become_neighbors<A: EventAccessor <Steward = Self::Steward>>(&self, accessor: &A, objects: [&DataHandle<Self::Object>; 2]) {
-    //println!("become {:?}", (objects [0].index, objects [1].index));
-    let relationship = accessor.new_handle (Relationship {
-      circles: (objects [0].clone(), objects [1].clone()),
-      varying: DataTimelineCell::new(SimpleTimeline::new ()),
-    });
-    set (accessor, & relationship.varying, RelationshipVarying {
-      induced_acceleration: None,
-      next_change: None,
-    });
-    for object in objects.iter() {
-      modify (accessor, &object.varying, | varying | varying.relationships.push (relationship.clone()));
-    }
-    update_relationship_change_prediction (accessor, &relationship) ;
+    contact_prediction::become_neighbors (accessor, &Contacts, objects);
   }
   fn stop_being_neighbors<A: EventAccessor <Steward = Self::Steward>>(&self, accessor: &A, objects: [&DataHandle<Self::Object>; 2]) {
-    //println!("stop {:?}", (objects [0].index, objects [1].index));
-    let varying = tracking_query (accessor, & objects[0].varying);
-    let relationship = varying.relationships.iter().find (| relationship | (
-      [&relationship.circles.0, &relationship.circles.1] == objects
-      || [&relationship.circles.1, &relationship.circles.0] == objects
-    )).unwrap().clone();
-    destroy (accessor, & relationship.varying);
-    for object in objects.iter() {
-      modify (accessor, &object.varying, | varying | varying.relationships.retain (| relationship | !(
-        [&relationship.circles.0, &relationship.circles.1] == objects
-        || [&relationship.circles.1, &relationship.circles.0] == objects
-      )));
+    contact_prediction::stop_being_neighbors (accessor, &Contacts, objects);
+  }
+  fn undo_detector_changes<A: FutureCleanupAccessor <Steward = Self::Steward>>(&self, accessor: &A, object: &DataHandle<Self::Object>, neighbors: &[DataHandle<Self::Object>]) {
+    unset_circle (accessor, object);
+    for neighbor in neighbors.iter() {
+      unset_circle (accessor, neighbor);
     }
   }
 }
@@ -228,62 +187,62 @@ impl collisions::Space for Space {
 
 
 
-pub fn update_relationship_change_prediction <Accessor: EventAccessor <Steward = Steward>>(accessor: &Accessor, relationship_handle: &RelationshipHandle) {
-  let circles = &relationship_handle.circles;
-  let us = (
-    tracking_query_ref (accessor, & circles.0.varying),
-    tracking_query_ref (accessor, & circles.1.varying));
+/// The circles again, as seen by contact prediction. Each contact remembers the acceleration it induced while the circles are touching.
+#[derive (Clone, PartialEq, Eq, Serialize, Deserialize, Debug)]
+pub struct Contacts;
+impl PersistentlyIdentifiedType for Contacts {
+  const ID: PersistentTypeId = PersistentTypeId(0xa1010b5e80c3465a);
+}
+impl contact_prediction::Space for Contacts {
+  type Steward = Steward;
+  type Object = Circle;
+  type Trajectory = QuadraticTrajectory;
+  type ContactData = Option <Vector2<SpaceCoordinate>>;
+  type UniqueId = usize;
+
+  const TIME_SHIFT: u32 = STATIC_TIME_SHIFT;
 
-  modify (accessor, & relationship_handle.varying, | relationship_varying | {
-    let difference = &(us.1).position - &(us.0).position;
-    let radius = circles.0.radius + circles.1.radius;
-    let result = if relationship_varying.induced_acceleration.is_none() {
-      difference.next_time_magnitude_significantly_lt([*accessor.now(), Time::max_value()], STATIC_TIME_SHIFT, radius-2)
+  fn get_contacts<A: Accessor <Steward = Self::Steward>>(&self, accessor: &A, object: &DataHandle<Self::Object>)->Vec<ContactHandle> {
+    query (accessor, &object.varying).contacts
+  }
+  fn set_contacts<A: EventAccessor <Steward = Self::Steward>>(&self, accessor: &A, object: &DataHandle<Self::Object>, contacts: Vec<ContactHandle>) {
+    modify (accessor, &object.varying, | varying | varying.contacts = contacts);
+  }
+  fn unique_id<A: Accessor <Steward = Self::Steward>>(&self, _accessor: &A, object: &DataHandle<Self::Object>)->Self::UniqueId {
+    object.index
+  }
+
+  fn trajectory<A: EventAccessor <Steward = Self::Steward>>(&self, accessor: &A, object: &DataHandle<Self::Object>)->QuadraticTrajectory {
+    tracking_query_ref (accessor, & object.varying).position.clone()
+  }
+  fn radius<A: EventAccessor <Steward = Self::Steward>>(&self, _accessor: &A, object: &DataHandle<Self::Object>)->SpaceCoordinate {
+    object.radius
+  }
+
+  fn touching_changed<A: EventAccessor <Steward = Self::Steward>>(&self, accessor: &A, contact: &ContactHandle, touching: bool) {
+    let circles = contact.objects();
+    let acceleration = if touching {
+      let acceleration = (query (accessor, & circles [0].varying).position.value(*accessor.now(), STATIC_TIME_SHIFT).unwrap() -
+                          query (accessor, & circles [1].varying).position.value(*accessor.now(), STATIC_TIME_SHIFT).unwrap()) *
+                          (ARENA_SIZE * 16 /
+                           (circles [0].radius + circles [1].radius));
+      set_contact_data (accessor, contact, Some(acceleration));
+      //println!("Joined {} At {}", self.id, mutator.now());
+      acceleration
     } else {
-      difference.next_time_magnitude_significantly_gt([*accessor.now(), Time::max_value()], STATIC_TIME_SHIFT, radius+2)
+      let induced_acceleration = contact_data (accessor, contact).unwrap();
+      set_contact_data (accessor, contact, None);
+      //println!("Parted {} At {}", self.id, mutator.now());
+      -induced_acceleration
     };
-    let time = result;
-    
-    // println!("Planning for {} At {}, {}", id, (us.0).1, (us.1).1);
-    if time.is_none() && relationship_varying.induced_acceleration.is_some() {
-      panic!(" fail {:?} {:?} {:?}", relationship_handle, relationship_varying, us)
-    }
-  
-    relationship_varying.next_change = time.and_then (| time | (time >= *accessor.now()).as_some_from(||
-      // println!(" planned for {}", &yes);
-      accessor.create_prediction (
-        time,
-        DeterministicRandomId::new (&(accessor.extended_now().id, circles.0.index, circles.1.index.wrapping_add (0x6515c48170b61837))),
-        RelationshipChange {relationship_handle: relationship_handle.clone()}
-      )
-    ));
-  });
-}
-
-define_event!{
-  pub struct RelationshipChange {pub relationship_handle: RelationshipHandle},
-  PersistentTypeId(0x08c4b60ad5d0ed08),
-  fn execute (&self, accessor: &mut Accessor) {
-    let circles = &self.relationship_handle.circles;
-    modify_trajectories(accessor, &self.relationship_handle, | relationship_varying, new | {
-      //let new_difference = new.0.position.value(*accessor.now(), STATIC_TIME_SHIFT).unwrap()-new.1.position.value(*accessor.now(), STATIC_TIME_SHIFT).unwrap();
-      //println!("event with error {:?}", (new_difference.dot(&new_difference) as f64).sqrt() - (circles.0.radius+circles.1.radius)  as f64);
-      if let Some(induced_acceleration) = relationship_varying.induced_acceleration {
-        new.0.position.add_acceleration(*accessor.now(), STATIC_TIME_SHIFT,-induced_acceleration).unwrap();
-        new.1.position.add_acceleration(*accessor.now(), STATIC_TIME_SHIFT, induced_acceleration).unwrap();
-        relationship_varying.induced_acceleration = None;
-        //println!("Parted {} At {}", self.id, mutator.now());
-      } else {
-        let acceleration = (new.0.position.value(*accessor.now(), STATIC_TIME_SHIFT).unwrap() -
-                            new.1.position.value(*accessor.now(), STATIC_TIME_SHIFT).unwrap()) *
-                            (ARENA_SIZE * 16 /
-                             (circles.0.radius + circles.1.radius));
-        new.0.position.add_acceleration(*accessor.now(), STATIC_TIME_SHIFT, acceleration).unwrap();
-        new.1.position.add_acceleration(*accessor.now(), STATIC_TIME_SHIFT,-acceleration).unwrap();
-        relationship_varying.induced_acceleration = Some(acceleration);
-        //println!("Joined {} At {}", self.id, mutator.now());
-      }
-    });
+    modify (accessor, & circles [0].varying, | new: &mut CircleVarying | new.position.add_acceleration(*accessor.now(), STATIC_TIME_SHIFT, acceleration).unwrap());
+    modify (accessor, & circles [1].varying, | new: &mut CircleVarying | new.position.add_acceleration(*accessor.now(), STATIC_TIME_SHIFT,-acceleration).unwrap());
+    trajectory_changed (accessor, & circles [0]);
+    trajectory_changed (accessor, & circles [1]);
+  }
+  fn undo_touching_changed<A: FutureCleanupAccessor <Steward = Self::Steward>>(&self, accessor: &A, contact: &ContactHandle) {
+    unset_circle (accessor, & contact.objects() [0]);
+    unset_circle (accessor, & contact.objects() [1]);
   }
 }
 
@@ -330,6 +289,9 @@ define_event!{
       }
     });
   }
+  fn undo (&self, accessor: &mut Accessor, _: ()) {
+    unset_circle (accessor, & self.circle_handle);
+  }
 }
 
 define_event!{
@@ -353,7 +315,7 @@ define_event!{
       )).unwrap();
       varying.push (CircleVarying {
         position: position,
-        relationships: Vec::new(),
+        contacts: Vec::new(),
         boundary_induced_acceleration: None,
         next_boundary_change: None,
         collision_data: None,
@@ -364,6 +326,15 @@ define_event!{
       SimpleGridDetector::insert (accessor, &query(accessor, &accessor.globals().detector), & circles [index], None);
     }
   }
+  fn undo (&self, accessor: &mut Accessor, _: ()) {
+    // the circles didn't exist before, so there are no earlier contacts to restore
+    for circle in accessor.globals().circles.iter() {
+      contact_prediction::unset_contacts (accessor, &Contacts, circle);
+      unset (accessor, & circle.varying);
+    }
+    SimpleGridDetector::unset (accessor, &query(accessor, &accessor.globals().detector));
+    unset (accessor, &accessor.globals().detector);
+  }
 }
 
 define_event!{
@@ -394,6 +365,11 @@ define_event!{
       new.position.add_velocity(*accessor.now(), TIME_SHIFT, impulse).unwrap();
     });
   }
+  fn undo (&self, accessor: &mut Accessor, _: ()) {
+    for circle in accessor.globals().circles.iter() {
+      unset_circle (accessor, circle);
+    }
+  }
 }
 
 pub fn make_globals()-> <Basics as BasicsTrait>::Globals {
@@ -2,16 +2,15 @@
 use time_steward::support::time_functions::QuadraticTrajectory;
 use nalgebra::Vector2;
 //use time_steward::support::rounding_error_tolerant_math::right_shift_round_up;
-use std::marker::PhantomData;
 
-use time_steward::{DeterministicRandomId};
+use time_steward::{DeterministicRandomId, event, Listable};
 use time_steward::{DataHandleTrait, DataTimelineCellTrait, QueryResult, Basics as BasicsTrait};
 use time_steward::type_utils::{PersistentTypeId, PersistentlyIdentifiedType};
 use time_steward::type_utils::list_of_types::{ListedType};
 pub use time_steward::stewards::{simple_full as steward_module};
-use crate::steward_module::{TimeSteward, Event, DataHandle, DataTimelineCell, Accessor, EventAccessor, FutureCleanupAccessor, bbox_collision_detection_2d as collisions};
+use crate::steward_module::{TimeSteward, Event, DataHandle, DataTimelineCell, Accessor, EventAccessor, FutureCleanupAccessor, bbox_collision_detection as collisions};
 use crate::simple_timeline::{SimpleTimeline, query, tracking_query, tracking_query_ref, set, destroy};
-use self::collisions::{BoundingBox, NumDimensions, Detector};
+use self::collisions::{BoundingBox, Detector};
 use self::collisions::simple_grid::{SimpleGridDetector};
 
 use rand::Rng;
@@ -42,13 +41,10 @@ $visibility struct $Struct {$($contents)*}
 impl PersistentlyIdentifiedType for $Struct {
   const ID: PersistentTypeId = PersistentTypeId($id);
 }
+#[event(not_undoable)]
 impl Event for $Struct {
   type Steward = Steward;
-  type ExecutionData = ();
   fn execute <Accessor: EventAccessor <Steward = Self::Steward>> $($execute)*
-  fn undo <Accessor: FutureCleanupAccessor <Steward = Self::Steward>> (&self, _accessor: &mut Accessor, _: ()) {
-    unimplemented!()
-  }
 }
   }
 }
@@ -96,7 +92,7 @@ pub struct Basics {}
 impl BasicsTrait for Basics {
   type Time = Time;
   type Globals = Globals;
-  type Types = (ListedType <RelationshipChange>, ListedType <BoundaryChange>, ListedType <Initialize>, ListedType <Disturb>, collisions::simple_grid::Types <Space>);
+  type Types = (ListedType <Circle>, ListedType <Relationship>, ListedType <RelationshipChange>, ListedType <BoundaryChange>, ListedType <Initialize>, ListedType <Disturb>, collisions::simple_grid::Types <Space, 2>);
 }
 
 pub type Steward = steward_module::Steward <Basics>;
@@ -110,10 +106,10 @@ pub enum ObjectType {
 #[derive (Clone, PartialEq, Eq, Serialize, Deserialize, Debug)]
 pub struct Globals {
   pub circles: Vec<CircleHandle>,
-  pub detector: DataTimelineCell <SimpleTimeline <DataHandle <SimpleGridDetector<Space>>, Steward>>,
+  pub detector: DataTimelineCell <SimpleTimeline <DataHandle <SimpleGridDetector<Space, 2>>, Steward>>,
 }
 
-#[derive (Clone, PartialEq, Eq, Serialize, Deserialize, Debug)]
+#[derive (Clone, PartialEq, Eq, Serialize, Deserialize, Debug, Listable)]
 pub struct Circle {
   pub id: DeterministicRandomId,
   pub radius: SpaceCoordinate,
@@ -127,14 +123,14 @@ pub struct CircleVarying {
   pub relationships: Vec<RelationshipHandle>,
   pub boundary_induced_acceleration: Option <Vector2<SpaceCoordinate>>,
   pub next_boundary_change: Option <<Steward as TimeSteward>::EventHandle>,
-  pub collision_data: Option<collisions::simple_grid::DetectorDataPerObject<Space>>,
+  pub collision_data: Option<collisions::simple_grid::DetectorDataPerObject<Space, 2>>,
 }
 impl PersistentlyIdentifiedType for Circle {
   const ID: PersistentTypeId = PersistentTypeId(0xd711cc7240c71607);
 }
 type CircleHandle = DataHandle <Circle>;
 
-#[derive (Clone, PartialEq, Eq, Serialize, Deserialize, Debug)]
+#[derive (Clone, PartialEq, Eq, Serialize, Deserialize, Debug, Listable)]
 pub struct Relationship {
   pub circles: (CircleHandle, CircleHandle),
   pub varying: DataTimelineCell <SimpleTimeline <RelationshipVarying, Steward>>,
@@ -162,13 +158,12 @@ pub struct Space;
 impl PersistentlyIdentifiedType for Space {
   const ID: PersistentTypeId = PersistentTypeId(0x879511343e48addd);
 }
-impl collisions::Space for Space {
+impl collisions::Space<2> for Space {
   type Steward = Steward;
   type Object = Circle;
-  type DetectorDataPerObject = collisions::simple_grid::DetectorDataPerObject<Self>;
+  type DetectorDataPerObject = collisions::simple_grid::DetectorDataPerObject<Self, 2>;
   type UniqueId = DeterministicRandomId;
-  
-  const DIMENSIONS: NumDimensions = 2;
+  type WorldCoordinate = i64;
 
   // An Object generally has to store some opaque data for the collision detector.
   // It would normally include a DataHandle to a tree node.
@@ -179,11 +174,11 @@ impl collisions::Space for Space {
   fn set_detector_data<A: EventAccessor <Steward = Self::Steward>>(&self, accessor: &A, object: &DataHandle<Self::Object>, data: Option<Self::DetectorDataPerObject>) {
     modify (accessor, &object.varying, | varying | varying.collision_data = data);
   }
-  fn unique_id<A: EventAccessor <Steward = Self::Steward>>(&self, _accessor: &A, object: &DataHandle<Self::Object>)->Self::UniqueId {
+  fn unique_id<A: Accessor <Steward = Self::Steward>>(&self, _accessor: &A, object: &DataHandle<Self::Object>)->Self::UniqueId {
     object.id
   }
 
-  fn current_bounding_box<A: EventAccessor <Steward = Self::Steward>>(&self, accessor: &A, object: &DataHandle<Self::Object>)->BoundingBox <Self> {
+  fn current_bounding_box<A: EventAccessor <Steward = Self::Steward>>(&self, accessor: &A, object: &DataHandle<Self::Object>)->BoundingBox <2> {
     let varying = tracking_query (accessor, & object.varying);
     let center = varying.position.updated_by (accessor.now() - varying.last_change).unwrap().evaluate();
     BoundingBox {
@@ -191,10 +186,9 @@ impl collisions::Space for Space {
         [to_collision_space (center [0] - object.radius), to_collision_space (center [0] + object.radius)],
         [to_collision_space (center [1] - object.radius), to_collision_space (center [1] + object.radius)],
       ],
-      _marker: PhantomData
     }
   }
-  fn when_escapes<A: EventAccessor <Steward = Self::Steward>>(&self, accessor: &A, object: &DataHandle<Self::Object>, bounds: BoundingBox <Self>)->Option<<<Self::Steward as TimeSteward>::Basics as BasicsTrait>::Time> {
+  fn when_escapes<A: EventAccessor <Steward = Self::Steward>>(&self, accessor: &A, object: &DataHandle<Self::Object>, bounds: BoundingBox <2>)->Option<<<Self::Steward as TimeSteward>::Basics as BasicsTrait>::Time> {
     let varying = tracking_query (accessor, & object.varying);
     varying.position.approximately_when_escapes (
       varying.last_change.clone(),
@@ -240,6 +234,9 @@ impl collisions::Space for Space {
       });
     }
   }
+  fn undo_detector_changes<A: FutureCleanupAccessor <Steward = Self::Steward>>(&self, _accessor: &A, _object: &DataHandle<Self::Object>, _neighbors: &[DataHandle<Self::Object>]) {
+    panic!("space_game's events are not undoable, so neither are the detector's")
+  }
 }
 
 
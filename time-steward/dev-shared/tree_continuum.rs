@@ -39,7 +39,7 @@ use std::collections::HashSet;
 
 use array_ext::*;
 
-use time_steward::{DeterministicRandomId};
+use time_steward::{DeterministicRandomId, Listable};
 use time_steward::{DataHandleTrait, DataTimelineCellTrait, QueryResult};
 use time_steward::type_utils::{PersistentTypeId, PersistentlyIdentifiedType};
 use time_steward::type_utils::list_of_types::{ListedType};
@@ -61,7 +61,7 @@ pub enum FaceBoundaries<Physics: TreeContinuumPhysics> {
 }
 pub type SplitBoundary<Physics> = [BoundaryHandle<Physics>; 1<<(DIMENSIONS-1)];
 
-#[derive (Clone, PartialEq, Eq, Serialize, Deserialize, Debug)]
+#[derive (Clone, PartialEq, Eq, Serialize, Deserialize, Debug, Listable)]
 pub struct NodeData<Physics: TreeContinuumPhysics> {
   pub width: Distance,
   pub center: [Distance ; DIMENSIONS],
@@ -97,7 +97,7 @@ impl<Physics: TreeContinuumPhysics> PersistentlyIdentifiedType for NodeData<Phys
 pub type NodeFaces<Face> = [[Face; 2]; DIMENSIONS];
 type NodeBoundaries<Physics> = NodeFaces<FaceBoundaries<Physics>>;
 
-#[derive (Clone, PartialEq, Eq, Serialize, Deserialize, Debug)]
+#[derive (Clone, PartialEq, Eq, Serialize, Deserialize, Debug, Listable)]
 pub struct BoundaryData<Physics: TreeContinuumPhysics> {
   pub length: Distance,
   pub normal_dimension: usize,
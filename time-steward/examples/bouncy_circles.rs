@@ -89,7 +89,7 @@ fn main() {
     //let mut steward: s::Steward<Basics,
                                 //inefficient_flat::Steward<Basics>,
                                 //memoized_flat::Steward<Basics>> = s::Steward::from_constants(());
-    let mut steward: Steward = Steward::from_globals(make_globals());
+    let mut steward: Steward = Steward::from_globals(make_globals()).unwrap();
     steward.insert_fiat_event(0, DeterministicRandomId::new(&0), Initialize{}).unwrap();
     run (steward, |_,_|());
   }
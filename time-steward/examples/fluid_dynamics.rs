@@ -1,4 +1,3 @@
-#![feature (custom_attribute)]
 #![allow (unused_imports)]
 
 extern crate serde;
@@ -35,10 +34,10 @@ use std::collections::HashSet;
 
 //use dimensioned::
 
-use time_steward::{DeterministicRandomId};
+use time_steward::{DeterministicRandomId, event};
 use time_steward::{DataHandleTrait, DataTimelineCellTrait, Basics as BasicsTrait};
 use time_steward::type_utils::{PersistentTypeId, PersistentlyIdentifiedType};
-use time_steward::type_utils::list_of_types::{ListedType};
+use time_steward::type_utils::list_of_types::ListedType;
 use time_steward::stewards::{simple_full as steward_module};
 use crate::steward_module::{TimeSteward, ConstructibleTimeSteward, IncrementalTimeSteward, Event, DataHandle, DataTimelineCell, EventHandle, Accessor, EventAccessor, FutureCleanupAccessor, simple_timeline};
 use crate::simple_timeline::{SimpleTimeline, query, query_ref, set, destroy, just_destroyed};
@@ -416,6 +415,7 @@ struct MomentumChange {boundary: BoundaryHandle}
 impl PersistentlyIdentifiedType for MomentumChange {
   const ID: PersistentTypeId = PersistentTypeId(0x202c5edfe6f3332d);
 }
+#[event]
 impl Event for MomentumChange {
   type Steward = Steward;
   type ExecutionData = ();
@@ -473,6 +473,7 @@ struct MassChange {node: NodeHandle}
 impl PersistentlyIdentifiedType for MassChange {
   const ID: PersistentTypeId = PersistentTypeId(0x047cd6caa4fb5958);
 }
+#[event]
 impl Event for MassChange {
   type Steward = Steward;
   type ExecutionData = ();
@@ -506,6 +507,7 @@ struct AddMass {coordinates: [Distance; 2], amount: Mass, accumulation: MassPerT
 impl PersistentlyIdentifiedType for AddMass {
   const ID: PersistentTypeId = PersistentTypeId(0x90ef948b75373af9);
 }
+#[event]
 impl Event for AddMass {
   type Steward = Steward;
   type ExecutionData = ();
@@ -542,6 +544,7 @@ struct Initialize {}
 impl PersistentlyIdentifiedType for Initialize {
   const ID: PersistentTypeId = PersistentTypeId(0x8b6a3e1d0c1967f6);
 }
+#[event]
 impl Event for Initialize {
   type Steward = Steward;
   type ExecutionData = ();
@@ -582,7 +585,7 @@ struct Basics {}
 impl BasicsTrait for Basics {
   type Time = Time;
   type Globals = Globals;
-  type Types = (ListedType <MomentumChange>, ListedType <MassChange>, ListedType <Initialize>, ListedType <AddMass>);
+  type Types = (ListedType <tree_continuum::NodeData <Physics>>, ListedType <tree_continuum::BoundaryData <Physics>>, ListedType <MomentumChange>, ListedType <MassChange>, ListedType <Initialize>, ListedType <AddMass>);
 }
 
 use std::time::{Instant, Duration};
@@ -598,7 +601,7 @@ struct Vertex {
 implement_vertex!(Vertex, center, slope, direction, density);
 
 fn main() {
-  let mut steward: Steward = Steward::from_globals(make_globals());
+  let mut steward: Steward = Steward::from_globals(make_globals()).unwrap();
   steward.insert_fiat_event(0*TIME_UNIT, DeterministicRandomId::new(&0), Initialize{}).unwrap();
   run (steward, |_,_|());
 }
@@ -1,4 +1,3 @@
-#![feature (custom_attribute)]
 
 extern crate serde;
 #[macro_use]
@@ -27,10 +26,10 @@ macro_rules! printlnerr(
 use std::cmp::{min, max};
 
 
-use time_steward::{DeterministicRandomId};
+use time_steward::{DeterministicRandomId, event};
 use time_steward::{DataHandleTrait, DataTimelineCellTrait, Basics as BasicsTrait};
 use time_steward::type_utils::{PersistentTypeId, PersistentlyIdentifiedType};
-use time_steward::type_utils::list_of_types::{ListedType};
+use time_steward::type_utils::list_of_types::ListedType;
 use time_steward::stewards::{simple_full as steward_module};
 use crate::steward_module::{TimeSteward, ConstructibleTimeSteward, IncrementalTimeSteward, Event, DataHandle, DataTimelineCell, EventHandle, Accessor, EventAccessor, FutureCleanupAccessor, simple_timeline};
 use crate::simple_timeline::{SimpleTimeline, query, query_ref, set, just_destroyed};
@@ -533,6 +532,7 @@ struct TransferChange {boundary: BoundaryHandle}
 impl PersistentlyIdentifiedType for TransferChange {
   const ID: PersistentTypeId = PersistentTypeId(0xd6621e9cfad1c765);
 }
+#[event]
 impl Event for TransferChange {
   type Steward = Steward;
   type ExecutionData = ();
@@ -620,6 +620,7 @@ serialization_cheat!([][AddInk]);
 impl PersistentlyIdentifiedType for AddInk {
   const ID: PersistentTypeId = PersistentTypeId(0x3e6d029c3da8b9a2);
 }
+#[event]
 impl Event for AddInk {
   type Steward = Steward;
   type ExecutionData = ();
@@ -660,6 +661,7 @@ struct Initialize {}
 impl PersistentlyIdentifiedType for Initialize {
   const ID: PersistentTypeId = PersistentTypeId(0xf0d2d9134cfe9b49);
 }
+#[event]
 impl Event for Initialize {
   type Steward = Steward;
   type ExecutionData = ();
@@ -698,7 +700,7 @@ struct Basics {}
 impl BasicsTrait for Basics {
   type Time = Time;
   type Globals = Globals;
-  type Types = (ListedType <TransferChange>, ListedType <Initialize>, ListedType <AddInk>);
+  type Types = (ListedType <tree_continuum::NodeData <Physics>>, ListedType <tree_continuum::BoundaryData <Physics>>, ListedType <TransferChange>, ListedType <Initialize>, ListedType <AddInk>);
 }
 
 use std::time::{Instant, Duration};
@@ -714,7 +716,7 @@ struct Vertex {
 implement_vertex!(Vertex, center, slope, direction, density);
 
 fn main() {
-  let mut steward: Steward = Steward::from_globals(make_globals());
+  let mut steward: Steward = Steward::from_globals(make_globals()).unwrap();
   steward.insert_fiat_event(0, DeterministicRandomId::new(&0), Initialize{}).unwrap();
   run (steward, |_,_|());
 }
@@ -20,13 +20,14 @@ pub use emscripten_compatibility::canvas_click;
 
 
 use std::cmp::{min, max};
-use time_steward::{DeterministicRandomId};
+use time_steward::{DeterministicRandomId, event};
 use time_steward::{DataTimelineCellTrait, Basics as BasicsTrait};
 use time_steward::type_utils::{PersistentTypeId, PersistentlyIdentifiedType};
-use time_steward::type_utils::list_of_types::{ListedType};
+use time_steward::type_utils::list_of_types::ListedType;
 use time_steward::stewards::{simple_flat as steward_module};
-use steward_module::{TimeSteward, ConstructibleTimeSteward, IncrementalTimeSteward, Event, DataTimelineCell, EventHandle, Accessor, EventAccessor, FutureCleanupAccessor, simple_timeline};
+use steward_module::{TimeSteward, ConstructibleTimeSteward, IncrementalTimeSteward, Event, DataTimelineCell, EventHandle, Accessor, EventAccessor, FutureCleanupAccessor, simple_timeline, accumulator_timeline};
 use simple_timeline::{SimpleTimeline, query, set, unset};
+use accumulator_timeline::{Accumulator, add, total};
 
 
 /// i64 makes a good time type:
@@ -56,13 +57,14 @@ struct Globals {
 struct Cell {
   varying: DataTimelineCell <SimpleTimeline <CellVarying, Steward>>,
   transfers: [DataTimelineCell <SimpleTimeline <TransferVarying, Steward>>; 2],
+  /// The current rate of change of ink in this cell: the fiat accumulation rate, plus the transfers in, minus the transfers out.
+  accumulation_rate: DataTimelineCell <Accumulator <i64, Steward>>,
 }
 #[derive (Clone, PartialEq, Eq, Serialize, Deserialize, Debug)]
 struct CellVarying {
   /// The exact amount of ink present in this cell at the last time we updated it.
   last_change: Time,
   ink_at_last_change: i64,
-  fiat_accumulation_rate: i64,
 }
 #[derive (Clone, PartialEq, Eq, Serialize, Deserialize, Debug)]
 struct TransferVarying {
@@ -353,28 +355,15 @@ fn update_transfer_change_prediction <A: EventAccessor <Steward = Steward>> (acc
   set (accessor, & cells[0].transfers [dimension], transfer);
 }
 
-/// A utility function used above. Gets the current rate of change of ink in a cell,
-/// by summing up the current transfer rates.
+/// A utility function used above. Gets the current rate of change of ink in a cell.
+/// Every event that changes a transfer rate adds the change to the cells on both ends,
+/// so we don't have to sum up the transfers here.
 /// 
 /// Since this function doesn't make predictions, it only needs to require trait Accessor,
 /// which is a supertrait of EventAccessor. Thus, it could also be used in Events,
 /// and with Snapshots, if needed.
 fn get_accumulation_rate <A: Accessor <Steward = Steward >> (accessor: &A, coordinates: [i32; 2])->i64 {
-  let me = get_cell (accessor, coordinates).unwrap();
-  let mut result = query(accessor, &me.varying).fiat_accumulation_rate;
-  for dimension in 0..2 {
-    result -= query(accessor, &me.transfers[dimension]).rate;
-    
-    let mut neighbor_coordinates = coordinates;
-    neighbor_coordinates [dimension] -= 1;
-    
-    // Adjacent cells might NOT exist (they could be out of bounds).
-    // We could also have just done a bounds check on the coordinates, like above.
-    if let Some (neighbor) = get_cell (accessor, neighbor_coordinates) {
-      result += query(accessor, &neighbor.transfers[dimension]).rate;
-    }
-  }
-  result
+  total (accessor, & get_cell (accessor, coordinates).unwrap().accumulation_rate)
 }
 
 fn cell_index (globals: & Globals, coordinates: [i32; 2])->usize {
@@ -397,6 +386,7 @@ struct TransferChange {coordinates: [i32; 2], dimension: usize}
 impl PersistentlyIdentifiedType for TransferChange {
   const ID: PersistentTypeId = PersistentTypeId(0xd6621e9cfad1c765);
 }
+#[event]
 impl Event for TransferChange {
   type Steward = Steward;
   type ExecutionData = ();
@@ -442,12 +432,15 @@ impl Event for TransferChange {
     accumulation_rates [1] += physics_transfer_rate - transfer.rate;
     let difference_change_rate = accumulation_rates [0] - accumulation_rates [1];
     let stability_adjustment = difference_change_rate.signum()*(difference_change_rate.abs()/8 + 2);
-    transfer.rate = physics_transfer_rate + stability_adjustment/2;
+    let rate_change = physics_transfer_rate + stability_adjustment/2 - transfer.rate;
+    transfer.rate += rate_change;
         
     transfer.last_change = accessor.now().clone();
     transfer.accumulated_error = 0;
 
     set (accessor, & me.transfers [self.dimension], transfer);
+    add (accessor, & me.accumulation_rate, -rate_change);
+    add (accessor, & neighbor.accumulation_rate, rate_change);
     
     /*let (neighbor_last_change, mut neighbor) = query_cell (accessor, neighbor_coordinates).expect("neighbor doesn't exist for TransferChange?");
     
@@ -497,6 +490,7 @@ struct Initialize {}
 impl PersistentlyIdentifiedType for Initialize {
   const ID: PersistentTypeId = PersistentTypeId(0xf0d2d9134cfe9b49);
 }
+#[event]
 impl Event for Initialize {
   type Steward = Steward;
   type ExecutionData = ();
@@ -508,7 +502,6 @@ impl Event for Initialize {
         set (accessor, & cell.varying, CellVarying {
           last_change: 0,
           ink_at_last_change: 0,
-          fiat_accumulation_rate: 0,
         });
         for dimension in 0..2 {
           set (accessor, & cell.transfers [dimension], TransferVarying {
@@ -530,6 +523,7 @@ struct AddInk {coordinates: [i32; 2], amount: i64, accumulation: i64}
 impl PersistentlyIdentifiedType for AddInk {
   const ID: PersistentTypeId = PersistentTypeId(0x3e6d029c3da8b9a2);
 }
+#[event]
 impl Event for AddInk {
   type Steward = Steward;
   type ExecutionData = ();
@@ -545,8 +539,8 @@ impl Event for AddInk {
     let me = get_cell (accessor, self.coordinates).unwrap();
     let mut my_varying = query(accessor, &me.varying);
     my_varying.ink_at_last_change += self.amount;
-    my_varying.fiat_accumulation_rate += self.accumulation;
     set (accessor, & me.varying, my_varying);
+    add (accessor, & me.accumulation_rate, self.accumulation);
     // TODO: some of the ones at the corners don't need to be updated
     for offsx in -1..1 {
       for offsy in -1..1 {
@@ -570,6 +564,7 @@ fn make_globals()-> Globals {
         DataTimelineCell::new (SimpleTimeline::new ()),
         DataTimelineCell::new (SimpleTimeline::new ()),
       ],
+      accumulation_rate: DataTimelineCell::new (Accumulator::new (0)),
     });
   }
   Globals {
@@ -652,7 +647,7 @@ fn main() {
     }
   }*/
   {
-    let mut steward: Steward = Steward::from_globals(globals);
+    let mut steward: Steward = Steward::from_globals(globals).unwrap();
     steward.insert_fiat_event(0, DeterministicRandomId::new(&0), Initialize{}).unwrap();
     run (steward, |_,_|());
   }
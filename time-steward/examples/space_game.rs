@@ -38,15 +38,14 @@ struct Args {
 
 
 use std::time::{Instant};
-use std::marker::PhantomData;
 use glium::{DisplayBuild, Surface};
 
 use time_steward::{DeterministicRandomId};
 //use time_steward::stewards::{simple_full as steward_module};
 use crate::steward_module::{TimeSteward, ConstructibleTimeSteward, Accessor, simple_timeline};
 use crate::simple_timeline::{query};
-use crate::steward_module::bbox_collision_detection_2d::{BoundingBox, Detector};
-use crate::steward_module::bbox_collision_detection_2d::simple_grid::SimpleGridDetector;
+use crate::steward_module::bbox_collision_detection::{BoundingBox, Detector};
+use crate::steward_module::bbox_collision_detection::simple_grid::SimpleGridDetector;
 
 #[path = "../dev-shared/space_game.rs"] mod space_game;
 use crate::space_game::*;
@@ -92,7 +91,7 @@ fn main() {
     //let mut steward: s::Steward<Basics,
                                 //inefficient_flat::Steward<Basics>,
                                 //memoized_flat::Steward<Basics>> = s::Steward::from_constants(());
-    let mut steward: Steward = Steward::from_globals(make_globals());
+    let mut steward: Steward = Steward::from_globals(make_globals()).unwrap();
     steward.insert_fiat_event(0, DeterministicRandomId::new(&0), Initialize{}).unwrap();
     run (steward, |_,_|());
   }
@@ -190,7 +189,7 @@ gl_FragColor = vec4 (0.0, 0.0, 0.0, 0.0);
         .expect("steward failed to provide snapshot");
       stew.forget_before(& time);
       settle (&mut stew, time);
-      for handle in SimpleGridDetector::objects_near_box (& accessor, & query (& accessor, & accessor.globals().detector), BoundingBox {bounds: [[to_collision_space (-ARENA_SIZE/2), to_collision_space (ARENA_SIZE*3/2)],[to_collision_space (-ARENA_SIZE/2), to_collision_space (ARENA_SIZE*3/2)]],_marker: PhantomData}, None).iter() {
+      for handle in SimpleGridDetector::objects_near_box (& accessor, & query (& accessor, & accessor.globals().detector), BoundingBox {bounds: [[to_collision_space (-ARENA_SIZE/2), to_collision_space (ARENA_SIZE*3/2)],[to_collision_space (-ARENA_SIZE/2), to_collision_space (ARENA_SIZE*3/2)]]}, None).iter() {
         let circle = query (& accessor, &handle.varying);
         let position = circle.position.updated_by(accessor.now() - circle.last_change).unwrap().evaluate();
         let center = [position[0] as f32 / ARENA_SIZE as f32 - 0.5,
@@ -35,6 +35,7 @@ impl<T: Any + Debug + Clone + Eq + Serialize + DeserializeOwned> QueryResult for
 //To audit, we record all of the queries and query results. Then after each event that modifies one or more DataTimelines, we rerun all queries to those timelines made by still-valid future events. If any query has a different result than before, it's an error.
 
 // Given a query input, the function (time->query output) must be piecewise constant, changing only at times when modifications have been inserted.
+// Exception: a query whose result is a deterministic function of the stored data and the query time, like a TrajectoryTimeline's coefficient queries, may change continuously between modifications. Tracking such a query only records a dependency on the stored data, so the event that made it is invalidated when the data changes, not as time passes.
 // Event must implement undo. After an event is undone, there may not remain any modifications at the time of that event. It follows that after doing and then undoing an event, all query results immediately before the event are equal to the corresponding results immediately after the event.
 
 //These would be associated type constructors if Rust supported those: DataTimelineHandle, EventHandle, DynamicEventHandle, PredictionHandle
@@ -72,6 +73,7 @@ pub trait Basics:
 {
   type Time: SimulationStateData + Send + Sync + Clone + Ord + Hash;
   type Globals: SimulationStateData;
+  /// All the event and data types in the simulation. Long lists can be written with `type_utils::list_of_types!`.
   type Types: ListOfTypes;
   const MAX_ITERATION: IterationType = 65535;
 }
@@ -168,14 +170,57 @@ macro_rules! time_steward_steward_specific_api {
       );
       // audit: should produce the same subsequent query results as doing undo() and then execute()
       // implementing this is simply an optimization that may allow you to invalidate fewer things, so we default-implement it
+      // like execute(), it returns the data that a later undo() or re_execute() will receive
       fn re_execute<Accessor: FutureCleanupAccessor<Steward = Self::Steward>>(
         &self,
         accessor: &mut Accessor,
         execution_data: Self::ExecutionData,
-      ) {
+      ) -> Self::ExecutionData {
         self.undo(accessor, execution_data);
-        self.execute(accessor);
+        self.execute(accessor)
       }
+
+      /// Describes this event type to visitors of `Basics::Types`. Events implement `Listable` by calling this (`#[event]` does it for you); there's no reason to override it.
+      #[doc(hidden)]
+      fn register_event<R: $crate::type_utils::list_of_types::Registrar>(registrar: &mut R) {
+        registrar.data::<Self>();
+        registrar.extension(&EventRegistration::<Self::Steward>::new::<Self>());
+      }
+    }
+
+    /// What stewards and support modules need to know about one event type, collected from `Basics::Types` through `Registrar::extension()`.
+    #[doc(hidden)]
+    pub struct EventRegistration<Steward: TimeSteward> {
+      pub id: $crate::type_utils::PersistentTypeId,
+      /// Reads the rest of a serialized event handle; see the serialization module.
+      pub initialize_event_handle: fn(&mut Read, u64) -> ::bincode::Result<()>,
+      /// Decodes a bincode-serialized event and inserts it as a fiat event.
+      pub insert_serialized_fiat_event: fn(
+        &mut Steward,
+        <Steward::Basics as Basics>::Time,
+        DeterministicRandomId,
+        &[u8],
+      ) -> ::bincode::Result<Result<(), FiatEventOperationError>>,
+    }
+
+    impl<Steward: TimeSteward> EventRegistration<Steward> {
+      pub fn new<E: Event<Steward = Steward>>() -> Self {
+        EventRegistration {
+          id: E::ID,
+          initialize_event_handle: Steward::event_handle_initialize_function::<E>(),
+          insert_serialized_fiat_event: insert_serialized_fiat_event::<E>,
+        }
+      }
+    }
+
+    fn insert_serialized_fiat_event<E: Event>(
+      steward: &mut E::Steward,
+      time: <<E::Steward as TimeSteward>::Basics as Basics>::Time,
+      id: DeterministicRandomId,
+      data: &[u8],
+    ) -> ::bincode::Result<Result<(), FiatEventOperationError>> {
+      let event: E = ::bincode::deserialize(data)?;
+      Ok(steward.insert_fiat_event(time, id, event))
     }
 
     pub trait Accessor {
@@ -259,8 +304,23 @@ macro_rules! time_steward_steward_specific_api {
     }
 
     pub trait SnapshotAccessor: Accessor {
+      type IncrementalSerializer: $crate::serialization::IncrementalSerializer;
       /// note: SnapshotAccessor::serialize() matches TimeSteward::deserialize()
       fn serialize_into<W: Write>(&self, writer: &mut W) -> ::bincode::Result<()>;
+      /// Like serialize_into(), but starts from `roots` as well as the globals, and cuts off DataHandles that `policy` doesn't include.
+      /// note: SnapshotAccessor::serialize_partial_into() matches PartialSnapshot::deserialize_from()
+      fn serialize_partial_into<
+        W: Write,
+        Roots: ::serde::Serialize + ?Sized,
+        P: $crate::serialization::PartialSnapshotPolicy + 'static,
+      >(
+        &self,
+        writer: &mut W,
+        roots: &Roots,
+        policy: P,
+      ) -> ::bincode::Result<()>;
+      /// Like serialize_into(), but lets you do the work a little at a time; see `IncrementalSerializer`.
+      fn incremental_serializer(&self) -> Self::IncrementalSerializer;
     }
 
     pub trait TimeSteward: Any + Sized + Debug {
@@ -286,12 +346,21 @@ macro_rules! time_steward_steward_specific_api {
 
       fn valid_since(&self) -> ValidSince<<Self::Basics as Basics>::Time>;
       fn forget_before(&mut self, time: &<Self::Basics as Basics>::Time);
+
+      /// The function that deserializes events of type `E` in this steward's snapshots. Used by `EventRegistration`.
+      #[doc(hidden)]
+      fn event_handle_initialize_function<E: Event<Steward = Self>>() -> fn(&mut Read, u64) -> ::bincode::Result<()>;
     }
 
     /// A trait for TimeSteward types that can be initialized from just the initial physics data.
     /// Most TimeSteward types should implement this. Exceptions are types that can't function without certain extra runtime metadata
     pub trait ConstructibleTimeSteward: TimeSteward {
-      fn from_globals(globals: <Self::Basics as Basics>::Globals) -> Self;
+      /// Fails if two of the types in `Basics::Types` have the same PersistentTypeId, or if the globals contain a DataHandle whose type isn't listed there.
+      ///
+      /// This used to return `Self` directly, so existing callers need to handle the error (or `unwrap()` it). Every DataHandle type in the globals has to be listed now, usually with `#[derive(Listable)]` and a `ListedType<T>` entry.
+      fn from_globals(
+        globals: <Self::Basics as Basics>::Globals,
+      ) -> Result<Self, $crate::serialization::TypeRegistrationError>;
       /// note: SnapshotAccessor::serialize() matches TimeSteward::deserialize()
       fn deserialize_from<R: Read>(data: &mut R) -> ::bincode::Result<Self>;
     }
@@ -10,15 +10,16 @@ pub fn split_off_greater<K: Ord + Borrow<Q> + Clone, V, Q: Ord + ?Sized>(
 ) -> BTreeMap<K, V> {
   // BTreeMap::split_off() DOES remove this splitting key, while we want to NOT include that key.
   // TODO: will Rust eventually make this easier?
+  // Several keys may borrow as the same `split` (for instance, two distinct event handles with the same time), so move back all of them.
   let mut result = input.split_off(split);
-  let mut transfer = None;
-  if let Some(whoops) = result.iter().next() {
-    if whoops.0.borrow() == split {
-      transfer = Some(whoops.0.clone());
-    }
-  }
-  if let Some(key) = transfer {
-    input.insert(key, result.remove(split).unwrap());
+  while let Some(key) = result
+    .keys()
+    .next()
+    .filter(|key| (*key).borrow() == split)
+    .cloned()
+  {
+    let value = result.remove::<K>(&key).unwrap();
+    input.insert(key, value);
   }
   result
 }
@@ -29,16 +30,11 @@ pub fn split_off_greater_set<K: Ord + Borrow<Q>, Q: Ord + ?Sized>(
 ) -> BTreeSet<K> {
   // BTreeMap::split_off() DOES remove this splitting key, while we want to NOT include that key.
   // TODO: will Rust eventually make this easier?
+  // Several keys may borrow as the same `split` (for instance, two distinct event handles with the same time), so move back all of them.
   let mut result = input.split_off(split);
-  if let Some(whoops) = result.take(split) {
+  while let Some(whoops) = result.take(split) {
     input.insert(whoops);
   }
-  #[cfg(debug_assertions)]
-  {
-    if result.take(split).is_some() {
-      panic!("Some code broke the Ord/Borrow rules for BTreeSet calls")
-    }
-  }
   result
 }
 
@@ -60,24 +56,6 @@ pub fn split_off_greater_set<K: Ord + Borrow<Q>, Q: Ord + ?Sized>(
     result
   }}
 }*/
-macro_rules! downcast_ref {
-  ($input: expr, $T: ty, $($Trait:tt)*) => {{
-    let result: Option<&$T> = {
-      let input = $input;
-      if (*input).get_type_id() == ::std::any::TypeId::of::<$T>() {
-        //println!( "succeeded");
-        unsafe {
-          let raw: ::std::raw::TraitObject = ::std::mem::transmute(input);
-          Some(::std::mem::transmute(raw.data))
-        }
-      } else {
-        None
-      }
-    };
-    result
-  }};
-}
-
 #[doc(hidden)]
 #[macro_export]
 macro_rules! time_steward_common_impls_for_event_handle {
@@ -94,11 +72,6 @@ macro_rules! time_steward_common_impls_for_event_handle {
         self.extended_time().eq(other.extended_time())
       }
     }*/
-    impl<$($bounds)*> Ord for $($concrete)* {
-      fn cmp(&self, other: &Self) -> Ordering {
-        self.extended_time().cmp(other.extended_time())
-      }
-    }
     impl<$($bounds)*> PartialOrd for $($concrete)* {
       fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
         Some(self.cmp(other))
@@ -139,7 +112,24 @@ impl <$($bounds)*> PartialEq for $($concrete)* {
 macro_rules! time_steward_common_impls_for_handles {
   () => {
     time_steward_common_impls_for_event_handle! ([B: Basics] [EventHandle <B>] [B]);
-    time_steward_common_impls_for_uniquely_identified_handle! ([B: Basics] [EventHandle <B>] self => (self.extended_time().id): DeterministicRandomId);
+
+// Handles are equal only if they refer to the same event. Re-executing an event can create a new prediction with the same time as an old one, and the two must not compare equal, or code that compares data containing them would miss the difference. To keep Ord consistent with that, handles are ordered by time and then by address. A stale prediction and its replacement can have the same time, so lookups through `Borrow<ExtendedTime>` may match either of them, and `split_off_greater()` keeps all of them together.
+impl <B: Basics> Hash for EventHandle <B> {
+  fn hash <H: Hasher> (&self, state: &mut H) {
+    self.extended_time().id.hash (state);
+  }
+}
+impl <B: Basics> Eq for EventHandle <B> {}
+impl <B: Basics> PartialEq for EventHandle <B> {
+  fn eq(&self, other: &Self) -> bool {
+    ::std::rc::Rc::ptr_eq (&self.data, &other.data)
+  }
+}
+impl <B: Basics> Ord for EventHandle <B> {
+  fn cmp(&self, other: &Self) -> Ordering {
+    self.extended_time().cmp(other.extended_time()).then_with (|| ::std::rc::Rc::as_ptr (&self.data).cmp (&::std::rc::Rc::as_ptr (&other.data)))
+  }
+}
 
 impl <T: SimulationStateData + $crate::type_utils::PersistentlyIdentifiedType> ::std::fmt::Debug for DataHandle <T> {
   fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
@@ -148,7 +138,7 @@ impl <T: SimulationStateData + $crate::type_utils::PersistentlyIdentifiedType> :
 }
 impl <B: Basics> ::std::fmt::Debug for EventHandle <B> {
   fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
-    write!(f, "DataHandle(@{:p})", self.data)
+    write!(f, "{} @ t={:?}", $crate::type_utils::registry::short_type_name (self.data.data.type_name()), self.extended_time().base)
   }
 }
 
@@ -20,13 +20,6 @@
 //
 //
 
-#![feature(
-  unboxed_closures,
-  specialization,
-  never_type,
-  raw,
-  try_trait
-)]
 // #![feature (shared)]
 // #![feature (plugin, custom_derive)]
 // #![plugin (serde_macros)]
@@ -58,6 +51,8 @@ extern crate smallvec;
 
 pub extern crate time_steward_integer_math;
 pub extern crate time_steward_type_utils as type_utils;
+/// `#[derive(PersistentlyIdentifiedType)]`, `#[derive(Listable)]` and `#[event]`; see the `time_steward_derive` crate.
+pub use time_steward_derive::{event, Listable, PersistentlyIdentifiedType};
 
 macro_rules! printlnerr(
     ($($arg:tt)*) => { {use std::io::Write;
@@ -105,7 +100,23 @@ pub mod support {
   #[macro_use]
   pub mod simple_timeline;
   #[macro_use]
+  pub mod ordered_map_timeline;
+  #[macro_use]
+  pub mod accumulator_timeline;
+  #[macro_use]
+  pub mod priority_queue_timeline;
+  #[macro_use]
+  pub mod trajectory_timeline;
+  #[macro_use]
+  pub mod data_timeline_conformance;
+  #[macro_use]
   pub mod bbox_collision_detection;
+  #[macro_use]
+  pub mod contact_prediction;
+  #[macro_use]
+  pub mod autosave;
+  #[macro_use]
+  pub mod journal;
 }
 
 #[macro_use]
@@ -4,6 +4,57 @@
 //! * DataTimelineHandle objects get special consideration, to support DAGs and cyclic data structures.
 //! * The serialization needs to not block other operations for more than O(1) time at a time.
 //! * The serialization must be lossless and platform-independent. For this reason, we always use bincode in low-endian mode.
+//!
+//! Besides full snapshots, a snapshot can also be exported *partially*, starting from a chosen set of roots as well as the globals. This is meant for things like spectator clients and area-of-interest networking, where the receiver only needs to see part of the simulation. A `PartialSnapshotPolicy` decides where to cut off the exported region; DataHandles outside the region are written as opaque stubs.
+//!
+//! Serialized types are identified by their `PersistentTypeId`s, so those IDs must be unique among the types listed in `Basics::Types`. Stewards check this when they are constructed (see `TypeRegistry`).
+
+use crate::type_utils::PersistentTypeId;
+pub use crate::type_utils::registry::{TypeRegistrationError, TypeRegistry};
+
+/// Describes a DataHandle that was reached while exporting a partial snapshot.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct PartialSnapshotTarget {
+  pub type_id: PersistentTypeId,
+  /// The number of handles that were followed to get here. Handles that appear directly in the roots or the globals have depth 0.
+  pub depth: usize,
+}
+
+/// Decides which DataHandles get serialized in full during a partial snapshot export.
+///
+/// Handles that aren't included are serialized as stubs, and nothing that is only reachable through them gets serialized at all. When the partial snapshot is loaded, stubs are filled in with `Default::default()`, so the types of cut-off handles must implement `Default`.
+///
+/// EventHandles are always included, because there's no meaningful placeholder for an event.
+pub trait PartialSnapshotPolicy {
+  fn include(&mut self, target: &PartialSnapshotTarget) -> bool;
+}
+impl<F: FnMut(&PartialSnapshotTarget) -> bool> PartialSnapshotPolicy for F {
+  fn include(&mut self, target: &PartialSnapshotTarget) -> bool {
+    (self)(target)
+  }
+}
+
+/// Includes every DataHandle that is at most this many handles away from the roots.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct MaxDepth(pub usize);
+impl PartialSnapshotPolicy for MaxDepth {
+  fn include(&mut self, target: &PartialSnapshotTarget) -> bool {
+    target.depth <= self.0
+  }
+}
+
+/// Serializes a snapshot a little bit at a time.
+///
+/// TimeSteward handles are generally `Rc`-based, so a snapshot can't be sent to another thread to be serialized there. Instead, an IncrementalSerializer lets you spread the work over many short steps, interleaved with running the steward. Each step serializes at most one handle target. The result is plain bytes, which *can* be handed to another thread (see `support::autosave`).
+pub trait IncrementalSerializer {
+  /// Serialize one more piece of the snapshot. Returns true once the whole snapshot has been serialized.
+  fn step(&mut self) -> ::bincode::Result<bool>;
+  fn is_finished(&self) -> bool;
+  /// The serialized snapshot, in the same format as `SnapshotAccessor::serialize_into()`.
+  ///
+  /// Panics if the serialization hasn't finished yet.
+  fn into_bytes(self) -> Vec<u8>;
+}
 
 #[doc(hidden)]
 #[macro_export]
@@ -11,11 +62,14 @@ macro_rules! time_steward_serialization_impls {
   () => {
   
 
+  /// Receives the handles found by `TimeStewardStructuresVisitable::visit_all()`.
+  ///
+  /// DataHandles and DataTimelineCells are passed as `&Any`, because the visiting is done by a serializer, and Rust can't hand their concrete types through it; downcast them to `DataHandle <T>` or `DataTimelineCell <T>` to get at them.
   #[allow (unused_variables)]
   pub trait TimeStewardStructuresVisitor <Steward: TimeSteward> {
-    fn visit_data_handle <T: SimulationStateData + PersistentlyIdentifiedType> (&mut self, handle: & DataHandle <T>) {}
+    fn visit_data_handle (&mut self, handle: &Any, type_id: crate::type_utils::PersistentTypeId, type_name: &'static str) {}
     fn visit_event_handle (&mut self, handle: & Steward::EventHandle) {}
-    fn visit_data_timeline_cell <T: DataTimeline> (&mut self, cell: & DataTimelineCell <T>) {}
+    fn visit_data_timeline_cell (&mut self, cell: &Any) {}
   }
 
   pub trait TimeStewardStructuresVisitable <Steward: TimeSteward> {
@@ -24,67 +78,70 @@ macro_rules! time_steward_serialization_impls {
 
   impl <Steward: TimeSteward, Data: SimulationStateData> TimeStewardStructuresVisitable<Steward> for Data {
     fn visit_all <T: TimeStewardStructuresVisitor <Steward>>(&self, visitor: T) {
-      self.serialize (&mut TimeStewardStructuresVisitingSerializeHack::<T,Steward>(visitor, PhantomData)).unwrap();
+      let was_visiting = VISITING.with (| visiting | visiting.replace (true));
+      let result = self.serialize (&mut TimeStewardStructuresVisitingSerializeHack::<T,Steward>(visitor, PhantomData));
+      VISITING.with (| visiting | visiting.set (was_visiting));
+      result.unwrap()
     }
   }
 
   use serde::ser;
   use std::fmt::{self,Display};
   use std::marker::PhantomData;
-  use crate::type_utils::static_downcast;
-  use crate::type_utils::list_of_types::{ListOfTypes, ListOfTypesVisitor};     
+  use crate::type_utils::list_of_types::{ListOfTypes, ListOfTypesVisitor, Listable, Registrar, SerializableData};
   struct TimeStewardStructuresVisitingSerializeHack<T, Steward>(T, PhantomData<Steward>);
 
-  trait MaybeVisitSerializeHack <Steward: TimeSteward> {
-    fn visit_event_handle (&mut self, handle: & Steward::EventHandle)->bool;
-    fn visit_data_timeline_cell <T: DataTimeline> (&mut self, cell: & DataTimelineCell <T>)->bool;
+  // A generic Serialize impl can't tell whether its serializer is the visiting one.
+  // So while a visit is in progress, handles leave a pointer to themselves in VISITED_STRUCTURE,
+  // then serialize a unit struct with this name, which only the visiting serializer acts on.
+  const VISITED_STRUCTURE_MARKER: &str = "TimeStewardVisitedStructure";
+  #[derive (Copy, Clone)]
+  enum VisitedStructure {
+    DataHandle (*const Any, crate::type_utils::PersistentTypeId, &'static str),
+    EventHandle (*const Any),
+    DataTimelineCell (*const Any),
   }
-  impl <Steward: TimeSteward, MaybeVisitor> MaybeVisitSerializeHack <Steward> for MaybeVisitor {
-    default fn visit_event_handle (&mut self, _handle: & Steward::EventHandle)->bool {false}
-    default fn visit_data_timeline_cell <T: DataTimeline> (&mut self, _cell: & DataTimelineCell <T>)->bool {false}
-  }
-  impl <'a, Steward: TimeSteward, Visitor: TimeStewardStructuresVisitor <Steward>> MaybeVisitSerializeHack <Steward> for &'a mut TimeStewardStructuresVisitingSerializeHack<Visitor, Steward> {
-    fn visit_event_handle (&mut self, handle: & Steward::EventHandle)->bool {
-      TimeStewardStructuresVisitor::<Steward>::visit_event_handle (&mut self.0, handle);
-      true
-    }
-    fn visit_data_timeline_cell <T: DataTimeline> (&mut self, cell: & DataTimelineCell <T>)->bool {
-      TimeStewardStructuresVisitor::<Steward>::visit_data_timeline_cell (&mut self.0, cell);
-      true
-    }
-  }
-
-  trait MaybeVisitSerializeHackUntyped {
-    fn visit_data_handle <T: SimulationStateData + PersistentlyIdentifiedType> (&mut self, handle: & DataHandle <T>)->bool;
-  }
-  impl <MaybeVisitor> MaybeVisitSerializeHackUntyped for MaybeVisitor {
-    default fn visit_data_handle <T: SimulationStateData + PersistentlyIdentifiedType> (&mut self, _handle: & DataHandle <T>)->bool {false}
-  }
-  impl <'a, Steward: TimeSteward, Visitor: TimeStewardStructuresVisitor <Steward>> MaybeVisitSerializeHackUntyped for &'a mut TimeStewardStructuresVisitingSerializeHack<Visitor, Steward> {
-    fn visit_data_handle <T: SimulationStateData + PersistentlyIdentifiedType> (&mut self, handle: & DataHandle <T>)->bool {
-      TimeStewardStructuresVisitor::<Steward>::visit_data_handle (&mut self.0, handle);
-      true
+  thread_local! {
+    static VISITING: Cell<bool> = Cell::new (false);
+    static VISITED_STRUCTURE: Cell<Option <VisitedStructure>> = Cell::new (None);
+  }
+  fn visiting()->bool {
+    VISITING.with (| visiting | visiting.get())
+  }
+  // The pointer is only used while the handle is still being serialized, so it can't dangle.
+  fn visit_structure <S: $crate::serde::Serializer> (serializer: S, visited: VisitedStructure)->Result <S::Ok, S::Error> {
+    VISITED_STRUCTURE.with (| cell | cell.set (Some (visited)));
+    serializer.serialize_unit_struct (VISITED_STRUCTURE_MARKER)
+  }
+  impl <Steward: TimeSteward, Visitor: TimeStewardStructuresVisitor <Steward>> TimeStewardStructuresVisitingSerializeHack<Visitor, Steward> {
+    fn visit_structure (&mut self, visited: VisitedStructure) {
+      unsafe {
+        match visited {
+          VisitedStructure::DataHandle (handle, type_id, type_name) => self.0.visit_data_handle (&*handle, type_id, type_name),
+          VisitedStructure::EventHandle (handle) => if let Some (handle) = (*handle).downcast_ref::<Steward::EventHandle>() {
+            self.0.visit_event_handle (handle)
+          },
+          VisitedStructure::DataTimelineCell (cell) => self.0.visit_data_timeline_cell (&*cell),
+        }
+      }
     }
   }
 
-
-
-  #[allow (unreachable_code, unreachable_patterns)]
   #[derive (Debug)]
-  struct NeverError(!);
+  enum NeverError {}
   impl ser::Error for NeverError {
-    fn custom<T: Display>(_msg: T) -> Self {
-        panic!()
+    fn custom<T: Display>(msg: T) -> Self {
+        panic!("{}", msg)
     }
 }
 impl ::std::error::Error for NeverError {
     fn description(&self) -> &str {
-        unreachable!()
+        match *self {}
     }
 }
 impl Display for NeverError {
     fn fmt(&self, _formatter: &mut fmt::Formatter) -> fmt::Result {
-        unreachable!()
+        match *self {}
     }
 }
   impl<'a, Steward: TimeSteward, Visitor: TimeStewardStructuresVisitor <Steward>> ser::Serializer for &'a mut TimeStewardStructuresVisitingSerializeHack<Visitor, Steward> {
@@ -117,7 +174,14 @@ impl Display for NeverError {
     fn serialize_some<T>(self, value: &T) -> Result<(),NeverError>
         where T: ?Sized + Serialize { value.serialize(self) }
     fn serialize_unit(self) -> Result<(),NeverError> { Ok(()) }
-    fn serialize_unit_struct(self, _name: &'static str) -> Result<(),NeverError> { Ok(()) }
+    fn serialize_unit_struct(self, name: &'static str) -> Result<(),NeverError> {
+        if name == VISITED_STRUCTURE_MARKER {
+            if let Some (visited) = VISITED_STRUCTURE.with (| cell | cell.take()) {
+                self.visit_structure (visited);
+            }
+        }
+        Ok(())
+    }
     fn serialize_unit_variant(
         self,
         _name: &'static str,
@@ -217,11 +281,15 @@ impl<'a, Steward: TimeSteward, Visitor: TimeStewardStructuresVisitor <Steward>>
 
 
   
-  fn bincode_error_to_generic <T, U> (result: $crate::bincode::Result <T>)->Result <T, U> {
-    result.map_err (|e| static_downcast::<$crate::bincode::Error, U>(e).expect("Tried to do TimeSteward serialization with non-bincode serializer/deserializer. TimeSteward serialization only supports bincode"))
+  // TimeSteward serialization only supports bincode, but serde's error types are generic, so errors cross between them as messages.
+  fn bincode_error_to_serializer <T, U: ser::Error> (result: $crate::bincode::Result <T>)->Result <T, U> {
+    result.map_err (|e| U::custom (e))
+  }
+  fn bincode_error_to_deserializer <T, U: $crate::serde::de::Error> (result: $crate::bincode::Result <T>)->Result <T, U> {
+    result.map_err (|e| U::custom (e))
   }
-  fn generic_error_to_bincode <T, U> (result: Result <T, U>)->$crate::bincode::Result <T> {
-    result.map_err (|e| static_downcast::<U, $crate::bincode::Error>(e).expect("Tried to do TimeSteward serialization with non-bincode serializer/deserializer. TimeSteward serialization only supports bincode"))
+  fn generic_error_to_bincode <T, U: Display> (result: Result <T, U>)->$crate::bincode::Result <T> {
+    result.map_err (|e| $crate::bincode::Error::custom (e))
   }
 
   use $crate::serde::{Serialize};
@@ -232,6 +300,7 @@ impl<'a, Steward: TimeSteward, Visitor: TimeStewardStructuresVisitor <Steward>>
     DataHandleData(u64, crate::type_utils::PersistentTypeId),
     EventHandleData(u64, crate::type_utils::PersistentTypeId),
     Finished,
+    DataHandleStub(u64, crate::type_utils::PersistentTypeId),
   }
 
   trait SerializeTargetInto {
@@ -246,16 +315,37 @@ impl<'a, Steward: TimeSteward, Visitor: TimeStewardStructuresVisitor <Steward>>
       $crate::bincode::serialize_into (writer, &*self.data, $crate::bincode::Infinite)
     }
   }
+  struct StubTarget (crate::type_utils::PersistentTypeId);
+  impl SerializeTargetInto for StubTarget {
+    fn serialize_target_into(&self, writer: &mut Write, object_id: u64)->$crate::bincode::Result <()> {
+      $crate::bincode::serialize_into (writer, & SerializationElement::DataHandleStub (object_id, self.0), $crate::bincode::Infinite)
+    }
+  }
+  // Handles can be referred to before their targets are deserialized, so they start out pointing at uninitialized memory, which the target is written into later.
+  fn uninitialized_rc <T> ()->Rc <T> {
+    unsafe {Rc::from_raw (Rc::into_raw (Rc::new (::std::mem::MaybeUninit::<T>::uninit())) as *const T)}
+  }
   fn data_handle_initialize_function <T: SimulationStateData + PersistentlyIdentifiedType>(reader: &mut Read, object_id: u64)->$crate::bincode::Result <()> {
+    // don't hold the context while deserializing the target, because any handles inside it will need the context again
+    let handle = with_deserialization_context (| context | {
+      let handle = context.find_handle::<_, DataHandle <T>> (object_id, || {
+        Box::new (DataHandle{data:uninitialized_rc::<T>()})
+      })?.clone();
+      context.uninitialized_handles.remove(&object_id);
+      Ok(handle)
+    })?;
+    let target: T = $crate::bincode::deserialize_from (reader, $crate::bincode::Infinite)?;
+    unsafe {::std::ptr::write (Rc::as_ptr (&handle.data) as *mut T, target);}
+    Ok(())
+  }
+  fn data_handle_stub_function <T: SimulationStateData + PersistentlyIdentifiedType + Default>(object_id: u64)->$crate::bincode::Result <()> {
     with_deserialization_context (| context | {
       context.uninitialized_handles.remove(&object_id);
       let handle = context.find_handle::<_, DataHandle <T>> (object_id, || {
-        Box::new (DataHandle{data:Rc::<T>::new(unsafe {::std::mem::uninitialized()})})
-      })?;
-      unsafe {::std::ptr::write (
-        &*handle.data as *const T as *mut T,
-        $crate::bincode::deserialize_from (reader, $crate::bincode::Infinite)?
-      );}
+        Box::new (DataHandle{data:uninitialized_rc::<T>()})
+      })?.clone();
+      unsafe {::std::ptr::write (Rc::as_ptr (&handle.data) as *mut T, T::default());}
+      context.stubs.push ((&*handle.data as *const T as usize, Box::new (handle) as Box <Any>));
       Ok(())
     })
   }
@@ -273,41 +363,54 @@ impl<'a, Steward: TimeSteward, Visitor: TimeStewardStructuresVisitor <Steward>>
     }
   }
   fn event_handle_initialize_function <B: Basics, T: Event <Steward = Steward <B> >>(reader: &mut Read, object_id: u64)->$crate::bincode::Result <()> {
-    with_deserialization_context (| context | {
-      context.uninitialized_handles.remove(&object_id);
+    // as with DataHandles, the event data may contain handles, so don't hold the context while deserializing it
+    let (handle, now) = with_deserialization_context (| context | {
       let now = context.time.downcast_ref::<ExtendedTime <B>>().unwrap().clone();
       let handle = context.find_handle::<_, <T::Steward as TimeSteward>::EventHandle> (object_id, || {
-        let handle_box = Box::<<T::Steward as TimeSteward>::EventHandle>::new (EventHandle { data: Rc::new(unsafe {::std::mem::uninitialized()})});
+        let handle_box = Box::<<T::Steward as TimeSteward>::EventHandle>::new (EventHandle { data: uninitialized_rc()});
         handle_box.data.links.set(0);
         handle_box as Box<Any>
       })?.clone();
-      let time: ExtendedTime <B> = ::bincode::deserialize_from (reader, $crate::bincode::Infinite)?;
-      let in_future = time > now;
-      if in_future {context.predictions.insert (object_id);}
-      let data: T = ::bincode::deserialize_from (reader, $crate::bincode::Infinite)?;
-      unsafe {::std::ptr::write (
-        &*handle.data as *const EventInner<B> as *mut EventInner<B>,
-        deserialization_create_event_inner(time.clone(), data, in_future, handle.data.links.clone())
-      );}
-      Ok(())
-    })
+      context.uninitialized_handles.remove(&object_id);
+      Ok((handle, now))
+    })?;
+    let time: ExtendedTime <B> = ::bincode::deserialize_from (reader, $crate::bincode::Infinite)?;
+    let in_future = time > now;
+    if in_future {
+      with_deserialization_context (| context | {context.predictions.insert (object_id); Ok(())})?;
+    }
+    let data: T = ::bincode::deserialize_from (reader, $crate::bincode::Infinite)?;
+    unsafe {::std::ptr::write (
+      Rc::as_ptr (&handle.data) as *mut EventInner<B>,
+      deserialization_create_event_inner(time.clone(), data, in_future, handle.data.links.clone())
+    );}
+    Ok(())
   }
 
   struct SerializationContext {
     snapshot: Box <Any>,
+    types: $crate::serialization::TypeRegistry,
     handle_targets_observed: HashMap <usize, u64>,
-    handles_to_serialize_target: Vec<(u64, Box <SerializeTargetInto>)>,
+    handles_to_serialize_target: ::std::collections::VecDeque<(u64, usize, Box <SerializeTargetInto>)>,
     next_object_identifier: u64,
+    // None for full snapshots, which include everything
+    policy: Option <Box <$crate::serialization::PartialSnapshotPolicy>>,
+    // the depth that any newly observed handles will be recorded at
+    current_depth: usize,
   }
   struct DeserializationContext {
     time: Box <Any>,
+    types: $crate::serialization::TypeRegistry,
     data_handle_initialize_functions: ::std::collections::HashMap <
       crate::type_utils::PersistentTypeId, fn (&mut Read, u64)->$crate::bincode::Result <()>>,
     event_handle_initialize_functions: ::std::collections::HashMap <
       crate::type_utils::PersistentTypeId, fn (&mut Read, u64)->$crate::bincode::Result <()>>,
+    data_handle_stub_functions: ::std::collections::HashMap <
+      crate::type_utils::PersistentTypeId, fn (u64)->$crate::bincode::Result <()>>,
     handles: ::std::collections::HashMap <u64, Box <Any>>,
     uninitialized_handles: ::std::collections::HashSet <u64>,
     predictions: ::std::collections::HashSet <u64>,
+    stubs: Vec <(usize, Box <Any>)>,
     success: bool,
   }
 
@@ -349,14 +452,42 @@ impl<'a, Steward: TimeSteward, Visitor: TimeStewardStructuresVisitor <Steward>>
       let handle_targets_observed = &mut self.handle_targets_observed;
       let handles_to_serialize_target = &mut self.handles_to_serialize_target;
       let next_object_identifier = &mut self.next_object_identifier;
+      let depth = self.current_depth;
       let object_identifier = *handle_targets_observed.entry (pointer).or_insert_with (|| {
         let result = *next_object_identifier;
         *next_object_identifier += 1;
-        handles_to_serialize_target.push((result, create_serializable()));
+        handles_to_serialize_target.push_back((result, depth, create_serializable()));
         result
       });
       Ok(object_identifier)
     }
+    fn find_data_handle <F: FnOnce()->Box <SerializeTargetInto>> (&mut self, pointer: usize, type_id: crate::type_utils::PersistentTypeId, create_serializable: F)->Result<u64, $crate::bincode::Error> {
+      let handle_targets_observed = &mut self.handle_targets_observed;
+      let handles_to_serialize_target = &mut self.handles_to_serialize_target;
+      let next_object_identifier = &mut self.next_object_identifier;
+      let policy = &mut self.policy;
+      let depth = self.current_depth;
+      let object_identifier = *handle_targets_observed.entry (pointer).or_insert_with (|| {
+        let result = *next_object_identifier;
+        *next_object_identifier += 1;
+        let target = $crate::serialization::PartialSnapshotTarget {type_id, depth};
+        if policy.as_mut().map_or (true, | policy | policy.include (&target)) {
+          handles_to_serialize_target.push_back((result, depth, create_serializable()));
+        }
+        else {
+          handles_to_serialize_target.push_back((result, depth, Box::new (StubTarget (type_id))));
+        }
+        result
+      });
+      Ok(object_identifier)
+    }
+    // Targets are serialized breadth-first, so that each handle is first observed at its smallest depth.
+    fn next_target (&mut self)->Option <(u64, Box <SerializeTargetInto>)> {
+      self.handles_to_serialize_target.pop_front().map (| (object_identifier, depth, handle_box) | {
+        self.current_depth = depth + 1;
+        (object_identifier, handle_box)
+      })
+    }
   }
 
   impl DeserializationContext {
@@ -376,10 +507,11 @@ impl<'a, Steward: TimeSteward, Visitor: TimeStewardStructuresVisitor <Steward>>
   }
 
   impl <T: SimulationStateData + PersistentlyIdentifiedType> $crate::serde::Serialize for DataHandle <T> {
-    fn serialize <S: $crate::serde::Serializer> (&self, mut serializer: S)->Result <S::Ok, S::Error> {
-      if MaybeVisitSerializeHackUntyped::visit_data_handle(&mut serializer, self) {return serializer.serialize_none()}
-      bincode_error_to_generic(with_serialization_context (| context | {
-        let object_identifier = context.find_handle::<_, DataHandle <T>> (&*self.data as *const _ as usize, || {
+    fn serialize <S: $crate::serde::Serializer> (&self, serializer: S)->Result <S::Ok, S::Error> {
+      if visiting() {return visit_structure (serializer, VisitedStructure::DataHandle (self as *const Self as *const Any, T::ID, ::std::any::type_name::<T>()))}
+      bincode_error_to_serializer(with_serialization_context (| context | {
+        context.types.check_listed::<T>().map_err (| error | $crate::bincode::Error::custom (error.to_string()))?;
+        let object_identifier = context.find_data_handle (&*self.data as *const _ as usize, T::ID, || {
           Box::new (self.clone())
         })?;
         generic_error_to_bincode(object_identifier.serialize (serializer))
@@ -388,19 +520,20 @@ impl<'a, Steward: TimeSteward, Visitor: TimeStewardStructuresVisitor <Steward>>
   }
   impl <'a, T: SimulationStateData + PersistentlyIdentifiedType> $crate::serde::Deserialize <'a> for DataHandle <T> {
     fn deserialize <D: $crate::serde::Deserializer<'a>> (deserializer: D)->Result <Self, D::Error> {
-      bincode_error_to_generic(with_deserialization_context (| context | {
+      bincode_error_to_deserializer(with_deserialization_context (| context | {
         let object_identifier = generic_error_to_bincode(u64::deserialize (deserializer))?;
         Ok(context.find_handle::<_, DataHandle <T>> (object_identifier, || {
-          Box::<DataHandle <T>>::new (DataHandle{data:Rc::new(unsafe {::std::mem::uninitialized()})}) as Box<Any>
+          Box::<DataHandle <T>>::new (DataHandle{data:uninitialized_rc::<T>()}) as Box<Any>
         })?.clone())
       }))
     }
   }
 
   impl <B: Basics> $crate::serde::Serialize for EventHandle <B> {
-    fn serialize <S: $crate::serde::Serializer> (&self, mut serializer: S)->Result <S::Ok, S::Error> {
-      if MaybeVisitSerializeHack::<Steward<B>>::visit_event_handle(&mut serializer, self) {return serializer.serialize_none()}
-      bincode_error_to_generic(with_serialization_context (| context | {
+    fn serialize <S: $crate::serde::Serializer> (&self, serializer: S)->Result <S::Ok, S::Error> {
+      if visiting() {return visit_structure (serializer, VisitedStructure::EventHandle (self as *const Self as *const Any))}
+      bincode_error_to_serializer(with_serialization_context (| context | {
+        context.types.check_listed_dynamic (self.data.data.persistent_type_id(), self.data.data.type_name()).map_err (| error | $crate::bincode::Error::custom (error.to_string()))?;
         let object_identifier = context.find_handle::<_, EventHandle <B>> (&*self.data as *const _ as usize, || {
           Box::new (self.clone())
         })?;
@@ -410,10 +543,10 @@ impl<'a, Steward: TimeSteward, Visitor: TimeStewardStructuresVisitor <Steward>>
   }
   impl <'a, B: Basics> $crate::serde::Deserialize <'a> for EventHandle <B> {
     fn deserialize <D: $crate::serde::Deserializer<'a>> (deserializer: D)->Result <Self, D::Error> {
-      bincode_error_to_generic(with_deserialization_context (| context | {
+      bincode_error_to_deserializer(with_deserialization_context (| context | {
         let object_identifier = generic_error_to_bincode(u64::deserialize (deserializer))?;
         let handle = context.find_handle::<_, EventHandle <B>> (object_identifier, || {
-          let handle_box = Box::<EventHandle <B>>::new (EventHandle { data: Rc::new(unsafe {::std::mem::uninitialized()})});
+          let handle_box = Box::<EventHandle <B>>::new (EventHandle { data: uninitialized_rc()});
           handle_box.data.links.set(0);
           handle_box as Box<Any>
         })?.clone();
@@ -424,9 +557,9 @@ impl<'a, Steward: TimeSteward, Visitor: TimeStewardStructuresVisitor <Steward>>
   }
 
   impl <T: DataTimeline> $crate::serde::Serialize for DataTimelineCell <T> {
-    fn serialize <S: $crate::serde::Serializer> (&self, mut serializer: S)->Result <S::Ok, S::Error> {
-      if MaybeVisitSerializeHack::<Steward<T::Basics>>::visit_data_timeline_cell(&mut serializer, self) {return serializer.serialize_none()}
-      let foo = bincode_error_to_generic(with_serialization_context (| context | {
+    fn serialize <S: $crate::serde::Serializer> (&self, serializer: S)->Result <S::Ok, S::Error> {
+      if visiting() {return visit_structure (serializer, VisitedStructure::DataTimelineCell (self as *const Self as *const Any))}
+      let foo = bincode_error_to_serializer(with_serialization_context (| context | {
         Ok(context.snapshot.downcast_ref::<SnapshotHandle <T::Basics>>().unwrap().clone())
       }))?;
       let clone = foo.get_clone (&self);
@@ -442,23 +575,41 @@ impl<'a, Steward: TimeSteward, Visitor: TimeStewardStructuresVisitor <Steward>>
 
 
   fn serialize_snapshot <B: Basics, W: Write> (writer: &mut W, snapshot: SnapshotHandle <B>)->$crate::bincode::Result <()> {
+    let globals = snapshot.data.globals.clone();
+    serialize_snapshot_rooted (writer, snapshot, &*globals, None)
+  }
+  fn serialize_partial_snapshot <B: Basics, W: Write, Roots: Serialize + ?Sized> (writer: &mut W, snapshot: SnapshotHandle <B>, roots: &Roots, policy: Box <$crate::serialization::PartialSnapshotPolicy>)->$crate::bincode::Result <()> {
+    // the globals go along with the roots, so that the PartialSnapshot can be used as an Accessor
+    let globals = snapshot.data.globals.clone();
+    serialize_snapshot_rooted (writer, snapshot, &(&*globals, roots), Some (policy))
+  }
+  fn new_serialization_context <B: Basics> (snapshot: SnapshotHandle <B>, policy: Option <Box <$crate::serialization::PartialSnapshotPolicy>>)->SerializationContext {
+    SerializationContext {
+      snapshot: Box::new (snapshot),
+      types: $crate::serialization::TypeRegistry::new::<B::Types>(),
+      handle_targets_observed: HashMap::new(),
+      handles_to_serialize_target: ::std::collections::VecDeque::new(),
+      next_object_identifier: 0,
+      policy: policy,
+      current_depth: 0,
+    }
+  }
+  fn serialize_snapshot_rooted <B: Basics, W: Write, Roots: Serialize + ?Sized> (writer: &mut W, snapshot: SnapshotHandle <B>, roots: &Roots, policy: Option <Box <$crate::serialization::PartialSnapshotPolicy>>)->$crate::bincode::Result <()> {
     SERIALIZATION_CONTEXT.with (| cell | {
       {
         let mut guard = cell.borrow_mut();
         assert!(guard.is_none(), "serializing recursively breaks my hacks and probably makes no sense");
-        *guard = Some(SerializationContext {
-          snapshot: Box::new (snapshot.clone()),
-          handle_targets_observed: HashMap::new(),
-          handles_to_serialize_target: Vec::new(),
-          next_object_identifier: 0,
-        });
+        *guard = Some(new_serialization_context (snapshot.clone(), policy));
       }
       // serialize inside a closure so that errors can be collected and we still clear the context afterwards
       let result = (|| {
         $crate::bincode::serialize_into (writer, snapshot.extended_now(), $crate::bincode::Bounded (::std::mem::size_of::<ExtendedTime <B>>() as u64))?;
-        $crate::bincode::serialize_into (writer, snapshot.globals(), $crate::bincode::Infinite)?;
+        $crate::bincode::serialize_into (writer, roots, $crate::bincode::Infinite)?;
 
-        while let Some((object_identifier, handle_box)) = cell.borrow_mut().as_mut().unwrap().handles_to_serialize_target.pop() {
+        loop {
+          // don't hold the borrow while serializing, because serializing the target will need the context again
+          let next = cell.borrow_mut().as_mut().unwrap().next_target();
+          let (object_identifier, handle_box) = match next {Some (next) => next, None => break};
           handle_box.serialize_target_into (writer, object_identifier)?;
         }
 
@@ -473,28 +624,147 @@ impl<'a, Steward: TimeSteward, Visitor: TimeStewardStructuresVisitor <Steward>>
     })
   }
 
-  trait MaybeEvent { fn visit (context: &mut DeserializationContext); }
-  trait MaybeData { fn visit (context: &mut DeserializationContext); }
-  impl <T> MaybeEvent for T {default fn visit (_: &mut DeserializationContext) {} }
-  impl <T> MaybeData for T {default fn visit (_: &mut DeserializationContext) {} }
-  impl <B: Basics, T: Event <Steward = Steward <B>>> MaybeEvent for T {
-    fn visit (context: &mut DeserializationContext) {
-      context.event_handle_initialize_functions.insert (T::ID, event_handle_initialize_function::<B, T>);
+  /// Serializes a snapshot in small steps; see `IncrementalSerializer`.
+  ///
+  /// Between steps, the serialization context is kept here rather than in the thread-local, so other serializations can happen on the same thread in the meantime.
+  pub struct SnapshotSerializer <B: Basics> {
+    snapshot: SnapshotHandle <B>,
+    context: Option <SerializationContext>,
+    output: Vec <u8>,
+    started: bool,
+    finished: bool,
+  }
+
+  impl <B: Basics> SnapshotSerializer <B> {
+    fn new (snapshot: SnapshotHandle <B>)->Self {
+      SnapshotSerializer {
+        context: Some (new_serialization_context (snapshot.clone(), None)),
+        snapshot: snapshot,
+        output: Vec::new(),
+        started: false,
+        finished: false,
+      }
+    }
+  }
+
+  impl <B: Basics> $crate::serialization::IncrementalSerializer for SnapshotSerializer <B> {
+    fn step (&mut self)->$crate::bincode::Result <bool> {
+      if self.finished {return Ok (true)}
+      let SnapshotSerializer {snapshot, context, output, started, finished} = self;
+      SERIALIZATION_CONTEXT.with (| cell | {
+        {
+          let mut guard = cell.borrow_mut();
+          assert!(guard.is_none(), "serializing recursively breaks my hacks and probably makes no sense");
+          *guard = Some (context.take().expect ("tried to continue a SnapshotSerializer after it failed"));
+        }
+        let result = (|| {
+          if !*started {
+            *started = true;
+            $crate::bincode::serialize_into (output, snapshot.extended_now(), $crate::bincode::Bounded (::std::mem::size_of::<ExtendedTime <B>>() as u64))?;
+            $crate::bincode::serialize_into (output, snapshot.globals(), $crate::bincode::Infinite)?;
+            return Ok (false)
+          }
+          let next = cell.borrow_mut().as_mut().unwrap().next_target();
+          match next {
+            Some ((object_identifier, handle_box)) => {
+              handle_box.serialize_target_into (output, object_identifier)?;
+              Ok (false)
+            }
+            None => {
+              $crate::bincode::serialize_into (output, &SerializationElement::Finished, $crate::bincode::Infinite)?;
+              Ok (true)
+            }
+          }
+        })();
+
+        let taken = cell.borrow_mut().take();
+        // after an error, leave the context empty so that later steps panic instead of producing corrupt output
+        if let Ok (done) = result {
+          if done {*finished = true} else {*context = taken}
+        }
+        result
+      })
+    }
+    fn is_finished (&self)->bool {
+      self.finished
+    }
+    fn into_bytes (self)->Vec <u8> {
+      assert!(self.finished, "called into_bytes() on a SnapshotSerializer that hadn't finished");
+      self.output
+    }
+  }
+
+  // Collects the deserialization functions for the listed types of one steward.
+  struct DeserializationRegistrar <'a, B: Basics> (&'a mut DeserializationContext, PhantomData <B>);
+  impl <'a, B: Basics> Registrar for DeserializationRegistrar <'a, B> {
+    fn data <T: SerializableData> (&mut self) {
+      self.0.data_handle_initialize_functions.insert (T::ID, data_handle_initialize_function::<T>);
+    }
+    fn data_with_default <T: SerializableData + Default> (&mut self) {
+      self.data::<T>();
+      self.0.data_handle_stub_functions.insert (T::ID, data_handle_stub_function::<T>);
+    }
+    fn extension (&mut self, extension: &Any) {
+      if let Some (registration) = extension.downcast_ref::<EventRegistration <Steward <B>>>() {
+        self.0.event_handle_initialize_functions.insert (registration.id, registration.initialize_event_handle);
+      }
+    }
+  }
+  impl <'a, B: Basics> ListOfTypesVisitor for DeserializationRegistrar <'a, B> {
+    fn visit <T: Listable> (&mut self) {
+      T::register (self);
     }
   }
-  impl <T: SimulationStateData + PersistentlyIdentifiedType> MaybeData for T {
-    fn visit (context: &mut DeserializationContext) {
-      context.data_handle_initialize_functions.insert (T::ID, data_handle_initialize_function::<T>);
+
+  struct ListedTypesChecker <'a> {
+    types: &'a $crate::serialization::TypeRegistry,
+    error: &'a mut Option <$crate::serialization::TypeRegistrationError>,
+  }
+  impl <'a, B: Basics> TimeStewardStructuresVisitor <Steward <B>> for ListedTypesChecker <'a> {
+    fn visit_data_handle (&mut self, _handle: &Any, type_id: crate::type_utils::PersistentTypeId, type_name: &'static str) {
+      if self.error.is_none() {
+        *self.error = self.types.check_listed_dynamic (type_id, type_name).err();
+      }
     }
   }
-  impl ListOfTypesVisitor for DeserializationContext {
-    fn visit <T> (&mut self) {
-      <T as MaybeEvent>::visit (self);
-      <T as MaybeData>::visit (self);
+
+  /// Checks that every DataHandle in the globals has a type listed in `types`, which must come from `TypeRegistry::validated::<B::Types>()` so that the IDs are known to be unique.
+  ///
+  /// DataHandles and events created later can't be known in advance; they are checked when they are serialized.
+  fn validate_types <B: Basics> (types: & $crate::serialization::TypeRegistry, globals: & B::Globals)->Result <(), $crate::serialization::TypeRegistrationError> {
+    let mut error = None;
+    TimeStewardStructuresVisitable::<Steward <B>>::visit_all (globals, ListedTypesChecker {types, error: &mut error});
+    match error {
+      Some (error) => Err (error),
+      None => Ok (()),
     }
   }
 
   fn deserialize_something <B: Basics, R: Read> (reader: &mut R)->$crate::bincode::Result <Steward <B>> {
+    deserialize_rooted::<B, _, _, _, _> (reader, false, | time, globals: B::Globals, context | {
+      validate_types::<B> (&context.types, &globals).map_err (| error | $crate::bincode::Error::custom (error.to_string()))?;
+      let mut steward = Steward::from_validated_globals (globals/*, ValidSince::Before (time)*/);
+      for prediction in context.predictions.iter() {
+        deserialization_create_prediction(&mut steward, context.handles.get (prediction).unwrap().downcast_ref::<EventHandle <B>>().unwrap().clone());
+      }
+      steward.invalid_before = ValidSince::Before (time.base.clone()) ;
+      Ok(steward)
+    })
+  }
+
+  fn deserialize_partial_snapshot <B: Basics, Roots: SimulationStateData, R: Read> (reader: &mut R)->$crate::bincode::Result <PartialSnapshot <B, Roots>> {
+    deserialize_rooted (reader, true, | time, (globals, roots): (B::Globals, Roots), context | {
+      Ok(PartialSnapshot {
+        time: time,
+        globals: globals,
+        roots: roots,
+        stubs: mem::replace (&mut context.stubs, Vec::new()).into_iter().collect(),
+      })
+    })
+  }
+
+  fn deserialize_rooted <B: Basics, R: Read, Roots: SimulationStateData, Output, F: FnOnce (ExtendedTime <B>, Roots, &mut DeserializationContext)->$crate::bincode::Result <Output>> (reader: &mut R, allow_stubs: bool, finish: F)->$crate::bincode::Result <Output> {
+    let types = $crate::serialization::TypeRegistry::validated::<B::Types>().map_err (| error | $crate::bincode::Error::custom (error.to_string()))?;
     let time: ExtendedTime <B> = $crate::bincode::deserialize_from (reader, $crate::bincode::Bounded (::std::mem::size_of::<ExtendedTime <B>>() as u64))?;
     DESERIALIZATION_CONTEXT.with (| cell | {
       {
@@ -502,19 +772,22 @@ impl<'a, Steward: TimeSteward, Visitor: TimeStewardStructuresVisitor <Steward>>
         assert!(guard.is_none(), "deserializing recursively breaks my hacks and probably makes no sense");
         let mut context = DeserializationContext {
           time: Box::new (time.clone()),
+          types,
           data_handle_initialize_functions: ::std::collections::HashMap::new(),
           event_handle_initialize_functions: ::std::collections::HashMap::new(),
+          data_handle_stub_functions: ::std::collections::HashMap::new(),
           handles: ::std::collections::HashMap::new(),
           uninitialized_handles: ::std::collections::HashSet::new(),
           predictions: ::std::collections::HashSet::new(),
+          stubs: Vec::new(),
           success: false,
         };
-        B::Types::visit_all (&mut context);
+        B::Types::visit_all (&mut DeserializationRegistrar::<B> (&mut context, PhantomData));
         *guard = Some(context);
       }
       // deserialize inside a closure so that errors can be collected and we still clear the context afterwards
       let result = (|| {
-        let globals: B::Globals = $crate::bincode::deserialize_from (reader, $crate::bincode::Infinite)?;
+        let roots: Roots = $crate::bincode::deserialize_from (reader, $crate::bincode::Infinite)?;
 
         while !cell.borrow().as_ref().unwrap().uninitialized_handles.is_empty() {
           // TODO: use actual size limits
@@ -522,13 +795,20 @@ impl<'a, Steward: TimeSteward, Visitor: TimeStewardStructuresVisitor <Steward>>
           //printlnerr!("{:?}", next);
           match next {
             SerializationElement::DataHandleData (object_id, type_id) => {
-              let deserialize_function = *cell.borrow().as_ref().unwrap().data_handle_initialize_functions.get (&type_id).ok_or_else (|| $crate::bincode::Error::custom("Tried to deserialize a type that wasn't listed"))?;
+              let deserialize_function = *cell.borrow().as_ref().unwrap().data_handle_initialize_functions.get (&type_id).ok_or_else (|| $crate::bincode::Error::custom(format!("Tried to deserialize a DataHandle of type {}, which isn't listed as a data type", cell.borrow().as_ref().unwrap().types.describe (type_id))))?;
               deserialize_function(reader, object_id)?;
             }
             SerializationElement::EventHandleData (object_id, type_id) => {
-              let deserialize_function = *cell.borrow().as_ref().unwrap().event_handle_initialize_functions.get (&type_id).ok_or_else (|| $crate::bincode::Error::custom("Tried to deserialize a type that wasn't listed"))?;
+              let deserialize_function = *cell.borrow().as_ref().unwrap().event_handle_initialize_functions.get (&type_id).ok_or_else (|| $crate::bincode::Error::custom(format!("Tried to deserialize an event of type {}, which isn't listed as an event type of this steward", cell.borrow().as_ref().unwrap().types.describe (type_id))))?;
               deserialize_function(reader, object_id)?;
             }
+            SerializationElement::DataHandleStub (object_id, type_id) => {
+              if !allow_stubs {
+                return Err($crate::bincode::Error::custom("A full snapshot contained a stub; stubs can only be loaded as part of a PartialSnapshot"))
+              }
+              let stub_function = *cell.borrow().as_ref().unwrap().data_handle_stub_functions.get (&type_id).ok_or_else (|| $crate::bincode::Error::custom(format!("Tried to load a stub of type {}, which isn't listed, or wasn't registered with data_with_default()", cell.borrow().as_ref().unwrap().types.describe (type_id))))?;
+              stub_function(object_id)?;
+            }
             SerializationElement::Finished => {
               return Err($crate::bincode::Error::custom("Premature end of serialized snapshot"))
             }
@@ -543,15 +823,11 @@ impl<'a, Steward: TimeSteward, Visitor: TimeStewardStructuresVisitor <Steward>>
           }
         };
 
-        let mut steward = Steward::from_globals (globals/*, ValidSince::Before (time)*/);
         let mut guard = cell.borrow_mut();
         let context = guard.as_mut().unwrap();
-        for prediction in context.predictions.iter() {
-          deserialization_create_prediction(&mut steward, context.handles.get (prediction).unwrap().downcast_ref::<EventHandle <B>>().unwrap().clone());
-        }
-        steward.invalid_before = ValidSince::Before (time.base.clone()) ;
+        let output = finish (time, roots, context)?;
         context.success = true;
-        Ok(steward)
+        Ok(output)
       })();
 
       {
@@ -562,6 +838,43 @@ impl<'a, Steward: TimeSteward, Visitor: TimeStewardStructuresVisitor <Steward>>
     })
   }
 
+  /// A read-only view of the state exported by `SnapshotAccessor::serialize_partial_into`.
+  ///
+  /// DataHandles that the export cut off are filled in with `Default::default()`; use `is_stub()` to recognize them. It implements `Accessor`, answering queries at the time of the original snapshot. The globals are exported along with the roots, and the policy cuts them off the same way.
+  #[derive (Debug)]
+  pub struct PartialSnapshot <B: Basics, Roots> {
+    time: ExtendedTime <B>,
+    globals: B::Globals,
+    roots: Roots,
+    stubs: HashMap <usize, Box <Any>>,
+  }
+
+  impl <B: Basics, Roots: SimulationStateData> PartialSnapshot <B, Roots> {
+    pub fn deserialize_from <R: Read> (reader: &mut R)->$crate::bincode::Result <Self> {
+      deserialize_partial_snapshot (reader)
+    }
+    pub fn roots (&self)->& Roots {
+      & self.roots
+    }
+    pub fn is_stub <T: SimulationStateData + PersistentlyIdentifiedType> (&self, handle: & DataHandle <T>)->bool {
+      self.stubs.contains_key (&(&*handle.data as *const T as usize))
+    }
+  }
+
+  impl <B: Basics, Roots: SimulationStateData> Accessor for PartialSnapshot <B, Roots> {
+    type Steward = Steward <B>;
+    fn globals (&self)->&B::Globals {&self.globals}
+    fn extended_now (&self)->& ExtendedTime <B> {
+      & self.time
+    }
+    fn query <Q: Query, T: DataTimelineQueriableWith<Q, Basics = B>> (&self, timeline: & DataTimelineCell <T>, query: &Q)-> T::QueryResult {
+      DataTimelineQueriableWith::<Q>::query (&*timeline.data.borrow(), query, &self.time)
+    }
+    fn query_ref <'timeline, Q: Query, T: DataTimelineQueryRefableWith<Q, Basics = B>> (&'timeline self, timeline: &'timeline DataTimelineCell<T>, query: &Q)-> DataTimelineCellReadGuard<'timeline, T::QueryResult> {
+      Ref::map(timeline.data.borrow(), |timeline| DataTimelineQueryRefableWith::<Q>::query_ref (timeline, query, &self.time))
+    }
+  }
+
 
   };
 }
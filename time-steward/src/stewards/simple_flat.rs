@@ -1,4 +1,4 @@
-use std::any::{Any, TypeId};
+use std::any::Any;
 use std::borrow::Borrow;
 use std::cell::{Cell, Ref, RefCell, RefMut};
 use std::cmp::{max, Ordering};
@@ -16,6 +16,7 @@ use crate::DeterministicRandomId;
 use crate::type_utils::{PersistentlyIdentifiedType, DynamicPersistentlyIdentifiedType};
 
 use crate::implementation_support::insert_only;
+use crate::serialization::{PartialSnapshotPolicy, TypeRegistrationError, TypeRegistry};
 
 time_steward_steward_specific_api!();
 
@@ -48,7 +49,8 @@ trait EventInnerTrait<B: Basics>:
   Any + Debug + SerializeInto + DynamicPersistentlyIdentifiedType
 {
   fn execute(&self, self_handle: &EventHandle<B>, steward: &mut Steward<B>);
-  fn get_type_id(&self)->TypeId;
+  fn as_any(&self) -> &Any;
+  fn type_name(&self)->&'static str;
 }
 impl<B: Basics, T: Event<Steward = Steward<B>>> EventInnerTrait<B> for T {
   fn execute(&self, self_handle: &EventHandle<B>, steward: &mut Steward<B>) {
@@ -59,7 +61,10 @@ impl<B: Basics, T: Event<Steward = Steward<B>>> EventInnerTrait<B> for T {
     };
     <T as Event>::execute(self, &mut accessor);
   }
-  fn get_type_id(&self)->TypeId {TypeId::of::<T>()}
+  fn as_any(&self) -> &Any {
+    self
+  }
+  fn type_name(&self)->&'static str {::std::any::type_name::<T>()}
 }
 
 #[derive(Derivative)]
@@ -79,7 +84,7 @@ impl<B: Basics> EventHandleTrait<B> for EventHandle<B> {
     &self.data.time
   }
   fn downcast_ref<T: Any>(&self) -> Option<&T> {
-    downcast_ref!(&*self.data.data, T, EventInnerTrait<B>)
+    self.data.data.as_any().downcast_ref::<T>()
   }
 }
 
@@ -369,9 +374,25 @@ impl<'a, B: Basics> FutureCleanupAccessor for EventAccessorStruct<'a, B> {
 }
 
 impl<B: Basics> SnapshotAccessor for SnapshotHandle<B> {
+  type IncrementalSerializer = SnapshotSerializer<B>;
   fn serialize_into<W: Write>(&self, writer: &mut W) -> ::bincode::Result<()> {
     serialize_snapshot(writer, self.clone())
   }
+  fn serialize_partial_into<
+    W: Write,
+    Roots: ::serde::Serialize + ?Sized,
+    P: PartialSnapshotPolicy + 'static,
+  >(
+    &self,
+    writer: &mut W,
+    roots: &Roots,
+    policy: P,
+  ) -> ::bincode::Result<()> {
+    serialize_partial_snapshot(writer, self.clone(), roots, Box::new(policy))
+  }
+  fn incremental_serializer(&self) -> SnapshotSerializer<B> {
+    SnapshotSerializer::new(self.clone())
+  }
 }
 
 #[derive(Debug)]
@@ -489,10 +510,30 @@ impl<B: Basics> TimeSteward for Steward<B> {
       ValidSince::Before(time.clone()),
     );
   }
+
+  fn event_handle_initialize_function<E: Event<Steward = Self>>(
+  ) -> fn(&mut Read, u64) -> ::bincode::Result<()> {
+    event_handle_initialize_function::<B, E>
+  }
 }
 
 impl<B: Basics> ConstructibleTimeSteward for Steward<B> {
-  fn from_globals(globals: <Self::Basics as Basics>::Globals) -> Self {
+  fn from_globals(
+    globals: <Self::Basics as Basics>::Globals,
+  ) -> Result<Self, TypeRegistrationError> {
+    let types = TypeRegistry::validated::<B::Types>()?;
+    validate_types::<B>(&types, &globals)?;
+    Ok(Self::from_validated_globals(globals))
+  }
+
+  fn deserialize_from<R: Read>(data: &mut R) -> ::bincode::Result<Self> {
+    deserialize_something(data)
+  }
+}
+
+impl<B: Basics> Steward<B> {
+  /// Makes a steward from globals that `validate_types()` has already accepted.
+  fn from_validated_globals(globals: B::Globals) -> Self {
     Steward {
       globals: Rc::new(globals),
       invalid_before: ValidSince::TheBeginning,
@@ -503,10 +544,6 @@ impl<B: Basics> ConstructibleTimeSteward for Steward<B> {
       next_snapshot_index: 0,
     }
   }
-
-  fn deserialize_from<R: Read>(data: &mut R) -> ::bincode::Result<Self> {
-    deserialize_something(data)
-  }
 }
 
 impl<B: Basics> IncrementalTimeSteward for Steward<B> {
@@ -524,4 +561,12 @@ impl<B: Basics> IncrementalTimeSteward for Steward<B> {
 impl<B: Basics> CanonicalTimeSteward for Steward<B> {}
 
 time_steward_define_simple_timeline!();
+time_steward_define_ordered_map_timeline!();
+time_steward_define_accumulator_timeline!();
+time_steward_define_priority_queue_timeline!();
+time_steward_define_trajectory_timeline!();
+time_steward_define_data_timeline_conformance!();
+time_steward_define_autosave!();
+time_steward_define_journal!();
 time_steward_define_bbox_collision_detection!();
+time_steward_define_contact_prediction!();
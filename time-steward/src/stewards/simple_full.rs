@@ -6,7 +6,7 @@ use std::cell::{Cell, RefCell, Ref, RefMut};
 use std::collections::{BTreeMap, BTreeSet, HashMap, Bound};
 use std::cmp::{Ordering, max};
 use std::borrow::Borrow;
-use std::any::{Any, TypeId};
+use std::any::Any;
 use std::io::{Read, Write};
 use std::rc::Rc;
 use std::fmt::Debug;
@@ -19,6 +19,7 @@ use crate::{DeterministicRandomId};
 use crate::type_utils::{PersistentlyIdentifiedType, DynamicPersistentlyIdentifiedType};
 
 use crate::implementation_support::insert_only;
+use crate::serialization::{PartialSnapshotPolicy, TypeRegistrationError, TypeRegistry};
 
 time_steward_steward_specific_api!();
 
@@ -62,7 +63,8 @@ trait EventInnerTrait <B: Basics>: Any + Debug + SerializeInto + DynamicPersiste
   fn execute (&self, self_handle: & EventHandle <B>, steward: &mut Steward <B>);
   fn undo (&self, self_handle: & EventHandle <B>, steward: &mut Steward <B>);
   fn re_execute (&self, self_handle: & EventHandle <B>, steward: &mut Steward <B>);
-  fn get_type_id(&self)->TypeId;
+  fn as_any (&self)->&Any;
+  fn type_name(&self)->&'static str;
 }
 impl <B: Basics, T: Event <Steward = Steward <B>>> EventInnerTrait <B> for T {
   fn execute (&self, self_handle: & EventHandle<B>, steward: &mut Steward <B>) {
@@ -98,7 +100,8 @@ impl <B: Basics, T: Event <Steward = Steward <B>>> EventInnerTrait <B> for T {
       execution_data: Box::new (result),
     }));
   }
-  fn get_type_id(&self)->TypeId {TypeId::of::<T>()}
+  fn as_any (&self)->&Any {self}
+  fn type_name(&self)->&'static str {::std::any::type_name::<T>()}
 }
 
 
@@ -118,7 +121,7 @@ pub struct EventHandle <B: Basics> {
 impl <B: Basics> EventHandleTrait<B> for EventHandle <B> {
   fn extended_time (& self)->& ExtendedTime <B> {& self.data.time}
   fn downcast_ref <T: Any> (&self)->Option<&T> {
-    downcast_ref!(&*self.data.data, T, EventInnerTrait<B>)
+    self.data.data.as_any().downcast_ref::<T>()
   }
 }
 
@@ -323,21 +326,41 @@ impl <'a, B: Basics> FutureCleanupAccessor for EventAccessorStruct <'a, B> {
 }
 
 impl <B: Basics> SnapshotAccessor for SnapshotHandle <B> {
+  type IncrementalSerializer = SnapshotSerializer <B>;
   fn serialize_into <W: Write> (&self, writer: &mut W)->::bincode::Result <()> {
     serialize_snapshot (writer, self.clone())
   }
+  fn serialize_partial_into <W: Write, Roots: ::serde::Serialize + ?Sized, P: PartialSnapshotPolicy + 'static> (&self, writer: &mut W, roots: &Roots, policy: P)->::bincode::Result <()> {
+    serialize_partial_snapshot (writer, self.clone(), roots, Box::new (policy))
+  }
+  fn incremental_serializer (&self)->SnapshotSerializer <B> {
+    SnapshotSerializer::new (self.clone())
+  }
 }
 
 
 
-      // when you delete an event and then re-create it, we want
-      // undoing the deleted event to come BEFORE executing the new one.
-      // (false comes before true)
-#[derive (Clone, PartialEq, Eq, PartialOrd, Ord, Debug)]
+#[derive (Clone, PartialEq, Eq, Debug)]
 struct EventNeedingAttention<B: Basics> {
   handle: EventHandle<B>,
   should_be_executed: bool,
 }
+      // when you delete an event and then re-create it, we want
+      // undoing the deleted event to come BEFORE executing the new one.
+      // (false comes before true)
+      // The re-created event may be a distinct handle with the same time, so compare the times before the handles themselves.
+impl<B: Basics> Ord for EventNeedingAttention<B> {
+  fn cmp (&self, other: &Self)->Ordering {
+    self.handle.extended_time().cmp (other.handle.extended_time())
+      .then_with (|| self.should_be_executed.cmp (&other.should_be_executed))
+      .then_with (|| self.handle.cmp (&other.handle))
+  }
+}
+impl<B: Basics> PartialOrd for EventNeedingAttention<B> {
+  fn partial_cmp (&self, other: &Self)->Option<Ordering> {
+    Some (self.cmp (other))
+  }
+}
 
 #[derive (Debug)]
 pub struct Steward <B: Basics> {
@@ -491,11 +514,28 @@ impl<B: Basics> TimeSteward for Steward<B> {
     self.invalid_before = max (self.invalid_before.clone(), ValidSince::Before(time.clone()));
 
   }
+
+  fn event_handle_initialize_function <E: Event <Steward = Self>>()->fn (&mut Read, u64)->::bincode::Result <()> {
+    event_handle_initialize_function::<B, E>
+  }
 }
 
 
 impl <B: Basics> ConstructibleTimeSteward for Steward <B> {
-  fn from_globals (globals: <Self::Basics as Basics>::Globals)->Self {
+  fn from_globals (globals: <Self::Basics as Basics>::Globals)->Result <Self, TypeRegistrationError> {
+    let types = TypeRegistry::validated::<B::Types>()?;
+    validate_types::<B> (&types, &globals)?;
+    Ok(Self::from_validated_globals (globals))
+  }
+
+  fn deserialize_from <R: Read> (data: &mut R)->::bincode::Result <Self> {
+    deserialize_something (data)
+  }
+}
+
+impl<B: Basics> Steward<B> {
+  /// Makes a steward from globals that `validate_types()` has already accepted.
+  fn from_validated_globals (globals: B::Globals)->Self {
     Steward {
       globals: Rc::new (globals),
       invalid_before: ValidSince::TheBeginning,
@@ -505,10 +545,6 @@ impl <B: Basics> ConstructibleTimeSteward for Steward <B> {
       next_snapshot_index: 0,
     }
   }
-
-  fn deserialize_from <R: Read> (data: &mut R)->::bincode::Result <Self> {
-    deserialize_something (data)
-  }
 }
 
 impl<B: Basics> IncrementalTimeSteward for Steward<B> {
@@ -524,7 +560,15 @@ impl<B: Basics> IncrementalTimeSteward for Steward<B> {
 impl<B: Basics> CanonicalTimeSteward for Steward<B> {}
 
 time_steward_define_simple_timeline!();
+time_steward_define_ordered_map_timeline!();
+time_steward_define_accumulator_timeline!();
+time_steward_define_priority_queue_timeline!();
+time_steward_define_trajectory_timeline!();
+time_steward_define_data_timeline_conformance!();
+time_steward_define_autosave!();
+time_steward_define_journal!();
 time_steward_define_bbox_collision_detection!();
+time_steward_define_contact_prediction!();
 
 
 
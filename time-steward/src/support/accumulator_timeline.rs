@@ -0,0 +1,289 @@
+use num::Zero;
+use std::ops::Sub;
+
+use crate::QueryResult;
+
+/// Values that an `Accumulator` can total up. Additions must be commutative and associative, or the total would depend on the order the events were executed in, rather than the order of their times.
+pub trait Accumulable: QueryResult + Ord + Zero + Sub<Output = Self> {}
+impl<T: QueryResult + Ord + Zero + Sub<Output = Self>> Accumulable for T {}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! time_steward_define_accumulator_timeline {
+  () => {
+    pub mod accumulator_timeline {
+      use std::collections::{BTreeMap, BTreeSet, VecDeque};
+      use std::ops::Bound;
+
+      use super::super::super::api::*;
+      use super::*;
+      pub use crate::support::accumulator_timeline::Accumulable;
+      use crate::implementation_support::common::split_off_greater_set;
+      use crate::type_utils::{PersistentTypeId, PersistentlyIdentifiedType};
+
+      /// Query for the total at a time.
+      #[derive(Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize, Debug)]
+      pub struct Total;
+      impl PersistentlyIdentifiedType for Total {
+        const ID: PersistentTypeId = PersistentTypeId(0x72b5e1d80c4f9a36);
+      }
+
+      /// Query for whether the total is at least the given value.
+      #[derive(Clone, PartialEq, Eq, Hash, Serialize, Deserialize, Debug)]
+      pub struct AtLeast<Value>(pub Value);
+      impl<Value> PersistentlyIdentifiedType for AtLeast<Value> {
+        const ID: PersistentTypeId = PersistentTypeId(0xe940a3c6b27d1f58);
+      }
+
+      /// A DataTimeline that totals up deltas added by events.
+      ///
+      /// Because addition is commutative, adding a delta retroactively doesn't disturb any later additions. It only invalidates the later events that tracked the exact total, and the later events that tracked a threshold (using `tracking_at_least()`) whose answer the delta actually changed.
+      #[derive(Serialize, Deserialize, Derivative)]
+      #[serde(bound = "")]
+      #[derivative(Clone(bound = ""), Debug(bound = ""), Default(bound = ""))]
+      pub struct Accumulator<Value: Accumulable, Steward: TimeSteward> {
+        #[derivative(Default(value = "Value::zero()"))]
+        initial: Value,
+        // Hacky workaround for https://github.com/rust-lang/rust/issues/41617 (see https://github.com/serde-rs/serde/issues/943)
+        // (the event, the total of its deltas, and the total immediately after it)
+        #[serde(deserialize_with = "::serde::Deserialize::deserialize")]
+        changes: VecDeque<(<Steward as TimeSteward>::EventHandle, Value, Value)>,
+        #[serde(deserialize_with = "::serde::Deserialize::deserialize")]
+        total_dependent_events: BTreeSet<<Steward as TimeSteward>::EventHandle>,
+        #[serde(deserialize_with = "::serde::Deserialize::deserialize")]
+        threshold_dependent_events: BTreeMap<<Steward as TimeSteward>::EventHandle, Vec<Value>>,
+      }
+
+      impl<Value: Accumulable, Steward: TimeSteward> Accumulator<Value, Steward> {
+        pub fn new(initial: Value) -> Self {
+          Accumulator {
+            initial,
+            changes: VecDeque::new(),
+            total_dependent_events: BTreeSet::new(),
+            threshold_dependent_events: BTreeMap::new(),
+          }
+        }
+
+        fn search_changes(&self, time: &ExtendedTime<Steward::Basics>) -> Result<usize, usize> {
+          self
+            .changes
+            .binary_search_by(|change| change.0.extended_time().cmp(time))
+        }
+
+        fn total_at(&self, time: &ExtendedTime<Steward::Basics>) -> &Value {
+          let previous_change_index = match self.search_changes(time) {
+            Ok(index) => index,
+            Err(index) => match index.checked_sub(1) {
+              Some(index) => index,
+              None => return &self.initial,
+            },
+          };
+          &self.changes[previous_change_index].2
+        }
+
+        /// Invalidates the future events whose tracked queries would have different results if every total after now became `new_total(old_total)`.
+        fn invalidate_future_readers<
+          Accessor: FutureCleanupAccessor<Steward = Steward>,
+          F: Fn(&Value) -> Value,
+        >(
+          &mut self,
+          accessor: &Accessor,
+          new_total: F,
+        ) {
+          let removed =
+            split_off_greater_set(&mut self.total_dependent_events, accessor.extended_now());
+          for event in removed {
+            accessor.invalidate_execution(&event);
+          }
+
+          let invalidated: Vec<_> = self
+            .threshold_dependent_events
+            .range::<ExtendedTime<Steward::Basics>, _>((
+              Bound::Excluded(accessor.extended_now()),
+              Bound::Unbounded,
+            ))
+            .filter(|(event, thresholds)| {
+              let old_total = self.total_at(event.extended_time());
+              let changed_total = new_total(old_total);
+              thresholds
+                .iter()
+                .any(|threshold| (old_total >= threshold) != (&changed_total >= threshold))
+            })
+            .map(|(event, _)| event.clone())
+            .collect();
+          for event in invalidated {
+            self
+              .threshold_dependent_events
+              .remove::<<Steward as TimeSteward>::EventHandle>(&event);
+            accessor.invalidate_execution(&event);
+          }
+        }
+
+        fn add<Accessor: EventAccessor<Steward = Steward>>(&mut self, delta: Value, accessor: &Accessor) {
+          let index = match self.search_changes(accessor.extended_now()) {
+            Ok(index) => {
+              let change = &mut self.changes[index];
+              change.1 = change.1.clone() + delta.clone();
+              index
+            }
+            Err(index) => {
+              // the total gets the delta added below, along with the later totals
+              let total = self.total_at(accessor.extended_now()).clone();
+              self
+                .changes
+                .insert(index, (accessor.this_event().clone(), delta.clone(), total));
+              index
+            }
+          };
+          for change in self.changes.iter_mut().skip(index) {
+            change.2 = change.2.clone() + delta.clone();
+          }
+        }
+
+        fn remove_present<Accessor: FutureCleanupAccessor<Steward = Steward>>(&mut self, accessor: &Accessor) {
+          if let Ok(index) = self.search_changes(accessor.extended_now()) {
+            let (_, delta, _) = self.changes.remove(index).unwrap();
+            for change in self.changes.iter_mut().skip(index) {
+              change.2 = change.2.clone() - delta.clone();
+            }
+          }
+        }
+      }
+
+      impl<Value: Accumulable, Steward: TimeSteward> DataTimeline for Accumulator<Value, Steward> {
+        type Basics = Steward::Basics;
+
+        fn clone_for_snapshot(&self, time: &ExtendedTime<Self::Basics>) -> Self {
+          Accumulator::new(self.total_at(time).clone())
+        }
+
+        fn forget_before(&mut self, time: &ExtendedTime<Self::Basics>) {
+          self.total_dependent_events = self.total_dependent_events.split_off(time);
+          self.threshold_dependent_events = self.threshold_dependent_events.split_off(time);
+
+          while self
+            .changes
+            .front()
+            .map_or(false, |change| change.0.extended_time() < time)
+          {
+            self.initial = self.changes.pop_front().unwrap().2;
+          }
+        }
+      }
+      impl<Value: Accumulable, Steward: TimeSteward> DataTimelineQueriableWith<Total>
+        for Accumulator<Value, Steward>
+      {
+        type QueryResult = Value;
+
+        fn query(&self, _: &Total, time: &ExtendedTime<Self::Basics>) -> Self::QueryResult {
+          self.total_at(time).clone()
+        }
+      }
+      impl<Value: Accumulable, Steward: TimeSteward> DataTimelineQueryRefableWith<Total>
+        for Accumulator<Value, Steward>
+      {
+        fn query_ref(&self, _: &Total, time: &ExtendedTime<Self::Basics>) -> &Self::QueryResult {
+          self.total_at(time)
+        }
+      }
+      impl<Value: Accumulable, Steward: TimeSteward> DataTimelineQueriableWith<AtLeast<Value>>
+        for Accumulator<Value, Steward>
+      {
+        type QueryResult = bool;
+
+        fn query(&self, query: &AtLeast<Value>, time: &ExtendedTime<Self::Basics>) -> Self::QueryResult {
+          self.total_at(time) >= &query.0
+        }
+      }
+
+      pub fn total<Value: Accumulable, Steward: TimeSteward, A: Accessor<Steward = Steward>>(
+        accessor: &A,
+        handle: &DataTimelineCell<Accumulator<Value, Steward>>,
+      ) -> Value {
+        accessor.query(handle, &Total)
+      }
+      pub fn tracking_total<
+        Value: Accumulable,
+        Steward: TimeSteward,
+        Accessor: EventAccessor<Steward = Steward>,
+      >(
+        accessor: &Accessor,
+        handle: &DataTimelineCell<Accumulator<Value, Steward>>,
+      ) -> Value {
+        accessor.modify(handle, |timeline| {
+          timeline
+            .total_dependent_events
+            .insert(accessor.this_event().clone());
+        });
+        total(accessor, handle)
+      }
+      pub fn at_least<Value: Accumulable, Steward: TimeSteward, A: Accessor<Steward = Steward>>(
+        accessor: &A,
+        handle: &DataTimelineCell<Accumulator<Value, Steward>>,
+        threshold: Value,
+      ) -> bool {
+        accessor.query(handle, &AtLeast(threshold))
+      }
+      /// Like `tracking_total()`, but the event is only invalidated by changes that move the total across `threshold`.
+      pub fn tracking_at_least<
+        Value: Accumulable,
+        Steward: TimeSteward,
+        Accessor: EventAccessor<Steward = Steward>,
+      >(
+        accessor: &Accessor,
+        handle: &DataTimelineCell<Accumulator<Value, Steward>>,
+        threshold: Value,
+      ) -> bool {
+        let query = AtLeast(threshold.clone());
+        accessor.modify(handle, move |timeline| {
+          timeline
+            .threshold_dependent_events
+            .entry(accessor.this_event().clone())
+            .or_insert_with(Vec::new)
+            .push(threshold);
+        });
+        accessor.query(handle, &query)
+      }
+
+      /// Adds `delta` to the total from now on. An event may add to the same accumulator more than once.
+      pub fn add<
+        Value: Accumulable,
+        Steward: TimeSteward,
+        Accessor: EventAccessor<Steward = Steward>,
+      >(
+        accessor: &Accessor,
+        handle: &DataTimelineCell<Accumulator<Value, Steward>>,
+        delta: Value,
+      ) {
+        if delta.is_zero() {
+          return;
+        }
+        if let Some(accessor) = accessor.future_cleanup() {
+          accessor
+            .peek_mut(handle)
+            .invalidate_future_readers(accessor, |total| total.clone() + delta.clone());
+        }
+        accessor.modify(handle, move |timeline| {
+          timeline.add(delta, accessor);
+        });
+      }
+      /// Undoes all of the additions the current event made. Events that added to an accumulator must call this in `undo()`.
+      pub fn unset<
+        Value: Accumulable,
+        Steward: TimeSteward,
+        Accessor: FutureCleanupAccessor<Steward = Steward>,
+      >(
+        accessor: &Accessor,
+        handle: &DataTimelineCell<Accumulator<Value, Steward>>,
+      ) {
+        let mut guard = accessor.peek_mut(handle);
+        let delta = match guard.search_changes(accessor.extended_now()) {
+          Ok(index) => guard.changes[index].1.clone(),
+          Err(_) => return,
+        };
+        guard.invalidate_future_readers(accessor, |total| total.clone() - delta.clone());
+        guard.remove_present(accessor);
+      }
+    } //mod
+  };
+}
@@ -0,0 +1,244 @@
+//! Periodically saving snapshots to disk, without stalling the simulation.
+//!
+//! Snapshots can't leave the thread that owns the steward, so the autosaver serializes them a few steps at a time (see `IncrementalSerializer`) during `update()`, then hands the finished bytes to a writer thread. The writer thread writes each file atomically – to a temporary file that is then renamed into place – so a crash never leaves a half-written save with a valid-looking name.
+//!
+//! Only the disk writes happen in the background. All of the serialization, which is usually the bigger cost, runs on the thread that calls `update()`, so each call takes time roughly in proportion to `serialization_steps_per_update`. Choose it small enough to fit within a frame, and the interval long enough that each save finishes before the next one comes due.
+
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// Writes `data` to `path` such that `path` either keeps its old contents or has exactly the new ones, even if we crash partway.
+pub fn write_atomically(path: &Path, data: &[u8]) -> io::Result<()> {
+  let mut temporary_name = path.file_name().unwrap_or_default().to_os_string();
+  temporary_name.push(".tmp");
+  let temporary_path = path.with_file_name(temporary_name);
+  {
+    let mut file = File::create(&temporary_path)?;
+    file.write_all(data)?;
+    file.sync_all()?;
+  }
+  fs::rename(&temporary_path, path)?;
+  // Syncing the directory makes the rename itself durable. Not every platform lets you open a directory, so this is best-effort.
+  if let Some(directory) = path.parent() {
+    if let Ok(directory) = File::open(directory) {
+      let _ = directory.sync_all();
+    }
+  }
+  Ok(())
+}
+
+/// The name of the `number`th file with the given prefix. Numbers are zero-padded so that the names sort the same way as the numbers.
+pub fn numbered_file_name(file_prefix: &str, number: u64) -> String {
+  format!("{}{:020}", file_prefix, number)
+}
+
+/// Lists the files in `directory` named `file_prefix` followed by a number, sorted by number.
+pub fn numbered_files(directory: &Path, file_prefix: &str) -> io::Result<Vec<(u64, PathBuf)>> {
+  let mut result = Vec::new();
+  for entry in fs::read_dir(directory)? {
+    let entry = entry?;
+    let name = entry.file_name();
+    let number = name
+      .to_str()
+      .and_then(|name| {
+        if name.starts_with(file_prefix) {
+          Some(&name[file_prefix.len()..])
+        } else {
+          None
+        }
+      })
+      .and_then(|suffix| suffix.parse::<u64>().ok());
+    if let Some(number) = number {
+      result.push((number, entry.path()));
+    }
+  }
+  result.sort();
+  Ok(result)
+}
+
+/// Deletes all but the `files_kept` highest-numbered files with the given prefix.
+pub fn rotate(directory: &Path, file_prefix: &str, files_kept: usize) -> io::Result<()> {
+  let files = numbered_files(directory, file_prefix)?;
+  if files.len() > files_kept {
+    for (_, path) in &files[..files.len() - files_kept] {
+      fs::remove_file(path)?;
+    }
+  }
+  Ok(())
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! time_steward_define_autosave {
+  () => {
+    pub mod autosave {
+      use std::io;
+      use std::ops::Add;
+      use std::path::PathBuf;
+      use std::sync::mpsc::{channel, Receiver, Sender, TryRecvError};
+      use std::thread::{self, JoinHandle};
+
+      use super::super::super::api::*;
+      use super::*;
+      use crate::serialization::IncrementalSerializer;
+      use crate::support::autosave::{numbered_file_name, numbered_files, rotate, write_atomically};
+
+      #[derive(Clone, Debug)]
+      pub struct AutosaveSettings<Time> {
+        pub directory: PathBuf,
+        pub file_prefix: String,
+        /// Simulated time between saves.
+        pub interval: Time,
+        /// How many of the most recent saves to keep around. Older ones are deleted after each successful save.
+        pub files_kept: usize,
+        /// How much serialization work to do during each call to `Autosaver::update()`. This work runs on the caller's thread.
+        pub serialization_steps_per_update: usize,
+      }
+
+      struct WriteJob {
+        number: u64,
+        data: Vec<u8>,
+      }
+
+      /// Saves a snapshot of a steward every `interval` of simulated time.
+      ///
+      /// Call `update()` regularly – for instance, once per frame. Saved files can be loaded with `ConstructibleTimeSteward::deserialize_from()`.
+      pub struct Autosaver<Steward: TimeSteward> {
+        settings: AutosaveSettings<<Steward::Basics as Basics>::Time>,
+        next_save_time: <Steward::Basics as Basics>::Time,
+        next_file_number: u64,
+        in_progress: Option<(u64, <Steward::SnapshotAccessor as SnapshotAccessor>::IncrementalSerializer)>,
+        jobs: Option<Sender<WriteJob>>,
+        results: Receiver<io::Result<PathBuf>>,
+        writer: Option<JoinHandle<()>>,
+      }
+
+      impl<Steward: TimeSteward> Autosaver<Steward>
+      where
+        <Steward::Basics as Basics>::Time: Add<Output = <Steward::Basics as Basics>::Time>,
+      {
+        /// Creates an autosaver whose first save will be of the snapshot before `first_save_time`.
+        ///
+        /// File numbering continues after any saves that already exist in the directory.
+        pub fn new(
+          settings: AutosaveSettings<<Steward::Basics as Basics>::Time>,
+          first_save_time: <Steward::Basics as Basics>::Time,
+        ) -> io::Result<Self> {
+          assert!(settings.files_kept > 0, "an autosaver that keeps no files would delete every save it made");
+          ::std::fs::create_dir_all(&settings.directory)?;
+          let next_file_number = numbered_files(&settings.directory, &settings.file_prefix)?
+            .last()
+            .map_or(0, |&(number, _)| number + 1);
+
+          let (jobs, job_receiver) = channel::<WriteJob>();
+          let (result_sender, results) = channel();
+          let directory = settings.directory.clone();
+          let file_prefix = settings.file_prefix.clone();
+          let files_kept = settings.files_kept;
+          let writer = thread::spawn(move || {
+            for job in job_receiver {
+              let path = directory.join(numbered_file_name(&file_prefix, job.number));
+              let result = write_atomically(&path, &job.data)
+                .and_then(|()| rotate(&directory, &file_prefix, files_kept))
+                .map(|()| path);
+              if result_sender.send(result).is_err() {
+                break;
+              }
+            }
+          });
+
+          Ok(Autosaver {
+            settings,
+            next_save_time: first_save_time,
+            next_file_number,
+            in_progress: None,
+            jobs: Some(jobs),
+            results,
+            writer: Some(writer),
+          })
+        }
+
+        /// Starts a save if one is due as of `now`, does a bounded amount of serialization work, and reports files that the writer thread has finished since the last call.
+        ///
+        /// A save that is still being serialized when the next one comes due delays the next one, rather than being abandoned. A save whose serialization fails is abandoned, and the error is returned; later saves go ahead as usual.
+        pub fn update(
+          &mut self,
+          steward: &mut Steward,
+          now: &<Steward::Basics as Basics>::Time,
+        ) -> io::Result<Vec<PathBuf>> {
+          if self.in_progress.is_none() && *now >= self.next_save_time {
+            if let Some(snapshot) = steward.snapshot_before(&self.next_save_time) {
+              self.in_progress = Some((self.next_file_number, snapshot.incremental_serializer()));
+              self.next_file_number += 1;
+            }
+            // If the steward has already forgotten that time, there's nothing to save; just wait for the next one.
+            self.next_save_time = self.next_save_time.clone() + self.settings.interval.clone();
+          }
+
+          let mut finished = false;
+          let mut failure = None;
+          if let Some((_, serializer)) = self.in_progress.as_mut() {
+            for _ in 0..self.settings.serialization_steps_per_update {
+              match serializer.step() {
+                Ok(false) => {}
+                Ok(true) => {
+                  finished = true;
+                  break;
+                }
+                Err(error) => {
+                  failure = Some(error);
+                  break;
+                }
+              }
+            }
+          }
+          if let Some(error) = failure {
+            // A serializer can't continue after an error, so abandon this save; the next one starts when it's due.
+            self.in_progress = None;
+            return Err(io::Error::new(io::ErrorKind::Other, error.to_string()));
+          }
+          if finished {
+            let (number, serializer) = self.in_progress.take().unwrap();
+            let data = serializer.into_bytes();
+            if self.jobs.as_ref().unwrap().send(WriteJob { number, data }).is_err() {
+              return Err(io::Error::new(io::ErrorKind::Other, "the autosave writer thread stopped unexpectedly"));
+            }
+          }
+
+          let mut written = Vec::new();
+          loop {
+            match self.results.try_recv() {
+              Ok(result) => written.push(result?),
+              Err(TryRecvError::Empty) => break,
+              Err(TryRecvError::Disconnected) => {
+                return Err(io::Error::new(io::ErrorKind::Other, "the autosave writer thread stopped unexpectedly"));
+              }
+            }
+          }
+          Ok(written)
+        }
+
+        /// Whether a save is currently being serialized.
+        pub fn is_saving(&self) -> bool {
+          self.in_progress.is_some()
+        }
+
+        /// The time of the next save that hasn't started yet.
+        pub fn next_save_time(&self) -> &<Steward::Basics as Basics>::Time {
+          &self.next_save_time
+        }
+      }
+
+      impl<Steward: TimeSteward> Drop for Autosaver<Steward> {
+        /// Waits for any saves that have already been handed to the writer thread. A save that was still being serialized is abandoned.
+        fn drop(&mut self) {
+          self.jobs = None;
+          if let Some(writer) = self.writer.take() {
+            let _ = writer.join();
+          }
+        }
+      }
+    }
+  };
+}
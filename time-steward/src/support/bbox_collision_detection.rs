@@ -2,37 +2,74 @@
 #[macro_export]
 macro_rules! time_steward_define_bbox_collision_detection {
   () => {
-    time_steward_define_bbox_collision_detection!(2, bbox_collision_detection_2d);
-    time_steward_define_bbox_collision_detection!(3, bbox_collision_detection_3d);
-  };
-
-  ($dims: expr, $mod: ident) => {
-    pub mod $mod {
-      use super::simple_timeline::{query, set, SimpleTimeline};
+    pub mod bbox_collision_detection {
+      use super::simple_timeline::{query, set, unset, SimpleTimeline};
       use super::*;
       use super::{
         DataHandle, DataTimelineCell, Event, EventAccessor, FutureCleanupAccessor, TimeSteward,
       };
-      use array_ext::*;
-      use rpds::RedBlackTreeMap;
-      use std::collections::HashSet;
+      use crate::type_utils::list_of_types::{Listable, ListedType, Registrar};
       use crate::type_utils::{PersistentTypeId, PersistentlyIdentifiedType};
-      use crate::type_utils::list_of_types::{ListedType};
       use crate::{DeterministicRandomId, QueryResult, SimulationStateData};
+      use rpds::RedBlackTreeMap;
+      use std::array;
+      use std::cmp::{max, min, Ordering, Reverse};
+      use std::collections::{HashMap, HashSet};
+      use std::fmt::Debug;
+      use std::fmt::Write;
 
       pub type Coordinate = u64;
-      pub type NumDimensions = u32;
 
-      const DIMENSIONS: NumDimensions = $dims;
+      /// A type of coordinate that a Space can use for its objects. Detectors work with `Coordinate`s, which world coordinates are mapped to in an order-preserving way: unsigned coordinates stay the same, and signed ones are shifted so that 0 is in the middle of the space.
+      pub trait CoordinateType: Copy + Ord + Debug {
+        fn to_internal(self) -> Coordinate;
+        fn from_internal(coordinate: Coordinate) -> Self;
+        /// Where this coordinate is, from 0 to `extent - 1`, in a dimension that wraps around every `extent` coordinates.
+        fn wrapped(self, extent: Coordinate) -> Coordinate;
+        /// The inverse of `wrapped`, giving the world coordinate between 0 and `extent - 1`.
+        fn from_wrapped(coordinate: Coordinate) -> Self;
+      }
+      impl CoordinateType for u64 {
+        fn to_internal(self) -> Coordinate {
+          self
+        }
+        fn from_internal(coordinate: Coordinate) -> Self {
+          coordinate
+        }
+        fn wrapped(self, extent: Coordinate) -> Coordinate {
+          self % extent
+        }
+        fn from_wrapped(coordinate: Coordinate) -> Self {
+          coordinate
+        }
+      }
+      impl CoordinateType for i64 {
+        fn to_internal(self) -> Coordinate {
+          (self as Coordinate).wrapping_add(1 << 63)
+        }
+        fn from_internal(coordinate: Coordinate) -> Self {
+          coordinate.wrapping_sub(1 << 63) as i64
+        }
+        fn wrapped(self, extent: Coordinate) -> Coordinate {
+          (self as i128).rem_euclid(extent as i128) as Coordinate
+        }
+        fn from_wrapped(coordinate: Coordinate) -> Self {
+          coordinate as i64
+        }
+      }
 
       /// If there's only one interpretation of how your objects are arranged in space, it makes sense to implement this on a unit-like struct.
-      pub trait Space: SimulationStateData + PersistentlyIdentifiedType {
+      pub trait Space<const D: usize>: SimulationStateData + PersistentlyIdentifiedType {
         type Steward: TimeSteward;
         type Object: SimulationStateData + PersistentlyIdentifiedType;
         type DetectorDataPerObject: QueryResult;
-        type UniqueId: SimulationStateData;
+        type UniqueId: SimulationStateData + Ord;
+        type WorldCoordinate: CoordinateType;
 
-        const DIMENSIONS: NumDimensions;
+        /// For each dimension that wraps around, making the space a torus, the number of coordinates before it repeats. The coordinates in that dimension go from 0 to `extent - 1`, and a bounding box that crosses the seam has its lower bound above its upper bound.
+        ///
+        /// Every extent must be at least 1. In a dimension that wraps, segments don't cross the seam, and distances are measured the shorter way around.
+        const WRAP_EXTENTS: [Option<Coordinate>; D] = [None; D];
 
         // An Object generally has to store some opaque data for the collision detector.
         // It would normally include a DataHandle to a tree node.
@@ -50,7 +87,7 @@ macro_rules! time_steward_define_bbox_collision_detection {
         );
 
         // must be unique among ALL objects/space pairs
-        fn unique_id<A: EventAccessor<Steward = Self::Steward>>(
+        fn unique_id<A: Accessor<Steward = Self::Steward>>(
           &self,
           accessor: &A,
           object: &DataHandle<Self::Object>,
@@ -60,12 +97,12 @@ macro_rules! time_steward_define_bbox_collision_detection {
           &self,
           accessor: &A,
           object: &DataHandle<Self::Object>,
-        ) -> BoundingBox<Self>;
+        ) -> BoundingBox<D>;
         fn when_escapes<A: EventAccessor<Steward = Self::Steward>>(
           &self,
           accessor: &A,
           object: &DataHandle<Self::Object>,
-          _: BoundingBox<Self>,
+          _: BoundingBox<D>,
         ) -> Option<<<Self::Steward as TimeSteward>::Basics as Basics>::Time>;
 
         #[allow(unused_variables)]
@@ -82,67 +119,474 @@ macro_rules! time_steward_define_bbox_collision_detection {
           objects: [&DataHandle<Self::Object>; 2],
         ) {
         }
+
+        /// Undoes all changes that the methods above made at the current time to `object` and to `neighbors`. Detectors call this when undoing their own events. A simulation where those events can never be invalidated may panic here instead, the way `#[event(not_undoable)]` events do.
+        fn undo_detector_changes<A: FutureCleanupAccessor<Steward = Self::Steward>>(
+          &self,
+          accessor: &A,
+          object: &DataHandle<Self::Object>,
+          neighbors: &[DataHandle<Self::Object>],
+        );
+      }
+
+      /// A Space whose objects can be checked against segments, which detectors need for `objects_on_segment()` and `first_object_on_segment()`.
+      pub trait SegmentQueryableSpace<const D: usize>: Space<D> {
+        /// How far along `segment` it first touches `object`, or None if it misses. Detectors call this for the objects whose bounding boxes they find. For objects that fill their bounding boxes, it can just return `segment.box_entry(...)`.
+        fn segment_entry<A: Accessor<Steward = Self::Steward>>(
+          &self,
+          accessor: &A,
+          object: &DataHandle<Self::Object>,
+          segment: &Segment<D>,
+        ) -> Option<Fraction>;
+      }
+
+      /// A Space that can measure how far its objects are from a location, which detectors need for `objects_within_radius()` and `nearest_objects()`.
+      pub trait DistanceQueryableSpace<const D: usize>: Space<D> {
+        /// The square of the distance from `location` to the nearest point of `object`, which must be at least the squared distance to its bounding box. In a space that wraps around, this is the shorter way around. For objects that fill their bounding boxes, it can just return `bounding_box.wrapped_distance_squared::<Self>(location)`.
+        fn distance_squared<A: Accessor<Steward = Self::Steward>>(
+          &self,
+          accessor: &A,
+          object: &DataHandle<Self::Object>,
+          location: [Coordinate; D],
+        ) -> u128;
       }
 
-      pub trait Detector: SimulationStateData + PersistentlyIdentifiedType {
-        type Space: Space;
+      pub trait Detector<const D: usize>: SimulationStateData + PersistentlyIdentifiedType {
+        type Space: Space<D>;
         //type DetectorDataPerObject: SimulationStateData;
 
-        fn insert<A: EventAccessor<Steward = <Self::Space as Space>::Steward>>(
+        fn insert<A: EventAccessor<Steward = <Self::Space as Space<D>>::Steward>>(
           accessor: &A,
           detector: &DataHandle<Self>,
-          object: &DataHandle<<Self::Space as Space>::Object>,
-          location_hint: Option<&DataHandle<<Self::Space as Space>::Object>>,
+          object: &DataHandle<<Self::Space as Space<D>>::Object>,
+          location_hint: Option<&DataHandle<<Self::Space as Space<D>>::Object>>,
         );
-        fn remove<A: EventAccessor<Steward = <Self::Space as Space>::Steward>>(
+        fn remove<A: EventAccessor<Steward = <Self::Space as Space<D>>::Steward>>(
           accessor: &A,
           detector: &DataHandle<Self>,
-          object: &DataHandle<<Self::Space as Space>::Object>,
+          object: &DataHandle<<Self::Space as Space<D>>::Object>,
         );
-        fn changed_position<A: EventAccessor<Steward = <Self::Space as Space>::Steward>>(
+        fn changed_position<A: EventAccessor<Steward = <Self::Space as Space<D>>::Steward>>(
           accessor: &A,
           detector: &DataHandle<Self>,
-          object: &DataHandle<<Self::Space as Space>::Object>,
+          object: &DataHandle<<Self::Space as Space<D>>::Object>,
         );
-        fn changed_course<A: EventAccessor<Steward = <Self::Space as Space>::Steward>>(
+        fn changed_course<A: EventAccessor<Steward = <Self::Space as Space<D>>::Steward>>(
           accessor: &A,
           detector: &DataHandle<Self>,
-          object: &DataHandle<<Self::Space as Space>::Object>,
+          object: &DataHandle<<Self::Space as Space<D>>::Object>,
         );
-        fn objects_near_object<A: Accessor<Steward = <Self::Space as Space>::Steward>>(
+        fn objects_near_object<A: Accessor<Steward = <Self::Space as Space<D>>::Steward>>(
+          accessor: &A,
+          detector: &DataHandle<Self>,
+          object: &DataHandle<<Self::Space as Space<D>>::Object>,
+        ) -> Vec<DataHandle<<Self::Space as Space<D>>::Object>>;
+        fn objects_near_box<A: Accessor<Steward = <Self::Space as Space<D>>::Steward>>(
           accessor: &A,
           detector: &DataHandle<Self>,
-          object: &DataHandle<<Self::Space as Space>::Object>,
-        ) -> Vec<DataHandle<<Self::Space as Space>::Object>>;
-        fn objects_near_box<A: Accessor<Steward = <Self::Space as Space>::Steward>>(
+          bounds: BoundingBox<D>,
+          location_hint: Option<&DataHandle<<Self::Space as Space<D>>::Object>>,
+        ) -> Vec<DataHandle<<Self::Space as Space<D>>::Object>>;
+
+        fn space(&self) -> &Self::Space;
+
+        /// The objects that `segment` touches, each with how far along it they are first touched, nearest first. Objects touched at the same distance stay in the order the detector found them.
+        ///
+        /// The default implementation checks every object near the segment's bounding box, which is slow for long diagonal segments.
+        fn objects_on_segment<A: Accessor<Steward = <Self::Space as Space<D>>::Steward>>(
+          accessor: &A,
+          detector: &DataHandle<Self>,
+          segment: &Segment<D>,
+        ) -> Vec<(Fraction, DataHandle<<Self::Space as Space<D>>::Object>)>
+        where
+          Self::Space: SegmentQueryableSpace<D>,
+        {
+          assert_segment_inside::<Self::Space, D>(segment);
+          let mut hits: Vec<_> =
+            Self::objects_near_box(accessor, detector, segment.bounding_box(), None)
+              .into_iter()
+              .filter_map(|object| {
+                detector
+                  .space()
+                  .segment_entry(accessor, &object, segment)
+                  .map(|entry| (entry, object))
+              })
+              .collect();
+          hits.sort_by_key(|hit| hit.0);
+          hits
+        }
+        /// The first object that `segment` touches, if any, as `objects_on_segment()` would order them.
+        fn first_object_on_segment<A: Accessor<Steward = <Self::Space as Space<D>>::Steward>>(
+          accessor: &A,
+          detector: &DataHandle<Self>,
+          segment: &Segment<D>,
+        ) -> Option<(Fraction, DataHandle<<Self::Space as Space<D>>::Object>)>
+        where
+          Self::Space: SegmentQueryableSpace<D>,
+        {
+          Self::objects_on_segment(accessor, detector, segment)
+            .into_iter()
+            .next()
+        }
+
+        /// The objects at most `radius` away from `location`, nearest first, with their squared distances. Objects at the same distance are ordered by `Space::unique_id`, so that every peer gets the same order. To search around an object, pass it as `exclude`. A radius of `Coordinate::max_value()` includes every object.
+        ///
+        /// The default implementation checks every object near the box around the circle.
+        fn objects_within_radius<A: Accessor<Steward = <Self::Space as Space<D>>::Steward>>(
+          accessor: &A,
+          detector: &DataHandle<Self>,
+          location: [Coordinate; D],
+          radius: Coordinate,
+          exclude: Option<&DataHandle<<Self::Space as Space<D>>::Object>>,
+        ) -> Vec<(u128, DataHandle<<Self::Space as Space<D>>::Object>)>
+        where
+          Self::Space: DistanceQueryableSpace<D>,
+        {
+          assert_location_inside::<Self::Space, D>(location);
+          let bounds = box_around::<Self::Space, D>(location, radius);
+          let limit = squared_radius(radius);
+          let hits = Self::objects_near_box(accessor, detector, bounds, exclude)
+            .into_iter()
+            .filter(|object| Some(object) != exclude)
+            .filter_map(|object| {
+              let distance = detector
+                .space()
+                .distance_squared(accessor, &object, location);
+              if distance <= limit {
+                Some((distance, object))
+              } else {
+                None
+              }
+            })
+            .collect();
+          sort_by_distance(accessor, detector.space(), hits)
+        }
+        /// The `k` objects nearest to `location`, ordered like `objects_within_radius()`, or all of them if there are fewer than `k`.
+        ///
+        /// The default implementation calls `objects_within_radius()` with larger and larger radii.
+        fn nearest_objects<A: Accessor<Steward = <Self::Space as Space<D>>::Steward>>(
+          accessor: &A,
+          detector: &DataHandle<Self>,
+          location: [Coordinate; D],
+          k: usize,
+          exclude: Option<&DataHandle<<Self::Space as Space<D>>::Object>>,
+        ) -> Vec<(u128, DataHandle<<Self::Space as Space<D>>::Object>)>
+        where
+          Self::Space: DistanceQueryableSpace<D>,
+        {
+          nearest_by_growing_radius(accessor, detector, location, k, exclude, 1)
+        }
+
+        /// Reports what the detector has stored, for tuning it and checking its health. This works from any Accessor, including snapshots, but it looks at every object, so it's slow for big simulations.
+        fn inspect<A: Accessor<Steward = <Self::Space as Space<D>>::Steward>>(
           accessor: &A,
           detector: &DataHandle<Self>,
-          bounds: BoundingBox<Self::Space>,
-          location_hint: Option<&DataHandle<<Self::Space as Space>::Object>>,
-        ) -> Vec<DataHandle<<Self::Space as Space>::Object>>;
+        ) -> Inspection<Self::Space, D>;
       }
 
-      #[derive(Serialize, Deserialize, Debug, Derivative)]
-      #[derivative(Clone(bound = ""), PartialEq(bound = ""), Eq(bound = ""))]
+      /// What a detector has stored, as returned by `Detector::inspect()`. This can be serialized in any serde format, such as JSON, or written as CSV with `regions_csv()` and `objects_csv()`.
+      #[derive(Serialize, Deserialize, Debug)]
+      #[serde(bound = "")]
+      pub struct Inspection<S: Space<D>, const D: usize> {
+        /// Every grid cell or tree node that has objects stored in it. `SweepAndPruneDetector` doesn't divide space into regions, so it reports none.
+        pub regions: Vec<RegionInspection<D>>,
+        /// Every object in the detector, in order of `Space::unique_id`.
+        pub objects: Vec<ObjectInspection<S, D>>,
+      }
+      #[derive(Clone, PartialEq, Eq, Serialize, Deserialize, Debug)]
+      pub struct RegionInspection<const D: usize> {
+        pub bounds: BoundingBox<D>,
+        /// How many objects are stored in this region.
+        pub objects: usize,
+      }
+      #[derive(Serialize, Deserialize, Debug)]
       #[serde(bound = "")]
-      pub struct BoundingBox<S: Space> {
-        pub bounds: [[Coordinate; 2]; DIMENSIONS as usize],
-        pub _marker: PhantomData<S>, // waiting for the DIMENSIONS associated constant to be usable
+      pub struct ObjectInspection<S: Space<D>, const D: usize> {
+        pub id: S::UniqueId,
+        /// How many of the regions the object is stored in.
+        pub regions: usize,
+        /// When the object's pending Escape event is predicted to happen, if it has one.
+        pub escape_time: Option<<<S::Steward as TimeSteward>::Basics as Basics>::Time>,
+      }
+
+      impl<S: Space<D>, const D: usize> Inspection<S, D> {
+        /// The `count` regions with the most objects, most first.
+        pub fn largest_regions(&self, count: usize) -> Vec<&RegionInspection<D>> {
+          let mut result: Vec<_> = self.regions.iter().collect();
+          result.sort_by_key(|region| Reverse(region.objects));
+          result.truncate(count);
+          result
+        }
+        /// The `count` objects stored in the most regions, most first.
+        pub fn most_spread_objects(&self, count: usize) -> Vec<&ObjectInspection<S, D>> {
+          let mut result: Vec<_> = self.objects.iter().collect();
+          result.sort_by_key(|object| Reverse(object.regions));
+          result.truncate(count);
+          result
+        }
+        /// The objects that have pending Escape events, soonest first.
+        pub fn pending_escapes(&self) -> Vec<&ObjectInspection<S, D>> {
+          let mut result: Vec<_> = self
+            .objects
+            .iter()
+            .filter(|object| object.escape_time.is_some())
+            .collect();
+          result.sort_by(|first, second| first.escape_time.cmp(&second.escape_time));
+          result
+        }
+
+        /// One line for each region, with the number of objects in it followed by its bounds in each dimension, after a header line.
+        pub fn regions_csv(&self) -> String {
+          let mut result = String::from("objects");
+          for dimension in 0..D {
+            write!(result, ",lower_{},upper_{}", dimension, dimension).unwrap();
+          }
+          result.push('\n');
+          for region in self.regions.iter() {
+            write!(result, "{}", region.objects).unwrap();
+            for [lower, upper] in region.bounds.bounds.iter() {
+              write!(result, ",{},{}", lower, upper).unwrap();
+            }
+            result.push('\n');
+          }
+          result
+        }
+        /// One line for each object, with its unique ID, how many regions it's in, and the time of its pending Escape event (empty if there is none), after a header line. The IDs and times are written with their Debug formatting.
+        pub fn objects_csv(&self) -> String {
+          let mut result = String::from("id,regions,escape_time\n");
+          for object in self.objects.iter() {
+            let escape_time = object
+              .escape_time
+              .as_ref()
+              .map_or_else(String::new, |time| csv_field(&format!("{:?}", time)));
+            writeln!(
+              result,
+              "{},{},{}",
+              csv_field(&format!("{:?}", object.id)),
+              object.regions,
+              escape_time
+            )
+            .unwrap();
+          }
+          result
+        }
+      }
+
+      /// Quotes `text` if it has any characters that mean something in CSV.
+      fn csv_field(text: &str) -> String {
+        if text.contains(|character| matches!(character, ',' | '"' | '\n' | '\r')) {
+          format!("\"{}\"", text.replace('"', "\"\""))
+        } else {
+          text.to_string()
+        }
+      }
+
+      /// Lists the objects for an Inspection, given how many regions each one is stored in, and a function to get the pending Escape event from its detector data.
+      fn inspect_objects<S: Space<D>, A: Accessor<Steward = S::Steward>, const D: usize>(
+        accessor: &A,
+        space: &S,
+        regions: HashMap<DataHandle<S::Object>, usize>,
+        escape: impl Fn(S::DetectorDataPerObject) -> Option<<S::Steward as TimeSteward>::EventHandle>,
+      ) -> Vec<ObjectInspection<S, D>> {
+        let mut result: Vec<ObjectInspection<S, D>> = regions
+          .into_iter()
+          .map(|(object, regions)| ObjectInspection {
+            id: space.unique_id(accessor, &object),
+            regions,
+            escape_time: space
+              .get_detector_data(accessor, &object)
+              .and_then(&escape)
+              .map(|handle| handle.time().clone()),
+          })
+          .collect();
+        result.sort_by(|first, second| first.id.cmp(&second.id));
+        result
+      }
+
+      /// The biggest box that contains `bounds` and is covered by `expanded`, where each of `expanded` contains the corresponding one of `bounds.pieces()`. Detectors use this to find the box an object can move in without changing where it's stored.
+      fn combine_pieces<S: Space<D>, const D: usize>(
+        bounds: &BoundingBox<D>,
+        expanded: &[BoundingBox<D>],
+      ) -> BoundingBox<D> {
+        let pieces = bounds.pieces::<S>();
+        BoundingBox::new(array::from_fn(|dimension| {
+          let [low, high] = bounds.bounds[dimension];
+          let last = S::WRAP_EXTENTS[dimension].map_or(Coordinate::max_value(), |extent| extent - 1);
+          let sides = |lower_side: bool| {
+            pieces
+              .iter()
+              .zip(expanded)
+              .filter(move |(piece, _)| (piece.bounds[dimension][0] == 0) == lower_side)
+              .map(move |(_, expanded)| expanded.bounds[dimension])
+          };
+          if low <= high {
+            [
+              expanded
+                .iter()
+                .map(|expanded| expanded.bounds[dimension][0])
+                .max()
+                .unwrap(),
+              min(
+                last,
+                expanded
+                  .iter()
+                  .map(|expanded| expanded.bounds[dimension][1])
+                  .min()
+                  .unwrap(),
+              ),
+            ]
+          } else {
+            let upper_low = sides(false).map(|bounds| bounds[0]).max().unwrap();
+            let lower_high = min(last, sides(true).map(|bounds| bounds[1]).min().unwrap());
+            if upper_low <= lower_high + 1 {
+              [0, last]
+            } else {
+              [upper_low, lower_high]
+            }
+          }
+        }))
+      }
+
+      /// Panics if a dimension of `S` wraps around every 0 coordinates, which would leave no coordinates at all.
+      fn check_wrap_extents<S: Space<D>, const D: usize>() {
+        assert!(
+          !S::WRAP_EXTENTS.contains(&Some(0)),
+          "a Space can't wrap around every 0 coordinates"
+        );
+      }
+
+      /// Panics if `location` is past the end of a dimension of `S` that wraps around.
+      fn assert_location_inside<S: Space<D>, const D: usize>(location: [Coordinate; D]) {
+        for (coordinate, extent) in location.iter().zip(S::WRAP_EXTENTS.iter()) {
+          if let Some(extent) = extent {
+            assert!(
+              coordinate < extent,
+              "a location can't be past the end of a dimension that wraps around"
+            );
+          }
+        }
+      }
+      /// Panics if `segment` goes past the end of a dimension of `S` that wraps around. Segments don't cross the seam, so both ends have to be inside the space.
+      fn assert_segment_inside<S: Space<D>, const D: usize>(segment: &Segment<D>) {
+        assert_location_inside::<S, D>(segment.start);
+        assert_location_inside::<S, D>(segment.end);
+      }
+
+      /// The box of locations at most `radius` away from `location` in each dimension, going around the seam in dimensions that wrap.
+      fn box_around<S: Space<D>, const D: usize>(
+        location: [Coordinate; D],
+        radius: Coordinate,
+      ) -> BoundingBox<D> {
+        BoundingBox::new(array::from_fn(|dimension| {
+          let coordinate = location[dimension];
+          match S::WRAP_EXTENTS[dimension] {
+            None => [
+              coordinate.saturating_sub(radius),
+              coordinate.saturating_add(radius),
+            ],
+            Some(extent) => {
+              if radius >= extent / 2 {
+                [0, extent - 1]
+              } else {
+                [
+                  if coordinate >= radius {
+                    coordinate - radius
+                  } else {
+                    coordinate + (extent - radius)
+                  },
+                  if extent - 1 - coordinate >= radius {
+                    coordinate + radius
+                  } else {
+                    radius - (extent - coordinate)
+                  },
+                ]
+              }
+            }
+          }
+        }))
+      }
+
+      /// How many coordinates `bounds` covers in a dimension that wraps around every `extent`, minus 1, where a lower bound above the upper bound means it straddles the seam.
+      fn wrapped_width(bounds: [Coordinate; 2], extent: Coordinate) -> Coordinate {
+        let [low, high] = bounds;
+        if low <= high {
+          high - low
+        } else {
+          (extent - low) + high
+        }
+      }
+
+      fn squared_radius(radius: Coordinate) -> u128 {
+        if radius == Coordinate::max_value() {
+          u128::max_value()
+        } else {
+          radius as u128 * radius as u128
+        }
+      }
+
+      /// Sorts `hits` by distance, then by `Space::unique_id`.
+      fn sort_by_distance<S: Space<D>, A: Accessor<Steward = S::Steward>, const D: usize>(
+        accessor: &A,
+        space: &S,
+        hits: Vec<(u128, DataHandle<S::Object>)>,
+      ) -> Vec<(u128, DataHandle<S::Object>)> {
+        let mut keyed: Vec<_> = hits
+          .into_iter()
+          .map(|(distance, object)| (distance, space.unique_id(accessor, &object), object))
+          .collect();
+        keyed.sort_by(|first, second| (first.0, &first.1).cmp(&(second.0, &second.1)));
+        keyed
+          .into_iter()
+          .map(|(distance, _, object)| (distance, object))
+          .collect()
       }
 
-      impl<S: Space> BoundingBox<S> {
-        pub fn new(bounds: [[Coordinate; 2]; DIMENSIONS as usize]) -> Self {
-          BoundingBox {
-            bounds: bounds,
-            _marker: PhantomData,
+      /// Finds the `k` nearest objects by doubling the radius, starting at `initial_radius`, until there are at least `k` objects within it.
+      fn nearest_by_growing_radius<
+        T: Detector<D>,
+        A: Accessor<Steward = <T::Space as Space<D>>::Steward>,
+        const D: usize,
+      >(
+        accessor: &A,
+        detector: &DataHandle<T>,
+        location: [Coordinate; D],
+        k: usize,
+        exclude: Option<&DataHandle<<T::Space as Space<D>>::Object>>,
+        initial_radius: Coordinate,
+      ) -> Vec<(u128, DataHandle<<T::Space as Space<D>>::Object>)>
+      where
+        T::Space: DistanceQueryableSpace<D>,
+      {
+        if k == 0 {
+          return Vec::new();
+        }
+        let mut radius = max(initial_radius, 1);
+        loop {
+          let mut result = T::objects_within_radius(accessor, detector, location, radius, exclude);
+          if result.len() >= k || radius == Coordinate::max_value() {
+            result.truncate(k);
+            return result;
           }
+          radius = radius.saturating_mul(2);
         }
-        pub fn centered(center: [Coordinate; DIMENSIONS as usize], radius: Coordinate) -> Self {
-          Self::new(Array::from_fn(|dimension| {
+      }
+
+      #[derive(Clone, PartialEq, Eq, Serialize, Deserialize, Debug)]
+      pub struct BoundingBox<const D: usize> {
+        #[serde(with = "crate::type_utils::serde_array")]
+        pub bounds: [[Coordinate; 2]; D],
+      }
+
+      impl<const D: usize> BoundingBox<D> {
+        pub fn new(bounds: [[Coordinate; 2]; D]) -> Self {
+          BoundingBox { bounds }
+        }
+        pub fn centered(center: [Coordinate; D], radius: Coordinate) -> Self {
+          Self::new(array::from_fn(|dimension| {
             [center[dimension] - radius, center[dimension] + radius]
           }))
         }
-        pub fn locations(&self) -> Vec<[Coordinate; DIMENSIONS as usize]> {
+        pub fn locations(&self) -> Vec<[Coordinate; D]> {
           for bounds in self.bounds.iter() {
             assert!(bounds[0] <= bounds[1], "invalid bounding box");
           }
@@ -150,7 +594,7 @@ macro_rules! time_steward_define_bbox_collision_detection {
           let mut position = self.bounds.map(|bounds| bounds[0]);
           'outer: loop {
             result.push(position);
-            for dimension in 0..DIMENSIONS as usize {
+            for dimension in 0..D {
               position[dimension] += 1;
               if position[dimension] <= self.bounds[dimension][1] {
                 continue 'outer;
@@ -162,37 +606,310 @@ macro_rules! time_steward_define_bbox_collision_detection {
           result
         }
 
-        pub fn contains_location(&self, location: [Coordinate; DIMENSIONS as usize]) -> bool {
+        pub fn contains_location(&self, location: [Coordinate; D]) -> bool {
           self.bounds.iter().enumerate().all(|(dimension, bounds)| {
-            location[dimension] >= bounds[0] && location[dimension] <= bounds[1]
+            if bounds[0] <= bounds[1] {
+              location[dimension] >= bounds[0] && location[dimension] <= bounds[1]
+            } else {
+              location[dimension] >= bounds[0] || location[dimension] <= bounds[1]
+            }
+          })
+        }
+
+        /// Converts a box from world coordinates. In a dimension that wraps around, a box that crosses the seam comes out straddling it, and a box at least as wide as the space covers the whole dimension.
+        pub fn from_world<S: Space<D>>(bounds: [[S::WorldCoordinate; 2]; D]) -> Self {
+          check_wrap_extents::<S, D>();
+          Self::new(array::from_fn(|dimension| {
+            let [low, high] = bounds[dimension];
+            assert!(low <= high, "invalid bounding box");
+            match S::WRAP_EXTENTS[dimension] {
+              None => [low.to_internal(), high.to_internal()],
+              Some(extent) => {
+                if high.to_internal() - low.to_internal() >= extent - 1 {
+                  [0, extent - 1]
+                } else {
+                  [low.wrapped(extent), high.wrapped(extent)]
+                }
+              }
+            }
+          }))
+        }
+        /// Converts a box to world coordinates. Where the box straddles the wrap seam, the lower bound is above the upper bound.
+        pub fn to_world<S: Space<D>>(&self) -> [[S::WorldCoordinate; 2]; D] {
+          array::from_fn(|dimension| {
+            array::from_fn(|direction| {
+              let coordinate = self.bounds[dimension][direction];
+              match S::WRAP_EXTENTS[dimension] {
+                None => S::WorldCoordinate::from_internal(coordinate),
+                Some(_) => S::WorldCoordinate::from_wrapped(coordinate),
+              }
+            })
+          })
+        }
+
+        /// Splits this box where it straddles the wrap seam, giving one box for each combination of sides of the seam.
+        pub fn pieces<S: Space<D>>(&self) -> Vec<BoundingBox<D>> {
+          self.split_at_seam(|dimension| S::WRAP_EXTENTS[dimension].expect("invalid bounding box") - 1)
+        }
+        /// Like `pieces()`, with the upper piece in each straddled dimension ending at `last(dimension)`.
+        fn split_at_seam(&self, last: impl Fn(usize) -> Coordinate) -> Vec<BoundingBox<D>> {
+          let mut result = vec![self.clone()];
+          for dimension in 0..D {
+            let [low, high] = self.bounds[dimension];
+            if low > high {
+              let last = last(dimension);
+              result = result
+                .into_iter()
+                .flat_map(|piece| {
+                  let mut upper = piece.clone();
+                  upper.bounds[dimension] = [low, last];
+                  let mut lower = piece;
+                  lower.bounds[dimension] = [0, high];
+                  vec![upper, lower]
+                })
+                .collect();
+            }
+          }
+          result
+        }
+
+        /// The square of the distance from `location` to the nearest point in this box, or `u128::max_value()` if that's too big to represent.
+        pub fn distance_squared(&self, location: [Coordinate; D]) -> u128 {
+          self
+            .bounds
+            .iter()
+            .zip(location.iter())
+            .fold(0u128, |total, (bounds, &coordinate)| {
+              let gap = if coordinate < bounds[0] {
+                bounds[0] - coordinate
+              } else if coordinate > bounds[1] {
+                coordinate - bounds[1]
+              } else {
+                0
+              } as u128;
+              total.saturating_add(gap * gap)
+            })
+        }
+        /// Like `distance_squared()`, but in the dimensions of `S` that wrap around, this goes the shorter way around, and the box may straddle the seam. `location` must be inside the space.
+        pub fn wrapped_distance_squared<S: Space<D>>(&self, location: [Coordinate; D]) -> u128 {
+          assert_location_inside::<S, D>(location);
+          (0..D).fold(0u128, |total, dimension| {
+            let [low, high] = self.bounds[dimension];
+            let coordinate = location[dimension];
+            let gap = match S::WRAP_EXTENTS[dimension] {
+              // a tree node can begin past the end of the space, but nothing is stored there
+              Some(extent) if low < extent => {
+                let high = min(high, extent - 1);
+                if low > high {
+                  if coordinate >= low || coordinate <= high {
+                    0
+                  } else {
+                    min(low - coordinate, coordinate - high)
+                  }
+                } else if coordinate < low {
+                  min(low - coordinate, coordinate + (extent - high))
+                } else if coordinate > high {
+                  min(coordinate - high, low + (extent - coordinate))
+                } else {
+                  0
+                }
+              }
+              _ => {
+                if coordinate < low {
+                  low - coordinate
+                } else if coordinate > high {
+                  coordinate - high
+                } else {
+                  0
+                }
+              }
+            } as u128;
+            total.saturating_add(gap * gap)
           })
         }
       }
 
+      /// A distance along a `Segment`: `numerator / denominator` of the way from its start to its end. Fractions compare by value, so 1/2 == 2/4.
+      #[derive(Copy, Clone, Serialize, Deserialize, Debug)]
+      pub struct Fraction {
+        pub numerator: Coordinate,
+        pub denominator: Coordinate,
+      }
+
+      impl Fraction {
+        pub fn new(numerator: Coordinate, denominator: Coordinate) -> Self {
+          assert!(denominator != 0, "a Fraction's denominator can't be 0");
+          Fraction {
+            numerator,
+            denominator,
+          }
+        }
+        pub fn zero() -> Self {
+          Self::new(0, 1)
+        }
+        pub fn one() -> Self {
+          Self::new(1, 1)
+        }
+      }
+      impl Ord for Fraction {
+        fn cmp(&self, other: &Self) -> Ordering {
+          // both products are below 2^128, since neither factor can be 2^64
+          (self.numerator as u128 * other.denominator as u128)
+            .cmp(&(other.numerator as u128 * self.denominator as u128))
+        }
+      }
+      impl PartialOrd for Fraction {
+        fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+          Some(self.cmp(other))
+        }
+      }
+      impl PartialEq for Fraction {
+        fn eq(&self, other: &Self) -> bool {
+          self.cmp(other) == Ordering::Equal
+        }
+      }
+      impl Eq for Fraction {}
+
+      /// The straight path from `start` to `end`, including both ends. Rays are segments that go on until they leave the space.
+      ///
+      /// In a dimension that wraps around, a segment doesn't cross the seam, and both ends have to be inside the space. To follow a path across the seam, query the part on each side of it.
+      #[derive(Clone, PartialEq, Eq, Serialize, Deserialize, Debug)]
+      pub struct Segment<const D: usize> {
+        #[serde(with = "crate::type_utils::serde_array")]
+        pub start: [Coordinate; D],
+        #[serde(with = "crate::type_utils::serde_array")]
+        pub end: [Coordinate; D],
+      }
+
+      impl<const D: usize> Segment<D> {
+        pub fn new(start: [Coordinate; D], end: [Coordinate; D]) -> Self {
+          Segment { start, end }
+        }
+        /// The ray from `origin` in `direction`, which ends at the last multiple of `direction` that is still in the space.
+        pub fn ray(origin: [Coordinate; D], direction: [i64; D]) -> Self {
+          Self::ray_until(origin, direction, [Coordinate::max_value(); D])
+        }
+        /// Like `ray()`, but in the dimensions of `S` that wrap around, the ray ends before it would cross the seam.
+        pub fn ray_in<S: Space<D>>(origin: [Coordinate; D], direction: [i64; D]) -> Self {
+          Self::ray_until(
+            origin,
+            direction,
+            array::from_fn(|dimension| {
+              S::WRAP_EXTENTS[dimension].map_or(Coordinate::max_value(), |extent| extent - 1)
+            }),
+          )
+        }
+        fn ray_until(origin: [Coordinate; D], direction: [i64; D], last: [Coordinate; D]) -> Self {
+          let steps = (0..D)
+            .filter_map(|dimension| {
+              let step = direction[dimension].wrapping_abs() as Coordinate;
+              if direction[dimension] > 0 {
+                Some(last[dimension].saturating_sub(origin[dimension]) / step)
+              } else if direction[dimension] < 0 {
+                Some(origin[dimension] / step)
+              } else {
+                None
+              }
+            })
+            .min()
+            .expect("a ray needs a nonzero direction");
+          Self::new(
+            origin,
+            array::from_fn(|dimension| {
+              let distance = (direction[dimension].wrapping_abs() as Coordinate) * steps;
+              if direction[dimension] >= 0 {
+                origin[dimension] + distance
+              } else {
+                origin[dimension] - distance
+              }
+            }),
+          )
+        }
+        pub fn bounding_box(&self) -> BoundingBox<D> {
+          BoundingBox::new(array::from_fn(|dimension| {
+            [
+              min(self.start[dimension], self.end[dimension]),
+              max(self.start[dimension], self.end[dimension]),
+            ]
+          }))
+        }
+        /// How far along this segment it first touches `bounds`, or None if it never does. `bounds` may straddle the wrap seam, as long as the segment stays inside the space.
+        pub fn box_entry(&self, bounds: &BoundingBox<D>) -> Option<Fraction> {
+          if bounds.bounds.iter().any(|bounds| bounds[0] > bounds[1]) {
+            // the segment's coordinates are all before the end of the space, so the upper pieces can go on past it
+            return bounds
+              .split_at_seam(|_| Coordinate::max_value())
+              .iter()
+              .filter_map(|piece| self.box_entry(piece))
+              .min();
+          }
+          let mut entry = Fraction::zero();
+          let mut exit = Fraction::one();
+          for dimension in 0..D {
+            let (start, end) = (self.start[dimension], self.end[dimension]);
+            let [low, high] = bounds.bounds[dimension];
+            if max(start, end) < low || high < min(start, end) {
+              return None;
+            }
+            let (dimension_entry, dimension_exit) = if start < end {
+              let length = end - start;
+              (
+                Fraction::new(low.saturating_sub(start), length),
+                Fraction::new(min(high, end) - start, length),
+              )
+            } else if end < start {
+              let length = start - end;
+              (
+                Fraction::new(start.saturating_sub(high), length),
+                Fraction::new(start - max(low, end), length),
+              )
+            } else {
+              continue;
+            };
+            entry = max(entry, dimension_entry);
+            exit = min(exit, dimension_exit);
+          }
+          if entry <= exit {
+            Some(entry)
+          } else {
+            None
+          }
+        }
+      }
+
       pub mod simple_grid {
         use super::*;
-        //use array_ext::*;
 
-        pub type Types<S> = (ListedType<SimpleGridDetector<S>>, ListedType<Escape<S>>);
+        pub type Types<S, const D: usize> = (
+          ListedType<SimpleGridDetector<S, D>>,
+          ListedType<Escape<S, D>>,
+        );
 
         #[derive(Serialize, Deserialize, Debug)]
         #[serde(bound = "")]
-        pub struct SimpleGridDetector<S: Space> {
+        pub struct SimpleGridDetector<S: Space<D>, const D: usize> {
           space: S,
           cell_size: Coordinate,
-          cells: DataTimelineCell<
-            SimpleTimeline<RedBlackTreeMap<[Coordinate; DIMENSIONS as usize], Cell<S>>, S::Steward>,
-          >,
+          cells:
+            DataTimelineCell<SimpleTimeline<RedBlackTreeMap<CellLocation<D>, Cell<S, D>>, S::Steward>>,
         }
-        impl<S: Space> PersistentlyIdentifiedType for SimpleGridDetector<S> {
+        impl<S: Space<D>, const D: usize> PersistentlyIdentifiedType for SimpleGridDetector<S, D> {
           const ID: PersistentTypeId = PersistentTypeId(0x6763f785bae6fe43 ^ S::ID.0);
         }
+        impl<S: Space<D>, const D: usize> Listable for SimpleGridDetector<S, D> {
+          fn register<R: Registrar>(registrar: &mut R) {
+            registrar.data::<Self>();
+          }
+        }
 
         #[derive(Serialize, Deserialize, Debug, Derivative)]
         #[derivative(Clone(bound = ""), PartialEq(bound = ""), Eq(bound = ""))]
         #[serde(bound = "")]
-        pub struct DetectorDataPerObject<S: Space> {
-          current_grid_bounds: BoundingBox<S>,
+        pub struct DetectorDataPerObject<S: Space<D>, const D: usize> {
+          /// One box of cells for each piece of the object's bounding box, as split by the wrap seam.
+          current_grid_bounds: Vec<BoundingBox<D>>,
+          /// The box the object can move in without changing cells.
+          escape_bounds: BoundingBox<D>,
           escapes_bounds_prediction: Option<<S::Steward as TimeSteward>::EventHandle>,
         }
         #[derive(Serialize, Deserialize, Debug, Derivative)]
@@ -203,39 +920,62 @@ macro_rules! time_steward_define_bbox_collision_detection {
           Default(bound = "")
         )]
         #[serde(bound = "")]
-        struct Cell<S: Space> {
+        struct Cell<S: Space<D>, const D: usize> {
           objects: Vec<DataHandle<S::Object>>,
         }
+        /// Which cell, counting in `cell_size`s along each dimension. This wraps the array only because serde can't serialize arrays of any length as map keys.
+        #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, Debug)]
+        struct CellLocation<const D: usize>(
+          #[serde(with = "crate::type_utils::serde_array")] [Coordinate; D],
+        );
         #[derive(Serialize, Deserialize, Debug, Derivative)]
         #[derivative(Clone(bound = ""))]
         #[serde(bound = "")]
-        pub struct Escape<S: Space> {
-          detector: DataHandle<SimpleGridDetector<S>>,
+        pub struct Escape<S: Space<D>, const D: usize> {
+          detector: DataHandle<SimpleGridDetector<S, D>>,
           object: DataHandle<S::Object>,
         }
-        impl<S: Space> PersistentlyIdentifiedType for Escape<S> {
+        impl<S: Space<D>, const D: usize> PersistentlyIdentifiedType for Escape<S, D> {
           const ID: PersistentTypeId = PersistentTypeId(0xf693c99eca6bee45 ^ S::ID.0);
         }
-        impl<S: Space<DetectorDataPerObject = DetectorDataPerObject<S>>> Event for Escape<S> {
+        impl<S: Space<D, DetectorDataPerObject = DetectorDataPerObject<S, D>>, const D: usize> Listable
+          for Escape<S, D>
+        {
+          fn register<R: Registrar>(registrar: &mut R) {
+            Self::register_event(registrar);
+          }
+        }
+        impl<S: Space<D, DetectorDataPerObject = DetectorDataPerObject<S, D>>, const D: usize> Event
+          for Escape<S, D>
+        {
           type Steward = S::Steward;
-          type ExecutionData = ();
+          /// The objects that started or stopped being neighbors of the escaping object.
+          type ExecutionData = Vec<DataHandle<S::Object>>;
           fn execute<Accessor: EventAccessor<Steward = Self::Steward>>(
             &self,
             accessor: &mut Accessor,
-          ) {
-            SimpleGridDetector::changed_position(accessor, &self.detector, &self.object);
+          ) -> Self::ExecutionData {
+            let new_bounds = self
+              .detector
+              .space
+              .current_bounding_box(accessor, &self.object);
+            SimpleGridDetector::update(accessor, &self.detector, &self.object, Some(new_bounds))
           }
           fn undo<Accessor: FutureCleanupAccessor<Steward = Self::Steward>>(
             &self,
-            _accessor: &mut Accessor,
-            _: (),
+            accessor: &mut Accessor,
+            changed_neighbors: Self::ExecutionData,
           ) {
-            unimplemented!()
+            self
+              .detector
+              .space
+              .undo_detector_changes(accessor, &self.object, &changed_neighbors);
+            SimpleGridDetector::unset(accessor, &self.detector);
           }
         }
 
-        impl<S: Space<DetectorDataPerObject = DetectorDataPerObject<S>>> Detector
-          for SimpleGridDetector<S>
+        impl<S: Space<D, DetectorDataPerObject = DetectorDataPerObject<S, D>>, const D: usize>
+          Detector<D> for SimpleGridDetector<S, D>
         {
           type Space = S;
           //type DetectorDataPerObject = DetectorDataPerObject;
@@ -247,8 +987,7 @@ macro_rules! time_steward_define_bbox_collision_detection {
             _location_hint: Option<&DataHandle<S::Object>>,
           ) {
             let new_bounds = detector.space.current_bounding_box(accessor, object);
-            let new_grid_bounds = detector.grid_box(&new_bounds);
-            Self::update(accessor, detector, object, Some(new_grid_bounds));
+            Self::update(accessor, detector, object, Some(new_bounds));
           }
           fn remove<A: EventAccessor<Steward = S::Steward>>(
             accessor: &A,
@@ -278,7 +1017,7 @@ macro_rules! time_steward_define_bbox_collision_detection {
             .clone();
 
             data.escapes_bounds_prediction =
-              Self::create_prediction(accessor, detector, object, &data.current_grid_bounds);
+              Self::create_prediction(accessor, detector, object, &data.escape_bounds);
 
             detector
               .space
@@ -296,8 +1035,8 @@ macro_rules! time_steward_define_bbox_collision_detection {
             };
             let cells = query(accessor, &detector.cells);
             let mut result = Vec::new();
-            for location in data.current_grid_bounds.locations() {
-              if let Some(cell) = cells.get(&location) {
+            for location in Self::locations(&data.current_grid_bounds) {
+              if let Some(cell) = cells.get(&CellLocation(location)) {
                 for neighbor in cell.objects.iter() {
                   if neighbor != object && !result.contains(neighbor) {
                     result.push(neighbor.clone());
@@ -308,17 +1047,37 @@ macro_rules! time_steward_define_bbox_collision_detection {
             result
           }
 
-          fn objects_near_box<A: Accessor<Steward = <Self::Space as Space>::Steward>>(
+          fn objects_near_box<A: Accessor<Steward = <Self::Space as Space<D>>::Steward>>(
             accessor: &A,
             detector: &DataHandle<Self>,
-            bounds: BoundingBox<Self::Space>,
-            _location_hint: Option<&DataHandle<<Self::Space as Space>::Object>>,
-          ) -> Vec<DataHandle<<Self::Space as Space>::Object>> {
+            bounds: BoundingBox<D>,
+            _location_hint: Option<&DataHandle<<Self::Space as Space<D>>::Object>>,
+          ) -> Vec<DataHandle<<Self::Space as Space<D>>::Object>> {
             let cells = query(accessor, &detector.cells);
             let mut result_existences = HashSet::new();
             let mut result = Vec::new();
-            for location in detector.grid_box(&bounds).locations() {
-              if let Some(cell) = cells.get(&location) {
+            let grid_boxes = detector.grid_boxes(&bounds);
+            let box_cells = grid_boxes.iter().fold(0 as Coordinate, |total, grid_box| {
+              total.saturating_add(grid_box.bounds.iter().fold(1, |cells, bounds| {
+                cells.saturating_mul((bounds[1] - bounds[0]).saturating_add(1))
+              }))
+            });
+            // a big box can have far more cells than are stored
+            let locations = if box_cells > cells.size() as Coordinate {
+              cells
+                .iter()
+                .map(|(location, _)| location.0)
+                .filter(|location| {
+                  grid_boxes
+                    .iter()
+                    .any(|grid_box| grid_box.contains_location(*location))
+                })
+                .collect()
+            } else {
+              Self::locations(&grid_boxes)
+            };
+            for location in locations {
+              if let Some(cell) = cells.get(&CellLocation(location)) {
                 for neighbor in cell.objects.iter() {
                   if result_existences.insert(neighbor) {
                     result.push(neighbor.clone());
@@ -328,14 +1087,86 @@ macro_rules! time_steward_define_bbox_collision_detection {
             }
             result
           }
+
+          fn space(&self) -> &S {
+            &self.space
+          }
+
+          fn objects_on_segment<A: Accessor<Steward = S::Steward>>(
+            accessor: &A,
+            detector: &DataHandle<Self>,
+            segment: &Segment<D>,
+          ) -> Vec<(Fraction, DataHandle<S::Object>)>
+          where
+            S: SegmentQueryableSpace<D>,
+          {
+            Self::segment_hits(accessor, detector, segment, false)
+          }
+          fn first_object_on_segment<A: Accessor<Steward = S::Steward>>(
+            accessor: &A,
+            detector: &DataHandle<Self>,
+            segment: &Segment<D>,
+          ) -> Option<(Fraction, DataHandle<S::Object>)>
+          where
+            S: SegmentQueryableSpace<D>,
+          {
+            Self::segment_hits(accessor, detector, segment, true)
+              .into_iter()
+              .next()
+          }
+
+          fn nearest_objects<A: Accessor<Steward = S::Steward>>(
+            accessor: &A,
+            detector: &DataHandle<Self>,
+            location: [Coordinate; D],
+            k: usize,
+            exclude: Option<&DataHandle<S::Object>>,
+          ) -> Vec<(u128, DataHandle<S::Object>)>
+          where
+            S: DistanceQueryableSpace<D>,
+          {
+            nearest_by_growing_radius(accessor, detector, location, k, exclude, detector.cell_size)
+          }
+
+          fn inspect<A: Accessor<Steward = S::Steward>>(
+            accessor: &A,
+            detector: &DataHandle<Self>,
+          ) -> Inspection<S, D> {
+            let cells = query(accessor, &detector.cells);
+            let mut regions = Vec::new();
+            let mut object_regions = HashMap::new();
+            for (location, cell) in cells.iter() {
+              let grid_box = BoundingBox::new(location.0.map(|coordinate| [coordinate; 2]));
+              let mut bounds = detector.real_box_from_grid(&grid_box);
+              for bounds in bounds.bounds.iter_mut() {
+                bounds[1] = bounds[1].saturating_add(detector.cell_size - 1);
+              }
+              regions.push(RegionInspection {
+                bounds,
+                objects: cell.objects.len(),
+              });
+              for object in cell.objects.iter() {
+                *object_regions.entry(object.clone()).or_insert(0) += 1;
+              }
+            }
+            Inspection {
+              regions,
+              objects: inspect_objects(accessor, &detector.space, object_regions, |data| {
+                data.escapes_bounds_prediction
+              }),
+            }
+          }
         }
 
-        impl<S: Space<DetectorDataPerObject = DetectorDataPerObject<S>>> SimpleGridDetector<S> {
+        impl<S: Space<D, DetectorDataPerObject = DetectorDataPerObject<S, D>>, const D: usize>
+          SimpleGridDetector<S, D>
+        {
           pub fn new<A: EventAccessor<Steward = S::Steward>>(
             accessor: &A,
             space: S,
             cell_size: Coordinate,
           ) -> DataHandle<Self> {
+            check_wrap_extents::<S, D>();
             let result = accessor.new_handle(SimpleGridDetector {
               space: space,
               cell_size: cell_size,
@@ -344,41 +1175,167 @@ macro_rules! time_steward_define_bbox_collision_detection {
             set(accessor, &result.cells, RedBlackTreeMap::new());
             result
           }
-          fn grid_box(&self, exact_box: &BoundingBox<S>) -> BoundingBox<S> {
+          /// Undoes the current event's changes to the detector's own data. Events that call `new()`, `insert()`, `remove()`, or `changed_position()` must call this in their `undo()`, along with undoing whatever the Space's methods changed.
+          pub fn unset<A: FutureCleanupAccessor<Steward = S::Steward>>(
+            accessor: &A,
+            detector: &DataHandle<Self>,
+          ) {
+            unset(accessor, &detector.cells);
+          }
+          /// The cells that a box is in, as one box of cells for each of its pieces.
+          fn grid_boxes(&self, exact_box: &BoundingBox<D>) -> Vec<BoundingBox<D>> {
+            exact_box
+              .pieces::<S>()
+              .iter()
+              .map(|piece| self.grid_box(piece))
+              .collect()
+          }
+          fn locations(grid_boxes: &[BoundingBox<D>]) -> Vec<[Coordinate; D]> {
+            let mut seen = HashSet::new();
+            grid_boxes
+              .iter()
+              .flat_map(|grid_box| grid_box.locations())
+              .filter(|location| seen.insert(*location))
+              .collect()
+          }
+          fn grid_box(&self, exact_box: &BoundingBox<D>) -> BoundingBox<D> {
             BoundingBox {
-              bounds: Array::from_fn(|dimension| {
-                Array::from_fn(|direction| {
-                  (exact_box.bounds[dimension][direction]
-                    + (direction as Coordinate) * (self.cell_size - 1))
+              bounds: array::from_fn(|dimension| {
+                array::from_fn(|direction| {
+                  exact_box.bounds[dimension][direction]
+                    .saturating_add((direction as Coordinate) * (self.cell_size - 1))
                     / self.cell_size
                 })
               }),
-              _marker: PhantomData,
             }
           }
-          fn real_box_from_grid(&self, grid_box: &BoundingBox<S>) -> BoundingBox<S> {
+          fn real_box_from_grid(&self, grid_box: &BoundingBox<D>) -> BoundingBox<D> {
             BoundingBox {
-              bounds: Array::from_fn(|dimension| {
-                Array::from_fn(|direction| grid_box.bounds[dimension][direction] * self.cell_size)
+              bounds: array::from_fn(|dimension| {
+                array::from_fn(|direction| grid_box.bounds[dimension][direction] * self.cell_size)
               }),
-              _marker: PhantomData,
             }
           }
+          /// The cells that `segment` passes through, each with how far along it they are entered, in that order.
+          ///
+          /// This walks the grid along the segment, unless that would visit more cells than are stored, in which case it checks all of the stored cells instead.
+          fn cells_on_segment(
+            &self,
+            cells: &RedBlackTreeMap<CellLocation<D>, Cell<S, D>>,
+            segment: &Segment<D>,
+          ) -> Vec<(Fraction, [Coordinate; D])> {
+            let mut location = segment.start.map(|coordinate| coordinate / self.cell_size);
+            let mut result = vec![(Fraction::zero(), location)];
+            while result.len() <= cells.size() {
+              let next = (0..D)
+                .filter_map(|dimension| {
+                  let (start, end) = (segment.start[dimension], segment.end[dimension]);
+                  if start < end {
+                    let boundary = location[dimension]
+                      .checked_add(1)?
+                      .checked_mul(self.cell_size)?;
+                    if boundary > end {
+                      return None;
+                    }
+                    Some((Fraction::new(boundary - start, end - start), dimension))
+                  } else if end < start {
+                    let boundary = location[dimension] * self.cell_size;
+                    if boundary <= end {
+                      return None;
+                    }
+                    Some((Fraction::new(start - boundary, start - end), dimension))
+                  } else {
+                    None
+                  }
+                })
+                .min();
+              match next {
+                None => return result,
+                Some((entry, dimension)) => {
+                  if segment.start[dimension] < segment.end[dimension] {
+                    location[dimension] += 1;
+                  } else {
+                    location[dimension] -= 1;
+                  }
+                  result.push((entry, location));
+                }
+              }
+            }
+
+            let mut result: Vec<_> = cells
+              .iter()
+              .filter_map(|(CellLocation(location), _)| {
+                // include the lowest coordinates of the next cells, to cover the points in between
+                let cell_box = BoundingBox::new(array::from_fn(|dimension| {
+                  [
+                    location[dimension].saturating_mul(self.cell_size),
+                    location[dimension]
+                      .saturating_add(1)
+                      .saturating_mul(self.cell_size),
+                  ]
+                }));
+                segment.box_entry(&cell_box).map(|entry| (entry, *location))
+              })
+              .collect();
+            result.sort_by_key(|cell| cell.0);
+            result
+          }
+          /// If `first_only`, this may leave out objects that are further along than the first one.
+          fn segment_hits<A: Accessor<Steward = S::Steward>>(
+            accessor: &A,
+            detector: &DataHandle<Self>,
+            segment: &Segment<D>,
+            first_only: bool,
+          ) -> Vec<(Fraction, DataHandle<S::Object>)>
+          where
+            S: SegmentQueryableSpace<D>,
+          {
+            assert_segment_inside::<S, D>(segment);
+            let cells = query(accessor, &detector.cells);
+            let mut seen = HashSet::new();
+            let mut hits = Vec::new();
+            let mut nearest: Option<Fraction> = None;
+            for (cell_entry, location) in detector.cells_on_segment(&cells, segment) {
+              // the objects we haven't seen yet can't be touched before the segment enters the cells they're in
+              if first_only && nearest.map_or(false, |nearest| cell_entry > nearest) {
+                break;
+              }
+              if let Some(cell) = cells.get(&CellLocation(location)) {
+                for object in cell.objects.iter() {
+                  if seen.insert(object) {
+                    if let Some(entry) = detector.space.segment_entry(accessor, object, segment) {
+                      nearest = Some(nearest.map_or(entry, |nearest| min(nearest, entry)));
+                      hits.push((entry, object.clone()));
+                    }
+                  }
+                }
+              }
+            }
+            hits.sort_by_key(|hit| hit.0);
+            hits
+          }
+          /// Returns the objects that started or stopped being neighbors of `object`.
           fn update<A: EventAccessor<Steward = S::Steward>>(
             accessor: &A,
             detector: &DataHandle<Self>,
             object: &DataHandle<S::Object>,
-            new_bounds: Option<BoundingBox<S>>,
-          ) {
+            new_bounds: Option<BoundingBox<D>>,
+          ) -> Vec<DataHandle<S::Object>> {
             let old_data = detector.space.get_detector_data(accessor, object);
 
+            let new_grid_bounds = new_bounds
+              .as_ref()
+              .map(|new_bounds| detector.grid_boxes(new_bounds));
             let mut cells = query(accessor, &detector.cells);
             let mut new_neighbors = Vec::new();
             let mut old_neighbors = Vec::new();
             //printlnerr!("{:?}", (& detector.space. unique_id (accessor, object), &new_bounds));
-            if let Some(new_bounds) = new_bounds.as_ref() {
-              for location in new_bounds.locations() {
-                let mut cell = cells.get(&location).cloned().unwrap_or(Default::default());
+            if let Some(new_grid_bounds) = new_grid_bounds.as_ref() {
+              for location in Self::locations(new_grid_bounds) {
+                let mut cell = cells
+                  .get(&CellLocation(location))
+                  .cloned()
+                  .unwrap_or(Default::default());
                 //printlnerr!("{:?}", (& location, cell.objects.iter().map (| object |detector.space. unique_id (accessor, object)).collect::<Vec<_>>()));
                 for neighbor in cell.objects.iter() {
                   if neighbor != object && !new_neighbors.contains(neighbor) {
@@ -387,37 +1344,40 @@ macro_rules! time_steward_define_bbox_collision_detection {
                 }
                 if !cell.objects.contains(object) {
                   cell.objects.push(object.clone());
-                  cells = cells.insert(location, cell);
+                  cells = cells.insert(CellLocation(location), cell);
                 }
               }
             }
             if let Some(old_data) = old_data.as_ref() {
-              for location in old_data.current_grid_bounds.locations() {
-                let mut cell = cells.get(&location).unwrap().clone();
+              for location in Self::locations(&old_data.current_grid_bounds) {
+                let mut cell = cells.get(&CellLocation(location)).unwrap().clone();
                 for neighbor in cell.objects.iter() {
                   if neighbor != object && !old_neighbors.contains(neighbor) {
                     old_neighbors.push(neighbor.clone());
                   }
                 }
-                if new_bounds
-                  .as_ref()
-                  .map_or(true, |new_bounds| !new_bounds.contains_location(location))
-                {
+                if new_grid_bounds.as_ref().map_or(true, |new_grid_bounds| {
+                  !new_grid_bounds
+                    .iter()
+                    .any(|grid_box| grid_box.contains_location(location))
+                }) {
                   cell.objects.retain(|a| a != object);
                   if cell.objects.is_empty() {
-                    cells = cells.remove(&location);
+                    cells = cells.remove(&CellLocation(location));
                   } else {
-                    cells = cells.insert(location, cell);
+                    cells = cells.insert(CellLocation(location), cell);
                   }
                 }
               }
             }
 
+            let mut changed_neighbors = Vec::new();
             for neighbor in old_neighbors.iter() {
               if !new_neighbors.contains(&neighbor) {
                 detector
                   .space
                   .stop_being_neighbors(accessor, [object, neighbor]);
+                changed_neighbors.push(neighbor.clone());
               }
             }
             for neighbor in new_neighbors.iter() {
@@ -425,31 +1385,44 @@ macro_rules! time_steward_define_bbox_collision_detection {
                 detector
                   .space
                   .become_neighbors(accessor, [object, neighbor]);
+                changed_neighbors.push(neighbor.clone());
               }
             }
 
-            let new_data = new_bounds.map(|new_bounds| DetectorDataPerObject {
-              current_grid_bounds: new_bounds.clone(),
-              escapes_bounds_prediction: Self::create_prediction(
-                accessor,
-                detector,
-                object,
+            let new_data = new_bounds.map(|new_bounds| {
+              let new_grid_bounds = new_grid_bounds.unwrap();
+              let escape_bounds = combine_pieces::<S, D>(
                 &new_bounds,
-              ),
+                &new_grid_bounds
+                  .iter()
+                  .map(|grid_box| detector.real_box_from_grid(grid_box))
+                  .collect::<Vec<_>>(),
+              );
+              DetectorDataPerObject {
+                escapes_bounds_prediction: Self::create_prediction(
+                  accessor,
+                  detector,
+                  object,
+                  &escape_bounds,
+                ),
+                current_grid_bounds: new_grid_bounds,
+                escape_bounds,
+              }
             });
 
             detector.space.set_detector_data(accessor, object, new_data);
             set(accessor, &detector.cells, cells);
+            changed_neighbors
           }
           fn create_prediction<A: EventAccessor<Steward = S::Steward>>(
             accessor: &A,
             detector: &DataHandle<Self>,
             object: &DataHandle<S::Object>,
-            grid_box: &BoundingBox<S>,
+            escape_bounds: &BoundingBox<D>,
           ) -> Option<<S::Steward as TimeSteward>::EventHandle> {
             detector
               .space
-              .when_escapes(accessor, object, detector.real_box_from_grid(&grid_box))
+              .when_escapes(accessor, object, escape_bounds.clone())
               .map(|time| {
                 let time_id = accessor.extended_now().id;
                 accessor.create_prediction(
@@ -469,118 +1442,1489 @@ macro_rules! time_steward_define_bbox_collision_detection {
         }
       }
 
-      /*
+      /// A quadtree (or octree, etc.) with nodes of every power-of-2 size, for when object sizes vary too much for any single grid cell size.
+      ///
+      /// Each object is stored in the smallest nodes that are big enough that its bounding box overlaps at most 2 of them in each dimension. Two objects are neighbors if a node storing one of them overlaps a node storing the other – that is, if one node is the same as the other or one of its ancestors.
       pub mod tree {
+        use super::*;
+        use std::collections::BinaryHeap;
 
-      use super::*;
-
-      trait NodeAugmentation <S: Space>: Default {
-        fn start_overlapping <A: EventAccessor <Steward = <Self::Space as Space>::Steward> (&mut self, accessor: &A, object: & DataHandle <S::Object>) ;
-        fn stop_overlapping <A: EventAccessor <Steward = <Self::Space as Space>::Steward> (&mut self, accessor: &A, object: & DataHandle <S::Object>) ;
-      }
-      trait AugmentedSearchable: Detector {
-        fn search <A: Accessor <Steward = <Self::Space as Space>::Steward>, F: Fn (& augmentation)->bool>(accessor: &A, detector: &DataHandle<Self>, bounds: BoundingBox <Self::Space>, location_hint: Option < &DataHandle<<Self::Space as Space>::Object>>, filter: F) ->Vec<DataHandle<<Self::Space as Space>::Object>>;
+        pub type Types<S, const D: usize, N = NoAugmentation> = (
+          ListedType<TreeDetector<S, D, N>>,
+          ListedType<Escape<S, D, N>>,
+        );
 
-      }
+        /// Data about the objects in a node and its descendants, which the tree keeps up to date as objects move, so that searches can skip whole nodes. For instance, the total mass of the objects, or the greatest threat level among them.
+        ///
+        /// Each object is counted in one node (and that node's ancestors): the node holding its lowest corner, meaning the lowest coordinate of its bounding box in every dimension. So sums never count an object twice.
+        ///
+        /// That bounding box is the one the tree stored when the object was last inserted, changed position, or escaped its nodes, not the object's current one. An object moving around inside its nodes keeps the corner it had then, so searches by corner see moving objects where they were last stored.
+        pub trait NodeAugmentation<S: Space<D>, const D: usize>:
+          QueryResult + Default + PersistentlyIdentifiedType
+        {
+          /// The augmentation of a node containing only `object`. This is called when the object moves into a different node; if it would return something different without the object moving, call `TreeDetector::augmentation_changed()`.
+          fn of_object<A: EventAccessor<Steward = S::Steward>>(
+            accessor: &A,
+            space: &S,
+            object: &DataHandle<S::Object>,
+          ) -> Self;
+          /// Adds the objects counted in `other`. This must be associative and commutative, and combining with `Default::default()` must change nothing.
+          fn combine(&mut self, other: &Self);
+        }
+
+        /// The augmentation for trees that don't need one.
+        #[derive(Copy, Clone, PartialEq, Eq, Serialize, Deserialize, Debug, Default)]
+        pub struct NoAugmentation;
+        impl PersistentlyIdentifiedType for NoAugmentation {
+          const ID: PersistentTypeId = PersistentTypeId(0);
+        }
+        impl<S: Space<D>, const D: usize> NodeAugmentation<S, D> for NoAugmentation {
+          fn of_object<A: EventAccessor<Steward = S::Steward>>(
+            _accessor: &A,
+            _space: &S,
+            _object: &DataHandle<S::Object>,
+          ) -> Self {
+            NoAugmentation
+          }
+          fn combine(&mut self, _other: &Self) {}
+        }
+
+        const ROOT_SIZE_SHIFT: u32 = 64;
+
+        fn aligned(coordinate: Coordinate, size_shift: u32) -> Coordinate {
+          Coordinate::max_value()
+            .checked_shl(size_shift)
+            .map_or(0, |mask| coordinate & mask)
+        }
+        fn max_offset(size_shift: u32) -> Coordinate {
+          Coordinate::max_value()
+            .checked_shr(ROOT_SIZE_SHIFT - size_shift)
+            .unwrap_or(0)
+        }
+
+        /// The node covering the coordinates from `corner` to `corner + (1 << size_shift) - 1` in each dimension. The root node has a `size_shift` of 64 and covers the whole space.
+        #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, Debug)]
+        pub struct NodeBounds<const D: usize> {
+          pub size_shift: u32,
+          #[serde(with = "crate::type_utils::serde_array")]
+          pub corner: [Coordinate; D],
+        }
+
+        impl<const D: usize> NodeBounds<D> {
+          pub fn root() -> Self {
+            NodeBounds {
+              size_shift: ROOT_SIZE_SHIFT,
+              corner: [0; D],
+            }
+          }
+          pub fn containing(location: [Coordinate; D], size_shift: u32) -> Self {
+            NodeBounds {
+              size_shift,
+              corner: array::from_fn(|dimension| aligned(location[dimension], size_shift)),
+            }
+          }
+          pub fn parent(&self) -> Option<Self> {
+            if self.size_shift >= ROOT_SIZE_SHIFT {
+              None
+            } else {
+              Some(Self::containing(self.corner, self.size_shift + 1))
+            }
+          }
+          /// Bit `dimension` of `index` says whether the child is in the upper half of this node in that dimension.
+          pub fn child(&self, index: u32) -> Self {
+            let size_shift = self.size_shift - 1;
+            NodeBounds {
+              size_shift,
+              corner: array::from_fn(|dimension| {
+                self.corner[dimension] | ((((index >> dimension) & 1) as Coordinate) << size_shift)
+              }),
+            }
+          }
+          fn child_index(&self, child: &NodeBounds<D>) -> u32 {
+            (0..D).fold(0, |index, dimension| {
+              index | ((((child.corner[dimension] >> (self.size_shift - 1)) & 1) as u32) << dimension)
+            })
+          }
+          pub fn bounding_box(&self) -> BoundingBox<D> {
+            BoundingBox::new(array::from_fn(|dimension| {
+              [
+                self.corner[dimension],
+                self.corner[dimension] + max_offset(self.size_shift),
+              ]
+            }))
+          }
+          pub fn overlaps(&self, bounds: &BoundingBox<D>) -> bool {
+            bounds.bounds.iter().enumerate().all(|(dimension, bounds)| {
+              self.corner[dimension] <= bounds[1]
+                && bounds[0] <= self.corner[dimension] + max_offset(self.size_shift)
+            })
+          }
+          pub fn is_inside(&self, bounds: &BoundingBox<D>) -> bool {
+            bounds.bounds.iter().enumerate().all(|(dimension, bounds)| {
+              bounds[0] <= self.corner[dimension]
+                && self.corner[dimension] + max_offset(self.size_shift) <= bounds[1]
+            })
+          }
+        }
+
+        /// The nodes that an object with these bounds is stored in.
+        pub fn nodes_for<S: Space<D>, const D: usize>(bounds: &BoundingBox<D>) -> Vec<NodeBounds<D>> {
+          let mut result = Vec::new();
+          for piece in bounds.pieces::<S>() {
+            for node in nodes_for_piece(&piece) {
+              if !result.contains(&node) {
+                result.push(node);
+              }
+            }
+          }
+          result
+        }
+        fn nodes_for_piece<const D: usize>(bounds: &BoundingBox<D>) -> Vec<NodeBounds<D>> {
+          let extent = bounds
+            .bounds
+            .iter()
+            .map(|bounds| {
+              assert!(bounds[0] <= bounds[1], "invalid bounding box");
+              bounds[1] - bounds[0]
+            })
+            .max()
+            .unwrap_or(0);
+          let size_shift = ROOT_SIZE_SHIFT - extent.leading_zeros();
+          let mut result = vec![NodeBounds::containing(
+            bounds.bounds.map(|bounds| bounds[0]),
+            size_shift,
+          )];
+          for dimension in 0..D {
+            let upper = aligned(bounds.bounds[dimension][1], size_shift);
+            if upper != result[0].corner[dimension] {
+              let extra: Vec<_> = result
+                .iter()
+                .map(|node| {
+                  let mut node = *node;
+                  node.corner[dimension] = upper;
+                  node
+                })
+                .collect();
+              result.extend(extra);
+            }
+          }
+          result
+        }
+
+        /// The smallest box containing all of `nodes`.
+        fn nodes_box<const D: usize>(nodes: &[NodeBounds<D>]) -> BoundingBox<D> {
+          BoundingBox::new(array::from_fn(|dimension| {
+            [
+              nodes
+                .iter()
+                .map(|node| node.corner[dimension])
+                .min()
+                .unwrap(),
+              nodes
+                .iter()
+                .map(|node| node.corner[dimension] + max_offset(node.size_shift))
+                .max()
+                .unwrap(),
+            ]
+          }))
+        }
+
+        /// How far along `segment` it first touches `node`. This counts the points in between `node` and the nodes after it, which no node's bounding box includes.
+        fn segment_entry_into_node<const D: usize>(
+          segment: &Segment<D>,
+          node: &NodeBounds<D>,
+        ) -> Option<Fraction> {
+          segment.box_entry(&BoundingBox::new(array::from_fn(|dimension| {
+            [
+              node.corner[dimension],
+              (node.corner[dimension] + max_offset(node.size_shift)).saturating_add(1),
+            ]
+          })))
+        }
+
+        type Nodes<S, const D: usize, N> = RedBlackTreeMap<NodeBounds<D>, Node<S, D, N>>;
+
+        /// A tree whose nodes each cover one quarter of their parent (or one 2^D-th, in D dimensions). Each object is stored in the smallest nodes that can cover it with at most 2 nodes in each dimension.
+        ///
+        /// `N` is the augmentation kept for each node. Searching by augmentation, and combining the augmentations in a box, are in `search()` and `augmentation_in_box()`.
+        #[derive(Serialize, Deserialize, Debug)]
+        #[serde(bound = "")]
+        pub struct TreeDetector<S: Space<D>, const D: usize, N: NodeAugmentation<S, D> = NoAugmentation> {
+          space: S,
+          nodes: DataTimelineCell<SimpleTimeline<Nodes<S, D, N>, S::Steward>>,
+        }
+        impl<S: Space<D>, const D: usize, N: NodeAugmentation<S, D>> PersistentlyIdentifiedType
+          for TreeDetector<S, D, N>
+        {
+          const ID: PersistentTypeId = PersistentTypeId(0x3b9e0c5f71d2a846 ^ S::ID.0 ^ N::ID.0);
+        }
+        impl<S: Space<D>, const D: usize, N: NodeAugmentation<S, D>> Listable for TreeDetector<S, D, N> {
+          fn register<R: Registrar>(registrar: &mut R) {
+            registrar.data::<Self>();
+          }
+        }
+
+        #[derive(Serialize, Deserialize, Debug, Derivative)]
+        #[derivative(Clone(bound = ""), PartialEq(bound = ""), Eq(bound = ""))]
+        #[serde(bound = "")]
+        pub struct DetectorDataPerObject<S: Space<D>, const D: usize> {
+          nodes: Vec<NodeBounds<D>>,
+          /// The bounding box the object had when it was last stored.
+          bounds: BoundingBox<D>,
+          /// The box the object can move in without changing nodes.
+          escape_bounds: BoundingBox<D>,
+          escapes_bounds_prediction: Option<<S::Steward as TimeSteward>::EventHandle>,
+        }
+        /// Only nodes that have objects in them or below them are stored.
+        #[derive(Serialize, Deserialize, Debug, Derivative)]
+        #[derivative(
+          Clone(bound = ""),
+          PartialEq(bound = ""),
+          Eq(bound = ""),
+          Default(bound = "")
+        )]
+        #[serde(bound = "")]
+        struct Node<S: Space<D>, const D: usize, N: NodeAugmentation<S, D>> {
+          objects: Vec<DataHandle<S::Object>>,
+          /// The objects whose lowest corner is in this node, and their own augmentations. Each of them is also in `objects`.
+          homed: Vec<(DataHandle<S::Object>, N)>,
+          /// Bit `i` is set if `child(i)` is stored.
+          children: u32,
+          /// The combined augmentation of `homed` and all the children.
+          augmentation: N,
+        }
+        #[derive(Serialize, Deserialize, Debug, Derivative)]
+        #[derivative(Clone(bound = ""))]
+        #[serde(bound = "")]
+        pub struct Escape<S: Space<D>, const D: usize, N: NodeAugmentation<S, D> = NoAugmentation> {
+          detector: DataHandle<TreeDetector<S, D, N>>,
+          object: DataHandle<S::Object>,
+        }
+        impl<S: Space<D>, const D: usize, N: NodeAugmentation<S, D>> PersistentlyIdentifiedType
+          for Escape<S, D, N>
+        {
+          const ID: PersistentTypeId = PersistentTypeId(0x92d5e4a70b6c13f8 ^ S::ID.0 ^ N::ID.0);
+        }
+        impl<
+            S: Space<D, DetectorDataPerObject = DetectorDataPerObject<S, D>>,
+            const D: usize,
+            N: NodeAugmentation<S, D>,
+          > Listable for Escape<S, D, N>
+        {
+          fn register<R: Registrar>(registrar: &mut R) {
+            Self::register_event(registrar);
+          }
+        }
+        impl<
+            S: Space<D, DetectorDataPerObject = DetectorDataPerObject<S, D>>,
+            const D: usize,
+            N: NodeAugmentation<S, D>,
+          > Event for Escape<S, D, N>
+        {
+          type Steward = S::Steward;
+          /// The objects that started or stopped being neighbors of the escaping object.
+          type ExecutionData = Vec<DataHandle<S::Object>>;
+          fn execute<Accessor: EventAccessor<Steward = Self::Steward>>(
+            &self,
+            accessor: &mut Accessor,
+          ) -> Self::ExecutionData {
+            let new_bounds = self
+              .detector
+              .space
+              .current_bounding_box(accessor, &self.object);
+            TreeDetector::update(accessor, &self.detector, &self.object, Some(new_bounds))
+          }
+          fn undo<Accessor: FutureCleanupAccessor<Steward = Self::Steward>>(
+            &self,
+            accessor: &mut Accessor,
+            changed_neighbors: Self::ExecutionData,
+          ) {
+            self
+              .detector
+              .space
+              .undo_detector_changes(accessor, &self.object, &changed_neighbors);
+            TreeDetector::unset(accessor, &self.detector);
+          }
+        }
+
+        impl<
+            S: Space<D, DetectorDataPerObject = DetectorDataPerObject<S, D>>,
+            const D: usize,
+            N: NodeAugmentation<S, D>,
+          > Detector<D> for TreeDetector<S, D, N>
+        {
+          type Space = S;
+
+          fn insert<A: EventAccessor<Steward = S::Steward>>(
+            accessor: &A,
+            detector: &DataHandle<Self>,
+            object: &DataHandle<S::Object>,
+            _location_hint: Option<&DataHandle<S::Object>>,
+          ) {
+            let new_bounds = detector.space.current_bounding_box(accessor, object);
+            Self::update(accessor, detector, object, Some(new_bounds));
+          }
+          fn remove<A: EventAccessor<Steward = S::Steward>>(
+            accessor: &A,
+            detector: &DataHandle<Self>,
+            object: &DataHandle<S::Object>,
+          ) {
+            Self::update(accessor, detector, object, None);
+          }
+
+          fn changed_position<A: EventAccessor<Steward = S::Steward>>(
+            accessor: &A,
+            detector: &DataHandle<Self>,
+            object: &DataHandle<S::Object>,
+          ) {
+            Self::insert(accessor, detector, object, None);
+          }
+
+          fn changed_course<A: EventAccessor<Steward = S::Steward>>(
+            accessor: &A,
+            detector: &DataHandle<Self>,
+            object: &DataHandle<S::Object>,
+          ) {
+            let mut data = match detector.space.get_detector_data(accessor, object) {
+              None => return,
+              Some(a) => a,
+            };
+
+            data.escapes_bounds_prediction =
+              Self::create_prediction(accessor, detector, object, &data.escape_bounds);
+
+            detector
+              .space
+              .set_detector_data(accessor, object, Some(data));
+          }
+
+          fn objects_near_object<A: Accessor<Steward = S::Steward>>(
+            accessor: &A,
+            detector: &DataHandle<Self>,
+            object: &DataHandle<S::Object>,
+          ) -> Vec<DataHandle<S::Object>> {
+            let data = match detector.space.get_detector_data(accessor, object) {
+              None => return Vec::new(),
+              Some(a) => a,
+            };
+            let nodes = query(accessor, &detector.nodes);
+            objects_overlapping_nodes(&nodes, &data.nodes, object)
+          }
+
+          fn objects_near_box<A: Accessor<Steward = <Self::Space as Space<D>>::Steward>>(
+            accessor: &A,
+            detector: &DataHandle<Self>,
+            bounds: BoundingBox<D>,
+            _location_hint: Option<&DataHandle<<Self::Space as Space<D>>::Object>>,
+          ) -> Vec<DataHandle<<Self::Space as Space<D>>::Object>> {
+            let nodes = query(accessor, &detector.nodes);
+            let pieces = bounds.pieces::<S>();
+            let mut collector = Collector::new(None);
+            collect_subtree(&nodes, NodeBounds::root(), &mut collector, |node| {
+              pieces.iter().any(|piece| node.overlaps(piece))
+            });
+            collector.result
+          }
+
+          fn space(&self) -> &S {
+            &self.space
+          }
+
+          fn objects_on_segment<A: Accessor<Steward = S::Steward>>(
+            accessor: &A,
+            detector: &DataHandle<Self>,
+            segment: &Segment<D>,
+          ) -> Vec<(Fraction, DataHandle<S::Object>)>
+          where
+            S: SegmentQueryableSpace<D>,
+          {
+            Self::segment_hits(accessor, detector, segment, false)
+          }
+          fn first_object_on_segment<A: Accessor<Steward = S::Steward>>(
+            accessor: &A,
+            detector: &DataHandle<Self>,
+            segment: &Segment<D>,
+          ) -> Option<(Fraction, DataHandle<S::Object>)>
+          where
+            S: SegmentQueryableSpace<D>,
+          {
+            Self::segment_hits(accessor, detector, segment, true)
+              .into_iter()
+              .next()
+          }
+
+          fn objects_within_radius<A: Accessor<Steward = S::Steward>>(
+            accessor: &A,
+            detector: &DataHandle<Self>,
+            location: [Coordinate; D],
+            radius: Coordinate,
+            exclude: Option<&DataHandle<S::Object>>,
+          ) -> Vec<(u128, DataHandle<S::Object>)>
+          where
+            S: DistanceQueryableSpace<D>,
+          {
+            Self::nearest_search(
+              accessor,
+              detector,
+              location,
+              exclude,
+              squared_radius(radius),
+              usize::max_value(),
+            )
+          }
+          fn nearest_objects<A: Accessor<Steward = S::Steward>>(
+            accessor: &A,
+            detector: &DataHandle<Self>,
+            location: [Coordinate; D],
+            k: usize,
+            exclude: Option<&DataHandle<S::Object>>,
+          ) -> Vec<(u128, DataHandle<S::Object>)>
+          where
+            S: DistanceQueryableSpace<D>,
+          {
+            Self::nearest_search(accessor, detector, location, exclude, u128::max_value(), k)
+          }
+
+          fn inspect<A: Accessor<Steward = S::Steward>>(
+            accessor: &A,
+            detector: &DataHandle<Self>,
+          ) -> Inspection<S, D> {
+            let nodes = query(accessor, &detector.nodes);
+            let mut regions = Vec::new();
+            let mut object_regions = HashMap::new();
+            for (bounds, node) in nodes.iter() {
+              if node.objects.is_empty() {
+                continue;
+              }
+              regions.push(RegionInspection {
+                bounds: bounds.bounding_box(),
+                objects: node.objects.len(),
+              });
+              for object in node.objects.iter() {
+                *object_regions.entry(object.clone()).or_insert(0) += 1;
+              }
+            }
+            Inspection {
+              regions,
+              objects: inspect_objects(accessor, &detector.space, object_regions, |data| {
+                data.escapes_bounds_prediction
+              }),
+            }
+          }
+        }
+
+        impl<
+            S: Space<D, DetectorDataPerObject = DetectorDataPerObject<S, D>>,
+            const D: usize,
+            N: NodeAugmentation<S, D>,
+          > TreeDetector<S, D, N>
+        {
+          pub fn new<A: EventAccessor<Steward = S::Steward>>(
+            accessor: &A,
+            space: S,
+          ) -> DataHandle<Self> {
+            // each node keeps a bit for each of its 2^D children in a u32
+            assert!(D <= 5, "TreeDetector supports at most 5 dimensions");
+            check_wrap_extents::<S, D>();
+            let result = accessor.new_handle(TreeDetector {
+              space,
+              nodes: DataTimelineCell::new(SimpleTimeline::new()),
+            });
+            set(accessor, &result.nodes, RedBlackTreeMap::new());
+            result
+          }
+          /// Undoes the current event's changes to the detector's own data. Events that call `new()`, `insert()`, `remove()`, or `changed_position()` must call this in their `undo()`, along with undoing whatever the Space's methods changed.
+          pub fn unset<A: FutureCleanupAccessor<Steward = S::Steward>>(
+            accessor: &A,
+            detector: &DataHandle<Self>,
+          ) {
+            unset(accessor, &detector.nodes);
+          }
+          /// Recomputes `object`'s augmentation, and those of the nodes above it. Call this whenever `N::of_object()` would return something different for the object. Events that call this must call `unset()` in their `undo()`.
+          pub fn augmentation_changed<A: EventAccessor<Steward = S::Steward>>(
+            accessor: &A,
+            detector: &DataHandle<Self>,
+            object: &DataHandle<S::Object>,
+          ) {
+            let data = match detector.space.get_detector_data(accessor, object) {
+              None => return,
+              Some(a) => a,
+            };
+            let augmentation = N::of_object(accessor, &detector.space, object);
+            let nodes = query(accessor, &detector.nodes);
+            set(
+              accessor,
+              &detector.nodes,
+              set_home(nodes, data.nodes[0], object, Some(augmentation)),
+            );
+          }
+          /// Finds the objects whose lowest corners are in `bounds` and whose augmentations pass `filter`. The corners are the ones the tree last stored (see `NodeAugmentation`); call `changed_position()` on objects whose current corners matter. Nodes whose combined augmentation fails `filter` are skipped, so `filter` must pass every augmentation that includes an object it would pass.
+          pub fn search<A: Accessor<Steward = S::Steward>>(
+            accessor: &A,
+            detector: &DataHandle<Self>,
+            bounds: BoundingBox<D>,
+            filter: impl Fn(&N) -> bool,
+          ) -> Vec<DataHandle<S::Object>> {
+            let nodes = query(accessor, &detector.nodes);
+            let mut result = Vec::new();
+            for piece in bounds.pieces::<S>() {
+              let mut stack = vec![NodeBounds::root()];
+              while let Some(bounds) = stack.pop() {
+                let node = match nodes.get(&bounds) {
+                  Some(node) if bounds.overlaps(&piece) && filter(&node.augmentation) => node,
+                  _ => continue,
+                };
+                let inside = bounds.is_inside(&piece);
+                for (object, augmentation) in node.homed.iter() {
+                  if filter(augmentation)
+                    && (inside || Self::corner_inside(accessor, detector, object, &piece))
+                  {
+                    result.push(object.clone());
+                  }
+                }
+                for index in (0..1u32 << D).rev() {
+                  if node.children & (1 << index) != 0 {
+                    stack.push(bounds.child(index));
+                  }
+                }
+              }
+            }
+            result
+          }
+          /// The combined augmentation of the objects whose lowest corners are in `bounds`, using the corners the tree last stored, like `search()`. This uses the stored augmentation of each node inside `bounds`, so it only looks at the objects in the nodes along the edges of `bounds`.
+          pub fn augmentation_in_box<A: Accessor<Steward = S::Steward>>(
+            accessor: &A,
+            detector: &DataHandle<Self>,
+            bounds: BoundingBox<D>,
+          ) -> N {
+            let nodes = query(accessor, &detector.nodes);
+            let mut result = N::default();
+            for piece in bounds.pieces::<S>() {
+              let mut stack = vec![NodeBounds::root()];
+              while let Some(bounds) = stack.pop() {
+                let node = match nodes.get(&bounds) {
+                  Some(node) if bounds.overlaps(&piece) => node,
+                  _ => continue,
+                };
+                if bounds.is_inside(&piece) {
+                  result.combine(&node.augmentation);
+                  continue;
+                }
+                for (object, augmentation) in node.homed.iter() {
+                  if Self::corner_inside(accessor, detector, object, &piece) {
+                    result.combine(augmentation);
+                  }
+                }
+                for index in 0..1u32 << D {
+                  if node.children & (1 << index) != 0 {
+                    stack.push(bounds.child(index));
+                  }
+                }
+              }
+            }
+            result
+          }
+          fn corner_inside<A: Accessor<Steward = S::Steward>>(
+            accessor: &A,
+            detector: &DataHandle<Self>,
+            object: &DataHandle<S::Object>,
+            bounds: &BoundingBox<D>,
+          ) -> bool {
+            let data = detector
+              .space
+              .get_detector_data(accessor, object)
+              .expect("an object stored in the tree had no detector data");
+            bounds.contains_location(data.bounds.bounds.map(|bounds| bounds[0]))
+          }
+          /// Visits the nodes in the order the segment enters them. If `first_only`, this may leave out objects that are further along than the first one.
+          fn segment_hits<A: Accessor<Steward = S::Steward>>(
+            accessor: &A,
+            detector: &DataHandle<Self>,
+            segment: &Segment<D>,
+            first_only: bool,
+          ) -> Vec<(Fraction, DataHandle<S::Object>)>
+          where
+            S: SegmentQueryableSpace<D>,
+          {
+            assert_segment_inside::<S, D>(segment);
+            let nodes = query(accessor, &detector.nodes);
+            let mut seen = HashSet::new();
+            let mut hits = Vec::new();
+            let mut nearest: Option<Fraction> = None;
+            let mut frontier = BinaryHeap::new();
+            if let Some(entry) = segment_entry_into_node(segment, &NodeBounds::root()) {
+              frontier.push(Reverse((entry, NodeBounds::root())));
+            }
+            while let Some(Reverse((node_entry, bounds))) = frontier.pop() {
+              // the objects we haven't seen yet can't be touched before the segment enters the nodes they're in
+              if first_only && nearest.map_or(false, |nearest| node_entry > nearest) {
+                break;
+              }
+              if let Some(node) = nodes.get(&bounds) {
+                for object in node.objects.iter() {
+                  if seen.insert(object) {
+                    if let Some(entry) = detector.space.segment_entry(accessor, object, segment) {
+                      nearest = Some(nearest.map_or(entry, |nearest| min(nearest, entry)));
+                      hits.push((entry, object.clone()));
+                    }
+                  }
+                }
+                for index in 0..1u32 << D {
+                  if node.children & (1 << index) != 0 {
+                    let child = bounds.child(index);
+                    if let Some(entry) = segment_entry_into_node(segment, &child) {
+                      frontier.push(Reverse((entry, child)));
+                    }
+                  }
+                }
+              }
+            }
+            hits.sort_by_key(|hit| hit.0);
+            hits
+          }
+          /// Visits the nodes and objects nearest first, until it has found `k` objects or the rest are further away than `limit`.
+          fn nearest_search<A: Accessor<Steward = S::Steward>>(
+            accessor: &A,
+            detector: &DataHandle<Self>,
+            location: [Coordinate; D],
+            exclude: Option<&DataHandle<S::Object>>,
+            limit: u128,
+            k: usize,
+          ) -> Vec<(u128, DataHandle<S::Object>)>
+          where
+            S: DistanceQueryableSpace<D>,
+          {
+            assert_location_inside::<S, D>(location);
+            let nodes = query(accessor, &detector.nodes);
+            let mut result = Vec::new();
+            let mut seen = HashSet::new();
+            let mut pending_nodes = vec![NodeBounds::root()];
+            let mut pending_objects = Vec::new();
+            // Entries are (distance, unique ID, index into pending_objects) for objects, and (distance, None, index into pending_nodes) for nodes. A node comes before the objects at the same distance, since it may contain objects at that distance with lower IDs.
+            let mut frontier = BinaryHeap::new();
+            frontier.push(Reverse((
+              NodeBounds::root()
+                .bounding_box()
+                .wrapped_distance_squared::<S>(location),
+              None,
+              0,
+            )));
+            while let Some(Reverse((distance, id, index))) = frontier.pop() {
+              if distance > limit || result.len() >= k {
+                break;
+              }
+              if id.is_some() {
+                result.push((distance, pending_objects[index]));
+                continue;
+              }
+              let bounds = pending_nodes[index];
+              if let Some(node) = nodes.get(&bounds) {
+                for object in node.objects.iter() {
+                  if Some(object) != exclude && seen.insert(object) {
+                    frontier.push(Reverse((
+                      detector.space.distance_squared(accessor, object, location),
+                      Some(detector.space.unique_id(accessor, object)),
+                      pending_objects.len(),
+                    )));
+                    pending_objects.push(object);
+                  }
+                }
+                for index in 0..1u32 << D {
+                  if node.children & (1 << index) != 0 {
+                    let child = bounds.child(index);
+                    frontier.push(Reverse((
+                      child.bounding_box().wrapped_distance_squared::<S>(location),
+                      None,
+                      pending_nodes.len(),
+                    )));
+                    pending_nodes.push(child);
+                  }
+                }
+              }
+            }
+            result
+              .into_iter()
+              .map(|(distance, object)| (distance, object.clone()))
+              .collect()
+          }
+          /// Returns the objects that started or stopped being neighbors of `object`.
+          fn update<A: EventAccessor<Steward = S::Steward>>(
+            accessor: &A,
+            detector: &DataHandle<Self>,
+            object: &DataHandle<S::Object>,
+            new_bounds: Option<BoundingBox<D>>,
+          ) -> Vec<DataHandle<S::Object>> {
+            let new_nodes = new_bounds.as_ref().map(nodes_for::<S, D>);
+            let old_nodes = detector
+              .space
+              .get_detector_data(accessor, object)
+              .map(|data| data.nodes);
+
+            let mut changed_neighbors = Vec::new();
+            if old_nodes != new_nodes {
+              let mut nodes = query(accessor, &detector.nodes);
+              let old_neighbors = old_nodes.as_ref().map_or_else(Vec::new, |old_nodes| {
+                objects_overlapping_nodes(&nodes, old_nodes, object)
+              });
+              if let Some(old_nodes) = &old_nodes {
+                nodes = set_home(nodes, old_nodes[0], object, None);
+                for bounds in old_nodes.iter() {
+                  nodes = remove_object(nodes, *bounds, object);
+                }
+              }
+              if let Some(new_nodes) = &new_nodes {
+                for bounds in new_nodes.iter() {
+                  nodes = insert_object(nodes, *bounds, object);
+                }
+                // the first node holds the object's lowest corner
+                let augmentation = N::of_object(accessor, &detector.space, object);
+                nodes = set_home(nodes, new_nodes[0], object, Some(augmentation));
+              }
+              let new_neighbors = new_nodes.as_ref().map_or_else(Vec::new, |new_nodes| {
+                objects_overlapping_nodes(&nodes, new_nodes, object)
+              });
+              set(accessor, &detector.nodes, nodes);
+
+              for neighbor in old_neighbors.iter() {
+                if !new_neighbors.contains(neighbor) {
+                  detector
+                    .space
+                    .stop_being_neighbors(accessor, [object, neighbor]);
+                  changed_neighbors.push(neighbor.clone());
+                }
+              }
+              for neighbor in new_neighbors.iter() {
+                if !old_neighbors.contains(neighbor) {
+                  detector
+                    .space
+                    .become_neighbors(accessor, [object, neighbor]);
+                  changed_neighbors.push(neighbor.clone());
+                }
+              }
+            }
+
+            let new_data = new_bounds.map(|new_bounds| {
+              let escape_bounds = combine_pieces::<S, D>(
+                &new_bounds,
+                &new_bounds
+                  .pieces::<S>()
+                  .iter()
+                  .map(|piece| nodes_box(&nodes_for_piece(piece)))
+                  .collect::<Vec<_>>(),
+              );
+              DetectorDataPerObject {
+                escapes_bounds_prediction: Self::create_prediction(
+                  accessor,
+                  detector,
+                  object,
+                  &escape_bounds,
+                ),
+                nodes: new_nodes.unwrap(),
+                bounds: new_bounds,
+                escape_bounds,
+              }
+            });
+            detector.space.set_detector_data(accessor, object, new_data);
+            changed_neighbors
+          }
+          fn create_prediction<A: EventAccessor<Steward = S::Steward>>(
+            accessor: &A,
+            detector: &DataHandle<Self>,
+            object: &DataHandle<S::Object>,
+            escape_bounds: &BoundingBox<D>,
+          ) -> Option<<S::Steward as TimeSteward>::EventHandle> {
+            detector
+              .space
+              .when_escapes(accessor, object, escape_bounds.clone())
+              .map(|time| {
+                let time_id = accessor.extended_now().id;
+                accessor.create_prediction(
+                  time,
+                  DeterministicRandomId::new(&(
+                    0x4f17c2b8e96a0d35u64,
+                    time_id,
+                    detector.space.unique_id(accessor, object),
+                  )),
+                  Escape {
+                    detector: detector.clone(),
+                    object: object.clone(),
+                  },
+                )
+              })
+          }
+        }
 
-      }*/
+        fn insert_object<S: Space<D>, const D: usize, N: NodeAugmentation<S, D>>(
+          mut nodes: Nodes<S, D, N>,
+          bounds: NodeBounds<D>,
+          object: &DataHandle<S::Object>,
+        ) -> Nodes<S, D, N> {
+          let existing = nodes.get(&bounds).cloned();
+          let mut existed = existing.is_some();
+          let mut node = existing.unwrap_or_default();
+          node.objects.push(object.clone());
+          nodes = nodes.insert(bounds, node);
 
-      /*
-      struct NodeBounds<B: BoundingBoxCollisionDetectable> {
-        half_size_shift: u32,
-        center: [Coordinate; B::DIMENSIONS],
-      }
-      fn smallest_containing_node_bounds <B: BoundingBoxCollisionDetectable> (bounds: & BoundingBox<B>)->NodeBounds <B> {
-        unimplemented!()
-      }
+          // create any missing ancestors
+          let mut child = bounds;
+          while !existed {
+            let parent = match child.parent() {
+              None => break,
+              Some(parent) => parent,
+            };
+            let existing = nodes.get(&parent).cloned();
+            existed = existing.is_some();
+            let mut node = existing.unwrap_or_default();
+            node.children |= 1 << parent.child_index(&child);
+            nodes = nodes.insert(parent, node);
+            child = parent;
+          }
+          nodes
+        }
 
-      #[derive (Clone, PartialEq, Eq, Serialize, Deserialize, Debug)]
-      struct Node<B: BoundingBoxCollisionDetectable> {
-        bounds: NodeBounds<B>,
-        parent: Option<DataHandle <Node <B>>>,
-        larger_cousins: [[Option<DataHandle <Node <B>>>; 2]; B::DIMENSIONS],
-        varying: NodeVarying <B>,
-      }
-      impl<B: BoundingBoxCollisionDetectable> PersistentlyIdentifiedType for Node<B> {
-        const ID: PersistentTypeId = PersistentTypeId(B::ID.0 ^ 0x7c4c8993671ba023);
-      }
-      #[derive (Clone, PartialEq, Eq, Serialize, Deserialize, Debug)]
-      struct NodeVarying<B: BoundingBoxCollisionDetectable> {
-        children: [Option<DataHandle <Node <B>>>; 1<<B::DIMENSIONS],
-        overlapping_live_nodes: Vec<DataHandle <B::Object>>,
-        objects: Vec<DataHandle <B::Object>>,
-      }
+        fn remove_object<S: Space<D>, const D: usize, N: NodeAugmentation<S, D>>(
+          mut nodes: Nodes<S, D, N>,
+          mut bounds: NodeBounds<D>,
+          object: &DataHandle<S::Object>,
+        ) -> Nodes<S, D, N> {
+          let mut node = nodes
+            .get(&bounds)
+            .expect("an object's detector data referred to a node that doesn't exist")
+            .clone();
+          node.objects.retain(|a| a != object);
 
-      pub struct ObjectData<B: BoundingBoxCollisionDetectable> {
-        varying: ObjectVarying<B>,
-      }
-      struct ObjectVarying<B: BoundingBoxCollisionDetectable> {
-        node: DataHandle <Node <B>>,
-      }
-      pub struct BoundingBoxCollisionDetector<B: BoundingBoxCollisionDetectable> {
-        root: DataHandle <Node <B>>,
-      }
+          // discard any ancestors that are no longer needed
+          loop {
+            if !node.objects.is_empty() || node.children != 0 {
+              return nodes.insert(bounds, node);
+            }
+            nodes = nodes.remove(&bounds);
+            let parent = match bounds.parent() {
+              None => return nodes,
+              Some(parent) => parent,
+            };
+            node = nodes.get(&parent).unwrap().clone();
+            node.children &= !(1 << parent.child_index(&bounds));
+            bounds = parent;
+          }
+        }
+
+        /// Makes `object` homed in the node at `bounds` with the given augmentation, or not homed there if it's None, and updates the augmentations of that node's ancestors. The node must exist.
+        fn set_home<S: Space<D>, const D: usize, N: NodeAugmentation<S, D>>(
+          mut nodes: Nodes<S, D, N>,
+          mut bounds: NodeBounds<D>,
+          object: &DataHandle<S::Object>,
+          augmentation: Option<N>,
+        ) -> Nodes<S, D, N> {
+          let mut node = nodes
+            .get(&bounds)
+            .expect("tried to home an object in a node that doesn't exist")
+            .clone();
+          node.homed.retain(|(homed, _)| homed != object);
+          node
+            .homed
+            .extend(augmentation.map(|augmentation| (object.clone(), augmentation)));
 
-      impl<B: BoundingBoxCollisionDetectable> BoundingBoxCollisionDetector<B> {
-        pub fn insert<A: EventAccessor <Steward = B::Steward>>(accessor: &A, object: &DataHandle<B::Object>, space: &B::Space, location_hint: Option <& DataHandle<B::Object>>) {
+          // the ancestors only need updating until one's augmentation comes out the same
+          loop {
+            let mut augmentation = N::default();
+            for (_, homed) in node.homed.iter() {
+              augmentation.combine(homed);
+            }
+            for index in 0..1u32 << D {
+              if node.children & (1 << index) != 0 {
+                augmentation.combine(&nodes.get(&bounds.child(index)).unwrap().augmentation);
+              }
+            }
+            let unchanged = augmentation == node.augmentation;
+            node.augmentation = augmentation;
+            nodes = nodes.insert(bounds, node);
+            if unchanged {
+              return nodes;
+            }
+            bounds = match bounds.parent() {
+              None => return nodes,
+              Some(parent) => parent,
+            };
+            node = nodes.get(&bounds).unwrap().clone();
+          }
+        }
 
+        /// Gathers the objects stored in some nodes, without duplicates.
+        struct Collector<'a, S: Space<D>, const D: usize> {
+          excluded: Option<&'a DataHandle<S::Object>>,
+          seen: HashSet<&'a DataHandle<S::Object>>,
+          result: Vec<DataHandle<S::Object>>,
+        }
+        impl<'a, S: Space<D>, const D: usize> Collector<'a, S, D> {
+          fn new(excluded: Option<&'a DataHandle<S::Object>>) -> Self {
+            Collector {
+              excluded,
+              seen: HashSet::new(),
+              result: Vec::new(),
+            }
+          }
+          fn collect<N: NodeAugmentation<S, D>>(&mut self, node: &'a Node<S, D, N>) {
+            for object in node.objects.iter() {
+              if Some(object) != self.excluded && self.seen.insert(object) {
+                self.result.push(object.clone());
+              }
+            }
+          }
         }
-        pub fn remove<A: EventAccessor <Steward = B::Steward>>(accessor: &A, object: &DataHandle<B::Object>, space: &B::Space) {
 
+        /// Collects the objects in `top` and all of its descendants that pass `filter`.
+        fn collect_subtree<'a, S: Space<D>, const D: usize, N: NodeAugmentation<S, D>>(
+          nodes: &'a Nodes<S, D, N>,
+          top: NodeBounds<D>,
+          collector: &mut Collector<'a, S, D>,
+          filter: impl Fn(&NodeBounds<D>) -> bool,
+        ) {
+          let mut stack = vec![top];
+          while let Some(bounds) = stack.pop() {
+            if let Some(node) = nodes.get(&bounds) {
+              collector.collect(node);
+              for index in (0..1u32 << D).rev() {
+                if node.children & (1 << index) != 0 {
+                  let child = bounds.child(index);
+                  if filter(&child) {
+                    stack.push(child);
+                  }
+                }
+              }
+            }
+          }
         }
-        pub fn neighbors<A: EventAccessor <Steward = B::Steward>>(accessor: &A, object: &DataHandle<B::Object>, space: &B::Space) -> Iter {
 
+        /// The objects, other than `object`, stored in the ancestors or descendants of `stored` (or in `stored` themselves).
+        fn objects_overlapping_nodes<'a, S: Space<D>, const D: usize, N: NodeAugmentation<S, D>>(
+          nodes: &'a Nodes<S, D, N>,
+          stored: &[NodeBounds<D>],
+          object: &'a DataHandle<S::Object>,
+        ) -> Vec<DataHandle<S::Object>> {
+          let mut collector = Collector::new(Some(object));
+          let mut visited_ancestors = HashSet::new();
+          for bounds in stored.iter() {
+            let mut ancestor = bounds.parent();
+            while let Some(bounds) = ancestor {
+              if !visited_ancestors.insert(bounds) {
+                break;
+              }
+              if let Some(node) = nodes.get(&bounds) {
+                collector.collect(node);
+              }
+              ancestor = bounds.parent();
+            }
+          }
+          for bounds in stored.iter() {
+            collect_subtree(nodes, *bounds, &mut collector, |_| true);
+          }
+          collector.result
         }
       }
 
+      /// Sweep and prune: for each axis, a sorted list of the coordinates where objects begin and end. This suits scenes where objects are spread out along lines, like racing tracks, where a grid would waste memory on empty cells.
+      ///
+      /// Each object gets a stored box, which is its bounding box expanded by `margin` in every direction. Two objects are neighbors if their stored boxes overlap. An object's endpoints only move when it escapes its stored box, and then only the objects with endpoints in between the old and new positions can start or stop overlapping it.
+      ///
+      /// Inserting an object, and finding the objects near a box, check every object with an endpoint on the first axis between where the given box begins and where it ends plus the width of the widest stored box. In a space that wraps around, endpoints can pass each other either way around the seam, so moving an object checks the objects near its old and new boxes in the same way.
+      pub mod sweep_and_prune {
+        use super::super::ordered_map_timeline::{self, OrderedMapTimeline};
+        use super::*;
 
-      #[derive (Clone, PartialEq, Eq, Serialize, Deserialize, Debug)]
-      pub struct EscapesBounds<B: BoundingBoxCollisionDetectable> {}
-      impl<B: BoundingBoxCollisionDetectable> PersistentlyIdentifiedType for EscapesBounds<B> {
-        const ID: PersistentTypeId = PersistentTypeId(B::ID.0 ^ 0xb0cdbe951b688b70);
-      }
-      impl<B: BoundingBoxCollisionDetectable> Event for EscapesBounds<B> {
-        type Steward = B::Steward;
-        type ExecutionData = ();
-        fn execute <Accessor: EventAccessor <Steward = Self::Steward>> (&self, accessor: &mut Accessor) {
+        pub type Types<S, const D: usize> = (
+          ListedType<SweepAndPruneDetector<S, D>>,
+          ListedType<Escape<S, D>>,
+        );
+
+        /// The objects whose stored boxes begin or end at each coordinate.
+        type Axis<S, const D: usize> =
+          OrderedMapTimeline<Coordinate, Vec<Endpoint<S, D>>, <S as Space<D>>::Steward>;
 
+        #[derive(Serialize, Deserialize, Debug)]
+        #[serde(bound = "")]
+        pub struct SweepAndPruneDetector<S: Space<D>, const D: usize> {
+          space: S,
+          margin: Coordinate,
+          axes: Vec<DataTimelineCell<Axis<S, D>>>,
+          /// How many stored boxes have each width on the first axis. The greatest key is the width of the widest one, and every stored box that overlaps `[start, end]` on that axis has an endpoint in `[start, end + widest]`.
+          widths: DataTimelineCell<OrderedMapTimeline<Coordinate, usize, S::Steward>>,
+        }
+        impl<S: Space<D>, const D: usize> PersistentlyIdentifiedType for SweepAndPruneDetector<S, D> {
+          const ID: PersistentTypeId = PersistentTypeId(0xd61a0e3f58b2c794 ^ S::ID.0);
+        }
+        impl<S: Space<D>, const D: usize> Listable for SweepAndPruneDetector<S, D> {
+          fn register<R: Registrar>(registrar: &mut R) {
+            registrar.data::<Self>();
+          }
         }
 
-        fn undo <Accessor: FutureCleanupAccessor <Steward = Self::Steward>> (&self, accessor: &mut Accessor, _: ()) {
-          unimplemented!()
+        #[derive(Serialize, Deserialize, Debug, Derivative)]
+        #[derivative(Clone(bound = ""), PartialEq(bound = ""), Eq(bound = ""))]
+        #[serde(bound = "")]
+        pub struct DetectorDataPerObject<S: Space<D>, const D: usize> {
+          stored_box: BoundingBox<D>,
+          escapes_bounds_prediction: Option<<S::Steward as TimeSteward>::EventHandle>,
         }
-      }
+        #[derive(Serialize, Deserialize, Debug, Derivative)]
+        #[derivative(Clone(bound = ""), PartialEq(bound = ""), Eq(bound = ""))]
+        #[serde(bound = "")]
+        struct Endpoint<S: Space<D>, const D: usize> {
+          object: DataHandle<S::Object>,
+          stored_box: BoundingBox<D>,
+        }
+        #[derive(Serialize, Deserialize, Debug, Derivative)]
+        #[derivative(Clone(bound = ""))]
+        #[serde(bound = "")]
+        pub struct Escape<S: Space<D>, const D: usize> {
+          detector: DataHandle<SweepAndPruneDetector<S, D>>,
+          object: DataHandle<S::Object>,
+        }
+        impl<S: Space<D>, const D: usize> PersistentlyIdentifiedType for Escape<S, D> {
+          const ID: PersistentTypeId = PersistentTypeId(0x2c85f1a9d7e04b63 ^ S::ID.0);
+        }
+        impl<S: Space<D, DetectorDataPerObject = DetectorDataPerObject<S, D>>, const D: usize> Listable
+          for Escape<S, D>
+        {
+          fn register<R: Registrar>(registrar: &mut R) {
+            Self::register_event(registrar);
+          }
+        }
+        impl<S: Space<D, DetectorDataPerObject = DetectorDataPerObject<S, D>>, const D: usize> Event
+          for Escape<S, D>
+        {
+          type Steward = S::Steward;
+          /// The objects that started or stopped being neighbors of the escaping object.
+          type ExecutionData = Vec<DataHandle<S::Object>>;
+          fn execute<Accessor: EventAccessor<Steward = Self::Steward>>(
+            &self,
+            accessor: &mut Accessor,
+          ) -> Self::ExecutionData {
+            let new_bounds = self
+              .detector
+              .space
+              .current_bounding_box(accessor, &self.object);
+            let new_stored_box = self.detector.stored_box(&new_bounds);
+            SweepAndPruneDetector::update(accessor, &self.detector, &self.object, Some(new_stored_box))
+          }
+          fn undo<Accessor: FutureCleanupAccessor<Steward = Self::Steward>>(
+            &self,
+            accessor: &mut Accessor,
+            changed_neighbors: Self::ExecutionData,
+          ) {
+            self
+              .detector
+              .space
+              .undo_detector_changes(accessor, &self.object, &changed_neighbors);
+            SweepAndPruneDetector::unset(accessor, &self.detector);
+          }
+        }
+
+        /// Whether two boxes overlap. Where a box straddles the wrap seam, it covers everything from its lower bound up, and everything up to its upper bound.
+        fn overlaps<const D: usize>(first: &BoundingBox<D>, second: &BoundingBox<D>) -> bool {
+          first
+            .bounds
+            .iter()
+            .zip(second.bounds.iter())
+            .all(
+              |(first, second)| match (first[0] <= first[1], second[0] <= second[1]) {
+                (true, true) => first[0] <= second[1] && second[0] <= first[1],
+                (true, false) => first[1] >= second[0] || first[0] <= second[1],
+                (false, true) => second[1] >= first[0] || second[0] <= first[1],
+                (false, false) => true,
+              },
+            )
+        }
+
+        impl<S: Space<D, DetectorDataPerObject = DetectorDataPerObject<S, D>>, const D: usize>
+          Detector<D> for SweepAndPruneDetector<S, D>
+        {
+          type Space = S;
+
+          fn insert<A: EventAccessor<Steward = S::Steward>>(
+            accessor: &A,
+            detector: &DataHandle<Self>,
+            object: &DataHandle<S::Object>,
+            _location_hint: Option<&DataHandle<S::Object>>,
+          ) {
+            let new_bounds = detector.space.current_bounding_box(accessor, object);
+            let new_stored_box = detector.stored_box(&new_bounds);
+            Self::update(accessor, detector, object, Some(new_stored_box));
+          }
+          fn remove<A: EventAccessor<Steward = S::Steward>>(
+            accessor: &A,
+            detector: &DataHandle<Self>,
+            object: &DataHandle<S::Object>,
+          ) {
+            Self::update(accessor, detector, object, None);
+          }
+
+          fn changed_position<A: EventAccessor<Steward = S::Steward>>(
+            accessor: &A,
+            detector: &DataHandle<Self>,
+            object: &DataHandle<S::Object>,
+          ) {
+            Self::insert(accessor, detector, object, None);
+          }
+
+          fn changed_course<A: EventAccessor<Steward = S::Steward>>(
+            accessor: &A,
+            detector: &DataHandle<Self>,
+            object: &DataHandle<S::Object>,
+          ) {
+            let mut data = match detector.space.get_detector_data(accessor, object) {
+              None => return,
+              Some(a) => a,
+            };
 
+            data.escapes_bounds_prediction =
+              Self::create_prediction(accessor, detector, object, &data.stored_box);
 
-      impl<B: BoundingBoxCollisionDetectable> BoundingBoxCollisionDetector<B> {
-        fn reposition <A: EventAccessor <Steward = B::Steward>> (accessor: &A, object: &DataHandle<B::Object>) {
-          let varying = tracking_query (accessor, object.varying);
-          let mut current_node = varying.node;
-          let new_bounds = B::calculate_current_bounding_box (object);
-          let destination_node_bounds = smallest_containing_node_bounds (new_bounds) ;
-          let bigger_shift = max (current_node.bounds.half_size_shift, destination_node_bounds.half_size_shift);
+            detector
+              .space
+              .set_detector_data(accessor, object, Some(data));
+          }
 
-          while current_node.bounds.half_size_shift < bigger_shift {
-            current_node = current_node.parent.expect("All nodes except the root node have to have a parent, and we should never be trying to navigate to a node bigger than the root");
+          fn objects_near_object<A: Accessor<Steward = S::Steward>>(
+            accessor: &A,
+            detector: &DataHandle<Self>,
+            object: &DataHandle<S::Object>,
+          ) -> Vec<DataHandle<S::Object>> {
+            let data = match detector.space.get_detector_data(accessor, object) {
+              None => return Vec::new(),
+              Some(a) => a,
+            };
+            let mut result = Self::objects_overlapping(accessor, detector, &data.stored_box);
+            result.retain(|neighbor| neighbor != object);
+            result
           }
 
-          let mut destination_ancestor_bounds = destination_node_bounds;
-          while destination_ancestor_bounds.half_size_shift < bigger_shift {
-            destination_ancestor_bounds = parent_bounds(destination_ancestor_bounds);
+          fn objects_near_box<A: Accessor<Steward = <Self::Space as Space<D>>::Steward>>(
+            accessor: &A,
+            detector: &DataHandle<Self>,
+            bounds: BoundingBox<D>,
+            _location_hint: Option<&DataHandle<<Self::Space as Space<D>>::Object>>,
+          ) -> Vec<DataHandle<<Self::Space as Space<D>>::Object>> {
+            Self::objects_overlapping(accessor, detector, &bounds)
           }
 
-          while largest_single_dimension_distance (current_node.bounds, destination_ancestor_bounds) > (1 << destination_ancestor_bounds.half_size_shift) {
-            destination_ancestor_bounds = parent_bounds(destination_ancestor_bounds);
-            current_node = current_node.parent.expect("All nodes except the root node have to have a parent, and we should never be trying to navigate to a node bigger than the root");
+          fn space(&self) -> &S {
+            &self.space
           }
 
-          if current_node.bounds != destination_ancestor_bounds {
-            // they should now be the same size and half- or all-overlapping in each dimension.
+          fn inspect<A: Accessor<Steward = S::Steward>>(
+            accessor: &A,
+            detector: &DataHandle<Self>,
+          ) -> Inspection<S, D> {
+            let mut objects = HashMap::new();
+            for (_, endpoints) in ordered_map_timeline::range(accessor, &detector.axes[0], ..) {
+              for endpoint in endpoints {
+                objects.insert(endpoint.object, 0);
+              }
+            }
+            Inspection {
+              regions: Vec::new(),
+              objects: inspect_objects(accessor, &detector.space, objects, |data| {
+                data.escapes_bounds_prediction
+              }),
+            }
           }
         }
-      }
 
-      */
+        impl<S: Space<D, DetectorDataPerObject = DetectorDataPerObject<S, D>>, const D: usize>
+          SweepAndPruneDetector<S, D>
+        {
+          pub fn new<A: EventAccessor<Steward = S::Steward>>(
+            accessor: &A,
+            space: S,
+            margin: Coordinate,
+          ) -> DataHandle<Self> {
+            check_wrap_extents::<S, D>();
+            let result = accessor.new_handle(SweepAndPruneDetector {
+              space,
+              margin,
+              axes: (0..D)
+                .map(|_| DataTimelineCell::new(OrderedMapTimeline::new()))
+                .collect(),
+              widths: DataTimelineCell::new(OrderedMapTimeline::new()),
+            });
+            result
+          }
+          /// Undoes the current event's changes to the detector's own data. Events that call `new()`, `insert()`, `remove()`, or `changed_position()` must call this in their `undo()`, along with undoing whatever the Space's methods changed.
+          pub fn unset<A: FutureCleanupAccessor<Steward = S::Steward>>(
+            accessor: &A,
+            detector: &DataHandle<Self>,
+          ) {
+            // the axes remember which keys the current event set, so this doesn't look at the others
+            for axis in detector.axes.iter() {
+              ordered_map_timeline::unset_all(accessor, axis);
+            }
+            ordered_map_timeline::unset_all(accessor, &detector.widths);
+          }
+          /// The width of the widest stored box on the first axis, or 0 if there are none.
+          fn widest<A: Accessor<Steward = S::Steward>>(
+            accessor: &A,
+            detector: &DataHandle<Self>,
+          ) -> Coordinate {
+            ordered_map_timeline::range(accessor, &detector.widths, ..)
+              .last()
+              .map_or(0, |(width, _)| *width)
+          }
+          /// Like `widest()`, but the current event is invalidated if a retroactive change makes a different width the widest.
+          fn tracking_widest<A: EventAccessor<Steward = S::Steward>>(
+            accessor: &A,
+            detector: &DataHandle<Self>,
+          ) -> Coordinate {
+            // only the widths from the current widest up can change which one is widest
+            let widest = Self::widest(accessor, detector);
+            ordered_map_timeline::tracking_range(accessor, &detector.widths, widest..);
+            widest
+          }
+          /// How far along the first axis `stored_box` goes after it begins, going around the seam if it straddles it.
+          fn width(stored_box: &BoundingBox<D>) -> Coordinate {
+            match S::WRAP_EXTENTS[0] {
+              None => stored_box.bounds[0][1] - stored_box.bounds[0][0],
+              Some(extent) => wrapped_width(stored_box.bounds[0], extent),
+            }
+          }
+          /// The ranges of the first axis where every stored box that overlaps `bounds` has an endpoint: from where `bounds` begins to where it ends plus `widest`, going around the seam if the first axis wraps.
+          fn first_axis_ranges(
+            bounds: &BoundingBox<D>,
+            widest: Coordinate,
+          ) -> Vec<(Coordinate, Coordinate)> {
+            let start = bounds.bounds[0][0];
+            let length = Self::width(bounds).saturating_add(widest);
+            match S::WRAP_EXTENTS[0] {
+              None => vec![(start, start.saturating_add(length))],
+              Some(extent) => {
+                if length >= extent - 1 {
+                  vec![(0, extent - 1)]
+                } else if length <= extent - 1 - start {
+                  vec![(start, start + length)]
+                } else {
+                  vec![(start, extent - 1), (0, length - (extent - start))]
+                }
+              }
+            }
+          }
+          /// Counts a stored box of `width` on the first axis, or stops counting one if `change` is -1.
+          fn count_width<A: EventAccessor<Steward = S::Steward>>(
+            accessor: &A,
+            detector: &DataHandle<Self>,
+            width: Coordinate,
+            change: isize,
+          ) {
+            let count = ordered_map_timeline::get(accessor, &detector.widths, &width).unwrap_or(0);
+            let count = (count as isize + change) as usize;
+            ordered_map_timeline::set(
+              accessor,
+              &detector.widths,
+              width,
+              Some(count).filter(|&count| count > 0),
+            );
+          }
+          fn stored_box(&self, bounds: &BoundingBox<D>) -> BoundingBox<D> {
+            let margin = self.margin;
+            BoundingBox::new(array::from_fn(|dimension| {
+              let [low, high] = bounds.bounds[dimension];
+              match S::WRAP_EXTENTS[dimension] {
+                None => [low.saturating_sub(margin), high.saturating_add(margin)],
+                Some(extent) => {
+                  let width = wrapped_width([low, high], extent);
+                  if width.saturating_add(margin.saturating_mul(2)) >= extent - 1 {
+                    [0, extent - 1]
+                  } else {
+                    [
+                      if low >= margin {
+                        low - margin
+                      } else {
+                        low + (extent - margin)
+                      },
+                      if extent - 1 - high >= margin {
+                        high + margin
+                      } else {
+                        margin - (extent - high)
+                      },
+                    ]
+                  }
+                }
+              }
+            }))
+          }
+          fn objects_overlapping<A: Accessor<Steward = S::Steward>>(
+            accessor: &A,
+            detector: &DataHandle<Self>,
+            bounds: &BoundingBox<D>,
+          ) -> Vec<DataHandle<S::Object>> {
+            let mut seen = HashSet::new();
+            let mut result = Vec::new();
+            for (start, end) in Self::first_axis_ranges(bounds, Self::widest(accessor, detector)) {
+              for (_, endpoints) in
+                ordered_map_timeline::range(accessor, &detector.axes[0], start..=end)
+              {
+                for endpoint in endpoints {
+                  if overlaps(&endpoint.stored_box, bounds) && seen.insert(endpoint.object.clone()) {
+                    result.push(endpoint.object);
+                  }
+                }
+              }
+            }
+            result
+          }
+          /// Returns the objects that started or stopped being neighbors of `object`.
+          fn update<A: EventAccessor<Steward = S::Steward>>(
+            accessor: &A,
+            detector: &DataHandle<Self>,
+            object: &DataHandle<S::Object>,
+            new_stored_box: Option<BoundingBox<D>>,
+          ) -> Vec<DataHandle<S::Object>> {
+            let old_stored_box = detector
+              .space
+              .get_detector_data(accessor, object)
+              .map(|data| data.stored_box);
+
+            let mut changed_neighbors = Vec::new();
+            if old_stored_box != new_stored_box {
+              // Whether another object overlaps this one can only change if one of this object's endpoints passes one of its endpoints. Inserting or removing an object is like moving it in from, or out to, the far end of the first axis, but only the objects with an endpoint within `widest` after this one's end can overlap it. Where an axis wraps around, the endpoints could have passed each other either way around, so this checks the objects near the old and new boxes in the same way.
+              let wraps = S::WRAP_EXTENTS.iter().any(Option::is_some);
+              let mut candidates = Vec::new();
+              let mut seen = HashSet::new();
+              for dimension in 0..D {
+                let swept: Vec<(Coordinate, Coordinate)> =
+                  match (old_stored_box.as_ref(), new_stored_box.as_ref()) {
+                    (Some(old_stored_box), Some(new_stored_box)) if !wraps => (0..2)
+                      .map(|direction| {
+                        let old = old_stored_box.bounds[dimension][direction];
+                        let new = new_stored_box.bounds[dimension][direction];
+                        (min(old, new), max(old, new))
+                      })
+                      .collect(),
+                    _ if dimension == 0 => {
+                      let widest = Self::tracking_widest(accessor, detector);
+                      old_stored_box
+                        .iter()
+                        .chain(new_stored_box.iter())
+                        .flat_map(|stored_box| Self::first_axis_ranges(stored_box, widest))
+                        .collect()
+                    }
+                    _ => Vec::new(),
+                  };
+                for (start, end) in swept {
+                  for (_, endpoints) in ordered_map_timeline::tracking_range(
+                    accessor,
+                    &detector.axes[dimension],
+                    start..=end,
+                  ) {
+                    for endpoint in endpoints {
+                      if &endpoint.object != object && seen.insert(endpoint.object.clone()) {
+                        candidates.push(endpoint);
+                      }
+                    }
+                  }
+                }
+              }
+
+              for (dimension, axis) in detector.axes.iter().enumerate() {
+                let mut keys: Vec<Coordinate> = old_stored_box
+                  .iter()
+                  .chain(new_stored_box.iter())
+                  .flat_map(|stored_box| stored_box.bounds[dimension].to_vec())
+                  .collect();
+                keys.sort();
+                keys.dedup();
+                for key in keys {
+                  let mut endpoints =
+                    ordered_map_timeline::get(accessor, axis, &key).unwrap_or_default();
+                  endpoints.retain(|endpoint| &endpoint.object != object);
+                  if let Some(new_stored_box) = new_stored_box.as_ref() {
+                    if new_stored_box.bounds[dimension].contains(&key) {
+                      endpoints.push(Endpoint {
+                        object: object.clone(),
+                        stored_box: new_stored_box.clone(),
+                      });
+                    }
+                  }
+                  let value = if endpoints.is_empty() {
+                    None
+                  } else {
+                    Some(endpoints)
+                  };
+                  ordered_map_timeline::set(accessor, axis, key, value);
+                }
+              }
+              let old_width = old_stored_box.as_ref().map(Self::width);
+              let new_width = new_stored_box.as_ref().map(Self::width);
+              if old_width != new_width {
+                if let Some(old_width) = old_width {
+                  Self::count_width(accessor, detector, old_width, -1);
+                }
+                if let Some(new_width) = new_width {
+                  Self::count_width(accessor, detector, new_width, 1);
+                }
+              }
+
+              let overlapped = |stored_box: &Option<BoundingBox<D>>, candidate: &Endpoint<S, D>| {
+                stored_box.as_ref().map_or(false, |stored_box| {
+                  overlaps(stored_box, &candidate.stored_box)
+                })
+              };
+              for candidate in candidates.iter() {
+                if overlapped(&old_stored_box, candidate) && !overlapped(&new_stored_box, candidate) {
+                  detector
+                    .space
+                    .stop_being_neighbors(accessor, [object, &candidate.object]);
+                  changed_neighbors.push(candidate.object.clone());
+                }
+              }
+              for candidate in candidates.iter() {
+                if overlapped(&new_stored_box, candidate) && !overlapped(&old_stored_box, candidate) {
+                  detector
+                    .space
+                    .become_neighbors(accessor, [object, &candidate.object]);
+                  changed_neighbors.push(candidate.object.clone());
+                }
+              }
+            }
+
+            let new_data = new_stored_box.map(|new_stored_box| DetectorDataPerObject {
+              escapes_bounds_prediction: Self::create_prediction(
+                accessor,
+                detector,
+                object,
+                &new_stored_box,
+              ),
+              stored_box: new_stored_box,
+            });
+            detector.space.set_detector_data(accessor, object, new_data);
+            changed_neighbors
+          }
+          fn create_prediction<A: EventAccessor<Steward = S::Steward>>(
+            accessor: &A,
+            detector: &DataHandle<Self>,
+            object: &DataHandle<S::Object>,
+            stored_box: &BoundingBox<D>,
+          ) -> Option<<S::Steward as TimeSteward>::EventHandle> {
+            detector
+              .space
+              .when_escapes(accessor, object, stored_box.clone())
+              .map(|time| {
+                let time_id = accessor.extended_now().id;
+                accessor.create_prediction(
+                  time,
+                  DeterministicRandomId::new(&(
+                    0x71c3e95a0d28f4b6u64,
+                    time_id,
+                    detector.space.unique_id(accessor, object),
+                  )),
+                  Escape {
+                    detector: detector.clone(),
+                    object: object.clone(),
+                  },
+                )
+              })
+          }
+        }
+      }
     }
   };
 }
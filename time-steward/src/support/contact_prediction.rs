@@ -0,0 +1,302 @@
+#[doc(hidden)]
+#[macro_export]
+macro_rules! time_steward_define_contact_prediction {
+  () => {
+    pub mod contact_prediction {
+      use super::simple_timeline::{destroy, query, set, unset, SimpleTimeline};
+      use super::*;
+      use crate::support::trajectories::{self, MagnitudeTrajectory};
+      use crate::type_utils::list_of_types::{Listable, ListedType, Registrar};
+      use crate::type_utils::{PersistentTypeId, PersistentlyIdentifiedType};
+      use crate::{DeterministicRandomId, QueryResult, SimulationStateData};
+
+      /// How far past the sum of the radii the distance has to go, in either direction, before objects count as having started or stopped touching. Without it, rounding error could end a contact right after it started.
+      pub const LEEWAY: trajectories::Coordinate = 2;
+
+      pub type Types<S> = (ListedType<Contact<S>>, ListedType<ContactChange<S>>);
+
+      /// Round objects moving along trajectories, for predicting when pairs of them start and stop touching.
+      ///
+      /// Only pairs that have a Contact are watched. Typically, a collision detector's Space creates and destroys them by calling `become_neighbors()` and `stop_being_neighbors()` from its own methods of the same names. As with the collision detector, it makes sense to implement this on a unit-like struct; it can't be the same type as the detector's Space, because their associated types would be ambiguous.
+      pub trait Space: SimulationStateData + PersistentlyIdentifiedType + Clone {
+        type Steward: TimeSteward<Basics: Basics<Time = trajectories::Time>>;
+        type Object: SimulationStateData + PersistentlyIdentifiedType;
+        type Trajectory: MagnitudeTrajectory;
+        /// Whatever the simulation wants to remember about each pair of objects, such as the force they exert on each other while they're touching. New contacts start with the default.
+        type ContactData: QueryResult + Default;
+        type UniqueId: SimulationStateData + Ord;
+
+        /// The `time_shift` that the trajectories use, as in the arguments to the trajectory methods.
+        const TIME_SHIFT: u32;
+
+        // An Object has to store the list of its contacts.
+        // These are getter and setter methods for that list.
+        fn get_contacts<A: Accessor<Steward = Self::Steward>>(
+          &self,
+          accessor: &A,
+          object: &DataHandle<Self::Object>,
+        ) -> Vec<DataHandle<Contact<Self>>>;
+        fn set_contacts<A: EventAccessor<Steward = Self::Steward>>(
+          &self,
+          accessor: &A,
+          object: &DataHandle<Self::Object>,
+          contacts: Vec<DataHandle<Contact<Self>>>,
+        );
+
+        // must be unique among ALL objects/space pairs
+        fn unique_id<A: Accessor<Steward = Self::Steward>>(
+          &self,
+          accessor: &A,
+          object: &DataHandle<Self::Object>,
+        ) -> Self::UniqueId;
+
+        /// The object's current trajectory. This should be read with a tracking query, so that a retroactive change to it invalidates the predictions made from it.
+        fn trajectory<A: EventAccessor<Steward = Self::Steward>>(
+          &self,
+          accessor: &A,
+          object: &DataHandle<Self::Object>,
+        ) -> Self::Trajectory;
+        fn radius<A: EventAccessor<Steward = Self::Steward>>(
+          &self,
+          accessor: &A,
+          object: &DataHandle<Self::Object>,
+        ) -> trajectories::Coordinate;
+
+        /// Called when the objects of `contact` start or stop touching. Typically, this changes their trajectories – calling `trajectory_changed()` for each of them – and records what it did with `set_contact_data()`. The new prediction for `contact` is made afterwards, so this doesn't need to do it.
+        fn touching_changed<A: EventAccessor<Steward = Self::Steward>>(
+          &self,
+          accessor: &A,
+          contact: &DataHandle<Contact<Self>>,
+          touching: bool,
+        );
+
+        /// Undoes all changes that the current event made to the objects of `contact` and to their contacts (see `unset_contacts()`). ContactChange events call this when they are undone. A simulation where those events can never be invalidated may panic here instead, the way `#[event(not_undoable)]` events do.
+        fn undo_touching_changed<A: FutureCleanupAccessor<Steward = Self::Steward>>(
+          &self,
+          accessor: &A,
+          contact: &DataHandle<Contact<Self>>,
+        );
+      }
+
+      /// A pair of objects that are watched for touching, from when they become neighbors until they stop being neighbors.
+      #[derive(Serialize, Deserialize, Debug)]
+      #[serde(bound = "")]
+      pub struct Contact<S: Space> {
+        space: S,
+        objects: [DataHandle<S::Object>; 2],
+        varying: DataTimelineCell<SimpleTimeline<ContactVarying<S>, S::Steward>>,
+      }
+      impl<S: Space> PersistentlyIdentifiedType for Contact<S> {
+        const ID: PersistentTypeId = PersistentTypeId(0x2b1f6e93d8c4a057 ^ S::ID.0);
+      }
+      impl<S: Space> Listable for Contact<S> {
+        fn register<R: Registrar>(registrar: &mut R) {
+          registrar.data::<Self>();
+        }
+      }
+      impl<S: Space> Contact<S> {
+        pub fn objects(&self) -> &[DataHandle<S::Object>; 2] {
+          &self.objects
+        }
+      }
+
+      #[derive(Serialize, Deserialize, Debug, Derivative)]
+      #[derivative(Clone(bound = ""), PartialEq(bound = ""), Eq(bound = ""))]
+      #[serde(bound = "")]
+      struct ContactVarying<S: Space> {
+        touching: bool,
+        data: S::ContactData,
+        next_change: Option<<S::Steward as TimeSteward>::EventHandle>,
+      }
+
+      /// The event when the objects of a Contact start or stop touching.
+      #[derive(Serialize, Deserialize, Debug, Derivative)]
+      #[derivative(Clone(bound = ""))]
+      #[serde(bound = "")]
+      pub struct ContactChange<S: Space> {
+        contact: DataHandle<Contact<S>>,
+      }
+      impl<S: Space> PersistentlyIdentifiedType for ContactChange<S> {
+        const ID: PersistentTypeId = PersistentTypeId(0x94d7c20e5f38b61a ^ S::ID.0);
+      }
+      impl<S: Space> Listable for ContactChange<S> {
+        fn register<R: Registrar>(registrar: &mut R) {
+          Self::register_event(registrar);
+        }
+      }
+      impl<S: Space> Event for ContactChange<S> {
+        type Steward = S::Steward;
+        type ExecutionData = ();
+        fn execute<Accessor: EventAccessor<Steward = Self::Steward>>(&self, accessor: &mut Accessor) {
+          // If the event that created this contact was undone after a retroactive change had already removed the contact from its objects' lists, nothing could reach the contact to unset it, so its old prediction still comes here. Such a contact can never be listed again.
+          let [first, second] = &self.contact.objects;
+          if find_contact(accessor, &self.contact.space, [first, second]).as_ref()
+            != Some(&self.contact)
+          {
+            return;
+          }
+          let mut varying = query(accessor, &self.contact.varying);
+          varying.touching = !varying.touching;
+          let touching = varying.touching;
+          set(accessor, &self.contact.varying, varying);
+          self
+            .contact
+            .space
+            .touching_changed(accessor, &self.contact, touching);
+          update_prediction(accessor, &self.contact);
+        }
+        fn undo<Accessor: FutureCleanupAccessor<Steward = Self::Steward>>(
+          &self,
+          accessor: &mut Accessor,
+          _: (),
+        ) {
+          self
+            .contact
+            .space
+            .undo_touching_changed(accessor, &self.contact);
+        }
+      }
+
+      fn same_objects<S: Space>(contact: &Contact<S>, objects: [&DataHandle<S::Object>; 2]) -> bool {
+        [&contact.objects[0], &contact.objects[1]] == objects
+          || [&contact.objects[1], &contact.objects[0]] == objects
+      }
+
+      /// Replaces the prediction of when the objects of `contact` next start or stop touching.
+      fn update_prediction<S: Space, A: EventAccessor<Steward = S::Steward>>(
+        accessor: &A,
+        contact: &DataHandle<Contact<S>>,
+      ) {
+        let space = &contact.space;
+        let [first, second] = &contact.objects;
+        let mut varying = query(accessor, &contact.varying);
+        let difference = space.trajectory(accessor, second) - space.trajectory(accessor, first);
+        let radius = space.radius(accessor, first) + space.radius(accessor, second);
+        let now = *accessor.now();
+        let range = [now, trajectories::Time::max_value()];
+        let time = if varying.touching {
+          difference.next_time_magnitude_significantly_gt(range, S::TIME_SHIFT, radius + LEEWAY)
+        } else {
+          difference.next_time_magnitude_significantly_lt(range, S::TIME_SHIFT, radius - LEEWAY)
+        };
+        varying.next_change = time.filter(|time| *time >= now).map(|time| {
+          accessor.create_prediction(
+            time,
+            DeterministicRandomId::new(&(
+              0x7c35e1a9b28d064fu64,
+              accessor.extended_now().id,
+              space.unique_id(accessor, first),
+              space.unique_id(accessor, second),
+            )),
+            ContactChange {
+              contact: contact.clone(),
+            },
+          )
+        });
+        set(accessor, &contact.varying, varying);
+      }
+
+      /// Starts watching whether `objects` are touching. Call this from the `become_neighbors()` method of the collision detector's Space.
+      pub fn become_neighbors<S: Space, A: EventAccessor<Steward = S::Steward>>(
+        accessor: &A,
+        space: &S,
+        objects: [&DataHandle<S::Object>; 2],
+      ) -> DataHandle<Contact<S>> {
+        let contact = accessor.new_handle(Contact {
+          space: space.clone(),
+          objects: [objects[0].clone(), objects[1].clone()],
+          varying: DataTimelineCell::new(SimpleTimeline::new()),
+        });
+        set(
+          accessor,
+          &contact.varying,
+          ContactVarying {
+            touching: false,
+            data: S::ContactData::default(),
+            next_change: None,
+          },
+        );
+        for object in objects.iter() {
+          let mut contacts = space.get_contacts(accessor, object);
+          contacts.push(contact.clone());
+          space.set_contacts(accessor, object, contacts);
+        }
+        update_prediction(accessor, &contact);
+        contact
+      }
+
+      /// Stops watching whether `objects` are touching, destroying their Contact along with its prediction. Call this from the `stop_being_neighbors()` method of the collision detector's Space.
+      ///
+      /// This doesn't call `touching_changed()`, even if the objects were touching. Objects whose bounding boxes include their radii stay neighbors for as long as they touch.
+      pub fn stop_being_neighbors<S: Space, A: EventAccessor<Steward = S::Steward>>(
+        accessor: &A,
+        space: &S,
+        objects: [&DataHandle<S::Object>; 2],
+      ) {
+        let contact = find_contact(accessor, space, objects)
+          .expect("Tried to stop watching objects that had no Contact");
+        destroy(accessor, &contact.varying);
+        for object in objects.iter() {
+          let mut contacts = space.get_contacts(accessor, object);
+          contacts.retain(|contact| !same_objects(contact, objects));
+          space.set_contacts(accessor, object, contacts);
+        }
+      }
+
+      /// Replaces the predictions for all of `object`'s contacts. Call this whenever its trajectory or radius changes.
+      pub fn trajectory_changed<S: Space, A: EventAccessor<Steward = S::Steward>>(
+        accessor: &A,
+        space: &S,
+        object: &DataHandle<S::Object>,
+      ) {
+        for contact in space.get_contacts(accessor, object).iter() {
+          update_prediction(accessor, contact);
+        }
+      }
+
+      /// Undoes the current event's changes to `object`'s contacts. Since the event may have created or destroyed contacts, call this both before and after undoing the change to the object's list of contacts.
+      pub fn unset_contacts<S: Space, A: FutureCleanupAccessor<Steward = S::Steward>>(
+        accessor: &A,
+        space: &S,
+        object: &DataHandle<S::Object>,
+      ) {
+        for contact in space.get_contacts(accessor, object).iter() {
+          unset(accessor, &contact.varying);
+        }
+      }
+
+      pub fn find_contact<S: Space, A: Accessor<Steward = S::Steward>>(
+        accessor: &A,
+        space: &S,
+        objects: [&DataHandle<S::Object>; 2],
+      ) -> Option<DataHandle<Contact<S>>> {
+        space
+          .get_contacts(accessor, objects[0])
+          .into_iter()
+          .find(|contact| same_objects(contact, objects))
+      }
+
+      pub fn touching<S: Space, A: Accessor<Steward = S::Steward>>(
+        accessor: &A,
+        contact: &DataHandle<Contact<S>>,
+      ) -> bool {
+        query(accessor, &contact.varying).touching
+      }
+      pub fn contact_data<S: Space, A: Accessor<Steward = S::Steward>>(
+        accessor: &A,
+        contact: &DataHandle<Contact<S>>,
+      ) -> S::ContactData {
+        query(accessor, &contact.varying).data
+      }
+      pub fn set_contact_data<S: Space, A: EventAccessor<Steward = S::Steward>>(
+        accessor: &A,
+        contact: &DataHandle<Contact<S>>,
+        data: S::ContactData,
+      ) {
+        let mut varying = query(accessor, &contact.varying);
+        varying.data = data;
+        set(accessor, &contact.varying, varying);
+      }
+    } //mod
+  };
+}
@@ -0,0 +1,241 @@
+#[doc(hidden)]
+#[macro_export]
+macro_rules! time_steward_define_data_timeline_conformance {
+  () => {
+    /// Randomized checks that a DataTimeline follows the rules in the `DataTimeline` and `DataTimelineQueriableWith` docs.
+    ///
+    /// To check a timeline, implement `Scenario` with an event that modifies it and a way to query it, then call `check()` with generators for times, modifications, and queries. The suite runs random sequences of fiat event insertions and removals, queries, snapshots, and `forget_before()` calls through this steward, and checks that:
+    /// * every query gives the same result as a fresh simulation of the events that are still present (so retroactive changes invalidated everything they needed to)
+    /// * query results only change at event times (they're also checked strictly between events, using `Scenario::time_between()`)
+    /// * querying has no side effects (a snapshot taken after a query still agrees with the fresh simulation)
+    /// * snapshots keep giving the same results after later changes (so `clone_for_snapshot()` preserved them)
+    /// * `forget_before()` doesn't change any results that weren't forgotten
+    ///
+    /// If a rule is broken, `check()` panics with the minimal sequence of operations that breaks it.
+    ///
+    /// The suite assumes that query results only change at fiat event times, so the modification events shouldn't create predictions.
+    pub mod data_timeline_conformance {
+      use proptest::collection;
+      use proptest::strategy::{BoxedStrategy, Strategy, Union};
+      use proptest::test_runner::{Config, TestCaseError, TestError, TestRunner};
+      use std::fmt::Debug;
+
+      use super::super::super::api::*;
+      use super::*;
+      use crate::DeterministicRandomId;
+
+      pub trait Scenario: 'static {
+        type Basics: Basics;
+        /// A fiat event that modifies the timeline being checked. It must implement `undo()`, and be listed in `Basics::Types`.
+        type Modification: Event<Steward = Steward<Self::Basics>> + Clone;
+        type Query: Clone + Debug + 'static;
+        type QueryResult: PartialEq + Debug;
+
+        fn make_globals() -> <Self::Basics as Basics>::Globals;
+        /// Fiat events that are present from the start and are never removed, for timelines that can't be queried until something has set them. They should come before all the generated times.
+        fn initial_events() -> Vec<(ScenarioTime<Self>, Self::Modification)> {
+          Vec::new()
+        }
+        /// Some time strictly between `earlier` and `later`, if there is one. Query results are checked there too, to make sure they don't change between events.
+        fn time_between(
+          earlier: &ScenarioTime<Self>,
+          later: &ScenarioTime<Self>,
+        ) -> Option<ScenarioTime<Self>>;
+        fn query(
+          accessor: &<Steward<Self::Basics> as TimeSteward>::SnapshotAccessor,
+          query: &Self::Query,
+        ) -> Self::QueryResult;
+      }
+
+      type ScenarioTime<S> = <<S as Scenario>::Basics as Basics>::Time;
+
+      #[derive(Derivative)]
+      #[derivative(Clone(bound = ""), Debug(bound = ""))]
+      pub enum Operation<S: Scenario> {
+        Insert {
+          time: ScenarioTime<S>,
+          modification: S::Modification,
+        },
+        /// Removes one of the fiat events that are present, chosen by `which` modulo how many there are.
+        Remove { which: usize },
+        Query { time: ScenarioTime<S>, query: S::Query },
+        /// Takes a snapshot and remembers the query result, which must stay the same as long as the snapshot exists.
+        Snapshot { time: ScenarioTime<S>, query: S::Query },
+        ForgetBefore { time: ScenarioTime<S> },
+      }
+
+      /// A strategy for sequences of up to `max_operations` operations.
+      pub fn operations<S: Scenario>(
+        times: impl Fn() -> BoxedStrategy<ScenarioTime<S>>,
+        modifications: impl Fn() -> BoxedStrategy<S::Modification>,
+        queries: impl Fn() -> BoxedStrategy<S::Query>,
+        max_operations: usize,
+      ) -> BoxedStrategy<Vec<Operation<S>>> {
+        let operation = Union::new(vec![
+          (times(), modifications())
+            .prop_map(|(time, modification)| Operation::Insert { time, modification })
+            .boxed(),
+          (0usize..1 << 16)
+            .prop_map(|which| Operation::Remove { which })
+            .boxed(),
+          (times(), queries())
+            .prop_map(|(time, query)| Operation::Query { time, query })
+            .boxed(),
+          (times(), queries())
+            .prop_map(|(time, query)| Operation::Snapshot { time, query })
+            .boxed(),
+          times()
+            .prop_map(|time| Operation::ForgetBefore { time })
+            .boxed(),
+        ]);
+        collection::vec(operation, 0..max_operations).boxed()
+      }
+
+      fn new_steward<S: Scenario>() -> Steward<S::Basics> {
+        let mut steward: Steward<S::Basics> = Steward::from_globals(S::make_globals()).unwrap();
+        for (index, (time, modification)) in S::initial_events().into_iter().enumerate() {
+          steward
+            .insert_fiat_event(
+              time,
+              DeterministicRandomId::new(&(index, 0x51a7_e3b9u32)),
+              modification,
+            )
+            .unwrap();
+        }
+        steward
+      }
+
+      fn fresh_query<S: Scenario>(
+        present: &[(ScenarioTime<S>, DeterministicRandomId, S::Modification)],
+        time: &ScenarioTime<S>,
+        query: &S::Query,
+      ) -> S::QueryResult {
+        let mut steward = new_steward::<S>();
+        for (time, id, modification) in present {
+          steward
+            .insert_fiat_event(time.clone(), *id, modification.clone())
+            .unwrap();
+        }
+        let snapshot = steward.snapshot_before(time).unwrap();
+        S::query(&snapshot, query)
+      }
+
+      /// Runs a sequence of operations, returning a description of the first broken rule, if any.
+      pub fn check_operations<S: Scenario>(operations: &[Operation<S>]) -> Result<(), String> {
+        let mut steward = new_steward::<S>();
+        let mut present: Vec<(ScenarioTime<S>, DeterministicRandomId, S::Modification)> = Vec::new();
+        let mut snapshots = Vec::new();
+
+        for (index, operation) in operations.iter().enumerate() {
+          match operation {
+            Operation::Insert { time, modification } => {
+              let id = DeterministicRandomId::new(&(index, 0x8c1e_5d07u32));
+              if steward
+                .insert_fiat_event(time.clone(), id, modification.clone())
+                .is_ok()
+              {
+                present.push((time.clone(), id, modification.clone()));
+              }
+            }
+            Operation::Remove { which } => {
+              if !present.is_empty() {
+                let which = which % present.len();
+                let (time, id, _) = &present[which];
+                if steward.remove_fiat_event(time, *id).is_ok() {
+                  present.remove(which);
+                }
+              }
+            }
+            Operation::Query { time, query } => {
+              let result = steward
+                .snapshot_before(time)
+                .map(|snapshot| S::query(&snapshot, query));
+              if let Some(result) = result {
+                let expected = fresh_query::<S>(&present, time, query);
+                if result != expected {
+                  return Err(format!(
+                    "operation {}: the query gave {:?}, but a fresh simulation of the same events gives {:?}",
+                    index, result, expected
+                  ));
+                }
+                // if querying changed anything, a snapshot taken afterwards may disagree with the fresh simulation
+                if let Some(snapshot) = steward.snapshot_before(time) {
+                  let again = S::query(&snapshot, query);
+                  if again != expected {
+                    return Err(format!(
+                      "operation {}: the query gave {:?}, but a snapshot taken after it gave {:?}; queries must not have side effects",
+                      index, result, again
+                    ));
+                  }
+                }
+                let next_event_time = present
+                  .iter()
+                  .map(|event| &event.0)
+                  .filter(|event_time| *event_time >= time)
+                  .min();
+                if let Some(next_event_time) = next_event_time {
+                  let sample_times = S::time_between(time, next_event_time)
+                    .into_iter()
+                    .chain(Some(next_event_time.clone()));
+                  for sample_time in sample_times {
+                    if let Some(snapshot) = steward.snapshot_before(&sample_time) {
+                      let later = S::query(&snapshot, query);
+                      if later != result {
+                        return Err(format!(
+                          "operation {}: the query gave {:?} at {:?}, but {:?} at {:?}, although no events happened in between",
+                          index, result, time, later, sample_time
+                        ));
+                      }
+                    }
+                  }
+                }
+              }
+            }
+            Operation::Snapshot { time, query } => {
+              if let Some(snapshot) = steward.snapshot_before(time) {
+                let result = S::query(&snapshot, query);
+                snapshots.push((index, snapshot, query.clone(), result));
+              }
+            }
+            Operation::ForgetBefore { time } => steward.forget_before(time),
+          }
+
+          for (taken, snapshot, query, result) in &snapshots {
+            let current = S::query(snapshot, query);
+            if &current != result {
+              return Err(format!(
+                "operation {}: the snapshot taken by operation {} gave {:?}, but now gives {:?}",
+                index, taken, result, current
+              ));
+            }
+          }
+        }
+        Ok(())
+      }
+
+      /// Checks random sequences of operations, and panics with a minimal failing sequence if any of them break a rule.
+      pub fn check<S: Scenario>(
+        config: Config,
+        times: impl Fn() -> BoxedStrategy<ScenarioTime<S>>,
+        modifications: impl Fn() -> BoxedStrategy<S::Modification>,
+        queries: impl Fn() -> BoxedStrategy<S::Query>,
+        max_operations: usize,
+      ) {
+        let strategy = operations::<S>(times, modifications, queries, max_operations);
+        let mut runner = TestRunner::new(config);
+        match runner.run(&strategy, |operations| {
+          check_operations::<S>(&operations).map_err(TestCaseError::fail)
+        }) {
+          Ok(()) => (),
+          Err(TestError::Fail(reason, operations)) => panic!(
+            "DataTimeline conformance failure: {}\nminimal failing operations: {:#?}",
+            reason, operations
+          ),
+          Err(TestError::Abort(reason)) => {
+            panic!("DataTimeline conformance check aborted: {}", reason)
+          }
+        }
+      }
+    } //mod
+  };
+}
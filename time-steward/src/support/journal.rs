@@ -0,0 +1,393 @@
+//! Crash-safe storage for a running simulation.
+//!
+//! A simulation is fully determined by its globals and its fiat events, so we store it as a series of *generations*. Generation N consists of `checkpoint_N` – a serialized snapshot, as from `SnapshotAccessor::serialize_into()` – and `journal_N`, an append-only log of the fiat events inserted and removed since that checkpoint. Generation 0 has no checkpoint file; it starts from the globals.
+//!
+//! Each journal record is framed with its length and a checksum. If we crash in the middle of appending a record, the next load notices the bad record and truncates the journal back to the last good one. Checkpoints are written atomically (see `support::autosave::write_atomically`); if the newest one is damaged anyway, we fall back to an older generation and replay every journal since.
+
+use siphasher::sip::SipHasher;
+use std::fmt;
+use std::fs::File;
+use std::hash::Hasher;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+
+use crate::FiatEventOperationError;
+
+#[derive(Debug)]
+pub enum JournalError {
+  Io(io::Error),
+  FiatEvent(FiatEventOperationError),
+}
+
+impl From<io::Error> for JournalError {
+  fn from(error: io::Error) -> Self {
+    JournalError::Io(error)
+  }
+}
+impl From<FiatEventOperationError> for JournalError {
+  fn from(error: FiatEventOperationError) -> Self {
+    JournalError::FiatEvent(error)
+  }
+}
+impl fmt::Display for JournalError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      JournalError::Io(error) => write!(f, "journal I/O error: {}", error),
+      JournalError::FiatEvent(error) => write!(f, "fiat event rejected by the steward: {:?}", error),
+    }
+  }
+}
+
+pub fn checkpoint_prefix() -> &'static str {
+  "checkpoint_"
+}
+pub fn journal_prefix() -> &'static str {
+  "journal_"
+}
+
+const RECORD_HEADER_SIZE: usize = 12;
+
+fn checksum(payload: &[u8]) -> u64 {
+  let mut hasher = SipHasher::new_with_keys(0x2b6fd4a4c0e8f8b1, 0x7d1c5a9e13f6b042);
+  hasher.write(payload);
+  hasher.finish()
+}
+
+/// Appends one record to a journal file and waits for it to reach the disk.
+///
+/// If that fails, this tries to truncate the file back to its old length, because records appended after a partial one would be ignored when the journal is read. The caller still shouldn't append anything else, since the truncation can fail too.
+pub fn append_record(file: &mut File, payload: &[u8]) -> io::Result<()> {
+  assert!(payload.len() <= u32::max_value() as usize, "journal records are limited to 4GiB");
+  let mut framed = Vec::with_capacity(RECORD_HEADER_SIZE + payload.len());
+  framed.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+  framed.extend_from_slice(&checksum(payload).to_le_bytes());
+  framed.extend_from_slice(payload);
+  let old_length = file.metadata()?.len();
+  let result = file.write_all(&framed).and_then(|()| file.sync_data());
+  if result.is_err() {
+    let _ = file.set_len(old_length).and_then(|()| file.sync_all());
+  }
+  result
+}
+
+/// Reads every intact record from the start of a journal file.
+///
+/// Returns the records and the length of the prefix of the file that they occupy. Anything after that – a partially written record, or one whose checksum doesn't match – is ignored.
+pub fn read_records(file: &mut File) -> io::Result<(Vec<Vec<u8>>, u64)> {
+  let mut contents = Vec::new();
+  file.seek(SeekFrom::Start(0))?;
+  file.read_to_end(&mut contents)?;
+  let mut records = Vec::new();
+  let mut position = 0;
+  while contents.len() - position >= RECORD_HEADER_SIZE {
+    let mut length = [0u8; 4];
+    length.copy_from_slice(&contents[position..position + 4]);
+    let length = u32::from_le_bytes(length) as usize;
+    let mut expected_checksum = [0u8; 8];
+    expected_checksum.copy_from_slice(&contents[position + 4..position + RECORD_HEADER_SIZE]);
+    let expected_checksum = u64::from_le_bytes(expected_checksum);
+    let start = position + RECORD_HEADER_SIZE;
+    if contents.len() - start < length {
+      break;
+    }
+    let payload = &contents[start..start + length];
+    if checksum(payload) != expected_checksum {
+      break;
+    }
+    records.push(payload.to_vec());
+    position = start + length;
+  }
+  Ok((records, position as u64))
+}
+
+/// Like `read_records()`, but also cuts off any damaged tail, so that new records can safely be appended.
+pub fn read_and_repair_records(file: &mut File) -> io::Result<Vec<Vec<u8>>> {
+  let (records, valid_length) = read_records(file)?;
+  if file.metadata()?.len() != valid_length {
+    file.set_len(valid_length)?;
+    file.sync_all()?;
+  }
+  file.seek(SeekFrom::End(0))?;
+  Ok(records)
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! time_steward_define_journal {
+  () => {
+    pub mod journal {
+      use std::any::Any;
+      use std::collections::{BTreeMap, HashMap};
+      use std::fs::{self, File, OpenOptions};
+      use std::io;
+      use std::path::{Path, PathBuf};
+
+      use super::super::super::api::*;
+      use super::*;
+      use crate::support::autosave::{numbered_file_name, numbered_files, write_atomically};
+      use crate::support::journal::{
+        append_record, checkpoint_prefix, journal_prefix, read_and_repair_records, read_records, JournalError,
+      };
+      use crate::type_utils::list_of_types::{ListOfTypes, ListOfTypesVisitor, Listable, Registrar};
+      use crate::type_utils::{PersistentTypeId, PersistentlyIdentifiedType};
+      use crate::DeterministicRandomId;
+
+      #[derive(Serialize, Deserialize, Debug)]
+      #[serde(bound = "")]
+      enum JournalRecord<B: Basics> {
+        Insert {
+          time: B::Time,
+          id: DeterministicRandomId,
+          type_id: PersistentTypeId,
+          event: Vec<u8>,
+        },
+        Remove {
+          time: B::Time,
+          id: DeterministicRandomId,
+        },
+      }
+
+      type ReplayFunction<Steward> = fn(
+        &mut Steward,
+        <<Steward as TimeSteward>::Basics as Basics>::Time,
+        DeterministicRandomId,
+        &[u8],
+      ) -> ::bincode::Result<Result<(), FiatEventOperationError>>;
+
+      struct ReplayFunctions<Steward: TimeSteward>(HashMap<PersistentTypeId, ReplayFunction<Steward>>);
+      impl<Steward: TimeSteward> Registrar for ReplayFunctions<Steward> {
+        fn extension(&mut self, extension: &Any) {
+          if let Some(registration) = extension.downcast_ref::<EventRegistration<Steward>>() {
+            self.0.insert(registration.id, registration.insert_serialized_fiat_event);
+          }
+        }
+      }
+      impl<Steward: TimeSteward> ListOfTypesVisitor for ReplayFunctions<Steward> {
+        fn visit<T: Listable>(&mut self) {
+          T::register(self);
+        }
+      }
+
+      fn invalid_data<E: Into<Box<::std::error::Error + Send + Sync>>>(error: E) -> io::Error {
+        io::Error::new(io::ErrorKind::InvalidData, error)
+      }
+
+      /// A steward whose fiat events are recorded on disk as they happen, so that it can be recovered after a crash.
+      ///
+      /// Insert and remove fiat events through the Journal rather than the steward itself; anything done directly to the steward isn't recorded. Call `checkpoint()` now and then to keep the journal from growing forever.
+      pub struct Journal<Steward: ConstructibleTimeSteward> {
+        steward: Steward,
+        directory: PathBuf,
+        checkpoints_kept: usize,
+        generation: u64,
+        file: File,
+        // the serialized Insert record of every fiat event that is currently inserted and not before the latest checkpoint
+        pending: BTreeMap<(<Steward::Basics as Basics>::Time, DeterministicRandomId), Vec<u8>>,
+        // set when a fiat event reached the steward but not the disk; from then on, the journal no longer describes the steward
+        failed: bool,
+      }
+
+      impl<Steward: ConstructibleTimeSteward> Journal<Steward> {
+        /// Recovers the simulation stored in `directory`, or starts a new one from `initial_globals` if the directory holds none.
+        ///
+        /// `checkpoints_kept` is how many generations to keep on disk; keeping more than one lets us recover even if the newest checkpoint is damaged.
+        pub fn open<F: FnOnce() -> <Steward::Basics as Basics>::Globals>(
+          directory: &Path,
+          checkpoints_kept: usize,
+          initial_globals: F,
+        ) -> io::Result<Self> {
+          assert!(checkpoints_kept > 0, "a journal must keep at least one checkpoint");
+          fs::create_dir_all(directory)?;
+          let checkpoints = numbered_files(directory, checkpoint_prefix())?;
+          let journals = numbered_files(directory, journal_prefix())?;
+
+          let mut loaded = None;
+          for (number, path) in checkpoints.iter().rev() {
+            if let Ok(steward) = File::open(path).and_then(|mut file| Steward::deserialize_from(&mut file).map_err(invalid_data)) {
+              loaded = Some((*number, steward));
+              break;
+            }
+          }
+          let (first_generation, mut steward) = match loaded {
+            Some(loaded) => loaded,
+            None => {
+              if journals.first().map_or(false, |&(number, _)| number != 0) {
+                return Err(invalid_data("no intact checkpoint remains for the oldest journal"));
+              }
+              (0, Steward::from_globals(initial_globals()).map_err(invalid_data)?)
+            }
+          };
+          let generation = journals.last().map_or(first_generation, |&(number, _)| ::std::cmp::max(number, first_generation));
+
+          let mut replay_functions = ReplayFunctions::<Steward>(HashMap::new());
+          <Steward::Basics as Basics>::Types::visit_all(&mut replay_functions);
+
+          // Later journals repeat the events that were still pending when their checkpoint was made, so collect the final set of events before inserting any of them.
+          let mut pending = BTreeMap::new();
+          let mut file = None;
+          for number in first_generation..=generation {
+            let path = directory.join(numbered_file_name(journal_prefix(), number));
+            let records = if number == generation {
+              let mut current = OpenOptions::new().read(true).append(true).create(true).open(&path)?;
+              let records = read_and_repair_records(&mut current)?;
+              file = Some(current);
+              records
+            } else {
+              match File::open(&path) {
+                Ok(mut older) => read_records(&mut older)?.0,
+                Err(ref error) if error.kind() == io::ErrorKind::NotFound => Vec::new(),
+                Err(error) => return Err(error),
+              }
+            };
+            for payload in records {
+              match ::bincode::deserialize::<JournalRecord<Steward::Basics>>(&payload).map_err(invalid_data)? {
+                JournalRecord::Insert { time, id, .. } => {
+                  pending.insert((time, id), payload);
+                }
+                JournalRecord::Remove { time, id } => {
+                  pending.remove(&(time, id));
+                }
+              }
+            }
+          }
+
+          for payload in pending.values() {
+            if let JournalRecord::Insert { time, id, type_id, event } = ::bincode::deserialize::<JournalRecord<Steward::Basics>>(payload).map_err(invalid_data)? {
+              let replay = replay_functions.0.get(&type_id).ok_or_else(|| invalid_data("the journal contains an event type that isn't listed in Basics::Types"))?;
+              replay(&mut steward, time, id, &event)
+                .map_err(invalid_data)?
+                .map_err(|error| invalid_data(format!("the steward rejected a journaled fiat event: {:?}", error)))?;
+            }
+          }
+
+          Ok(Journal {
+            steward,
+            directory: directory.to_path_buf(),
+            checkpoints_kept,
+            generation,
+            file: file.unwrap(),
+            pending,
+            failed: false,
+          })
+        }
+
+        pub fn steward(&self) -> &Steward {
+          &self.steward
+        }
+        /// Access to the steward for running it and taking snapshots. Fiat events changed through this reference are NOT journaled.
+        pub fn steward_mut(&mut self) -> &mut Steward {
+          &mut self.steward
+        }
+        pub fn into_steward(self) -> Steward {
+          self.steward
+        }
+        pub fn generation(&self) -> u64 {
+          self.generation
+        }
+
+        fn check_not_failed(&self) -> io::Result<()> {
+          if self.failed {
+            return Err(io::Error::new(
+              io::ErrorKind::Other,
+              "an earlier write to the journal failed; reopen it to recover the last state that was recorded",
+            ));
+          }
+          Ok(())
+        }
+        fn append(&mut self, payload: &[u8]) -> io::Result<()> {
+          let result = append_record(&mut self.file, payload);
+          if result.is_err() {
+            self.failed = true;
+          }
+          result
+        }
+
+        /// Inserts a fiat event into the steward and, if the steward accepts it, records it durably before returning.
+        ///
+        /// If recording it fails, the event is taken back out of the steward, so the steward still only has what was recorded. The journal file may then end with part of a record, so every later insertion, removal or checkpoint is refused; reopen the journal to carry on.
+        pub fn insert_fiat_event<E: Event<Steward = Steward>>(
+          &mut self,
+          time: <Steward::Basics as Basics>::Time,
+          id: DeterministicRandomId,
+          event: E,
+        ) -> Result<(), JournalError> {
+          self.check_not_failed()?;
+          let event_data = ::bincode::serialize(&event, ::bincode::Infinite).map_err(invalid_data)?;
+          let record: JournalRecord<Steward::Basics> = JournalRecord::Insert {
+            time: time.clone(),
+            id,
+            type_id: E::ID,
+            event: event_data,
+          };
+          let payload = ::bincode::serialize(&record, ::bincode::Infinite).map_err(invalid_data)?;
+          self.steward.insert_fiat_event(time.clone(), id, event)?;
+          if let Err(error) = self.append(&payload) {
+            // a reopened journal won't have this event, so the steward mustn't keep it either
+            self.steward.remove_fiat_event(&time, id).expect("the steward refused to remove a fiat event it had just accepted");
+            return Err(error.into());
+          }
+          self.pending.insert((time, id), payload);
+          Ok(())
+        }
+
+        /// Removes a fiat event from the steward and, if the steward allows it, records the removal durably before returning. Like `insert_fiat_event()`, a failure to record it makes the journal refuse everything else.
+        pub fn remove_fiat_event(
+          &mut self,
+          time: &<Steward::Basics as Basics>::Time,
+          id: DeterministicRandomId,
+        ) -> Result<(), JournalError> {
+          self.check_not_failed()?;
+          self.steward.remove_fiat_event(time, id)?;
+          let record: JournalRecord<Steward::Basics> = JournalRecord::Remove { time: time.clone(), id };
+          let payload = ::bincode::serialize(&record, ::bincode::Infinite).map_err(invalid_data)?;
+          self.append(&payload)?;
+          self.pending.remove(&(time.clone(), id));
+          Ok(())
+        }
+
+        /// Starts a new generation whose checkpoint is the snapshot before `time`, and deletes generations older than `checkpoints_kept`.
+        ///
+        /// Once the new checkpoint is safely on disk, this calls `forget_before(time)` on the steward, because fiat events before the checkpoint could no longer be recorded. Returns false, doing nothing, if the steward can't provide a snapshot at `time`. If writing the new generation fails, the journal and the steward are left as they were.
+        pub fn checkpoint(&mut self, time: &<Steward::Basics as Basics>::Time) -> io::Result<bool> {
+          self.check_not_failed()?;
+          let snapshot = match self.steward.snapshot_before(time) {
+            Some(snapshot) => snapshot,
+            None => return Ok(false),
+          };
+          let mut checkpoint_data = Vec::new();
+          snapshot.serialize_into(&mut checkpoint_data).map_err(invalid_data)?;
+          drop(snapshot);
+
+          let generation = self.generation + 1;
+          // The new journal must be complete before the checkpoint that refers to it exists; otherwise a crash in between could lose the pending events.
+          let journal_path = self.directory.join(numbered_file_name(journal_prefix(), generation));
+          let mut file = OpenOptions::new().read(true).write(true).create(true).truncate(true).open(&journal_path)?;
+          let kept: BTreeMap<_, _> = self
+            .pending
+            .range((time.clone(), DeterministicRandomId::MIN)..)
+            .map(|(key, payload)| (key.clone(), payload.clone()))
+            .collect();
+          for payload in kept.values() {
+            append_record(&mut file, payload)?;
+          }
+          write_atomically(&self.directory.join(numbered_file_name(checkpoint_prefix(), generation)), &checkpoint_data)?;
+          // Only now can the steward forget; if anything above failed, the old generation still needs the earlier history.
+          self.steward.forget_before(time);
+
+          self.pending = kept;
+          self.file = file;
+          self.generation = generation;
+
+          let oldest_kept = (generation + 1).saturating_sub(self.checkpoints_kept as u64);
+          for prefix in &[checkpoint_prefix(), journal_prefix()] {
+            for (number, path) in numbered_files(&self.directory, prefix)? {
+              if number < oldest_kept {
+                fs::remove_file(path)?;
+              }
+            }
+          }
+          Ok(true)
+        }
+      }
+    }
+  };
+}
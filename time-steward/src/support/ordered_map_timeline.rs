@@ -0,0 +1,542 @@
+#[doc(hidden)]
+#[macro_export]
+macro_rules! time_steward_define_ordered_map_timeline {
+  () => {
+    pub mod ordered_map_timeline {
+      use std::collections::{BTreeMap, BTreeSet, VecDeque};
+      use std::ops::{Bound, RangeBounds};
+
+      use super::super::super::api::*;
+      use super::simple_timeline::{link_predictions, unlink_predictions};
+      use super::*;
+      use crate::implementation_support::common::split_off_greater_set;
+      use crate::type_utils::{PersistentTypeId, PersistentlyIdentifiedType};
+
+      /// Query for the value of a single key, or None if the key is absent.
+      #[derive(Clone, PartialEq, Eq, Hash, Serialize, Deserialize, Debug)]
+      pub struct Get<Key>(pub Key);
+      impl<Key> PersistentlyIdentifiedType for Get<Key> {
+        const ID: PersistentTypeId = PersistentTypeId(0x5f0c4b7e2a9d1e83);
+      }
+
+      /// Query for all of the entries whose keys are within a range, in key order.
+      ///
+      /// Like `BTreeMap::range()`, this panics if the start is greater than the end, or if they are equal and both excluded.
+      #[derive(Clone, PartialEq, Eq, Hash, Serialize, Deserialize, Debug)]
+      pub struct Range<Key> {
+        pub start: Bound<Key>,
+        pub end: Bound<Key>,
+      }
+      impl<Key> PersistentlyIdentifiedType for Range<Key> {
+        const ID: PersistentTypeId = PersistentTypeId(0xb43d6a01c87f25e9);
+      }
+      impl<Key: Clone + Ord> Range<Key> {
+        pub fn new<R: RangeBounds<Key>>(range: R) -> Self {
+          Range {
+            start: range.start_bound().cloned(),
+            end: range.end_bound().cloned(),
+          }
+        }
+        pub fn contains(&self, key: &Key) -> bool {
+          (self.start.as_ref(), self.end.as_ref()).contains(key)
+        }
+      }
+
+      /// Query for the number of keys that are present.
+      ///
+      /// Takes time proportional to the number of keys that have ever been present and haven't been forgotten.
+      #[derive(Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize, Debug)]
+      pub struct Len;
+      impl PersistentlyIdentifiedType for Len {
+        const ID: PersistentTypeId = PersistentTypeId(0x1a7e93f5d02c64bb);
+      }
+
+      #[derive(Serialize, Deserialize, Derivative)]
+      #[serde(bound = "")]
+      #[derivative(Clone(bound = ""), Debug(bound = ""), Default(bound = ""))]
+      struct KeyHistory<Value: QueryResult, Steward: TimeSteward> {
+        // None means the key was removed
+        #[serde(deserialize_with = "::serde::Deserialize::deserialize")]
+        changes: VecDeque<(<Steward as TimeSteward>::EventHandle, Option<Value>)>,
+        #[serde(deserialize_with = "::serde::Deserialize::deserialize")]
+        dependent_events: BTreeSet<<Steward as TimeSteward>::EventHandle>,
+      }
+
+      impl<Value: QueryResult, Steward: TimeSteward> KeyHistory<Value, Steward> {
+        fn search_changes(&self, time: &ExtendedTime<Steward::Basics>) -> Result<usize, usize> {
+          self
+            .changes
+            .binary_search_by(|change| change.0.extended_time().cmp(time))
+        }
+
+        fn change_at(
+          &self,
+          time: &ExtendedTime<Steward::Basics>,
+        ) -> Option<&(<Steward as TimeSteward>::EventHandle, Option<Value>)> {
+          let previous_change_index = match self.search_changes(time) {
+            Ok(index) => index,
+            Err(index) => index.checked_sub(1)?,
+          };
+          self.changes.get(previous_change_index)
+        }
+
+        fn value_at(&self, time: &ExtendedTime<Steward::Basics>) -> Option<&Value> {
+          self.change_at(time).and_then(|change| change.1.as_ref())
+        }
+
+        /// Returns whether any changes were removed.
+        fn remove_future<Accessor: FutureCleanupAccessor<Steward = Steward>>(
+          &mut self,
+          accessor: &Accessor,
+          also_present: bool,
+        ) -> bool {
+          let removed = split_off_greater_set(&mut self.dependent_events, accessor.extended_now());
+          for event in removed {
+            accessor.invalidate_execution(&event);
+          }
+          let mut previous: Option<<Steward as TimeSteward>::EventHandle> = None;
+          while let Some(change) = self.changes.pop_back() {
+            let ordering = change.0.extended_time().cmp(accessor.extended_now());
+            if ordering == Ordering::Less || (!also_present && ordering == Ordering::Equal) {
+              // if we removed things later, we may need to re-link predictions
+              if let (Some(previous), Some(value)) = (previous.as_ref(), change.1.as_ref()) {
+                link_predictions(accessor, value, previous);
+              }
+              self.changes.push_back(change);
+              break;
+            }
+            // if we are actually discarding the change, we need to clean up some stuff about it
+            if let Some(value) = change.1.as_ref() {
+              unlink_predictions(accessor, value, &change.0, previous.as_ref());
+            }
+
+            // except don't re-invalidate the event we are currently in
+            if ordering == Ordering::Greater {
+              accessor.invalidate_execution(&change.0);
+            }
+            previous = Some(change.0);
+          }
+          previous.is_some()
+        }
+
+        fn modify<Accessor: EventAccessor<Steward = Steward>>(
+          &mut self,
+          modification: Option<Value>,
+          accessor: &Accessor,
+        ) {
+          let mut pop = false;
+          if let Some(last) = self.changes.back() {
+            assert!(
+              &last.0 <= accessor.this_event(),
+              "All future changes should have been cleared before calling modify() "
+            );
+            if let Some(value) = last.1.as_ref() {
+              unlink_predictions(accessor, value, accessor.this_event(), None);
+            }
+            if &last.0 == accessor.this_event() {
+              pop = true;
+            }
+          }
+          if pop {
+            self.changes.pop_back();
+          }
+
+          if let Some(value) = modification.as_ref() {
+            link_predictions(accessor, value, accessor.this_event());
+          }
+          self
+            .changes
+            .push_back((accessor.this_event().clone(), modification));
+        }
+      }
+
+      /// A DataTimeline holding an ordered map, where each key has its own history.
+      ///
+      /// Unlike a `SimpleTimeline` holding a whole map, changing one key doesn't copy the map, and only invalidates the events that tracked that key, a range containing that key, or (if the change adds or removes the key) the number of keys.
+      #[derive(Serialize, Deserialize, Derivative)]
+      #[serde(bound = "")]
+      #[derivative(Clone(bound = ""), Debug(bound = ""), Default(bound = ""))]
+      pub struct OrderedMapTimeline<Key: QueryResult + Ord, Value: QueryResult, Steward: TimeSteward> {
+        // Hacky workaround for https://github.com/rust-lang/rust/issues/41617 (see https://github.com/serde-rs/serde/issues/943)
+        #[serde(deserialize_with = "::serde::Deserialize::deserialize")]
+        keys: BTreeMap<Key, KeyHistory<Value, Steward>>,
+        #[serde(deserialize_with = "::serde::Deserialize::deserialize")]
+        range_dependent_events: BTreeMap<<Steward as TimeSteward>::EventHandle, Vec<Range<Key>>>,
+        #[serde(deserialize_with = "::serde::Deserialize::deserialize")]
+        len_dependent_events: BTreeSet<<Steward as TimeSteward>::EventHandle>,
+        // the keys each event set, so that undoing it doesn't have to look through every key
+        #[serde(deserialize_with = "::serde::Deserialize::deserialize")]
+        changed_keys: BTreeMap<<Steward as TimeSteward>::EventHandle, BTreeSet<Key>>,
+      }
+
+      impl<Key: QueryResult + Ord, Value: QueryResult, Steward: TimeSteward>
+        OrderedMapTimeline<Key, Value, Steward>
+      {
+        pub fn new() -> Self {
+          OrderedMapTimeline {
+            keys: BTreeMap::new(),
+            range_dependent_events: BTreeMap::new(),
+            len_dependent_events: BTreeSet::new(),
+            changed_keys: BTreeMap::new(),
+          }
+        }
+
+        fn present_at(&self, key: &Key, time: &ExtendedTime<Steward::Basics>) -> bool {
+          self
+            .keys
+            .get(key)
+            .map_or(false, |history| history.value_at(time).is_some())
+        }
+
+        fn remove_future<Accessor: FutureCleanupAccessor<Steward = Steward>>(
+          &mut self,
+          accessor: &Accessor,
+          key: &Key,
+          also_present: bool,
+        ) -> bool {
+          self
+            .keys
+            .get_mut(key)
+            .map_or(false, |history| history.remove_future(accessor, also_present))
+        }
+
+        /// Invalidates the future events that read `key` as part of a range, and, if `presence_changed`, the ones that read the number of keys.
+        fn invalidate_aggregate_readers<Accessor: FutureCleanupAccessor<Steward = Steward>>(
+          &mut self,
+          accessor: &Accessor,
+          key: &Key,
+          presence_changed: bool,
+        ) {
+          let invalidated: Vec<_> = self
+            .range_dependent_events
+            .range::<ExtendedTime<Steward::Basics>, _>((
+              Bound::Excluded(accessor.extended_now()),
+              Bound::Unbounded,
+            ))
+            .filter(|(_, ranges)| ranges.iter().any(|range| range.contains(key)))
+            .map(|(event, _)| event.clone())
+            .collect();
+          for event in invalidated {
+            self
+              .range_dependent_events
+              .remove::<<Steward as TimeSteward>::EventHandle>(&event);
+            accessor.invalidate_execution(&event);
+          }
+          if presence_changed {
+            let removed =
+              split_off_greater_set(&mut self.len_dependent_events, accessor.extended_now());
+            for event in removed {
+              accessor.invalidate_execution(&event);
+            }
+          }
+        }
+      }
+
+      impl<Key: QueryResult + Ord, Value: QueryResult, Steward: TimeSteward> DataTimeline
+        for OrderedMapTimeline<Key, Value, Steward>
+      {
+        type Basics = Steward::Basics;
+
+        fn clone_for_snapshot(&self, time: &ExtendedTime<Self::Basics>) -> Self {
+          let keys = self
+            .keys
+            .iter()
+            .filter_map(|(key, history)| {
+              // absent keys don't need to be in the snapshot at all
+              let change = history.change_at(time).filter(|change| change.1.is_some())?;
+              let mut changes = VecDeque::new();
+              changes.push_back(change.clone());
+              Some((
+                key.clone(),
+                KeyHistory {
+                  changes,
+                  dependent_events: BTreeSet::new(),
+                },
+              ))
+            })
+            .collect();
+          OrderedMapTimeline {
+            keys,
+            range_dependent_events: BTreeMap::new(),
+            len_dependent_events: BTreeSet::new(),
+            changed_keys: BTreeMap::new(),
+          }
+        }
+
+        fn forget_before(&mut self, time: &ExtendedTime<Self::Basics>) {
+          self.range_dependent_events = self.range_dependent_events.split_off(time);
+          self.len_dependent_events = self.len_dependent_events.split_off(time);
+          self.changed_keys = self.changed_keys.split_off(time);
+
+          self.keys.retain(|_, history| {
+            history.dependent_events = history.dependent_events.split_off(time);
+            while history
+              .changes
+              .get(1)
+              .map_or(false, |change| change.0.extended_time() < time)
+            {
+              history.changes.pop_front();
+            }
+            // a key that was removed before `time` and hasn't been touched since can be forgotten entirely
+            let forgettable = history.dependent_events.is_empty()
+              && match (history.changes.len(), history.changes.front()) {
+                (0, _) => true,
+                (1, Some(change)) => change.1.is_none() && change.0.extended_time() < time,
+                _ => false,
+              };
+            !forgettable
+          });
+        }
+      }
+      impl<Key: QueryResult + Ord, Value: QueryResult, Steward: TimeSteward>
+        DataTimelineQueriableWith<Get<Key>> for OrderedMapTimeline<Key, Value, Steward>
+      {
+        type QueryResult = Option<Value>;
+
+        fn query(&self, query: &Get<Key>, time: &ExtendedTime<Self::Basics>) -> Self::QueryResult {
+          self
+            .keys
+            .get(&query.0)
+            .and_then(|history| history.value_at(time))
+            .cloned()
+        }
+      }
+      impl<Key: QueryResult + Ord, Value: QueryResult, Steward: TimeSteward>
+        DataTimelineQueriableWith<Range<Key>> for OrderedMapTimeline<Key, Value, Steward>
+      {
+        type QueryResult = Vec<(Key, Value)>;
+
+        fn query(&self, query: &Range<Key>, time: &ExtendedTime<Self::Basics>) -> Self::QueryResult {
+          self
+            .keys
+            .range((query.start.as_ref(), query.end.as_ref()))
+            .filter_map(|(key, history)| {
+              history
+                .value_at(time)
+                .map(|value| (key.clone(), value.clone()))
+            })
+            .collect()
+        }
+      }
+      impl<Key: QueryResult + Ord, Value: QueryResult, Steward: TimeSteward>
+        DataTimelineQueriableWith<Len> for OrderedMapTimeline<Key, Value, Steward>
+      {
+        type QueryResult = usize;
+
+        fn query(&self, _: &Len, time: &ExtendedTime<Self::Basics>) -> Self::QueryResult {
+          self
+            .keys
+            .values()
+            .filter(|history| history.value_at(time).is_some())
+            .count()
+        }
+      }
+
+      pub fn get<Key: QueryResult + Ord, Value: QueryResult, Steward: TimeSteward, A: Accessor<Steward = Steward>>(
+        accessor: &A,
+        handle: &DataTimelineCell<OrderedMapTimeline<Key, Value, Steward>>,
+        key: &Key,
+      ) -> Option<Value> {
+        accessor.query(handle, &Get(key.clone()))
+      }
+      pub fn tracking_get<
+        Key: QueryResult + Ord,
+        Value: QueryResult,
+        Steward: TimeSteward,
+        Accessor: EventAccessor<Steward = Steward>,
+      >(
+        accessor: &Accessor,
+        handle: &DataTimelineCell<OrderedMapTimeline<Key, Value, Steward>>,
+        key: &Key,
+      ) -> Option<Value> {
+        accessor.modify(handle, |timeline| {
+          timeline
+            .keys
+            .entry(key.clone())
+            .or_insert_with(Default::default)
+            .dependent_events
+            .insert(accessor.this_event().clone());
+        });
+        get(accessor, handle, key)
+      }
+      pub fn range<
+        Key: QueryResult + Ord,
+        Value: QueryResult,
+        Steward: TimeSteward,
+        A: Accessor<Steward = Steward>,
+        R: RangeBounds<Key>,
+      >(
+        accessor: &A,
+        handle: &DataTimelineCell<OrderedMapTimeline<Key, Value, Steward>>,
+        range: R,
+      ) -> Vec<(Key, Value)> {
+        accessor.query(handle, &Range::new(range))
+      }
+      pub fn tracking_range<
+        Key: QueryResult + Ord,
+        Value: QueryResult,
+        Steward: TimeSteward,
+        Accessor: EventAccessor<Steward = Steward>,
+        R: RangeBounds<Key>,
+      >(
+        accessor: &Accessor,
+        handle: &DataTimelineCell<OrderedMapTimeline<Key, Value, Steward>>,
+        range: R,
+      ) -> Vec<(Key, Value)> {
+        let range = Range::new(range);
+        let query = range.clone();
+        accessor.modify(handle, move |timeline| {
+          timeline
+            .range_dependent_events
+            .entry(accessor.this_event().clone())
+            .or_insert_with(Vec::new)
+            .push(range);
+        });
+        accessor.query(handle, &query)
+      }
+      pub fn len<Key: QueryResult + Ord, Value: QueryResult, Steward: TimeSteward, A: Accessor<Steward = Steward>>(
+        accessor: &A,
+        handle: &DataTimelineCell<OrderedMapTimeline<Key, Value, Steward>>,
+      ) -> usize {
+        accessor.query(handle, &Len)
+      }
+      pub fn tracking_len<
+        Key: QueryResult + Ord,
+        Value: QueryResult,
+        Steward: TimeSteward,
+        Accessor: EventAccessor<Steward = Steward>,
+      >(
+        accessor: &Accessor,
+        handle: &DataTimelineCell<OrderedMapTimeline<Key, Value, Steward>>,
+      ) -> usize {
+        accessor.modify(handle, |timeline| {
+          timeline
+            .len_dependent_events
+            .insert(accessor.this_event().clone());
+        });
+        len(accessor, handle)
+      }
+
+      /// Sets the value of `key` from now on. `None` removes the key.
+      pub fn set<
+        Key: QueryResult + Ord,
+        Value: QueryResult,
+        Steward: TimeSteward,
+        Accessor: EventAccessor<Steward = Steward>,
+      >(
+        accessor: &Accessor,
+        handle: &DataTimelineCell<OrderedMapTimeline<Key, Value, Steward>>,
+        key: Key,
+        value: Option<Value>,
+      ) {
+        #[cfg(debug_assertions)]
+        let confirm = (key.clone(), value.clone());
+
+        if let Some(accessor) = accessor.future_cleanup() {
+          let mut guard = accessor.peek_mut(handle);
+          let was_present = guard.present_at(&key, accessor.extended_now());
+          let removed_any = guard.remove_future(accessor, &key, false);
+          guard.invalidate_aggregate_readers(
+            accessor,
+            &key,
+            removed_any || was_present != value.is_some(),
+          );
+        }
+        accessor.modify(handle, move |timeline| {
+          timeline
+            .changed_keys
+            .entry(accessor.this_event().clone())
+            .or_insert_with(BTreeSet::new)
+            .insert(key.clone());
+          timeline
+            .keys
+            .entry(key)
+            .or_insert_with(Default::default)
+            .modify(value, accessor);
+        });
+
+        #[cfg(debug_assertions)]
+        debug_assert!(get(accessor, handle, &confirm.0) == confirm.1);
+      }
+      pub fn insert<
+        Key: QueryResult + Ord,
+        Value: QueryResult,
+        Steward: TimeSteward,
+        Accessor: EventAccessor<Steward = Steward>,
+      >(
+        accessor: &Accessor,
+        handle: &DataTimelineCell<OrderedMapTimeline<Key, Value, Steward>>,
+        key: Key,
+        value: Value,
+      ) {
+        set(accessor, handle, key, Some(value))
+      }
+      pub fn remove<
+        Key: QueryResult + Ord,
+        Value: QueryResult,
+        Steward: TimeSteward,
+        Accessor: EventAccessor<Steward = Steward>,
+      >(
+        accessor: &Accessor,
+        handle: &DataTimelineCell<OrderedMapTimeline<Key, Value, Steward>>,
+        key: Key,
+      ) {
+        set(accessor, handle, key, None)
+      }
+      /// Undoes any change the current event made to `key`. Events that set or removed a key must call this for that key in `undo()`.
+      pub fn unset<
+        Key: QueryResult + Ord,
+        Value: QueryResult,
+        Steward: TimeSteward,
+        Accessor: FutureCleanupAccessor<Steward = Steward>,
+      >(
+        accessor: &Accessor,
+        handle: &DataTimelineCell<OrderedMapTimeline<Key, Value, Steward>>,
+        key: &Key,
+      ) {
+        let mut guard = accessor.peek_mut(handle);
+        if let Some(keys) = guard
+          .changed_keys
+          .get_mut::<<Steward as TimeSteward>::EventHandle>(accessor.this_event())
+        {
+          keys.remove(key);
+        }
+        let changed_now = guard.keys.get(key).map_or(false, |history| {
+          history.search_changes(accessor.extended_now()).is_ok()
+        });
+        if changed_now {
+          guard.remove_future(accessor, key, true);
+          guard.invalidate_aggregate_readers(accessor, key, true);
+        }
+      }
+      /// Undoes every change the current event made, for events that don't keep track of which keys they changed.
+      ///
+      /// The timeline records which keys each event set, so this only looks at those keys.
+      pub fn unset_all<
+        Key: QueryResult + Ord,
+        Value: QueryResult,
+        Steward: TimeSteward,
+        Accessor: FutureCleanupAccessor<Steward = Steward>,
+      >(
+        accessor: &Accessor,
+        handle: &DataTimelineCell<OrderedMapTimeline<Key, Value, Steward>>,
+      ) {
+        let mut guard = accessor.peek_mut(handle);
+        let changed = guard
+          .changed_keys
+          .remove::<<Steward as TimeSteward>::EventHandle>(accessor.this_event())
+          .unwrap_or_default();
+        for key in changed {
+          let changed_now = guard.keys.get(&key).map_or(false, |history| {
+            history.search_changes(accessor.extended_now()).is_ok()
+          });
+          if !changed_now {
+            continue;
+          }
+          guard.remove_future(accessor, &key, true);
+          guard.invalidate_aggregate_readers(accessor, &key, true);
+        }
+      }
+    } //mod
+  };
+}
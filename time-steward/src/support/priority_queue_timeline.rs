@@ -0,0 +1,340 @@
+#[doc(hidden)]
+#[macro_export]
+macro_rules! time_steward_define_priority_queue_timeline {
+  () => {
+    pub mod priority_queue_timeline {
+      use std::collections::BTreeMap;
+      use std::ops::Bound;
+
+      use super::super::super::api::*;
+      use super::*;
+      use crate::type_utils::{PersistentTypeId, PersistentlyIdentifiedType};
+
+      /// Query for the smallest item in the queue, or None if the queue is empty.
+      #[derive(Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize, Debug)]
+      pub struct Peek;
+      impl PersistentlyIdentifiedType for Peek {
+        const ID: PersistentTypeId = PersistentTypeId(0x9a0f61c3e8d2b574);
+      }
+
+      // An item, the event that pushed it, and how many items that event had pushed before it.
+      type Entry<Item, Steward> = (Item, <Steward as TimeSteward>::EventHandle, usize);
+
+      #[derive(Serialize, Deserialize, Derivative)]
+      #[serde(bound = "")]
+      #[derivative(Clone(bound = ""), Debug(bound = ""), Default(bound = ""))]
+      struct EventOperations<Item: QueryResult + Ord, Steward: TimeSteward> {
+        pushed: Vec<Item>,
+        #[serde(deserialize_with = "::serde::Deserialize::deserialize")]
+        popped: Vec<Entry<Item, Steward>>,
+      }
+
+      // What an event observed through its tracking peeks (including the peeks done by `pop()`).
+      #[derive(Serialize, Deserialize, Derivative)]
+      #[serde(bound = "")]
+      #[derivative(Clone(bound = ""), Debug(bound = ""), Default(bound = ""))]
+      struct PeekRecord<Item: QueryResult + Ord> {
+        largest: Option<Item>,
+        saw_empty: bool,
+      }
+
+      /// A DataTimeline holding a priority queue, where the smallest item comes out first.
+      ///
+      /// Items can be pushed and popped retroactively. Each push or pop only invalidates the later events (peeks and pops) whose observed smallest item could have changed, judging by the largest item each one saw, plus the later pop of any item that now leaves the queue earlier.
+      ///
+      /// Items aren't scanned for prediction handles, so they shouldn't contain any.
+      #[derive(Serialize, Deserialize, Derivative)]
+      #[serde(bound = "")]
+      #[derivative(Clone(bound = ""), Debug(bound = ""), Default(bound = ""))]
+      pub struct PriorityQueueTimeline<Item: QueryResult + Ord, Steward: TimeSteward> {
+        // Hacky workaround for https://github.com/rust-lang/rust/issues/41617 (see https://github.com/serde-rs/serde/issues/943)
+        // maps each entry to the event that popped it, if any
+        #[serde(deserialize_with = "::serde::Deserialize::deserialize")]
+        entries: BTreeMap<Entry<Item, Steward>, Option<<Steward as TimeSteward>::EventHandle>>,
+        #[serde(deserialize_with = "::serde::Deserialize::deserialize")]
+        operations: BTreeMap<<Steward as TimeSteward>::EventHandle, EventOperations<Item, Steward>>,
+        // Records aren't cleared when an event is re-executed for other reasons, which can only make them more conservative.
+        #[serde(deserialize_with = "::serde::Deserialize::deserialize")]
+        peek_dependent_events: BTreeMap<<Steward as TimeSteward>::EventHandle, PeekRecord<Item>>,
+      }
+
+      impl<Item: QueryResult + Ord, Steward: TimeSteward> PriorityQueueTimeline<Item, Steward> {
+        pub fn new() -> Self {
+          PriorityQueueTimeline {
+            entries: BTreeMap::new(),
+            operations: BTreeMap::new(),
+            peek_dependent_events: BTreeMap::new(),
+          }
+        }
+
+        /// The smallest entry in the queue at `time`.
+        ///
+        /// Takes time proportional to the number of smaller entries that are pushed later or popped earlier.
+        fn min_entry_at(&self, time: &ExtendedTime<Steward::Basics>) -> Option<&Entry<Item, Steward>> {
+          self
+            .entries
+            .iter()
+            .find(|(entry, popper)| {
+              entry.1.extended_time() <= time
+                && popper
+                  .as_ref()
+                  .map_or(true, |popper| popper.extended_time() > time)
+            })
+            .map(|(entry, _)| entry)
+        }
+
+        fn invalidate_peeker<Accessor: FutureCleanupAccessor<Steward = Steward>>(
+          &mut self,
+          accessor: &Accessor,
+          event: &<Steward as TimeSteward>::EventHandle,
+        ) {
+          self
+            .peek_dependent_events
+            .remove::<<Steward as TimeSteward>::EventHandle>(event);
+          accessor.invalidate_execution(event);
+        }
+
+        /// Invalidates the future events that would observe something different if an item with value `item` were in the queue from now on.
+        ///
+        /// This compares against what each event recorded seeing, not the queue at the event's time, which no longer includes the items the event popped itself.
+        fn invalidate_for_appearance<Accessor: FutureCleanupAccessor<Steward = Steward>>(
+          &mut self,
+          accessor: &Accessor,
+          item: &Item,
+        ) {
+          let invalidated: Vec<_> = self
+            .peek_dependent_events
+            .range::<ExtendedTime<Steward::Basics>, _>((
+              Bound::Excluded(accessor.extended_now()),
+              Bound::Unbounded,
+            ))
+            .filter(|(_, record)| {
+              record.saw_empty
+                || record
+                  .largest
+                  .as_ref()
+                  .map_or(false, |largest| item < largest)
+            })
+            .map(|(event, _)| event.clone())
+            .collect();
+          for event in invalidated {
+            self.invalidate_peeker(accessor, &event);
+          }
+        }
+
+        /// Invalidates the future events that would observe something different if `entry` left the queue now, including the event that popped it.
+        fn invalidate_for_disappearance<Accessor: FutureCleanupAccessor<Steward = Steward>>(
+          &mut self,
+          accessor: &Accessor,
+          entry: &Entry<Item, Steward>,
+        ) {
+          let popper = self.entries.get(entry).cloned().unwrap_or(None);
+          let end = match popper.as_ref() {
+            Some(popper) => Bound::Excluded(popper.extended_time()),
+            None => Bound::Unbounded,
+          };
+          let invalidated: Vec<_> = self
+            .peek_dependent_events
+            .range::<ExtendedTime<Steward::Basics>, _>((
+              Bound::Excluded(accessor.extended_now()),
+              end,
+            ))
+            // an event can only have seen the entry if it's no larger than the largest item the event saw
+            .filter(|(_, record)| {
+              record
+                .largest
+                .as_ref()
+                .map_or(false, |largest| &entry.0 <= largest)
+            })
+            .map(|(event, _)| event.clone())
+            .collect();
+          for event in invalidated {
+            self.invalidate_peeker(accessor, &event);
+          }
+          if let Some(popper) = popper {
+            if &popper > accessor.this_event() {
+              self.invalidate_peeker(accessor, &popper);
+            }
+          }
+        }
+
+        fn push<Accessor: EventAccessor<Steward = Steward>>(&mut self, item: Item, accessor: &Accessor) {
+          let operations = self
+            .operations
+            .entry(accessor.this_event().clone())
+            .or_insert_with(Default::default);
+          let entry = (item.clone(), accessor.this_event().clone(), operations.pushed.len());
+          operations.pushed.push(item);
+          self.entries.insert(entry, None);
+        }
+
+        fn pop<Accessor: EventAccessor<Steward = Steward>>(&mut self, accessor: &Accessor) {
+          if let Some(entry) = self.min_entry_at(accessor.extended_now()).cloned() {
+            self
+              .entries
+              .insert(entry.clone(), Some(accessor.this_event().clone()));
+            self
+              .operations
+              .entry(accessor.this_event().clone())
+              .or_insert_with(Default::default)
+              .popped
+              .push(entry);
+          }
+        }
+      }
+
+      impl<Item: QueryResult + Ord, Steward: TimeSteward> DataTimeline
+        for PriorityQueueTimeline<Item, Steward>
+      {
+        type Basics = Steward::Basics;
+
+        fn clone_for_snapshot(&self, time: &ExtendedTime<Self::Basics>) -> Self {
+          let entries = self
+            .entries
+            .iter()
+            .filter(|(entry, popper)| {
+              entry.1.extended_time() <= time
+                && popper
+                  .as_ref()
+                  .map_or(true, |popper| popper.extended_time() > time)
+            })
+            .map(|(entry, _)| (entry.clone(), None))
+            .collect();
+          PriorityQueueTimeline {
+            entries,
+            operations: BTreeMap::new(),
+            peek_dependent_events: BTreeMap::new(),
+          }
+        }
+
+        fn forget_before(&mut self, time: &ExtendedTime<Self::Basics>) {
+          self.operations = self.operations.split_off(time);
+          self.peek_dependent_events = self.peek_dependent_events.split_off(time);
+          self.entries.retain(|_, popper| {
+            popper
+              .as_ref()
+              .map_or(true, |popper| popper.extended_time() >= time)
+          });
+        }
+      }
+      impl<Item: QueryResult + Ord, Steward: TimeSteward> DataTimelineQueriableWith<Peek>
+        for PriorityQueueTimeline<Item, Steward>
+      {
+        type QueryResult = Option<Item>;
+
+        fn query(&self, _: &Peek, time: &ExtendedTime<Self::Basics>) -> Self::QueryResult {
+          self.min_entry_at(time).map(|entry| entry.0.clone())
+        }
+      }
+
+      pub fn peek<Item: QueryResult + Ord, Steward: TimeSteward, A: Accessor<Steward = Steward>>(
+        accessor: &A,
+        handle: &DataTimelineCell<PriorityQueueTimeline<Item, Steward>>,
+      ) -> Option<Item> {
+        accessor.query(handle, &Peek)
+      }
+      pub fn tracking_peek<
+        Item: QueryResult + Ord,
+        Steward: TimeSteward,
+        Accessor: EventAccessor<Steward = Steward>,
+      >(
+        accessor: &Accessor,
+        handle: &DataTimelineCell<PriorityQueueTimeline<Item, Steward>>,
+      ) -> Option<Item> {
+        let result = peek(accessor, handle);
+        accessor.modify(handle, |timeline| {
+          let record = timeline
+            .peek_dependent_events
+            .entry(accessor.this_event().clone())
+            .or_insert_with(Default::default);
+          match &result {
+            None => record.saw_empty = true,
+            Some(item) => {
+              if record
+                .largest
+                .as_ref()
+                .map_or(true, |largest| item > largest)
+              {
+                record.largest = Some(item.clone());
+              }
+            }
+          }
+        });
+        result
+      }
+
+      pub fn push<
+        Item: QueryResult + Ord,
+        Steward: TimeSteward,
+        Accessor: EventAccessor<Steward = Steward>,
+      >(
+        accessor: &Accessor,
+        handle: &DataTimelineCell<PriorityQueueTimeline<Item, Steward>>,
+        item: Item,
+      ) {
+        if let Some(accessor) = accessor.future_cleanup() {
+          accessor
+            .peek_mut(handle)
+            .invalidate_for_appearance(accessor, &item);
+        }
+        accessor.modify(handle, move |timeline| {
+          timeline.push(item, accessor);
+        });
+      }
+      /// Removes and returns the smallest item. The event is invalidated if a retroactive change would make it pop a different item.
+      pub fn pop<
+        Item: QueryResult + Ord,
+        Steward: TimeSteward,
+        Accessor: EventAccessor<Steward = Steward>,
+      >(
+        accessor: &Accessor,
+        handle: &DataTimelineCell<PriorityQueueTimeline<Item, Steward>>,
+      ) -> Option<Item> {
+        let result = tracking_peek(accessor, handle);
+        if result.is_some() {
+          if let Some(accessor) = accessor.future_cleanup() {
+            let mut guard = accessor.peek_mut(handle);
+            let entry = guard
+              .min_entry_at(accessor.extended_now())
+              .cloned()
+              .unwrap();
+            guard.invalidate_for_disappearance(accessor, &entry);
+          }
+          accessor.modify(handle, |timeline| {
+            timeline.pop(accessor);
+          });
+        }
+        result
+      }
+      /// Undoes all of the pushes and pops the current event did. Events that pushed or popped must call this in `undo()`.
+      pub fn unset<
+        Item: QueryResult + Ord,
+        Steward: TimeSteward,
+        Accessor: FutureCleanupAccessor<Steward = Steward>,
+      >(
+        accessor: &Accessor,
+        handle: &DataTimelineCell<PriorityQueueTimeline<Item, Steward>>,
+      ) {
+        let mut guard = accessor.peek_mut(handle);
+        let this_event = accessor.this_event();
+        if let Some(operations) = guard
+          .operations
+          .remove::<<Steward as TimeSteward>::EventHandle>(this_event)
+        {
+          for entry in operations.popped.into_iter().rev() {
+            // if a retroactive pop took this entry from us, it's not ours to restore
+            if guard.entries.get(&entry).and_then(Option::as_ref) == Some(this_event) {
+              guard.invalidate_for_appearance(accessor, &entry.0);
+              guard.entries.insert(entry, None);
+            }
+          }
+          for (index, item) in operations.pushed.into_iter().enumerate().rev() {
+            let entry = (item, this_event.clone(), index);
+            guard.invalidate_for_disappearance(accessor, &entry);
+            guard.entries.remove(&entry);
+          }
+        }
+      }
+    } //mod
+  };
+}
@@ -1,14 +1,28 @@
+use crate::QueryResult;
+
+/// A part of a SimpleTimeline's data that a reader depends on, for `tracking_query_projected()`.
+///
+/// Implementors are usually unit structs. The projection is an associated function rather than a closure, so it can't capture anything, and its type identifies it.
+pub trait Projection<VaryingData>: 'static {
+  type Projected: QueryResult;
+  /// Must be deterministic and depend only on `data`.
+  fn project(data: &VaryingData) -> Self::Projected;
+}
+
 #[doc(hidden)]
 #[macro_export]
 macro_rules! time_steward_define_simple_timeline {
   () => {
     pub mod simple_timeline {
-      use std::collections::{BTreeSet, VecDeque};
+      use std::any::TypeId;
+      use std::collections::{BTreeMap, BTreeSet, VecDeque};
       use std::mem;
+      use std::ops::Bound;
 
       use super::super::super::api::*;
       use super::*;
       use crate::implementation_support::common::split_off_greater_set;
+      pub use crate::support::simple_timeline::Projection;
       use crate::type_utils::{PersistentTypeId, PersistentlyIdentifiedType};
 
       #[derive(Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize, Debug)]
@@ -37,7 +51,7 @@ macro_rules! time_steward_define_simple_timeline {
           }
         }
       }
-      fn link_predictions<Accessor: EventAccessor, Data: SimulationStateData>(
+      pub(super) fn link_predictions<Accessor: EventAccessor, Data: SimulationStateData>(
         accessor: &Accessor,
         data: &Data,
         after: &<Accessor::Steward as TimeSteward>::EventHandle,
@@ -66,7 +80,7 @@ macro_rules! time_steward_define_simple_timeline {
           }
         }
       }
-      fn unlink_predictions<Accessor: EventAccessor, Data: SimulationStateData>(
+      pub(super) fn unlink_predictions<Accessor: EventAccessor, Data: SimulationStateData>(
         accessor: &Accessor,
         data: &Data,
         after: &<Accessor::Steward as TimeSteward>::EventHandle,
@@ -78,8 +92,54 @@ macro_rules! time_steward_define_simple_timeline {
         );
       }
 
-      #[serde(bound = "")]
+      /// Decides whether a change from the first value to the second one affects a projected dependency.
+      ///
+      /// The type of the projection identifies it; an event that records the same projection again (usually because it was re-executed) doesn't get a second comparator.
+      ///
+      /// Function pointers can't be serialized, so this is serialized as nothing, and deserialized as None, which counts every change. The worst that can happen after a reload is some unnecessary invalidation.
+      #[derive(Derivative)]
+      #[derivative(Clone(bound = ""), Default(bound = ""))]
+      struct ProjectionComparator<VaryingData> {
+        projection: Option<TypeId>,
+        changed: Option<fn(&VaryingData, &VaryingData) -> bool>,
+      }
+      fn projection_changed<VaryingData, P: Projection<VaryingData>>(
+        old: &VaryingData,
+        new: &VaryingData,
+      ) -> bool {
+        P::project(old) != P::project(new)
+      }
+      impl<VaryingData> ProjectionComparator<VaryingData> {
+        /// Whether recording `other` as well would be redundant.
+        fn covers(&self, other: &Self) -> bool {
+          self.projection.is_none() || self.projection == other.projection
+        }
+        fn changed(&self, old: &VaryingData, new: Option<&VaryingData>) -> bool {
+          match (self.changed.as_ref(), new) {
+            (Some(comparator), Some(new)) => comparator(old, new),
+            _ => true,
+          }
+        }
+      }
+      impl<VaryingData> Debug for ProjectionComparator<VaryingData> {
+        fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+          write!(f, "ProjectionComparator")
+        }
+      }
+      impl<VaryingData> ::serde::Serialize for ProjectionComparator<VaryingData> {
+        fn serialize<S: ::serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+          ::serde::Serialize::serialize(&(), serializer)
+        }
+      }
+      impl<'de, VaryingData> ::serde::Deserialize<'de> for ProjectionComparator<VaryingData> {
+        fn deserialize<D: ::serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+          <() as ::serde::Deserialize>::deserialize(deserializer)?;
+          Ok(ProjectionComparator::default())
+        }
+      }
+
       #[derive(Serialize, Deserialize, Derivative)]
+      #[serde(bound = "")]
       #[derivative(Clone(bound = ""), Debug(bound = ""), Default(bound = ""))]
       pub struct SimpleTimeline<VaryingData: QueryResult, Steward: TimeSteward> {
         // Hacky workaround for https://github.com/rust-lang/rust/issues/41617 (see https://github.com/serde-rs/serde/issues/943)
@@ -89,6 +149,9 @@ macro_rules! time_steward_define_simple_timeline {
         destroyer: Option<<Steward as TimeSteward>::EventHandle>,
         #[serde(deserialize_with = "::serde::Deserialize::deserialize")]
         other_dependent_events: BTreeSet<<Steward as TimeSteward>::EventHandle>,
+        #[serde(deserialize_with = "::serde::Deserialize::deserialize")]
+        projected_dependent_events:
+          BTreeMap<<Steward as TimeSteward>::EventHandle, Vec<ProjectionComparator<VaryingData>>>,
       }
 
       impl<VaryingData: QueryResult, Steward: TimeSteward> SimpleTimeline<VaryingData, Steward> {
@@ -97,6 +160,7 @@ macro_rules! time_steward_define_simple_timeline {
             changes: VecDeque::new(),
             destroyer: None,
             other_dependent_events: BTreeSet::new(),
+            projected_dependent_events: BTreeMap::new(),
           }
         }
 
@@ -144,22 +208,28 @@ macro_rules! time_steward_define_simple_timeline {
             }
           }
 
-          if self.changes.get(min).unwrap().0.extended_time() > time {
-            return Err(min);
+          // the loops above never compare the time at `min` when they stop at the front
+          match self.changes.get(min).unwrap().0.extended_time().cmp(time) {
+            Ordering::Greater => Err(min),
+            Ordering::Equal => Ok(min),
+            Ordering::Less => Err(max),
           }
-          Err(max)
         }
       }
       impl<VaryingData: QueryResult, Steward: TimeSteward> SimpleTimeline<VaryingData, Steward> {
+        /// Discards the changes after now (and, if `also_present`, the change now), and invalidates the events that made them or depended on them.
+        ///
+        /// `new_value` is what the data will be from now on, if it's known; projected dependencies whose projection of it is unchanged aren't invalidated.
         fn remove_future<Accessor: FutureCleanupAccessor<Steward = Steward>>(
           &mut self,
           accessor: &Accessor,
           also_present: bool,
+          new_value: Option<&VaryingData>,
         ) {
           let mut previous = self.destroyer.clone();
+          // a destruction by the current event is always discarded: either the event is being undone, or it's about to make its modifications again
           if self.destroyer.as_ref().map_or(false, |event| {
-            let ordering = event.extended_time().cmp(accessor.extended_now());
-            ordering == Ordering::Greater || (!also_present && ordering == Ordering::Equal)
+            event.extended_time() >= accessor.extended_now()
           }) {
             self.destroyer = None;
           }
@@ -168,6 +238,29 @@ macro_rules! time_steward_define_simple_timeline {
           for event in removed {
             accessor.invalidate_execution(&event);
           }
+          let invalidated: Vec<_> = self
+            .projected_dependent_events
+            .range::<ExtendedTime<Steward::Basics>, _>((
+              Bound::Excluded(accessor.extended_now()),
+              Bound::Unbounded,
+            ))
+            .filter(|(event, comparators)| {
+              let old_value = match self.search_changes(event.extended_time()) {
+                Ok(index) => &self.changes[index].1,
+                Err(index) => &self.changes[index - 1].1,
+              };
+              comparators
+                .iter()
+                .any(|comparator| comparator.changed(old_value, new_value))
+            })
+            .map(|(event, _)| event.clone())
+            .collect();
+          for event in invalidated {
+            self
+              .projected_dependent_events
+              .remove::<<Steward as TimeSteward>::EventHandle>(&event);
+            accessor.invalidate_execution(&event);
+          }
           while let Some(change) = self.changes.pop_back() {
             let ordering = change.0.extended_time().cmp(accessor.extended_now());
             if ordering == Ordering::Less || (!also_present && ordering == Ordering::Equal) {
@@ -189,6 +282,24 @@ macro_rules! time_steward_define_simple_timeline {
           }
         }
 
+        /// Whether the current event has already set exactly this data.
+        fn present_change_is<Accessor: EventAccessor<Steward = Steward>>(
+          &self,
+          accessor: &Accessor,
+          data: &VaryingData,
+        ) -> bool {
+          if self.destroyer.is_some() {
+            return false;
+          }
+          match self.search_changes(accessor.extended_now()) {
+            Ok(index) => {
+              let change = &self.changes[index];
+              &change.0 == accessor.this_event() && &change.1 == data
+            }
+            Err(_) => false,
+          }
+        }
+
         fn modify<Accessor: EventAccessor<Steward = Steward>>(
           &mut self,
           modification: VaryingData,
@@ -268,12 +379,14 @@ macro_rules! time_steward_define_simple_timeline {
             changes: changes,
             destroyer: self.destroyer.clone(),
             other_dependent_events: BTreeSet::new(),
+            projected_dependent_events: BTreeMap::new(),
           }
         }
 
         fn forget_before(&mut self, time: &ExtendedTime<Self::Basics>) {
           let retained = self.other_dependent_events.split_off(time);
           mem::replace(&mut self.other_dependent_events, retained);
+          self.projected_dependent_events = self.projected_dependent_events.split_off(time);
 
           while self
             .changes
@@ -405,6 +518,38 @@ macro_rules! time_steward_define_simple_timeline {
         });
         query_ref(accessor, handle)
       }
+      /// Like `tracking_query()`, but returns a projection of the data, and the current event is only invalidated by changes that change the projected value. For instance, a reader that only uses one field can ignore changes to the others.
+      ///
+      /// (If you already know that the current event will be invalidated whenever something it reads changes, you can use plain `query()` and not record a dependency at all.)
+      pub fn tracking_query_projected<
+        VaryingData: QueryResult,
+        Steward: TimeSteward,
+        Accessor: EventAccessor<Steward = Steward>,
+        P: Projection<VaryingData>,
+      >(
+        accessor: &Accessor,
+        handle: &DataTimelineCell<SimpleTimeline<VaryingData, Steward>>,
+        _: &P,
+      ) -> P::Projected {
+        let result = P::project(&query_ref(accessor, handle));
+        let comparator = ProjectionComparator {
+          projection: Some(TypeId::of::<P>()),
+          changed: Some(projection_changed::<VaryingData, P>),
+        };
+        accessor.modify(handle, move |timeline| {
+          let comparators = timeline
+            .projected_dependent_events
+            .entry(accessor.this_event().clone())
+            .or_insert_with(Vec::new);
+          if !comparators
+            .iter()
+            .any(|existing| existing.covers(&comparator))
+          {
+            comparators.push(comparator);
+          }
+        });
+        result
+      }
       pub fn set<
         VaryingData: QueryResult,
         Steward: TimeSteward,
@@ -419,24 +564,15 @@ macro_rules! time_steward_define_simple_timeline {
         #[cfg(debug_assertions)]
         let confirm2 = modification.clone();
 
-        let /*mut*/ do_modify = true;
+        let mut do_modify = true;
         if let Some(accessor) = accessor.future_cleanup() {
-          // currently, we can't do this culling because
-          // distinct predictions at the same time compare equal to each other, so
-          // updates that replace an older version of the prediction with a newer version
-          // would be incorrectly omitted (in fact, this occurred in bouncy_circles)
-          /*match accessor.query (handle, &GetVarying) {
-            Some((time, data)) =>
-              if let Some (new_data) = modification.as_ref() {
-                if &time == accessor.extended_now() && &data == new_data {
-                  do_modify = false;
-                }
-              },
-            None =>
-              if modification.is_none() {do_modify = false;}
-          };*/
-          if do_modify {
-            accessor.peek_mut(handle).remove_future(accessor, false);
+          // If this event already set the same data (for instance, because it was re-executed without being undone first), there's nothing to change, and nothing later needs to be invalidated.
+          // This relies on EventHandle equality being identity: a new prediction at the same time as an old one must not compare equal to it, or replacing the old one would be culled.
+          let mut guard = accessor.peek_mut(handle);
+          if guard.present_change_is(accessor, &modification) {
+            do_modify = false;
+          } else {
+            guard.remove_future(accessor, false, Some(&modification));
           }
         }
         if do_modify {
@@ -462,8 +598,21 @@ macro_rules! time_steward_define_simple_timeline {
         //let confirm = accessor.query (handle, &GetVarying, QueryOffset::Before);
 
         let mut guard = accessor.peek_mut(handle);
-        if guard.search_changes(accessor.extended_now()).is_ok() {
-          guard.remove_future(accessor, true);
+        // the current event may have destroyed the timeline rather than changing it
+        let destroyed_now = guard
+          .destroyer
+          .as_ref()
+          .map_or(false, |event| event == accessor.this_event());
+        // a stale event can have the same time as the one that replaced it, so only remove a change if the current event made it
+        let index = match guard.search_changes(accessor.extended_now()) {
+          Ok(index) => Some(index).filter(|&index| &guard.changes[index].0 == accessor.this_event()),
+          Err(index) => Some(index).filter(|_| destroyed_now),
+        };
+        if let Some(index) = index {
+          let previous = index
+            .checked_sub(1)
+            .map(|previous| guard.changes[previous].1.clone());
+          guard.remove_future(accessor, true, previous.as_ref());
         }
 
         //#[cfg (debug_assertions)]
@@ -481,7 +630,9 @@ macro_rules! time_steward_define_simple_timeline {
         handle: &DataTimelineCell<SimpleTimeline<VaryingData, Steward>>,
       ) {
         if let Some(accessor) = accessor.future_cleanup() {
-          accessor.peek_mut(handle).remove_future(accessor, false);
+          accessor
+            .peek_mut(handle)
+            .remove_future(accessor, false, None);
         }
         accessor.modify(handle, move |timeline| {
           timeline.destroy(accessor);
@@ -1,6 +1,6 @@
 //use nalgebra::Vector2;
 use super::integer_math::{Vector as GenericVector, *};
-use num::traits::{Signed};
+use num::traits::{CheckedAdd, Signed};
 use num::{One, Zero};
 use std::cmp::min;
 //use self::polynomial::RootSearchResult;
@@ -33,6 +33,24 @@ pub trait Trajectory: Sized {
   type Coefficient: Vector<Coordinate = Coordinate>;
 }
 
+/// Trajectories that are polynomials in time, which can be read and changed one Taylor coefficient at a time.
+///
+/// This lets code (such as `TrajectoryTimeline`) work with any degree of trajectory. All of the methods forward to the inherent methods of the same names; they return None on overflow.
+pub trait PolynomialTrajectory: Trajectory + Clone {
+  const DEGREE: usize;
+  fn nth_coefficient(&self, which: usize, time_numerator: Time, time_shift: u32) -> Option<Self::Coefficient>;
+  fn set_nth_coefficient(&mut self, which: usize, time_numerator: Time, time_shift: u32, target_value: Self::Coefficient) -> Option<()>;
+  fn add_nth_coefficient(&mut self, which: usize, time_numerator: Time, time_shift: u32, added_value: Self::Coefficient) -> Option<()>;
+}
+
+/// Trajectories whose distance from the origin can be searched, such as the difference between two objects' trajectories.
+///
+/// This lets code (such as contact prediction) work with any degree of trajectory that supports it. The methods forward to the inherent methods of the same names.
+pub trait MagnitudeTrajectory: PolynomialTrajectory + Sub<Output = Self> {
+  fn next_time_magnitude_significantly_gt(&self, range: [Time; 2], input_shift: u32, target: Coordinate) -> Option<Time>;
+  fn next_time_magnitude_significantly_lt(&self, range: [Time; 2], input_shift: u32, target: Coordinate) -> Option<Time>;
+}
+
 pub trait ScalarTrajectory: Trajectory
 where
   Self::Coefficient: Integer,
@@ -214,6 +232,19 @@ impl <T: Vector> Trajectory for $Trajectory <T> {
   type Coefficient = T;
 }
 
+impl <T: Vector> PolynomialTrajectory for $Trajectory <T> where Time: From <T::Coordinate>,  [T::Coordinate; $degree+1]: Polynomial<T::Coordinate> {
+  const DEGREE: usize = $degree;
+  fn nth_coefficient (&self, which: usize, time_numerator: Time, time_shift: u32)->Option<T> {$Trajectory::nth_coefficient (self, which, time_numerator, time_shift)}
+  fn set_nth_coefficient (&mut self, which: usize, time_numerator: Time, time_shift: u32, target_value: T)->Option<()> {$Trajectory::set_nth_coefficient (self, which, time_numerator, time_shift, target_value)}
+  fn add_nth_coefficient (&mut self, which: usize, time_numerator: Time, time_shift: u32, added_value: T)->Option<()> {$Trajectory::add_nth_coefficient (self, which, time_numerator, time_shift, added_value)}
+}
+
+#[cfg $multiplication]
+impl <T: Vector> MagnitudeTrajectory for $Trajectory <T> where Time: From <T::Coordinate>,  [T::Coordinate; $degree+1]: Polynomial<T::Coordinate>, for <'a> & 'a T::Coordinate: Neg <Output = T::Coordinate> {
+  fn next_time_magnitude_significantly_gt (&self, range: [Time; 2], input_shift: u32, target: Coordinate)->Option<Time> {$Trajectory::<T>::next_time_magnitude_significantly_gt (self, range, input_shift, target)}
+  fn next_time_magnitude_significantly_lt (&self, range: [Time; 2], input_shift: u32, target: Coordinate)->Option<Time> {$Trajectory::<T>::next_time_magnitude_significantly_lt (self, range, input_shift, target)}
+}
+
 impl <T: Vector> $Trajectory <T> where Time: From <T::Coordinate>,  [T::Coordinate; $degree+1]: Polynomial<T::Coordinate> {
   pub fn constant (value: T)->Self {
     let mut coefficients = [T::zero(); $degree + 1];
@@ -270,7 +301,11 @@ impl <T: Vector> $Trajectory <T> where Time: From <T::Coordinate>,  [T::Coordina
   }
   pub fn add_nth_coefficient (&mut self, which: usize, time_numerator: Time, time_shift: u32, added_value: T)->Option<()> {
     let current_value = self.nth_coefficient (which, time_numerator, time_shift)?;
-    self.set_nth_coefficient (which, time_numerator, time_shift, current_value + added_value)
+    let mut target_value = current_value;
+    for dimension in 0..T::DIMENSIONS {
+      target_value.set_coordinate (dimension, CheckedAdd::checked_add (&current_value.coordinate (dimension), &added_value.coordinate (dimension))?);
+    }
+    self.set_nth_coefficient (which, time_numerator, time_shift, target_value)
   }
   pub fn value (&self, time_numerator: Time, time_shift: u32)->Option<T> {self.nth_coefficient (0, time_numerator, time_shift)}
   pub fn velocity (&self, time_numerator: Time, time_shift: u32)->Option<T> {self.nth_coefficient (1, time_numerator, time_shift)}
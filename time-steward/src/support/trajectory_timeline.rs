@@ -0,0 +1,495 @@
+#[doc(hidden)]
+#[macro_export]
+macro_rules! time_steward_define_trajectory_timeline {
+  () => {
+    pub mod trajectory_timeline {
+      use std::collections::{BTreeSet, VecDeque};
+
+      use super::super::super::api::*;
+      use super::*;
+      use crate::implementation_support::common::split_off_greater_set;
+      pub use crate::support::integer_math::polynomial::OverflowError;
+      use crate::support::trajectories::{self, PolynomialTrajectory, Trajectory};
+      use crate::type_utils::{PersistentTypeId, PersistentlyIdentifiedType};
+
+      /// Query for the whole trajectory.
+      #[derive(Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize, Debug)]
+      pub struct GetTrajectory;
+      impl PersistentlyIdentifiedType for GetTrajectory {
+        const ID: PersistentTypeId = PersistentTypeId(0x5c2e9b07d4a18f63);
+      }
+
+      /// Query for the nth Taylor coefficient of the trajectory at the query time (0 is the position, 1 is the velocity, and so on), or None if it overflows or `n` is beyond the trajectory's degree.
+      #[derive(Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize, Debug)]
+      pub struct NthCoefficient(pub usize);
+      impl PersistentlyIdentifiedType for NthCoefficient {
+        const ID: PersistentTypeId = PersistentTypeId(0xa7f31d6e95c04b28);
+      }
+
+      /// Query for the time shift the timeline was created with. It never changes, so it doesn't need to be tracked.
+      #[derive(Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize, Debug)]
+      pub struct GetTimeShift;
+      impl PersistentlyIdentifiedType for GetTimeShift {
+        const ID: PersistentTypeId = PersistentTypeId(0x3e86d0b4f1297ac5);
+      }
+
+      /// A DataTimeline holding a trajectory, which can be queried for its position, velocity, or any other coefficient at the query time, rather than making every reader evaluate it at `accessor.now()`.
+      ///
+      /// Trajectory times are measured in steward time units shifted right by `time_shift`, like the `time_shift` arguments to the trajectory methods.
+      ///
+      /// Like a SimpleTimeline, each change replaces the trajectory from then on, so a retroactive change invalidates all of the later changes and tracked queries. Unlike a SimpleTimeline, trajectories never contain prediction handles, so they aren't scanned for them, and the timeline starts out with a trajectory, so it can be queried before anything sets it.
+      ///
+      /// Coefficient queries are an exception to the rule that query results are piecewise constant in time (see the notes in `api.rs`): the position of a moving trajectory changes continuously between modifications. That's still deterministic, since the result only depends on the trajectory and the query time, but an event that tracked a coefficient is only invalidated when the trajectory changes, not as time passes, so it must not assume the value stays the same after its own time.
+      #[derive(Serialize, Deserialize, Derivative)]
+      #[serde(bound = "")]
+      #[derivative(Clone(bound = ""), Debug(bound = ""), Default(bound = ""))]
+      pub struct TrajectoryTimeline<Traj: PolynomialTrajectory + QueryResult, Steward: TimeSteward> {
+        time_shift: u32,
+        // the trajectory before the first change, which is only missing from a timeline made by `Default`, such as a stub in a partial snapshot
+        initial: Option<Traj>,
+        // Hacky workaround for https://github.com/rust-lang/rust/issues/41617 (see https://github.com/serde-rs/serde/issues/943)
+        #[serde(deserialize_with = "::serde::Deserialize::deserialize")]
+        changes: VecDeque<(<Steward as TimeSteward>::EventHandle, Traj)>,
+        #[serde(deserialize_with = "::serde::Deserialize::deserialize")]
+        dependent_events: BTreeSet<<Steward as TimeSteward>::EventHandle>,
+      }
+
+      impl<Traj: PolynomialTrajectory + QueryResult, Steward: TimeSteward>
+        TrajectoryTimeline<Traj, Steward>
+      {
+        /// Makes a timeline whose trajectory is `initial` until something changes it.
+        pub fn new(time_shift: u32, initial: Traj) -> Self {
+          TrajectoryTimeline {
+            time_shift,
+            initial: Some(initial),
+            changes: VecDeque::new(),
+            dependent_events: BTreeSet::new(),
+          }
+        }
+
+        fn search_changes(&self, time: &ExtendedTime<Steward::Basics>) -> Result<usize, usize> {
+          self
+            .changes
+            .binary_search_by(|change| change.0.extended_time().cmp(time))
+        }
+
+        fn trajectory_at(&self, time: &ExtendedTime<Steward::Basics>) -> &Traj {
+          let previous_change_index = match self.search_changes(time) {
+            Ok(index) => index,
+            Err(0) => {
+              return self
+                .initial
+                .as_ref()
+                .expect("Tried to query a TrajectoryTimeline that was made by Default")
+            }
+            Err(index) => index - 1,
+          };
+          &self.changes[previous_change_index].1
+        }
+
+        /// Discards the changes after now (and, if `also_present`, the change now) and invalidates the events that made them or read them.
+        fn remove_future<Accessor: FutureCleanupAccessor<Steward = Steward>>(
+          &mut self,
+          accessor: &Accessor,
+          also_present: bool,
+        ) {
+          let removed = split_off_greater_set(&mut self.dependent_events, accessor.extended_now());
+          for event in removed {
+            accessor.invalidate_execution(&event);
+          }
+          while let Some(change) = self.changes.pop_back() {
+            if change.0.extended_time() < accessor.extended_now()
+              || (!also_present && &change.0 == accessor.this_event())
+            {
+              self.changes.push_back(change);
+              break;
+            }
+            // except don't re-invalidate the event we are currently in
+            if &change.0 != accessor.this_event() {
+              accessor.invalidate_execution(&change.0);
+            }
+          }
+        }
+
+        fn modify<Accessor: EventAccessor<Steward = Steward>>(
+          &mut self,
+          trajectory: Traj,
+          accessor: &Accessor,
+        ) {
+          if let Some(last) = self.changes.back() {
+            assert!(
+              &last.0 <= accessor.this_event(),
+              "All future changes should have been cleared before calling modify() "
+            );
+            if &last.0 == accessor.this_event() {
+              self.changes.pop_back();
+            }
+          }
+          self
+            .changes
+            .push_back((accessor.this_event().clone(), trajectory));
+        }
+      }
+
+      impl<Traj: PolynomialTrajectory + QueryResult, Steward: TimeSteward> DataTimeline
+        for TrajectoryTimeline<Traj, Steward>
+      {
+        type Basics = Steward::Basics;
+
+        fn clone_for_snapshot(&self, time: &ExtendedTime<Self::Basics>) -> Self {
+          let index = match self.search_changes(time) {
+            Ok(index) => Some(index),
+            Err(index) => index.checked_sub(1),
+          };
+          TrajectoryTimeline {
+            time_shift: self.time_shift,
+            initial: self.initial.clone(),
+            changes: index
+              .map(|index| self.changes[index].clone())
+              .into_iter()
+              .collect(),
+            dependent_events: BTreeSet::new(),
+          }
+        }
+
+        fn forget_before(&mut self, time: &ExtendedTime<Self::Basics>) {
+          self.dependent_events = self.dependent_events.split_off(time);
+
+          while self
+            .changes
+            .get(1)
+            .map_or(false, |change| change.0.extended_time() < time)
+          {
+            self.changes.pop_front();
+          }
+        }
+      }
+      impl<Traj: PolynomialTrajectory + QueryResult, Steward: TimeSteward>
+        DataTimelineQueriableWith<GetTrajectory> for TrajectoryTimeline<Traj, Steward>
+      {
+        type QueryResult = Traj;
+
+        fn query(&self, _: &GetTrajectory, time: &ExtendedTime<Self::Basics>) -> Self::QueryResult {
+          self.trajectory_at(time).clone()
+        }
+      }
+      impl<Traj: PolynomialTrajectory + QueryResult, Steward: TimeSteward>
+        DataTimelineQueryRefableWith<GetTrajectory> for TrajectoryTimeline<Traj, Steward>
+      {
+        fn query_ref(
+          &self,
+          _: &GetTrajectory,
+          time: &ExtendedTime<Self::Basics>,
+        ) -> &Self::QueryResult {
+          self.trajectory_at(time)
+        }
+      }
+      impl<Traj: PolynomialTrajectory + QueryResult, Steward: TimeSteward>
+        DataTimelineQueriableWith<GetTimeShift> for TrajectoryTimeline<Traj, Steward>
+      {
+        type QueryResult = u32;
+
+        fn query(&self, _: &GetTimeShift, _: &ExtendedTime<Self::Basics>) -> Self::QueryResult {
+          self.time_shift
+        }
+      }
+      impl<Traj: PolynomialTrajectory + QueryResult, Steward: TimeSteward>
+        DataTimelineQueriableWith<NthCoefficient> for TrajectoryTimeline<Traj, Steward>
+      where
+        Steward::Basics: Basics<Time = trajectories::Time>,
+        <Traj as Trajectory>::Coefficient: QueryResult,
+      {
+        type QueryResult = Option<<Traj as Trajectory>::Coefficient>;
+
+        fn query(
+          &self,
+          query: &NthCoefficient,
+          time: &ExtendedTime<Self::Basics>,
+        ) -> Self::QueryResult {
+          if query.0 > Traj::DEGREE {
+            return None;
+          }
+          self
+            .trajectory_at(time)
+            .nth_coefficient(query.0, time.base, self.time_shift)
+        }
+      }
+
+      pub fn trajectory<
+        Traj: PolynomialTrajectory + QueryResult,
+        Steward: TimeSteward,
+        A: Accessor<Steward = Steward>,
+      >(
+        accessor: &A,
+        handle: &DataTimelineCell<TrajectoryTimeline<Traj, Steward>>,
+      ) -> Traj {
+        accessor.query(handle, &GetTrajectory)
+      }
+      pub fn tracking_trajectory<
+        Traj: PolynomialTrajectory + QueryResult,
+        Steward: TimeSteward,
+        Accessor: EventAccessor<Steward = Steward>,
+      >(
+        accessor: &Accessor,
+        handle: &DataTimelineCell<TrajectoryTimeline<Traj, Steward>>,
+      ) -> Traj {
+        accessor.modify(handle, |timeline| {
+          timeline
+            .dependent_events
+            .insert(accessor.this_event().clone());
+        });
+        trajectory(accessor, handle)
+      }
+      pub fn nth_coefficient<
+        Traj: PolynomialTrajectory + QueryResult,
+        Steward: TimeSteward,
+        A: Accessor<Steward = Steward>,
+      >(
+        accessor: &A,
+        handle: &DataTimelineCell<TrajectoryTimeline<Traj, Steward>>,
+        which: usize,
+      ) -> Option<<Traj as Trajectory>::Coefficient>
+      where
+        Steward::Basics: Basics<Time = trajectories::Time>,
+        <Traj as Trajectory>::Coefficient: QueryResult,
+      {
+        accessor.query(handle, &NthCoefficient(which))
+      }
+      pub fn tracking_nth_coefficient<
+        Traj: PolynomialTrajectory + QueryResult,
+        Steward: TimeSteward,
+        Accessor: EventAccessor<Steward = Steward>,
+      >(
+        accessor: &Accessor,
+        handle: &DataTimelineCell<TrajectoryTimeline<Traj, Steward>>,
+        which: usize,
+      ) -> Option<<Traj as Trajectory>::Coefficient>
+      where
+        Steward::Basics: Basics<Time = trajectories::Time>,
+        <Traj as Trajectory>::Coefficient: QueryResult,
+      {
+        accessor.modify(handle, |timeline| {
+          timeline
+            .dependent_events
+            .insert(accessor.this_event().clone());
+        });
+        nth_coefficient(accessor, handle, which)
+      }
+      pub fn position<
+        Traj: PolynomialTrajectory + QueryResult,
+        Steward: TimeSteward,
+        A: Accessor<Steward = Steward>,
+      >(
+        accessor: &A,
+        handle: &DataTimelineCell<TrajectoryTimeline<Traj, Steward>>,
+      ) -> Option<<Traj as Trajectory>::Coefficient>
+      where
+        Steward::Basics: Basics<Time = trajectories::Time>,
+        <Traj as Trajectory>::Coefficient: QueryResult,
+      {
+        nth_coefficient(accessor, handle, 0)
+      }
+      pub fn tracking_position<
+        Traj: PolynomialTrajectory + QueryResult,
+        Steward: TimeSteward,
+        Accessor: EventAccessor<Steward = Steward>,
+      >(
+        accessor: &Accessor,
+        handle: &DataTimelineCell<TrajectoryTimeline<Traj, Steward>>,
+      ) -> Option<<Traj as Trajectory>::Coefficient>
+      where
+        Steward::Basics: Basics<Time = trajectories::Time>,
+        <Traj as Trajectory>::Coefficient: QueryResult,
+      {
+        tracking_nth_coefficient(accessor, handle, 0)
+      }
+      pub fn velocity<
+        Traj: PolynomialTrajectory + QueryResult,
+        Steward: TimeSteward,
+        A: Accessor<Steward = Steward>,
+      >(
+        accessor: &A,
+        handle: &DataTimelineCell<TrajectoryTimeline<Traj, Steward>>,
+      ) -> Option<<Traj as Trajectory>::Coefficient>
+      where
+        Steward::Basics: Basics<Time = trajectories::Time>,
+        <Traj as Trajectory>::Coefficient: QueryResult,
+      {
+        nth_coefficient(accessor, handle, 1)
+      }
+      pub fn tracking_velocity<
+        Traj: PolynomialTrajectory + QueryResult,
+        Steward: TimeSteward,
+        Accessor: EventAccessor<Steward = Steward>,
+      >(
+        accessor: &Accessor,
+        handle: &DataTimelineCell<TrajectoryTimeline<Traj, Steward>>,
+      ) -> Option<<Traj as Trajectory>::Coefficient>
+      where
+        Steward::Basics: Basics<Time = trajectories::Time>,
+        <Traj as Trajectory>::Coefficient: QueryResult,
+      {
+        tracking_nth_coefficient(accessor, handle, 1)
+      }
+
+      /// Replaces the trajectory from now on.
+      pub fn set_trajectory<
+        Traj: PolynomialTrajectory + QueryResult,
+        Steward: TimeSteward,
+        Accessor: EventAccessor<Steward = Steward>,
+      >(
+        accessor: &Accessor,
+        handle: &DataTimelineCell<TrajectoryTimeline<Traj, Steward>>,
+        trajectory: Traj,
+      ) {
+        if let Some(accessor) = accessor.future_cleanup() {
+          accessor.peek_mut(handle).remove_future(accessor, false);
+        }
+        accessor.modify(handle, move |timeline| {
+          timeline.modify(trajectory, accessor);
+        });
+      }
+      /// Changes the trajectory from now on, by calling `change` with the current trajectory, the current time numerator and the time shift. The trajectory methods re-base the origin to now by themselves.
+      ///
+      /// If `change` returns None, the timeline is left untouched and this returns an error.
+      pub fn modify_trajectory<
+        Traj: PolynomialTrajectory + QueryResult,
+        Steward: TimeSteward,
+        Accessor: EventAccessor<Steward = Steward>,
+        F: FnOnce(&mut Traj, trajectories::Time, u32) -> Option<()>,
+      >(
+        accessor: &Accessor,
+        handle: &DataTimelineCell<TrajectoryTimeline<Traj, Steward>>,
+        change: F,
+      ) -> Result<(), OverflowError>
+      where
+        Steward::Basics: Basics<Time = trajectories::Time>,
+      {
+        let mut changed = trajectory(accessor, handle);
+        let time_shift = accessor.query(handle, &GetTimeShift);
+        change(&mut changed, *accessor.now(), time_shift).ok_or(OverflowError)?;
+        set_trajectory(accessor, handle, changed);
+        Ok(())
+      }
+      pub fn set_nth_coefficient<
+        Traj: PolynomialTrajectory + QueryResult,
+        Steward: TimeSteward,
+        Accessor: EventAccessor<Steward = Steward>,
+      >(
+        accessor: &Accessor,
+        handle: &DataTimelineCell<TrajectoryTimeline<Traj, Steward>>,
+        which: usize,
+        value: <Traj as Trajectory>::Coefficient,
+      ) -> Result<(), OverflowError>
+      where
+        Steward::Basics: Basics<Time = trajectories::Time>,
+      {
+        assert!(
+          which <= Traj::DEGREE,
+          "Tried to set a coefficient beyond the degree of a TrajectoryTimeline"
+        );
+        modify_trajectory(accessor, handle, |trajectory, time, time_shift| {
+          trajectory.set_nth_coefficient(which, time, time_shift, value)
+        })
+      }
+      pub fn add_nth_coefficient<
+        Traj: PolynomialTrajectory + QueryResult,
+        Steward: TimeSteward,
+        Accessor: EventAccessor<Steward = Steward>,
+      >(
+        accessor: &Accessor,
+        handle: &DataTimelineCell<TrajectoryTimeline<Traj, Steward>>,
+        which: usize,
+        value: <Traj as Trajectory>::Coefficient,
+      ) -> Result<(), OverflowError>
+      where
+        Steward::Basics: Basics<Time = trajectories::Time>,
+      {
+        assert!(
+          which <= Traj::DEGREE,
+          "Tried to add to a coefficient beyond the degree of a TrajectoryTimeline"
+        );
+        modify_trajectory(accessor, handle, |trajectory, time, time_shift| {
+          trajectory.add_nth_coefficient(which, time, time_shift, value)
+        })
+      }
+      pub fn set_position<
+        Traj: PolynomialTrajectory + QueryResult,
+        Steward: TimeSteward,
+        Accessor: EventAccessor<Steward = Steward>,
+      >(
+        accessor: &Accessor,
+        handle: &DataTimelineCell<TrajectoryTimeline<Traj, Steward>>,
+        value: <Traj as Trajectory>::Coefficient,
+      ) -> Result<(), OverflowError>
+      where
+        Steward::Basics: Basics<Time = trajectories::Time>,
+      {
+        set_nth_coefficient(accessor, handle, 0, value)
+      }
+      pub fn set_velocity<
+        Traj: PolynomialTrajectory + QueryResult,
+        Steward: TimeSteward,
+        Accessor: EventAccessor<Steward = Steward>,
+      >(
+        accessor: &Accessor,
+        handle: &DataTimelineCell<TrajectoryTimeline<Traj, Steward>>,
+        value: <Traj as Trajectory>::Coefficient,
+      ) -> Result<(), OverflowError>
+      where
+        Steward::Basics: Basics<Time = trajectories::Time>,
+      {
+        set_nth_coefficient(accessor, handle, 1, value)
+      }
+      pub fn add_velocity<
+        Traj: PolynomialTrajectory + QueryResult,
+        Steward: TimeSteward,
+        Accessor: EventAccessor<Steward = Steward>,
+      >(
+        accessor: &Accessor,
+        handle: &DataTimelineCell<TrajectoryTimeline<Traj, Steward>>,
+        value: <Traj as Trajectory>::Coefficient,
+      ) -> Result<(), OverflowError>
+      where
+        Steward::Basics: Basics<Time = trajectories::Time>,
+      {
+        add_nth_coefficient(accessor, handle, 1, value)
+      }
+      /// Adds to the acceleration, which is twice the second Taylor coefficient.
+      pub fn add_acceleration<
+        Traj: PolynomialTrajectory + QueryResult,
+        Steward: TimeSteward,
+        Accessor: EventAccessor<Steward = Steward>,
+      >(
+        accessor: &Accessor,
+        handle: &DataTimelineCell<TrajectoryTimeline<Traj, Steward>>,
+        value: <Traj as Trajectory>::Coefficient,
+      ) -> Result<(), OverflowError>
+      where
+        Steward::Basics: Basics<Time = trajectories::Time>,
+      {
+        use crate::support::integer_math::{shr_round_to_even, Vector};
+        add_nth_coefficient(
+          accessor,
+          handle,
+          2,
+          value.map_coordinates(|coordinate| shr_round_to_even(coordinate, 1u32)),
+        )
+      }
+      /// Undoes the change the current event made, if any. Events that changed a trajectory must call this in `undo()`.
+      pub fn unset<
+        Traj: PolynomialTrajectory + QueryResult,
+        Steward: TimeSteward,
+        Accessor: FutureCleanupAccessor<Steward = Steward>,
+      >(
+        accessor: &Accessor,
+        handle: &DataTimelineCell<TrajectoryTimeline<Traj, Steward>>,
+      ) {
+        let mut guard = accessor.peek_mut(handle);
+        if guard.search_changes(accessor.extended_now()).is_ok() {
+          guard.remove_future(accessor, true);
+        }
+      }
+    } //mod
+  };
+}
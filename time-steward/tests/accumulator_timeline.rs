@@ -0,0 +1,130 @@
+extern crate time_steward;
+
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+
+use std::cell::Cell;
+
+use crate::accumulator_timeline::{add, total, tracking_at_least, Accumulator};
+use crate::steward_module::{
+  accumulator_timeline, simple_timeline, Accessor, ConstructibleTimeSteward, DataTimelineCell,
+  Event, EventAccessor, FutureCleanupAccessor, TimeSteward,
+};
+use time_steward::stewards::simple_full as steward_module;
+use time_steward::type_utils::list_of_types::ListedType;
+use time_steward::DeterministicRandomId;
+use time_steward::{Basics as BasicsTrait, DataTimelineCellTrait, PersistentlyIdentifiedType};
+
+type Time = i64;
+type Steward = steward_module::Steward<Basics>;
+type Mined = DataTimelineCell<Accumulator<i64, Steward>>;
+type Goal = DataTimelineCell<simple_timeline::SimpleTimeline<bool, Steward>>;
+
+#[derive(
+  Copy, Clone, Hash, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, Debug, Default,
+)]
+struct Basics {}
+impl BasicsTrait for Basics {
+  type Time = Time;
+  type Globals = (Mined, Goal);
+  type Types = (ListedType<Mine>, ListedType<CheckGoal>);
+}
+
+thread_local! {
+  static CHECK_EXECUTIONS: Cell<usize> = Cell::new(0);
+}
+
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize, Debug, PersistentlyIdentifiedType)]
+#[persistent_id(0x0b7d3e92f4a1c658)]
+struct Mine {
+  amount: i64,
+}
+#[time_steward::event]
+impl Event for Mine {
+  type Steward = Steward;
+  fn execute<Accessor: EventAccessor<Steward = Self::Steward>>(&self, accessor: &mut Accessor) {
+    add(accessor, &accessor.globals().0, self.amount);
+  }
+  fn undo<Accessor: FutureCleanupAccessor<Steward = Self::Steward>>(
+    &self,
+    accessor: &mut Accessor,
+    _: (),
+  ) {
+    accumulator_timeline::unset(accessor, &accessor.globals().0);
+  }
+}
+
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize, Debug, PersistentlyIdentifiedType)]
+#[persistent_id(0xc25f8a0e61d93b47)]
+struct CheckGoal {}
+#[time_steward::event]
+impl Event for CheckGoal {
+  type Steward = Steward;
+  fn execute<Accessor: EventAccessor<Steward = Self::Steward>>(&self, accessor: &mut Accessor) {
+    CHECK_EXECUTIONS.with(|count| count.set(count.get() + 1));
+    let reached = tracking_at_least(accessor, &accessor.globals().0, 100);
+    simple_timeline::set(accessor, &accessor.globals().1, reached);
+  }
+  fn undo<Accessor: FutureCleanupAccessor<Steward = Self::Steward>>(
+    &self,
+    accessor: &mut Accessor,
+    _: (),
+  ) {
+    simple_timeline::unset(accessor, &accessor.globals().1);
+  }
+}
+
+fn make_globals() -> <Basics as BasicsTrait>::Globals {
+  (
+    DataTimelineCell::new(Accumulator::new(0)),
+    DataTimelineCell::new(simple_timeline::SimpleTimeline::new()),
+  )
+}
+
+fn id(time: Time) -> DeterministicRandomId {
+  DeterministicRandomId::new(&(time, 0x51d3))
+}
+
+fn observe(stew: &mut Steward) -> (i64, bool, usize) {
+  let snapshot = stew.snapshot_before(&1000).unwrap();
+  (
+    total(&snapshot, &snapshot.globals().0),
+    simple_timeline::query(&snapshot, &snapshot.globals().1),
+    CHECK_EXECUTIONS.with(|count| count.get()),
+  )
+}
+
+#[test]
+fn accumulator_threshold_invalidation() {
+  let mut stew: Steward = Steward::from_globals(make_globals()).unwrap();
+  stew
+    .insert_fiat_event(10, id(10), Mine { amount: 60 })
+    .unwrap();
+  stew.insert_fiat_event(500, id(500), CheckGoal {}).unwrap();
+  stew
+    .insert_fiat_event(600, id(600), Mine { amount: 1000 })
+    .unwrap();
+  assert_eq!(observe(&mut stew), (1060, false, 1));
+
+  // doesn't cross the threshold, so the check stays valid
+  stew
+    .insert_fiat_event(20, id(20), Mine { amount: 30 })
+    .unwrap();
+  assert_eq!(observe(&mut stew), (1090, false, 1));
+
+  // crosses it
+  stew
+    .insert_fiat_event(30, id(30), Mine { amount: 10 })
+    .unwrap();
+  assert_eq!(observe(&mut stew), (1100, true, 2));
+
+  // deltas after the check don't matter to it
+  stew
+    .insert_fiat_event(700, id(700), Mine { amount: -5000 })
+    .unwrap();
+  assert_eq!(observe(&mut stew), (-3900, true, 2));
+
+  stew.remove_fiat_event(&20, id(20)).unwrap();
+  assert_eq!(observe(&mut stew), (-3930, false, 3));
+}
@@ -0,0 +1,286 @@
+extern crate time_steward;
+
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+
+#[macro_use]
+mod common;
+
+use crate::collisions::{BoundingBox, Coordinate, Detector, Fraction, Segment};
+use crate::simple_timeline::{query, set, SimpleTimeline};
+use crate::steward_module::{
+  bbox_collision_detection as collisions, simple_timeline, Accessor, ConstructibleTimeSteward,
+  DataHandle, DataTimelineCell, Event, EventAccessor, FutureCleanupAccessor, TimeSteward,
+};
+use time_steward::stewards::simple_full as steward_module;
+use time_steward::type_utils::list_of_types::ListedType;
+use time_steward::DeterministicRandomId;
+use time_steward::{
+  Basics as BasicsTrait, DataHandleTrait, DataTimelineCellTrait, Listable,
+  PersistentlyIdentifiedType,
+};
+
+const BODIES: usize = 40;
+const SCENE_SIZE: Coordinate = 12_000;
+
+fn random_coordinates(seed: u64, salt: u64) -> [Coordinate; 4] {
+  let random = DeterministicRandomId::new(&(seed, salt)).data().clone();
+  [
+    random[0] % SCENE_SIZE,
+    (random[0] >> 32) % SCENE_SIZE,
+    random[1] % SCENE_SIZE,
+    (random[1] >> 32) % SCENE_SIZE,
+  ]
+}
+/// A scene of boxes scattered at random.
+fn body_bounds(index: usize) -> [[Coordinate; 2]; 2] {
+  let [x, y, width, height] = random_coordinates(index as u64, 0xb0d1);
+  // one body much bigger than the others
+  let scale = if index == 0 { 8 } else { 40 };
+  [[x, x + width / scale], [y, y + height / scale]]
+}
+/// Random segments and rays through the scene, some of them parallel to an axis.
+fn random_segment(index: u64) -> Segment<2> {
+  let [x0, y0, x1, y1] = random_coordinates(index, 0x5e9);
+  match index % 5 {
+    0 => {
+      let direction = [(x1 % 101) as i64 - 50, (y1 % 101) as i64 - 50];
+      Segment::ray(
+        [x0, y0],
+        if direction == [0, 0] {
+          [1, 0]
+        } else {
+          direction
+        },
+      )
+    }
+    1 => Segment::new([x0, y0], [x1, y0]),
+    _ => Segment::new([x0, y0], [x1, y1]),
+  }
+}
+
+/// Every body that `segment` touches, in order of distance and then index.
+fn brute_force(segment: &Segment<2>) -> Vec<(Fraction, usize)> {
+  let mut result: Vec<(Fraction, usize)> = (0..BODIES)
+    .filter_map(|index| {
+      segment
+        .box_entry(&BoundingBox::new(body_bounds(index)))
+        .map(|entry| (entry, index))
+    })
+    .collect();
+  result.sort();
+  result
+}
+
+/// Every body other than `exclude`, in order of distance from `location` and then index.
+fn by_distance(location: [Coordinate; 2], exclude: Option<usize>) -> Vec<(u128, usize)> {
+  let mut result: Vec<(u128, usize)> = (0..BODIES)
+    .filter(|index| Some(*index) != exclude)
+    .map(|index| {
+      (
+        BoundingBox::new(body_bounds(index)).distance_squared(location),
+        index,
+      )
+    })
+    .collect();
+  result.sort();
+  result
+}
+
+macro_rules! detector_query_tests {
+  (
+    $test: ident,
+    $detector_module: ident :: $Detector: ident,
+    |$accessor: ident| $new_detector: expr
+  ) => {
+    mod $test {
+      use super::*;
+      use crate::collisions::$detector_module::{self, $Detector};
+
+      static_bodies_fixture! {
+        dimensions: 2,
+        detector: $detector_module::$Detector,
+        new_detector: |$accessor| $new_detector,
+        world_coordinate: u64,
+        wrap_extents: [None; 2],
+        bounding_box: |index| BoundingBox::new(body_bounds(index)),
+      }
+      impl collisions::SegmentQueryableSpace<2> for Space {
+        fn segment_entry<A: Accessor<Steward = Self::Steward>>(
+          &self,
+          _accessor: &A,
+          object: &BodyHandle,
+          segment: &Segment<2>,
+        ) -> Option<Fraction> {
+          segment.box_entry(&bounding_box(object.index))
+        }
+      }
+      impl collisions::DistanceQueryableSpace<2> for Space {
+        fn distance_squared<A: Accessor<Steward = Self::Steward>>(
+          &self,
+          _accessor: &A,
+          object: &BodyHandle,
+          location: [Coordinate; 2],
+        ) -> u128 {
+          bounding_box(object.index).distance_squared(location)
+        }
+      }
+
+      #[test]
+      fn segment_queries_match_brute_force() {
+        let mut stew = make_steward();
+        let snapshot = stew.snapshot_before(&1).unwrap();
+        let detector = query(&snapshot, &snapshot.globals().detector).unwrap();
+
+        for index in 0..300 {
+          let segment = random_segment(index);
+          let expected = brute_force(&segment);
+          let mut found: Vec<(Fraction, usize)> =
+            $Detector::objects_on_segment(&snapshot, &detector, &segment)
+              .into_iter()
+              .map(|(entry, body)| (entry, body.index))
+              .collect();
+          assert!(found.windows(2).all(|pair| pair[0].0 <= pair[1].0));
+          found.sort();
+          assert_eq!(found, expected, "{:?}", segment);
+          assert_eq!(
+            $Detector::first_object_on_segment(&snapshot, &detector, &segment)
+              .map(|(entry, _)| entry),
+            expected.first().map(|(entry, _)| *entry)
+          );
+        }
+      }
+
+      #[test]
+      fn nearest_queries_match_brute_force() {
+        let mut stew = make_steward();
+        let snapshot = stew.snapshot_before(&1).unwrap();
+        let detector = query(&snapshot, &snapshot.globals().detector).unwrap();
+        let bodies = &snapshot.globals().bodies;
+
+        for index in 0..100 {
+          let [x, y, radius, k] = random_coordinates(index, 0x4ea7);
+          // sometimes search far outside the scene, and sometimes around a body
+          let location = if index % 7 == 0 {
+            [x * 1000, Coordinate::max_value() - y]
+          } else {
+            [x, y]
+          };
+          let radius = radius / 4;
+          let k = (k % 8) as usize;
+          let exclude = if index % 3 == 0 {
+            Some(index as usize % BODIES)
+          } else {
+            None
+          };
+          let exclude_handle = exclude.map(|index| &bodies[index]);
+          let expected = by_distance(location, exclude);
+          let indices = |found: Vec<(u128, BodyHandle)>| -> Vec<(u128, usize)> {
+            found
+              .into_iter()
+              .map(|(distance, body)| (distance, body.index))
+              .collect()
+          };
+
+          assert_eq!(
+            indices($Detector::nearest_objects(
+              &snapshot,
+              &detector,
+              location,
+              k,
+              exclude_handle
+            )),
+            expected.iter().cloned().take(k).collect::<Vec<_>>()
+          );
+          assert_eq!(
+            indices($Detector::objects_within_radius(
+              &snapshot,
+              &detector,
+              location,
+              radius,
+              exclude_handle
+            )),
+            expected
+              .iter()
+              .cloned()
+              .filter(|(distance, _)| *distance <= radius as u128 * radius as u128)
+              .collect::<Vec<_>>()
+          );
+        }
+      }
+
+      #[test]
+      fn inspection_matches_contents() {
+        let mut stew = make_steward();
+        let snapshot = stew.snapshot_before(&1).unwrap();
+        let detector = query(&snapshot, &snapshot.globals().detector).unwrap();
+        let inspection = $Detector::inspect(&snapshot, &detector);
+
+        assert_eq!(
+          inspection
+            .objects
+            .iter()
+            .map(|object| object.id)
+            .collect::<Vec<_>>(),
+          (0..BODIES).collect::<Vec<_>>()
+        );
+        assert!(inspection.pending_escapes().is_empty());
+        assert_eq!(
+          inspection
+            .regions
+            .iter()
+            .map(|region| region.objects)
+            .sum::<usize>(),
+          inspection
+            .objects
+            .iter()
+            .map(|object| object.regions)
+            .sum::<usize>()
+        );
+        if !inspection.regions.is_empty() {
+          assert!(inspection.objects.iter().all(|object| object.regions >= 1));
+          let largest = inspection.largest_regions(3);
+          assert_eq!(
+            largest[0].objects,
+            inspection
+              .regions
+              .iter()
+              .map(|region| region.objects)
+              .max()
+              .unwrap()
+          );
+          assert!(largest
+            .windows(2)
+            .all(|pair| pair[0].objects >= pair[1].objects));
+        }
+        assert_eq!(inspection.most_spread_objects(BODIES + 1).len(), BODIES);
+
+        let regions_csv = inspection.regions_csv();
+        assert_eq!(regions_csv.lines().count(), inspection.regions.len() + 1);
+        assert_eq!(
+          regions_csv.lines().next(),
+          Some("objects,lower_0,upper_0,lower_1,upper_1")
+        );
+        let objects_csv = inspection.objects_csv();
+        assert_eq!(objects_csv.lines().count(), BODIES + 1);
+        assert_eq!(
+          objects_csv.lines().nth(1),
+          Some(format!("0,{},", inspection.objects[0].regions).as_str())
+        );
+      }
+    }
+  };
+}
+
+detector_query_tests!(grid, simple_grid::SimpleGridDetector, |accessor| {
+  SimpleGridDetector::new(accessor, Space, 500)
+});
+detector_query_tests!(tree, tree::TreeDetector, |accessor| TreeDetector::new(
+  accessor, Space
+));
+detector_query_tests!(
+  sweep_and_prune,
+  sweep_and_prune::SweepAndPruneDetector,
+  |accessor| SweepAndPruneDetector::new(accessor, Space, 0)
+);
@@ -0,0 +1,174 @@
+extern crate time_steward;
+
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+
+#[macro_use]
+mod common;
+
+use crate::collisions::{BoundingBox, Coordinate, Detector};
+use crate::simple_timeline::{query, set, SimpleTimeline};
+use crate::steward_module::{
+  bbox_collision_detection as collisions, simple_timeline, Accessor, ConstructibleTimeSteward,
+  DataHandle, DataTimelineCell, Event, EventAccessor, FutureCleanupAccessor, TimeSteward,
+};
+use std::array;
+use time_steward::stewards::simple_full as steward_module;
+use time_steward::type_utils::list_of_types::ListedType;
+use time_steward::DeterministicRandomId;
+use time_steward::{
+  Basics as BasicsTrait, DataHandleTrait, DataTimelineCellTrait, Listable,
+  PersistentlyIdentifiedType,
+};
+
+const BODIES: usize = 40;
+const SCENE_SIZE: Coordinate = 12_000;
+
+fn random_coordinate(seed: u64, dimension: usize, salt: u64) -> Coordinate {
+  DeterministicRandomId::new(&(seed, dimension as u64, salt)).data()[0]
+}
+/// A scene of boxes scattered at random, in any number of dimensions.
+fn random_bounds<const D: usize>(
+  seed: u64,
+  salt: u64,
+  max_size: Coordinate,
+) -> [[Coordinate; 2]; D] {
+  array::from_fn(|dimension| {
+    let random = random_coordinate(seed, dimension, salt);
+    let start = random % SCENE_SIZE;
+    [start, start + (random >> 32) % max_size]
+  })
+}
+fn body_bounds<const D: usize>(index: usize) -> [[Coordinate; 2]; D] {
+  // one body much bigger than the others
+  random_bounds(index as u64, 0xb0d1, if index == 0 { 6_000 } else { 600 })
+}
+fn overlap<const D: usize>(a: &BoundingBox<D>, b: &BoundingBox<D>) -> bool {
+  (0..D).all(|dimension| {
+    a.bounds[dimension][0] <= b.bounds[dimension][1]
+      && b.bounds[dimension][0] <= a.bounds[dimension][1]
+  })
+}
+
+macro_rules! dimension_tests {
+  (
+    $test: ident,
+    $dimensions: literal,
+    $detector_module: ident :: $Detector: ident,
+    |$accessor: ident| $new_detector: expr
+  ) => {
+    mod $test {
+      use super::*;
+      use crate::collisions::$detector_module::{self, $Detector};
+
+      static_bodies_fixture! {
+        dimensions: $dimensions,
+        detector: $detector_module::$Detector,
+        new_detector: |$accessor| $new_detector,
+        world_coordinate: u64,
+        wrap_extents: [None; $dimensions],
+        bounding_box: |index| BoundingBox::new(body_bounds(index)),
+      }
+      impl collisions::DistanceQueryableSpace<$dimensions> for Space {
+        fn distance_squared<A: Accessor<Steward = Self::Steward>>(
+          &self,
+          _accessor: &A,
+          object: &BodyHandle,
+          location: [Coordinate; $dimensions],
+        ) -> u128 {
+          bounding_box(object.index).distance_squared(location)
+        }
+      }
+
+      #[test]
+      fn neighbors_match_brute_force() {
+        let mut stew = make_steward();
+        let snapshot = stew.snapshot_before(&1).unwrap();
+        let detector = query(&snapshot, &snapshot.globals().detector).unwrap();
+        let bodies = &snapshot.globals().bodies;
+
+        for (index, body) in bodies.iter().enumerate() {
+          let bounds = BoundingBox::<$dimensions>::new(body_bounds(index));
+          let near_object: Vec<usize> = $Detector::objects_near_object(&snapshot, &detector, body)
+            .into_iter()
+            .map(|neighbor| neighbor.index)
+            .collect();
+          for other in 0..BODIES {
+            if other != index && overlap(&bounds, &BoundingBox::new(body_bounds(other))) {
+              assert!(near_object.contains(&other), "{} {}", index, other);
+            }
+          }
+        }
+
+        for seed in 0..100 {
+          let bounds = BoundingBox::<$dimensions>::new(random_bounds(seed, 0xb0c5, 3_000));
+          let near_box: Vec<usize> =
+            $Detector::objects_near_box(&snapshot, &detector, bounds.clone(), None)
+              .into_iter()
+              .map(|neighbor| neighbor.index)
+              .collect();
+          for other in 0..BODIES {
+            if overlap(&bounds, &BoundingBox::new(body_bounds(other))) {
+              assert!(near_box.contains(&other), "{:?} {}", bounds, other);
+            }
+          }
+        }
+      }
+
+      #[test]
+      fn nearest_queries_match_brute_force() {
+        let mut stew = make_steward();
+        let snapshot = stew.snapshot_before(&1).unwrap();
+        let detector = query(&snapshot, &snapshot.globals().detector).unwrap();
+
+        for seed in 0..100 {
+          let location: [Coordinate; $dimensions] =
+            array::from_fn(|dimension| random_coordinate(seed, dimension, 0x4ea7) % SCENE_SIZE);
+          let k = seed as usize % 8;
+          let mut expected: Vec<(u128, usize)> = (0..BODIES)
+            .map(|index| {
+              (
+                BoundingBox::new(body_bounds(index)).distance_squared(location),
+                index,
+              )
+            })
+            .collect();
+          expected.sort();
+          expected.truncate(k);
+          let found: Vec<(u128, usize)> =
+            $Detector::nearest_objects(&snapshot, &detector, location, k, None)
+              .into_iter()
+              .map(|(distance, body)| (distance, body.index))
+              .collect();
+          assert_eq!(found, expected, "{:?}", location);
+        }
+      }
+    }
+  };
+}
+
+dimension_tests!(grid_1d, 1, simple_grid::SimpleGridDetector, |accessor| {
+  SimpleGridDetector::new(accessor, Space, 700)
+});
+dimension_tests!(grid_4d, 4, simple_grid::SimpleGridDetector, |accessor| {
+  SimpleGridDetector::new(accessor, Space, 2_000)
+});
+dimension_tests!(
+  tree_1d,
+  1,
+  tree::TreeDetector,
+  |accessor| TreeDetector::new(accessor, Space)
+);
+dimension_tests!(
+  tree_4d,
+  4,
+  tree::TreeDetector,
+  |accessor| TreeDetector::new(accessor, Space)
+);
+dimension_tests!(
+  sweep_and_prune_4d,
+  4,
+  sweep_and_prune::SweepAndPruneDetector,
+  |accessor| SweepAndPruneDetector::new(accessor, Space, 0)
+);
@@ -0,0 +1,90 @@
+extern crate time_steward;
+
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+
+#[macro_use]
+mod common;
+
+use crate::collisions::sweep_and_prune::{self, SweepAndPruneDetector};
+use crate::collisions::{BoundingBox, Coordinate, Detector};
+use crate::simple_timeline::{query, set, tracking_query, unset, SimpleTimeline};
+use crate::steward_module::{
+  bbox_collision_detection as collisions, simple_timeline, Accessor, ConstructibleTimeSteward,
+  DataHandle, DataTimelineCell, Event, EventAccessor, FutureCleanupAccessor, TimeSteward,
+};
+use time_steward::stewards::simple_full as steward_module;
+use time_steward::type_utils::list_of_types::ListedType;
+use time_steward::DeterministicRandomId;
+use time_steward::{
+  Basics as BasicsTrait, DataHandleTrait, DataTimelineCellTrait, Listable,
+  PersistentlyIdentifiedType,
+};
+
+const BODIES: usize = 6;
+const MARGIN: Coordinate = 40;
+
+moving_bodies_fixture! {
+  detector: SweepAndPruneDetector<Space, 2>,
+  detector_data: sweep_and_prune::DetectorDataPerObject<Space, 2>,
+  new_detector: |accessor| SweepAndPruneDetector::new(accessor, Space, MARGIN),
+  types: (sweep_and_prune::Types<Space, 2>, ListedType<Place>),
+  state: {},
+}
+moving_bodies_retroactive_test!(sweep_and_prune_detector_retroactive_changes);
+
+/// Long, thin bodies lying along either axis, so that most of them overlap on one axis but not the other.
+fn place(time: Time, salt: u64) -> Place {
+  let random = DeterministicRandomId::new(&(time, salt)).data()[0];
+  let lengths = [1, 30, 900, 20_000];
+  let length = lengths[((random >> 48) % lengths.len() as u64) as usize];
+  let half_size = if (random >> 56) % 2 == 0 {
+    [length, 3]
+  } else {
+    [3, length]
+  };
+  let center = [
+    ((random >> 8) % 4001) as i64 - 2000,
+    ((random >> 20) % 4001) as i64 - 2000,
+  ];
+  Place {
+    index: (random % BODIES as u64) as usize,
+    bounds: [
+      [center[0] - half_size[0], center[0] + half_size[0]],
+      [center[1] - half_size[1], center[1] + half_size[1]],
+    ],
+    velocity: [
+      ((random >> 32) % 61) as i64 - 30,
+      ((random >> 40) % 61) as i64 - 30,
+    ],
+  }
+}
+
+/// The same bodies in a space that wraps around in x, where moving bodies cross the seam and the longest ones cover the whole width.
+mod wrapping {
+  use super::*;
+
+  moving_bodies_fixture! {
+    detector: SweepAndPruneDetector<Space, 2>,
+    detector_data: sweep_and_prune::DetectorDataPerObject<Space, 2>,
+    new_detector: |accessor| SweepAndPruneDetector::new(accessor, Space, MARGIN),
+    wrap_extents: [Some(5_000), None],
+    types: (sweep_and_prune::Types<Space, 2>, ListedType<Place>),
+    state: {},
+  }
+  moving_bodies_retroactive_test!(wrapping_sweep_and_prune_detector_retroactive_changes);
+
+  fn place(time: Time, salt: u64) -> Place {
+    let super::Place {
+      index,
+      bounds,
+      velocity,
+    } = super::place(time, salt);
+    Place {
+      index,
+      bounds,
+      velocity,
+    }
+  }
+}
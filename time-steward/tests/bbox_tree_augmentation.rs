@@ -0,0 +1,273 @@
+extern crate time_steward;
+
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+
+#[macro_use]
+mod common;
+
+use crate::collisions::tree::{self, NodeAugmentation, TreeDetector};
+use crate::collisions::{BoundingBox, Detector};
+use crate::simple_timeline::{query, set, tracking_query, unset, SimpleTimeline};
+use crate::steward_module::{
+  bbox_collision_detection as collisions, simple_timeline, Accessor, ConstructibleTimeSteward,
+  DataHandle, DataTimelineCell, Event, EventAccessor, FutureCleanupAccessor, TimeSteward,
+};
+use std::cmp::max;
+use time_steward::stewards::simple_full as steward_module;
+use time_steward::type_utils::list_of_types::ListedType;
+use time_steward::DeterministicRandomId;
+use time_steward::{
+  Basics as BasicsTrait, DataHandleTrait, DataTimelineCellTrait, Listable,
+  PersistentlyIdentifiedType,
+};
+
+const BODIES: usize = 30;
+const HEAVY: u64 = 90;
+
+// The bodies sit still, so they only move between nodes when they are placed again.
+moving_bodies_fixture! {
+  detector: TreeDetector<Space, 2, Mass>,
+  detector_data: tree::DetectorDataPerObject<Space, 2>,
+  new_detector: |accessor| TreeDetector::new(accessor, Space),
+  types: (
+    tree::Types<Space, 2, Mass>,
+    ListedType<Place>,
+    ListedType<SetMass>,
+  ),
+  state: { mass: u64 },
+}
+
+/// The total mass of a group of bodies, and the mass of the heaviest one.
+#[derive(
+  Clone, PartialEq, Eq, Serialize, Deserialize, Debug, Default, PersistentlyIdentifiedType,
+)]
+#[persistent_id(0x86e1d34a0b7fc259)]
+struct Mass {
+  total: u64,
+  heaviest: u64,
+}
+impl NodeAugmentation<Space, 2> for Mass {
+  fn of_object<A: EventAccessor<Steward = Steward>>(
+    accessor: &A,
+    _space: &Space,
+    object: &BodyHandle,
+  ) -> Self {
+    let mass = query(accessor, &object.varying).mass;
+    Mass {
+      total: mass,
+      heaviest: mass,
+    }
+  }
+  fn combine(&mut self, other: &Self) {
+    self.total += other.total;
+    self.heaviest = max(self.heaviest, other.heaviest);
+  }
+}
+
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize, Debug, PersistentlyIdentifiedType)]
+#[persistent_id(0x9e15c8f7a24b306d)]
+struct Place {
+  index: usize,
+  bounds: [[i64; 2]; 2],
+  mass: u64,
+}
+#[time_steward::event]
+impl Event for Place {
+  type Steward = Steward;
+  fn execute<Accessor: EventAccessor<Steward = Self::Steward>>(&self, accessor: &mut Accessor) {
+    let body = &accessor.globals().bodies[self.index];
+    let motion = Motion {
+      start: *accessor.now(),
+      bounds: self.bounds,
+      velocity: [0, 0],
+    };
+    modify(accessor, body, |varying| {
+      varying.motion = Some(motion);
+      varying.mass = self.mass;
+    });
+    // the body may stay in the same nodes, in which case inserting it doesn't update its augmentation
+    TreeDetector::insert(accessor, &detector(accessor), body, None);
+    TreeDetector::augmentation_changed(accessor, &detector(accessor), body);
+  }
+  fn undo<Accessor: FutureCleanupAccessor<Steward = Self::Steward>>(
+    &self,
+    accessor: &mut Accessor,
+    _: (),
+  ) {
+    undo_everything(accessor);
+  }
+}
+
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize, Debug, PersistentlyIdentifiedType)]
+#[persistent_id(0x1a6f70d3e95cb284)]
+struct SetMass {
+  index: usize,
+  mass: u64,
+}
+#[time_steward::event]
+impl Event for SetMass {
+  type Steward = Steward;
+  fn execute<Accessor: EventAccessor<Steward = Self::Steward>>(&self, accessor: &mut Accessor) {
+    let body = &accessor.globals().bodies[self.index];
+    modify(accessor, body, |varying| varying.mass = self.mass);
+    TreeDetector::augmentation_changed(accessor, &detector(accessor), body);
+  }
+  fn undo<Accessor: FutureCleanupAccessor<Steward = Self::Steward>>(
+    &self,
+    accessor: &mut Accessor,
+    _: (),
+  ) {
+    undo_everything(accessor);
+  }
+}
+
+fn random_box(random: u64, max_size: i64) -> [[i64; 2]; 2] {
+  let mut result = [[0; 2]; 2];
+  for dimension in 0..2 {
+    let random = random >> (dimension * 24);
+    let start = (random % 10_001) as i64 - 5_000;
+    result[dimension] = [
+      start,
+      start + ((random >> 14) % 1024) as i64 * max_size / 1024,
+    ];
+  }
+  result
+}
+
+fn insert_events(stew: &mut Steward, times: &[Time], salt: u64) {
+  for &time in times {
+    let random = DeterministicRandomId::new(&(time, salt)).data()[0];
+    let index = (random % BODIES as u64) as usize;
+    let mass = (random >> 56) % 100;
+    if time % 7 == 6 {
+      stew
+        .insert_fiat_event(time, id(time, salt), Vanish { index })
+        .unwrap();
+    } else if time % 3 == 2 {
+      stew
+        .insert_fiat_event(time, id(time, salt), SetMass { index, mass })
+        .unwrap();
+    } else {
+      // bodies of very different sizes, many of them crossing the node boundaries at 0
+      let sizes = [1, 60, 3_000, 200_000];
+      let bounds = random_box(random >> 8, sizes[(random >> 60) as usize % sizes.len()]);
+      stew
+        .insert_fiat_event(
+          time,
+          id(time, salt),
+          Place {
+            index,
+            bounds,
+            mass,
+          },
+        )
+        .unwrap();
+    }
+  }
+}
+
+/// The mass in some boxes, and the heavy bodies in them, after checking them against every body.
+fn observe(stew: &mut Steward, time: Time) -> Vec<(Mass, Vec<usize>)> {
+  let snapshot = stew.snapshot_before(&time).unwrap();
+  let detector = detector(&snapshot);
+  let varyings: Vec<BodyVarying> = snapshot
+    .globals()
+    .bodies
+    .iter()
+    .map(|body| query(&snapshot, &body.varying))
+    .collect();
+
+  (0..40)
+    .map(|seed| {
+      let random = DeterministicRandomId::new(&(time, seed, 0xb0c5)).data()[0];
+      let query_box = if seed == 0 {
+        [[i64::min_value(), i64::max_value()]; 2]
+      } else {
+        random_box(random, 8_000)
+      };
+      let mut expected_mass = Mass::default();
+      let mut expected_heavy = Vec::new();
+      for (index, varying) in varyings.iter().enumerate() {
+        if let Some(bounds) = varying.motion.as_ref().map(|motion| motion.bounds) {
+          if (0..2).all(|dimension| {
+            query_box[dimension][0] <= bounds[dimension][0]
+              && bounds[dimension][0] <= query_box[dimension][1]
+          }) {
+            expected_mass.combine(&Mass {
+              total: varying.mass,
+              heaviest: varying.mass,
+            });
+            if varying.mass >= HEAVY {
+              expected_heavy.push(index);
+            }
+          }
+        }
+      }
+
+      let collision_box = BoundingBox::from_world::<Space>(query_box);
+      let mass = TreeDetector::augmentation_in_box(&snapshot, &detector, collision_box.clone());
+      let mut heavy: Vec<usize> =
+        TreeDetector::search(&snapshot, &detector, collision_box, |mass| {
+          mass.heaviest >= HEAVY
+        })
+        .into_iter()
+        .map(|body| body.index)
+        .collect();
+      heavy.sort();
+      assert_eq!(mass, expected_mass, "{:?}", query_box);
+      assert_eq!(heavy, expected_heavy, "{:?}", query_box);
+      (mass, heavy)
+    })
+    .collect()
+}
+
+#[test]
+fn tree_augmentation_matches_brute_force() {
+  let mut stew: Steward = Steward::from_globals(make_globals()).unwrap();
+  stew.insert_fiat_event(0, id(0, 0), Initialize {}).unwrap();
+  insert_events(&mut stew, &(1..200).collect::<Vec<_>>(), 1);
+  for &time in [10, 50, 120, 200].iter() {
+    observe(&mut stew, time);
+  }
+}
+
+#[test]
+fn tree_augmentation_retroactive_changes() {
+  let observation_times = [30, 75, 140, 220, 300];
+  let first_times: Vec<Time> = (1..50).map(|index| index * 6).collect();
+  let later_times: Vec<Time> = (1..40).map(|index| index * 7 + 2).collect();
+
+  let mut stew: Steward = Steward::from_globals(make_globals()).unwrap();
+  stew.insert_fiat_event(0, id(0, 0), Initialize {}).unwrap();
+  insert_events(&mut stew, &first_times, 1);
+  for &time in observation_times.iter() {
+    observe(&mut stew, time);
+  }
+  // insert events in the past of everything that has already been simulated, then take some away again
+  insert_events(&mut stew, &later_times, 2);
+  for &time in observation_times.iter() {
+    observe(&mut stew, time);
+  }
+  for &time in first_times.iter().filter(|time| *time % 4 == 0) {
+    stew.remove_fiat_event(&time, id(time, 1)).unwrap();
+  }
+
+  let mut fresh: Steward = Steward::from_globals(make_globals()).unwrap();
+  fresh.insert_fiat_event(0, id(0, 0), Initialize {}).unwrap();
+  insert_events(
+    &mut fresh,
+    &first_times
+      .iter()
+      .cloned()
+      .filter(|time| time % 4 != 0)
+      .collect::<Vec<_>>(),
+    1,
+  );
+  insert_events(&mut fresh, &later_times, 2);
+
+  for &time in observation_times.iter().rev() {
+    assert_eq!(observe(&mut stew, time), observe(&mut fresh, time));
+  }
+}
@@ -0,0 +1,56 @@
+extern crate time_steward;
+
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+
+#[macro_use]
+mod common;
+
+use crate::collisions::tree::{self, TreeDetector};
+use crate::collisions::{BoundingBox, Detector};
+use crate::simple_timeline::{query, set, tracking_query, unset, SimpleTimeline};
+use crate::steward_module::{
+  bbox_collision_detection as collisions, simple_timeline, Accessor, ConstructibleTimeSteward,
+  DataHandle, DataTimelineCell, Event, EventAccessor, FutureCleanupAccessor, TimeSteward,
+};
+use time_steward::stewards::simple_full as steward_module;
+use time_steward::type_utils::list_of_types::ListedType;
+use time_steward::DeterministicRandomId;
+use time_steward::{
+  Basics as BasicsTrait, DataHandleTrait, DataTimelineCellTrait, Listable,
+  PersistentlyIdentifiedType,
+};
+
+const BODIES: usize = 6;
+
+moving_bodies_fixture! {
+  detector: TreeDetector<Space, 2>,
+  detector_data: tree::DetectorDataPerObject<Space, 2>,
+  new_detector: |accessor| TreeDetector::new(accessor, Space),
+  types: (tree::Types<Space, 2>, ListedType<Place>),
+  state: {},
+}
+moving_bodies_retroactive_test!(tree_detector_retroactive_changes);
+
+/// Bodies of very different sizes, moving in both directions through the node boundaries at 0.
+fn place(time: Time, salt: u64) -> Place {
+  let random = DeterministicRandomId::new(&(time, salt)).data()[0];
+  let radii = [1, 7, 300, 20_000, 1_000_000];
+  let radius = radii[((random >> 48) % radii.len() as u64) as usize];
+  let center = [
+    ((random >> 8) % 4001) as i64 - 2000,
+    ((random >> 20) % 4001) as i64 - 2000,
+  ];
+  Place {
+    index: (random % BODIES as u64) as usize,
+    bounds: [
+      [center[0] - radius, center[0] + radius],
+      [center[1] - radius, center[1] + radius],
+    ],
+    velocity: [
+      ((random >> 32) % 61) as i64 - 30,
+      ((random >> 40) % 61) as i64 - 30,
+    ],
+  }
+}
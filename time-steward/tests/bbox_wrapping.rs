@@ -0,0 +1,255 @@
+extern crate time_steward;
+
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+
+#[macro_use]
+mod common;
+
+use crate::collisions::{BoundingBox, Coordinate, Detector, Fraction, Segment};
+use crate::simple_timeline::{query, set, SimpleTimeline};
+use crate::steward_module::{
+  bbox_collision_detection as collisions, simple_timeline, Accessor, ConstructibleTimeSteward,
+  DataHandle, DataTimelineCell, Event, EventAccessor, FutureCleanupAccessor, TimeSteward,
+};
+use time_steward::stewards::simple_full as steward_module;
+use time_steward::type_utils::list_of_types::ListedType;
+use time_steward::DeterministicRandomId;
+use time_steward::{
+  Basics as BasicsTrait, DataHandleTrait, DataTimelineCellTrait, Listable,
+  PersistentlyIdentifiedType,
+};
+
+const BODIES: usize = 40;
+const EXTENT: Coordinate = 10_000;
+
+fn world_bounds(index: usize) -> [[i64; 2]; 2] {
+  let random = DeterministicRandomId::new(&(index as u64, 0x70e5u64))
+    .data()
+    .clone();
+  let x = (random[0] % 30_000) as i64 - 15_000;
+  let y = ((random[0] >> 32) % 8_000) as i64 - 4_000;
+  // every few bodies sits right on the seam, and one is wider than the whole space
+  let x = if index % 4 == 0 { -(x % 300) } else { x };
+  let width = if index == 1 {
+    12_000
+  } else {
+    (random[1] % 1_500) as i64
+  };
+  let height = ((random[1] >> 32) % 1_500) as i64;
+  [[x, x + width], [y, y + height]]
+}
+fn body_box<S: collisions::Space<2, WorldCoordinate = i64>>(index: usize) -> BoundingBox<2> {
+  BoundingBox::from_world::<S>(world_bounds(index))
+}
+fn overlap<S: collisions::Space<2>>(a: &BoundingBox<2>, b: &BoundingBox<2>) -> bool {
+  a.pieces::<S>().iter().any(|a| {
+    b.pieces::<S>().iter().any(|b| {
+      (0..2).all(|dimension| {
+        a.bounds[dimension][0] <= b.bounds[dimension][1]
+          && b.bounds[dimension][0] <= a.bounds[dimension][1]
+      })
+    })
+  })
+}
+
+macro_rules! wrapping_tests {
+  (
+    $test: ident,
+    $detector_module: ident :: $Detector: ident,
+    |$accessor: ident| $new_detector: expr
+  ) => {
+    mod $test {
+      use super::*;
+      use crate::collisions::$detector_module::{self, $Detector};
+
+      // wraps around in x but not in y
+      static_bodies_fixture! {
+        dimensions: 2,
+        detector: $detector_module::$Detector,
+        new_detector: |$accessor| $new_detector,
+        world_coordinate: i64,
+        wrap_extents: [Some(EXTENT), None],
+        bounding_box: |index| body_box::<Space>(index),
+      }
+      impl collisions::DistanceQueryableSpace<2> for Space {
+        fn distance_squared<A: Accessor<Steward = Self::Steward>>(
+          &self,
+          _accessor: &A,
+          object: &BodyHandle,
+          location: [Coordinate; 2],
+        ) -> u128 {
+          bounding_box(object.index).wrapped_distance_squared::<Self>(location)
+        }
+      }
+      impl collisions::SegmentQueryableSpace<2> for Space {
+        fn segment_entry<A: Accessor<Steward = Self::Steward>>(
+          &self,
+          _accessor: &A,
+          object: &BodyHandle,
+          segment: &Segment<2>,
+        ) -> Option<Fraction> {
+          segment.box_entry(&bounding_box(object.index))
+        }
+      }
+
+      #[test]
+      fn neighbors_across_the_seam() {
+        let mut stew = make_steward();
+        let snapshot = stew.snapshot_before(&1).unwrap();
+        let detector = query(&snapshot, &snapshot.globals().detector).unwrap();
+        let bodies = &snapshot.globals().bodies;
+
+        for (index, body) in bodies.iter().enumerate() {
+          let bounds = body_box::<Space>(index);
+          let near_object: Vec<usize> = $Detector::objects_near_object(&snapshot, &detector, body)
+            .into_iter()
+            .map(|neighbor| neighbor.index)
+            .collect();
+          let near_box: Vec<usize> =
+            $Detector::objects_near_box(&snapshot, &detector, bounds.clone(), None)
+              .into_iter()
+              .map(|neighbor| neighbor.index)
+              .collect();
+          for other in 0..BODIES {
+            if other != index && overlap::<Space>(&bounds, &body_box::<Space>(other)) {
+              assert!(near_object.contains(&other), "{} {}", index, other);
+              assert!(near_box.contains(&other), "{} {}", index, other);
+            }
+          }
+        }
+      }
+
+      #[test]
+      fn nearest_objects_across_the_seam() {
+        let mut stew = make_steward();
+        let snapshot = stew.snapshot_before(&1).unwrap();
+        let detector = query(&snapshot, &snapshot.globals().detector).unwrap();
+
+        for &x in [0, 40, EXTENT / 2, EXTENT - 1].iter() {
+          let location = [x, (1 << 63) + 300];
+          let mut expected: Vec<u128> = (0..BODIES)
+            .map(|index| body_box::<Space>(index).wrapped_distance_squared::<Space>(location))
+            .collect();
+          expected.sort();
+          let nearest: Vec<u128> =
+            $Detector::nearest_objects(&snapshot, &detector, location, 6, None)
+              .into_iter()
+              .map(|(distance, _)| distance)
+              .collect();
+          assert_eq!(nearest, expected[..6].to_vec(), "{}", x);
+
+          let radius = 2_000;
+          let within: Vec<u128> =
+            $Detector::objects_within_radius(&snapshot, &detector, location, radius, None)
+              .into_iter()
+              .map(|(distance, object)| {
+                assert_eq!(
+                  distance,
+                  body_box::<Space>(object.index).wrapped_distance_squared::<Space>(location)
+                );
+                distance
+              })
+              .collect();
+          let expected: Vec<u128> = expected
+            .into_iter()
+            .filter(|&distance| distance <= (radius as u128).pow(2))
+            .collect();
+          assert_eq!(within, expected, "{}", x);
+        }
+      }
+
+      #[test]
+      fn segments_beside_the_seam() {
+        let mut stew = make_steward();
+        let snapshot = stew.snapshot_before(&1).unwrap();
+        let detector = query(&snapshot, &snapshot.globals().detector).unwrap();
+
+        let segments = [
+          Segment::ray_in::<Space>([EXTENT - 500, (1 << 63) + 200], [1, 0]),
+          Segment::ray_in::<Space>([300, (1 << 63) - 2_000], [-3, 2]),
+          Segment::new([EXTENT - 1, 1 << 63], [0, (1 << 63) + 500]),
+        ];
+        assert_eq!(segments[0].end, [EXTENT - 1, (1 << 63) + 200]);
+        for segment in segments.iter() {
+          let hits: Vec<(Fraction, usize)> =
+            $Detector::objects_on_segment(&snapshot, &detector, segment)
+              .into_iter()
+              .map(|(entry, object)| (entry, object.index))
+              .collect();
+          let mut expected: Vec<(Fraction, usize)> = (0..BODIES)
+            .filter_map(|index| {
+              segment
+                .box_entry(&body_box::<Space>(index))
+                .map(|entry| (entry, index))
+            })
+            .collect();
+          let mut sorted = hits.clone();
+          sorted.sort();
+          expected.sort();
+          assert_eq!(sorted, expected, "{:?}", segment);
+          assert!(hits.windows(2).all(|pair| pair[0].0 <= pair[1].0));
+        }
+      }
+
+      #[test]
+      #[should_panic(expected = "past the end of a dimension that wraps around")]
+      fn segments_must_stay_inside() {
+        let mut stew = make_steward();
+        let snapshot = stew.snapshot_before(&1).unwrap();
+        let detector = query(&snapshot, &snapshot.globals().detector).unwrap();
+        $Detector::objects_on_segment(
+          &snapshot,
+          &detector,
+          &Segment::new([0, 1 << 63], [EXTENT, 1 << 63]),
+        );
+      }
+
+      #[test]
+      fn boxes_straddle_the_seam() {
+        let straddling = BoundingBox::from_world::<Space>([[-100, 200], [-100, 200]]);
+        assert_eq!(
+          straddling.bounds,
+          [
+            [EXTENT - 100, 200],
+            [
+              (-100i64 as Coordinate).wrapping_add(1 << 63),
+              (1 << 63) + 200
+            ]
+          ]
+        );
+        assert_eq!(straddling.to_world::<Space>(), [[9_900, 200], [-100, 200]]);
+        assert!(straddling.contains_location([0, 1 << 63]));
+        assert!(straddling.contains_location([EXTENT - 1, 1 << 63]));
+        assert!(!straddling.contains_location([EXTENT / 2, 1 << 63]));
+        assert_eq!(
+          straddling
+            .pieces::<Space>()
+            .iter()
+            .map(|piece| piece.bounds[0])
+            .collect::<Vec<_>>(),
+          vec![[EXTENT - 100, EXTENT - 1], [0, 200]]
+        );
+
+        let shifted = BoundingBox::from_world::<Space>([[29_900, 30_200], [0, 0]]);
+        assert_eq!(shifted.bounds[0], straddling.bounds[0]);
+        let whole = BoundingBox::from_world::<Space>([[-5, EXTENT as i64 + 5], [0, 0]]);
+        assert_eq!(whole.bounds[0], [0, EXTENT - 1]);
+        assert_eq!(whole.pieces::<Space>().len(), 1);
+      }
+    }
+  };
+}
+
+wrapping_tests!(grid, simple_grid::SimpleGridDetector, |accessor| {
+  SimpleGridDetector::new(accessor, Space, 700)
+});
+wrapping_tests!(tree, tree::TreeDetector, |accessor| TreeDetector::new(
+  accessor, Space
+));
+wrapping_tests!(
+  sweep_and_prune,
+  sweep_and_prune::SweepAndPruneDetector,
+  |accessor| SweepAndPruneDetector::new(accessor, Space, 50)
+);
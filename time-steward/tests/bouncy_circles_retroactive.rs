@@ -0,0 +1,86 @@
+extern crate time_steward;
+
+extern crate boolinator;
+extern crate nalgebra;
+extern crate rand;
+
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+
+use nalgebra::Vector2;
+
+use crate::contact_prediction::contact_data;
+use crate::simple_timeline::query;
+use crate::steward_module::{
+  contact_prediction, simple_timeline, Accessor, ConstructibleTimeSteward, TimeSteward,
+};
+use time_steward::DeterministicRandomId;
+
+#[path = "../dev-shared/bouncy_circles.rs"]
+mod bouncy_circles;
+use crate::bouncy_circles::*;
+
+type Relationships = Vec<(usize, usize, Option<Vector2<SpaceCoordinate>>)>;
+
+fn disturb(stew: &mut Steward, time: Time, coordinates: [SpaceCoordinate; 2]) {
+  stew
+    .insert_fiat_event(
+      time,
+      DeterministicRandomId::new(&(time, 0x45d1)),
+      Disturb { coordinates },
+    )
+    .unwrap();
+}
+
+/// The circles' trajectories, and which circles are neighbors and pushing on each other.
+fn observe(stew: &mut Steward, time: Time) -> (Vec<QuadraticTrajectory>, Relationships) {
+  let snapshot = stew.snapshot_before(&time).unwrap();
+  let mut trajectories = Vec::new();
+  let mut relationships = Vec::new();
+  for circle in snapshot.globals().circles.iter() {
+    let varying = query(&snapshot, &circle.varying);
+    trajectories.push(varying.position);
+    for contact in varying.contacts.iter() {
+      relationships.push((
+        contact.objects()[0].index,
+        contact.objects()[1].index,
+        contact_data(&snapshot, contact),
+      ));
+    }
+  }
+  relationships.sort_by_key(|relationship| (relationship.0, relationship.1));
+  (trajectories, relationships)
+}
+
+#[test]
+fn bouncy_circles_fiat_events_in_the_past() {
+  let end = 4 * SECOND;
+  let mut stew: Steward = Steward::from_globals(make_globals()).unwrap();
+  stew
+    .insert_fiat_event(0, DeterministicRandomId::new(&0), Initialize {})
+    .unwrap();
+  disturb(&mut stew, SECOND, [ARENA_SIZE / 3, ARENA_SIZE / 3]);
+  disturb(&mut stew, 3 * SECOND, [ARENA_SIZE / 2, ARENA_SIZE / 4]);
+  observe(&mut stew, end);
+
+  // changes in the past of everything simulated so far, which invalidate many escapes and collisions
+  disturb(&mut stew, SECOND / 2, [ARENA_SIZE * 2 / 3, ARENA_SIZE / 3]);
+  observe(&mut stew, end);
+  disturb(&mut stew, 2 * SECOND, [ARENA_SIZE / 5, ARENA_SIZE * 3 / 4]);
+  stew
+    .remove_fiat_event(&SECOND, DeterministicRandomId::new(&(SECOND, 0x45d1)))
+    .unwrap();
+
+  let mut fresh: Steward = Steward::from_globals(make_globals()).unwrap();
+  fresh
+    .insert_fiat_event(0, DeterministicRandomId::new(&0), Initialize {})
+    .unwrap();
+  disturb(&mut fresh, SECOND / 2, [ARENA_SIZE * 2 / 3, ARENA_SIZE / 3]);
+  disturb(&mut fresh, 2 * SECOND, [ARENA_SIZE / 5, ARENA_SIZE * 3 / 4]);
+  disturb(&mut fresh, 3 * SECOND, [ARENA_SIZE / 2, ARENA_SIZE / 4]);
+
+  for &time in [end, 3 * SECOND, SECOND].iter() {
+    assert_eq!(observe(&mut stew, time), observe(&mut fresh, time));
+  }
+}
@@ -0,0 +1,652 @@
+//! Simulations shared by the bbox collision detector tests.
+//!
+//! Each test file is its own crate, and the simulation's types depend on which detector it uses, so these are macros that define the types in the module that invokes them. They expect the names that the bbox tests import (`collisions`, `query`, `set`, `DataHandle`, `Listable`, and so on) to be in scope, along with a `BODIES` constant.
+#![allow(unused_macros)]
+
+/// Bodies in a 2D space that fiat events place, set moving in straight lines, and remove. The Space records which bodies are neighbors, and wraps around in the dimensions given by `wrap_extents`, if any. Every fiat event is undone by unsetting everything, so the detector needs an `unset()` method.
+///
+/// This defines `Basics`, `Globals`, `Body`, `BodyVarying`, `Motion`, `Space`, the `Initialize` and `Vanish` events, and `TestDetector` as an alias for the detector type. `types` lists what to register besides `Initialize` and `Vanish`, and `state` adds fields to `BodyVarying`.
+macro_rules! moving_bodies_fixture {
+  (
+    detector: $Detector: ty,
+    detector_data: $DetectorData: ty,
+    new_detector: |$accessor: ident| $new_detector: expr,
+    $(wrap_extents: $wrap_extents: expr,)?
+    types: ($($types: ty),* $(,)?),
+    state: {$($field: ident: $FieldType: ty),* $(,)?} $(,)?
+  ) => {
+    type Time = i64;
+    type Steward = steward_module::Steward<Basics>;
+    type BodyHandle = DataHandle<Body>;
+    type TestDetector = $Detector;
+
+    #[derive(
+      Copy, Clone, Hash, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, Debug, Default,
+    )]
+    struct Basics {}
+    impl BasicsTrait for Basics {
+      type Time = Time;
+      type Globals = Globals;
+      type Types = (ListedType<Body>, ListedType<Initialize>, ListedType<Vanish>, $($types,)*);
+    }
+
+    #[derive(Clone, PartialEq, Eq, Serialize, Deserialize, Debug)]
+    struct Globals {
+      detector: DataTimelineCell<SimpleTimeline<Option<DataHandle<TestDetector>>, Steward>>,
+      bodies: Vec<BodyHandle>,
+    }
+
+    #[derive(
+      Clone, PartialEq, Eq, Serialize, Deserialize, Debug, PersistentlyIdentifiedType, Listable,
+    )]
+    #[persistent_id(0x5d0e93a7c2f1b846)]
+    struct Body {
+      index: usize,
+      varying: DataTimelineCell<SimpleTimeline<BodyVarying, Steward>>,
+    }
+
+    #[derive(Clone, PartialEq, Eq, Serialize, Deserialize, Debug, Default)]
+    struct BodyVarying {
+      motion: Option<Motion>,
+      detector_data: Option<$DetectorData>,
+      neighbors: Vec<usize>,
+      $($field: $FieldType,)*
+    }
+
+    /// Where a body was at `start`, and how fast it moves.
+    #[derive(Clone, PartialEq, Eq, Serialize, Deserialize, Debug)]
+    struct Motion {
+      start: Time,
+      bounds: [[i64; 2]; 2],
+      velocity: [i64; 2],
+    }
+    impl Motion {
+      fn bounds(&self, time: Time) -> [[i64; 2]; 2] {
+        let mut result = self.bounds;
+        for dimension in 0..2 {
+          let offset = self.velocity[dimension] * (time - self.start);
+          result[dimension] = [result[dimension][0] + offset, result[dimension][1] + offset];
+        }
+        result
+      }
+    }
+
+    fn modify<A: EventAccessor<Steward = Steward>, F: FnOnce(&mut BodyVarying)>(
+      accessor: &A,
+      body: &BodyHandle,
+      f: F,
+    ) {
+      let mut varying = query(accessor, &body.varying);
+      f(&mut varying);
+      set(accessor, &body.varying, varying);
+    }
+
+    #[derive(Clone, PartialEq, Eq, Serialize, Deserialize, Debug, PersistentlyIdentifiedType)]
+    #[persistent_id(0xa83c61f05e29d7b4)]
+    struct Space;
+    impl collisions::Space<2> for Space {
+      type Steward = Steward;
+      type Object = Body;
+      type DetectorDataPerObject = $DetectorData;
+      type UniqueId = usize;
+      type WorldCoordinate = i64;
+
+      $(const WRAP_EXTENTS: [Option<collisions::Coordinate>; 2] = $wrap_extents;)?
+
+      fn get_detector_data<A: Accessor<Steward = Self::Steward>>(
+        &self,
+        accessor: &A,
+        object: &BodyHandle,
+      ) -> Option<Self::DetectorDataPerObject> {
+        query(accessor, &object.varying).detector_data
+      }
+      fn set_detector_data<A: EventAccessor<Steward = Self::Steward>>(
+        &self,
+        accessor: &A,
+        object: &BodyHandle,
+        data: Option<Self::DetectorDataPerObject>,
+      ) {
+        modify(accessor, object, |varying| varying.detector_data = data);
+      }
+      fn unique_id<A: Accessor<Steward = Self::Steward>>(
+        &self,
+        _accessor: &A,
+        object: &BodyHandle,
+      ) -> usize {
+        object.index
+      }
+
+      fn current_bounding_box<A: EventAccessor<Steward = Self::Steward>>(
+        &self,
+        accessor: &A,
+        object: &BodyHandle,
+      ) -> BoundingBox<2> {
+        let varying = tracking_query(accessor, &object.varying);
+        BoundingBox::from_world::<Space>(varying.motion.unwrap().bounds(*accessor.now()))
+      }
+      fn when_escapes<A: EventAccessor<Steward = Self::Steward>>(
+        &self,
+        accessor: &A,
+        object: &BodyHandle,
+        bounds: BoundingBox<2>,
+      ) -> Option<Time> {
+        let motion = tracking_query(accessor, &object.varying).motion.unwrap();
+        let now = *accessor.now();
+        let current = BoundingBox::from_world::<Space>(motion.bounds(now));
+        let world_bounds = bounds.to_world::<Space>();
+        (0..2)
+          .filter_map(|dimension| {
+            let velocity = motion.velocity[dimension] as i128;
+            if velocity == 0 {
+              return None;
+            }
+            // where the space wraps around, measure from where the body is now, forward around the seam
+            let (from, distance) = match <Self as collisions::Space<2>>::WRAP_EXTENTS[dimension] {
+              None => {
+                let [low, high] = motion.bounds[dimension];
+                let distance = if velocity > 0 {
+                  world_bounds[dimension][1] as i128 - high as i128
+                } else {
+                  low as i128 - world_bounds[dimension][0] as i128
+                };
+                (motion.start, distance)
+              }
+              Some(extent) => {
+                if bounds.bounds[dimension] == [0, extent - 1] {
+                  return None;
+                }
+                let [low, high] = current.bounds[dimension];
+                let distance = if velocity > 0 {
+                  bounds.bounds[dimension][1] as i128 - high as i128
+                } else {
+                  low as i128 - bounds.bounds[dimension][0] as i128
+                };
+                (now, distance.rem_euclid(extent as i128))
+              }
+            };
+            let time = from as i128 + distance.div_euclid(velocity.abs()) + 1;
+            if time > Time::max_value() as i128 {
+              None
+            } else {
+              Some(time as Time)
+            }
+          })
+          .min()
+      }
+
+      fn become_neighbors<A: EventAccessor<Steward = Self::Steward>>(
+        &self,
+        accessor: &A,
+        objects: [&BodyHandle; 2],
+      ) {
+        for &(body, other) in &[(objects[0], objects[1]), (objects[1], objects[0])] {
+          modify(accessor, body, |varying| {
+            assert!(!varying.neighbors.contains(&other.index));
+            varying.neighbors.push(other.index);
+            varying.neighbors.sort();
+          });
+        }
+      }
+      fn stop_being_neighbors<A: EventAccessor<Steward = Self::Steward>>(
+        &self,
+        accessor: &A,
+        objects: [&BodyHandle; 2],
+      ) {
+        for &(body, other) in &[(objects[0], objects[1]), (objects[1], objects[0])] {
+          modify(accessor, body, |varying| {
+            assert!(varying.neighbors.contains(&other.index));
+            varying.neighbors.retain(|index| *index != other.index);
+          });
+        }
+      }
+      fn undo_detector_changes<A: FutureCleanupAccessor<Steward = Self::Steward>>(
+        &self,
+        accessor: &A,
+        object: &BodyHandle,
+        neighbors: &[BodyHandle],
+      ) {
+        unset(accessor, &object.varying);
+        for neighbor in neighbors {
+          unset(accessor, &neighbor.varying);
+        }
+      }
+    }
+
+    fn detector<A: Accessor<Steward = Steward>>(accessor: &A) -> DataHandle<TestDetector> {
+      query(accessor, &accessor.globals().detector).unwrap()
+    }
+
+    /// Undoes the changes of a fiat event that used the detector; these events don't know which bodies they affected, so they unset all of them.
+    fn undo_everything<A: FutureCleanupAccessor<Steward = Steward>>(accessor: &A) {
+      for body in accessor.globals().bodies.iter() {
+        unset(accessor, &body.varying);
+      }
+      if let Some(detector) = query(accessor, &accessor.globals().detector) {
+        TestDetector::unset(accessor, &detector);
+      }
+      unset(accessor, &accessor.globals().detector);
+    }
+
+    #[derive(Clone, PartialEq, Eq, Serialize, Deserialize, Debug, PersistentlyIdentifiedType)]
+    #[persistent_id(0x1e6b49d8f073ac52)]
+    struct Initialize {}
+    #[time_steward::event]
+    impl Event for Initialize {
+      type Steward = Steward;
+      fn execute<Accessor: EventAccessor<Steward = Self::Steward>>(&self, accessor: &mut Accessor) {
+        let detector = {
+          let $accessor: &Accessor = accessor;
+          $new_detector
+        };
+        set(accessor, &accessor.globals().detector, Some(detector));
+        for body in accessor.globals().bodies.iter() {
+          set(accessor, &body.varying, BodyVarying::default());
+        }
+      }
+      fn undo<Accessor: FutureCleanupAccessor<Steward = Self::Steward>>(
+        &self,
+        accessor: &mut Accessor,
+        _: (),
+      ) {
+        undo_everything(accessor);
+      }
+    }
+
+    #[derive(Clone, PartialEq, Eq, Serialize, Deserialize, Debug, PersistentlyIdentifiedType)]
+    #[persistent_id(0xc4d8177e20b5fa39)]
+    struct Vanish {
+      index: usize,
+    }
+    #[time_steward::event]
+    impl Event for Vanish {
+      type Steward = Steward;
+      fn execute<Accessor: EventAccessor<Steward = Self::Steward>>(&self, accessor: &mut Accessor) {
+        let body = &accessor.globals().bodies[self.index];
+        TestDetector::remove(accessor, &detector(accessor), body);
+        modify(accessor, body, |varying| varying.motion = None);
+      }
+      fn undo<Accessor: FutureCleanupAccessor<Steward = Self::Steward>>(
+        &self,
+        accessor: &mut Accessor,
+        _: (),
+      ) {
+        undo_everything(accessor);
+      }
+    }
+
+    fn make_globals() -> Globals {
+      Globals {
+        detector: DataTimelineCell::new(SimpleTimeline::new()),
+        bodies: (0..BODIES)
+          .map(|index| {
+            DataHandle::new_for_globals(Body {
+              index,
+              varying: DataTimelineCell::new(SimpleTimeline::new()),
+            })
+          })
+          .collect(),
+      }
+    }
+
+    fn id(time: Time, salt: u64) -> DeterministicRandomId {
+      DeterministicRandomId::new(&(time, salt, 0x3a71))
+    }
+  };
+}
+
+/// For a `moving_bodies_fixture!`, a `Place` event and a test named `$test` that inserts and removes `Place` and `Vanish` events in the past of what has already been simulated, checking the neighbors the detector reports against the bodies' positions. The invoking module supplies `fn place(time: Time, salt: u64) -> Place`, which decides the shapes of the bodies.
+macro_rules! moving_bodies_retroactive_test {
+  ($test: ident) => {
+    #[derive(Clone, PartialEq, Eq, Serialize, Deserialize, Debug, PersistentlyIdentifiedType)]
+    #[persistent_id(0x7f25c0a3b9e8146d)]
+    struct Place {
+      index: usize,
+      bounds: [[i64; 2]; 2],
+      velocity: [i64; 2],
+    }
+    #[time_steward::event]
+    impl Event for Place {
+      type Steward = Steward;
+      fn execute<Accessor: EventAccessor<Steward = Self::Steward>>(&self, accessor: &mut Accessor) {
+        let body = &accessor.globals().bodies[self.index];
+        let motion = Motion {
+          start: *accessor.now(),
+          bounds: self.bounds,
+          velocity: self.velocity,
+        };
+        modify(accessor, body, |varying| varying.motion = Some(motion));
+        TestDetector::insert(accessor, &detector(accessor), body, None);
+      }
+      fn undo<Accessor: FutureCleanupAccessor<Steward = Self::Steward>>(
+        &self,
+        accessor: &mut Accessor,
+        _: (),
+      ) {
+        undo_everything(accessor);
+      }
+    }
+
+    fn insert_events(stew: &mut Steward, times: &[Time], salt: u64) {
+      for &time in times {
+        if time % 5 == 4 {
+          stew
+            .insert_fiat_event(
+              time,
+              id(time, salt),
+              Vanish {
+                index: (time as usize / 5) % BODIES,
+              },
+            )
+            .unwrap();
+        } else {
+          stew
+            .insert_fiat_event(time, id(time, salt), place(time, salt))
+            .unwrap();
+        }
+      }
+    }
+
+    /// The motion and neighbors of each body, after checking that the neighbors are consistent with the detector and with the bodies' actual positions.
+    fn observe(stew: &mut Steward, time: Time) -> Vec<(Option<Motion>, Vec<usize>)> {
+      let snapshot = stew.snapshot_before(&time).unwrap();
+      let detector = detector(&snapshot);
+      let bodies = &snapshot.globals().bodies;
+      let varyings: Vec<BodyVarying> = bodies
+        .iter()
+        .map(|body| query(&snapshot, &body.varying))
+        .collect();
+      // no escapes are pending before `time`, so every body is within its stored box at `time - 1`
+      let bounds: Vec<Option<[[i64; 2]; 2]>> = varyings
+        .iter()
+        .map(|varying| {
+          varying
+            .motion
+            .as_ref()
+            .map(|motion| motion.bounds(time - 1))
+        })
+        .collect();
+      let overlap = |a: &[[i64; 2]; 2], b: &[[i64; 2]; 2]| {
+        let b = BoundingBox::from_world::<Space>(*b).pieces::<Space>();
+        BoundingBox::from_world::<Space>(*a)
+          .pieces::<Space>()
+          .iter()
+          .any(|a| {
+            b.iter().any(|b| {
+              (0..2).all(|dimension| {
+                a.bounds[dimension][0] <= b.bounds[dimension][1]
+                  && b.bounds[dimension][0] <= a.bounds[dimension][1]
+              })
+            })
+          })
+      };
+
+      for (index, body) in bodies.iter().enumerate() {
+        let mut near: Vec<usize> = TestDetector::objects_near_object(&snapshot, &detector, body)
+          .iter()
+          .map(|neighbor| neighbor.index)
+          .collect();
+        near.sort();
+        assert_eq!(near, varyings[index].neighbors);
+        assert_eq!(
+          varyings[index].detector_data.is_some(),
+          bounds[index].is_some()
+        );
+        for &neighbor in varyings[index].neighbors.iter() {
+          assert!(varyings[neighbor].neighbors.contains(&index));
+        }
+        for (other, other_bounds) in bounds.iter().enumerate() {
+          if let (Some(bounds), Some(other_bounds)) =
+            (bounds[index].as_ref(), other_bounds.as_ref())
+          {
+            if other != index && overlap(bounds, other_bounds) {
+              assert!(varyings[index].neighbors.contains(&other));
+            }
+          }
+        }
+      }
+
+      let query_box = [[-500, 700], [-50, 3000]];
+      let near_box: Vec<usize> = TestDetector::objects_near_box(
+        &snapshot,
+        &detector,
+        BoundingBox::from_world::<Space>(query_box),
+        None,
+      )
+      .iter()
+      .map(|body| body.index)
+      .collect();
+      for (index, bounds) in bounds.iter().enumerate() {
+        if let Some(bounds) = bounds.as_ref() {
+          if overlap(bounds, &query_box) {
+            assert!(near_box.contains(&index));
+          }
+        }
+      }
+
+      // every body in the detector is listed, and the escapes before `time` have already happened
+      let inspection = TestDetector::inspect(&snapshot, &detector);
+      assert_eq!(
+        inspection
+          .objects
+          .iter()
+          .map(|object| object.id)
+          .collect::<Vec<_>>(),
+        (0..BODIES)
+          .filter(|index| bounds[*index].is_some())
+          .collect::<Vec<_>>()
+      );
+      for object in inspection.pending_escapes() {
+        assert!(object.escape_time.unwrap() >= time);
+      }
+
+      varyings
+        .into_iter()
+        .map(|varying| (varying.motion, varying.neighbors))
+        .collect()
+    }
+
+    #[test]
+    fn $test() {
+      let observation_times = [30, 75, 140, 220, 300];
+      let first_times: Vec<Time> = (1..50).map(|index| index * 6).collect();
+      let later_times: Vec<Time> = (1..40).map(|index| index * 7 + 2).collect();
+
+      let mut stew: Steward = Steward::from_globals(make_globals()).unwrap();
+      stew.insert_fiat_event(0, id(0, 0), Initialize {}).unwrap();
+      insert_events(&mut stew, &first_times, 1);
+      for &time in observation_times.iter() {
+        observe(&mut stew, time);
+      }
+      // insert events in the past of everything that has already been simulated, then take some away again
+      insert_events(&mut stew, &later_times, 2);
+      for &time in observation_times.iter() {
+        observe(&mut stew, time);
+      }
+      for &time in first_times.iter().filter(|time| *time % 4 == 0) {
+        stew.remove_fiat_event(&time, id(time, 1)).unwrap();
+      }
+
+      let mut fresh: Steward = Steward::from_globals(make_globals()).unwrap();
+      fresh.insert_fiat_event(0, id(0, 0), Initialize {}).unwrap();
+      insert_events(
+        &mut fresh,
+        &first_times
+          .iter()
+          .cloned()
+          .filter(|time| time % 4 != 0)
+          .collect::<Vec<_>>(),
+        1,
+      );
+      insert_events(&mut fresh, &later_times, 2);
+
+      for &time in observation_times.iter().rev() {
+        assert_eq!(observe(&mut stew, time), observe(&mut fresh, time));
+      }
+    }
+  };
+}
+
+/// `BODIES` bodies that never move, all inserted into the detector by an `Initialize` event at time 0, in a Space with `$dimensions` dimensions. `bounding_box` gives each body's box from its index. Detectors' events are never undone, since nothing changes after time 0.
+///
+/// This defines `Basics`, `Globals`, `Body`, `Space`, `Initialize`, and `make_steward()`, which returns a steward with `Initialize` already inserted. Tests that use the segment or nearest-object queries implement `SegmentQueryableSpace` or `DistanceQueryableSpace` for `Space` themselves.
+macro_rules! static_bodies_fixture {
+  (
+    dimensions: $dimensions: literal,
+    detector: $detector_module: ident :: $Detector: ident,
+    new_detector: |$accessor: ident| $new_detector: expr,
+    world_coordinate: $WorldCoordinate: ty,
+    wrap_extents: $wrap_extents: expr,
+    bounding_box: |$index: ident| $bounding_box: expr $(,)?
+  ) => {
+    type Time = i64;
+    type Steward = steward_module::Steward<Basics>;
+    type BodyHandle = DataHandle<Body>;
+
+    #[derive(
+      Copy, Clone, Hash, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, Debug, Default,
+    )]
+    struct Basics {}
+    impl BasicsTrait for Basics {
+      type Time = Time;
+      type Globals = Globals;
+      type Types = (
+        ListedType<Body>,
+        ListedType<Initialize>,
+        $detector_module::Types<Space, $dimensions>,
+      );
+    }
+
+    #[derive(Clone, PartialEq, Eq, Serialize, Deserialize, Debug)]
+    struct Globals {
+      detector: DataTimelineCell<
+        SimpleTimeline<Option<DataHandle<$Detector<Space, $dimensions>>>, Steward>,
+      >,
+      bodies: Vec<BodyHandle>,
+    }
+
+    #[derive(
+      Clone, PartialEq, Eq, Serialize, Deserialize, Debug, PersistentlyIdentifiedType, Listable,
+    )]
+    #[persistent_id(0x27f4c9e1a05d3b68)]
+    struct Body {
+      index: usize,
+      detector_data: DataTimelineCell<
+        SimpleTimeline<
+          Option<$detector_module::DetectorDataPerObject<Space, $dimensions>>,
+          Steward,
+        >,
+      >,
+    }
+
+    fn bounding_box($index: usize) -> BoundingBox<$dimensions> {
+      $bounding_box
+    }
+
+    #[derive(Clone, PartialEq, Eq, Serialize, Deserialize, Debug, PersistentlyIdentifiedType)]
+    #[persistent_id(0xe81b5a3d6c2f0947)]
+    struct Space;
+    impl collisions::Space<$dimensions> for Space {
+      type Steward = Steward;
+      type Object = Body;
+      type DetectorDataPerObject = $detector_module::DetectorDataPerObject<Self, $dimensions>;
+      type UniqueId = usize;
+      type WorldCoordinate = $WorldCoordinate;
+
+      const WRAP_EXTENTS: [Option<collisions::Coordinate>; $dimensions] = $wrap_extents;
+
+      fn get_detector_data<A: Accessor<Steward = Self::Steward>>(
+        &self,
+        accessor: &A,
+        object: &BodyHandle,
+      ) -> Option<Self::DetectorDataPerObject> {
+        query(accessor, &object.detector_data)
+      }
+      fn set_detector_data<A: EventAccessor<Steward = Self::Steward>>(
+        &self,
+        accessor: &A,
+        object: &BodyHandle,
+        data: Option<Self::DetectorDataPerObject>,
+      ) {
+        set(accessor, &object.detector_data, data);
+      }
+      fn unique_id<A: Accessor<Steward = Self::Steward>>(
+        &self,
+        _accessor: &A,
+        object: &BodyHandle,
+      ) -> usize {
+        object.index
+      }
+
+      fn current_bounding_box<A: EventAccessor<Steward = Self::Steward>>(
+        &self,
+        _accessor: &A,
+        object: &BodyHandle,
+      ) -> BoundingBox<$dimensions> {
+        bounding_box(object.index)
+      }
+      fn when_escapes<A: EventAccessor<Steward = Self::Steward>>(
+        &self,
+        _accessor: &A,
+        object: &BodyHandle,
+        bounds: BoundingBox<$dimensions>,
+      ) -> Option<Time> {
+        // the bodies never escape, but the box they can move in must still contain them
+        for piece in bounding_box(object.index).pieces::<Self>() {
+          assert!(bounds.contains_location(piece.bounds.map(|bounds| bounds[0])));
+          assert!(bounds.contains_location(piece.bounds.map(|bounds| bounds[1])));
+        }
+        None
+      }
+      fn undo_detector_changes<A: FutureCleanupAccessor<Steward = Self::Steward>>(
+        &self,
+        _accessor: &A,
+        _object: &BodyHandle,
+        _neighbors: &[BodyHandle],
+      ) {
+        panic!("the bodies never move, so the detector's events are never undone")
+      }
+    }
+
+    #[derive(Clone, PartialEq, Eq, Serialize, Deserialize, Debug, PersistentlyIdentifiedType)]
+    #[persistent_id(0x93d06f7b41e8a52c)]
+    struct Initialize {}
+    #[time_steward::event(not_undoable)]
+    impl Event for Initialize {
+      type Steward = Steward;
+      fn execute<Accessor: EventAccessor<Steward = Self::Steward>>(&self, accessor: &mut Accessor) {
+        let detector = {
+          let $accessor: &Accessor = accessor;
+          $new_detector
+        };
+        set(
+          accessor,
+          &accessor.globals().detector,
+          Some(detector.clone()),
+        );
+        for body in accessor.globals().bodies.iter() {
+          set(accessor, &body.detector_data, None);
+          $Detector::insert(accessor, &detector, body, None);
+        }
+      }
+    }
+
+    fn make_steward() -> Steward {
+      let mut stew: Steward = Steward::from_globals(Globals {
+        detector: DataTimelineCell::new(SimpleTimeline::new()),
+        bodies: (0..BODIES)
+          .map(|index| {
+            DataHandle::new_for_globals(Body {
+              index,
+              detector_data: DataTimelineCell::new(SimpleTimeline::new()),
+            })
+          })
+          .collect(),
+      })
+      .unwrap();
+      stew
+        .insert_fiat_event(0, DeterministicRandomId::new(&0), Initialize {})
+        .unwrap();
+      stew
+    }
+  };
+}
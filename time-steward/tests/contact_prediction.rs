@@ -0,0 +1,317 @@
+extern crate time_steward;
+
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+
+use crate::contact_prediction::{contact_data, set_contact_data, touching, Contact};
+use crate::simple_timeline::{query, set, SimpleTimeline};
+use crate::steward_module::{
+  contact_prediction, simple_timeline, trajectory_timeline, Accessor, ConstructibleTimeSteward,
+  DataHandle, DataTimelineCell, Event, EventAccessor, FutureCleanupAccessor, TimeSteward,
+};
+use crate::trajectory_timeline::{
+  set_trajectory, set_velocity, tracking_trajectory, velocity, TrajectoryTimeline,
+};
+use time_steward::stewards::simple_full as steward_module;
+use time_steward::support::trajectories::QuadraticTrajectory;
+use time_steward::type_utils::list_of_types::ListedType;
+use time_steward::DeterministicRandomId;
+use time_steward::{
+  Basics as BasicsTrait, DataHandleTrait, DataTimelineCellTrait, Listable,
+  PersistentlyIdentifiedType,
+};
+
+type Time = i64;
+type Steward = steward_module::Steward<Basics>;
+type BallHandle = DataHandle<Ball>;
+type ContactHandle = DataHandle<Contact<Balls>>;
+
+const RADIUS: i32 = 10;
+
+#[derive(
+  Copy, Clone, Hash, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, Debug, Default,
+)]
+struct Basics {}
+impl BasicsTrait for Basics {
+  type Time = Time;
+  type Globals = Globals;
+  type Types = (
+    ListedType<Ball>,
+    ListedType<Initialize>,
+    ListedType<Push>,
+    ListedType<Separate>,
+    contact_prediction::Types<Balls>,
+  );
+}
+
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize, Debug)]
+struct Globals {
+  balls: [BallHandle; 2],
+  /// When the balls started or stopped touching.
+  log: DataTimelineCell<SimpleTimeline<Vec<(Time, bool)>, Steward>>,
+}
+
+#[derive(
+  Clone, PartialEq, Eq, Serialize, Deserialize, Debug, PersistentlyIdentifiedType, Listable,
+)]
+#[persistent_id(0x3d8a51c7e6f0294b)]
+struct Ball {
+  index: usize,
+  trajectory: DataTimelineCell<TrajectoryTimeline<QuadraticTrajectory<i32>, Steward>>,
+  contacts: DataTimelineCell<SimpleTimeline<Vec<ContactHandle>, Steward>>,
+}
+
+/// Balls on a line that bounce off each other, and count how many times they have touched.
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize, Debug, PersistentlyIdentifiedType)]
+#[persistent_id(0xa6c09e4b17d83f25)]
+struct Balls;
+impl contact_prediction::Space for Balls {
+  type Steward = Steward;
+  type Object = Ball;
+  type Trajectory = QuadraticTrajectory<i32>;
+  type ContactData = u32;
+  type UniqueId = usize;
+
+  const TIME_SHIFT: u32 = 0;
+
+  fn get_contacts<A: Accessor<Steward = Self::Steward>>(
+    &self,
+    accessor: &A,
+    object: &BallHandle,
+  ) -> Vec<ContactHandle> {
+    query(accessor, &object.contacts)
+  }
+  fn set_contacts<A: EventAccessor<Steward = Self::Steward>>(
+    &self,
+    accessor: &A,
+    object: &BallHandle,
+    contacts: Vec<ContactHandle>,
+  ) {
+    set(accessor, &object.contacts, contacts);
+  }
+  fn unique_id<A: Accessor<Steward = Self::Steward>>(
+    &self,
+    _accessor: &A,
+    object: &BallHandle,
+  ) -> usize {
+    object.index
+  }
+
+  fn trajectory<A: EventAccessor<Steward = Self::Steward>>(
+    &self,
+    accessor: &A,
+    object: &BallHandle,
+  ) -> QuadraticTrajectory<i32> {
+    tracking_trajectory(accessor, &object.trajectory)
+  }
+  fn radius<A: EventAccessor<Steward = Self::Steward>>(
+    &self,
+    _accessor: &A,
+    _object: &BallHandle,
+  ) -> i32 {
+    RADIUS
+  }
+
+  fn touching_changed<A: EventAccessor<Steward = Self::Steward>>(
+    &self,
+    accessor: &A,
+    contact: &ContactHandle,
+    touching: bool,
+  ) {
+    let mut log = query(accessor, &accessor.globals().log);
+    log.push((*accessor.now(), touching));
+    set(accessor, &accessor.globals().log, log);
+    if touching {
+      let touches = contact_data(accessor, contact);
+      set_contact_data(accessor, contact, touches + 1);
+      for ball in contact.objects().iter() {
+        let bounced = -velocity(accessor, &ball.trajectory).unwrap();
+        set_velocity(accessor, &ball.trajectory, bounced).unwrap();
+        contact_prediction::trajectory_changed(accessor, self, ball);
+      }
+    }
+  }
+  fn undo_touching_changed<A: FutureCleanupAccessor<Steward = Self::Steward>>(
+    &self,
+    accessor: &A,
+    contact: &ContactHandle,
+  ) {
+    simple_timeline::unset(accessor, &accessor.globals().log);
+    for ball in contact.objects().iter() {
+      trajectory_timeline::unset(accessor, &ball.trajectory);
+      contact_prediction::unset_contacts(accessor, self, ball);
+    }
+  }
+}
+
+/// Starts the second ball rolling toward the first, which stays still.
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize, Debug, PersistentlyIdentifiedType)]
+#[persistent_id(0x5f27b8d04c1ae963)]
+struct Initialize {}
+#[time_steward::event(not_undoable)]
+impl Event for Initialize {
+  type Steward = Steward;
+  fn execute<Accessor: EventAccessor<Steward = Self::Steward>>(&self, accessor: &mut Accessor) {
+    let [first, second] = &accessor.globals().balls;
+    set_trajectory(
+      accessor,
+      &first.trajectory,
+      QuadraticTrajectory::constant(0),
+    );
+    set_trajectory(
+      accessor,
+      &second.trajectory,
+      QuadraticTrajectory::constant(1000),
+    );
+    set_velocity(accessor, &second.trajectory, -5).unwrap();
+    for ball in accessor.globals().balls.iter() {
+      set(accessor, &ball.contacts, Vec::new());
+    }
+    set(accessor, &accessor.globals().log, Vec::new());
+    contact_prediction::become_neighbors(accessor, &Balls, [first, second]);
+  }
+}
+
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize, Debug, PersistentlyIdentifiedType)]
+#[persistent_id(0xe04c6b93a58d172f)]
+struct Push {
+  velocity: i32,
+}
+#[time_steward::event]
+impl Event for Push {
+  type Steward = Steward;
+  fn execute<Accessor: EventAccessor<Steward = Self::Steward>>(&self, accessor: &mut Accessor) {
+    let ball = &accessor.globals().balls[1];
+    set_velocity(accessor, &ball.trajectory, self.velocity).unwrap();
+    contact_prediction::trajectory_changed(accessor, &Balls, ball);
+  }
+  fn undo<Accessor: FutureCleanupAccessor<Steward = Self::Steward>>(
+    &self,
+    accessor: &mut Accessor,
+    _: (),
+  ) {
+    let ball = &accessor.globals().balls[1];
+    trajectory_timeline::unset(accessor, &ball.trajectory);
+    contact_prediction::unset_contacts(accessor, &Balls, ball);
+  }
+}
+
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize, Debug, PersistentlyIdentifiedType)]
+#[persistent_id(0x71b3f8e26d09c4a5)]
+struct Separate {}
+#[time_steward::event]
+impl Event for Separate {
+  type Steward = Steward;
+  fn execute<Accessor: EventAccessor<Steward = Self::Steward>>(&self, accessor: &mut Accessor) {
+    let [first, second] = &accessor.globals().balls;
+    contact_prediction::stop_being_neighbors(accessor, &Balls, [first, second]);
+  }
+  fn undo<Accessor: FutureCleanupAccessor<Steward = Self::Steward>>(
+    &self,
+    accessor: &mut Accessor,
+    _: (),
+  ) {
+    for ball in accessor.globals().balls.iter() {
+      contact_prediction::unset_contacts(accessor, &Balls, ball);
+      simple_timeline::unset(accessor, &ball.contacts);
+      contact_prediction::unset_contacts(accessor, &Balls, ball);
+    }
+  }
+}
+
+fn make_steward() -> Steward {
+  let ball = |index| {
+    DataHandle::new_for_globals(Ball {
+      index,
+      trajectory: DataTimelineCell::new(TrajectoryTimeline::new(
+        0,
+        QuadraticTrajectory::constant(0),
+      )),
+      contacts: DataTimelineCell::new(SimpleTimeline::new()),
+    })
+  };
+  let mut stew: Steward = Steward::from_globals(Globals {
+    balls: [ball(0), ball(1)],
+    log: DataTimelineCell::new(SimpleTimeline::new()),
+  })
+  .unwrap();
+  stew
+    .insert_fiat_event(0, DeterministicRandomId::new(&0), Initialize {})
+    .unwrap();
+  stew
+}
+
+fn id(time: Time) -> DeterministicRandomId {
+  DeterministicRandomId::new(&(time, 0x7a31))
+}
+
+/// The log, and how many times the balls have touched if they are still watched.
+fn observe(stew: &mut Steward, time: Time) -> (Vec<(Time, bool)>, Option<(u32, bool)>) {
+  let snapshot = stew.snapshot_before(&time).unwrap();
+  let contacts = query(&snapshot, &snapshot.globals().balls[0].contacts);
+  assert_eq!(
+    contacts,
+    query(&snapshot, &snapshot.globals().balls[1].contacts)
+  );
+  assert!(contacts.len() <= 1);
+  (
+    query(&snapshot, &snapshot.globals().log),
+    contacts.first().map(|contact| {
+      (
+        contact_data(&snapshot, contact),
+        touching(&snapshot, contact),
+      )
+    }),
+  )
+}
+
+#[test]
+fn balls_bounce_once() {
+  let mut stew = make_steward();
+  let (log, contact) = observe(&mut stew, 1000);
+  assert_eq!(contact, Some((1, false)));
+  assert_eq!(log.len(), 2);
+  let (touched, parted) = (log[0], log[1]);
+  assert_eq!((touched.1, parted.1), (true, false));
+  let diameter = 2 * RADIUS as Time;
+  let distance_when_touched = 1000 - 5 * touched.0;
+  assert!(distance_when_touched < diameter && distance_when_touched > diameter / 2);
+  assert!(distance_when_touched + 5 * (parted.0 - touched.0) > diameter);
+}
+
+#[test]
+fn predictions_follow_trajectory_changes() {
+  let mut stew = make_steward();
+  let bounced = observe(&mut stew, 1000);
+
+  // stopping the ball before it arrives replaces the prediction with none
+  stew
+    .insert_fiat_event(100, id(100), Push { velocity: 0 })
+    .unwrap();
+  assert_eq!(observe(&mut stew, 1000), (Vec::new(), Some((0, false))));
+
+  // slowing it down delays the bounce
+  stew
+    .insert_fiat_event(150, id(150), Push { velocity: -1 })
+    .unwrap();
+  let (log, contact) = observe(&mut stew, 1000);
+  assert_eq!(contact, Some((1, false)));
+  assert!(log[0].0 > 500);
+
+  stew.remove_fiat_event(&150, id(150)).unwrap();
+  stew.remove_fiat_event(&100, id(100)).unwrap();
+  assert_eq!(observe(&mut stew, 1000), bounced);
+}
+
+#[test]
+fn separated_balls_are_not_watched() {
+  let mut stew = make_steward();
+  let bounced = observe(&mut stew, 1000);
+
+  stew.insert_fiat_event(150, id(150), Separate {}).unwrap();
+  assert_eq!(observe(&mut stew, 1000), (Vec::new(), None));
+
+  stew.remove_fiat_event(&150, id(150)).unwrap();
+  assert_eq!(observe(&mut stew, 1000), bounced);
+}
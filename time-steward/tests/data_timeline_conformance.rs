@@ -0,0 +1,357 @@
+extern crate proptest;
+extern crate time_steward;
+
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+
+use proptest::strategy::{BoxedStrategy, Strategy};
+use proptest::test_runner::Config;
+
+use crate::data_timeline_conformance::{check, Scenario};
+use crate::steward_module::{
+  accumulator_timeline, data_timeline_conformance, ordered_map_timeline, simple_timeline, Accessor,
+  DataTimelineCell, Event, EventAccessor, FutureCleanupAccessor,
+};
+use time_steward::stewards::simple_full as steward_module;
+use time_steward::type_utils::list_of_types::ListedType;
+use time_steward::{Basics as BasicsTrait, DataTimelineCellTrait, PersistentlyIdentifiedType};
+
+fn config() -> Config {
+  Config {
+    cases: 64,
+    ..Config::default()
+  }
+}
+fn times() -> BoxedStrategy<i64> {
+  (0i64..20).boxed()
+}
+fn time_between(earlier: &i64, later: &i64) -> Option<i64> {
+  if later - earlier >= 2 {
+    Some(earlier + (later - earlier) / 2)
+  } else {
+    None
+  }
+}
+
+mod ordered_map {
+  use super::*;
+  use crate::ordered_map_timeline::OrderedMapTimeline;
+
+  type Steward = steward_module::Steward<Basics>;
+
+  #[derive(
+    Copy, Clone, Hash, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, Debug, Default,
+  )]
+  pub struct Basics {}
+  impl BasicsTrait for Basics {
+    type Time = i64;
+    type Globals = DataTimelineCell<OrderedMapTimeline<u8, i64, Steward>>;
+    type Types = (ListedType<MapChange>,);
+  }
+
+  /// Sets `key` to `value`, or to the value of `copy_from` if there is one.
+  #[derive(Clone, PartialEq, Eq, Serialize, Deserialize, Debug, PersistentlyIdentifiedType)]
+  #[persistent_id(0x6b3f08d25ea9c147)]
+  pub struct MapChange {
+    key: u8,
+    value: Option<i64>,
+    copy_from: Option<u8>,
+  }
+  #[time_steward::event]
+  impl Event for MapChange {
+    type Steward = Steward;
+    fn execute<Accessor: EventAccessor<Steward = Self::Steward>>(&self, accessor: &mut Accessor) {
+      let value = match self.copy_from {
+        Some(source) => ordered_map_timeline::tracking_get(accessor, accessor.globals(), &source),
+        None => self.value,
+      };
+      ordered_map_timeline::set(accessor, accessor.globals(), self.key, value);
+    }
+    fn undo<Accessor: FutureCleanupAccessor<Steward = Self::Steward>>(
+      &self,
+      accessor: &mut Accessor,
+      _: (),
+    ) {
+      ordered_map_timeline::unset(accessor, accessor.globals(), &self.key);
+    }
+  }
+
+  pub struct MapScenario;
+  impl Scenario for MapScenario {
+    type Basics = Basics;
+    type Modification = MapChange;
+    type Query = u8;
+    type QueryResult = (Option<i64>, usize, Vec<(u8, i64)>);
+
+    fn make_globals() -> <Basics as BasicsTrait>::Globals {
+      DataTimelineCell::new(OrderedMapTimeline::new())
+    }
+    fn time_between(earlier: &i64, later: &i64) -> Option<i64> {
+      time_between(earlier, later)
+    }
+    fn query(
+      accessor: &<Steward as steward_module::TimeSteward>::SnapshotAccessor,
+      key: &u8,
+    ) -> Self::QueryResult {
+      let map = accessor.globals();
+      (
+        ordered_map_timeline::get(accessor, map, key),
+        ordered_map_timeline::len(accessor, map),
+        ordered_map_timeline::range(accessor, map, ..*key),
+      )
+    }
+  }
+
+  pub fn changes() -> BoxedStrategy<MapChange> {
+    (
+      0u8..4,
+      proptest::option::of(-5i64..5),
+      proptest::option::of(0u8..4),
+    )
+      .prop_map(|(key, value, copy_from)| MapChange {
+        key,
+        value,
+        copy_from,
+      })
+      .boxed()
+  }
+}
+
+mod accumulator {
+  use super::*;
+  use crate::accumulator_timeline::Accumulator;
+
+  type Steward = steward_module::Steward<Basics>;
+
+  #[derive(
+    Copy, Clone, Hash, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, Debug, Default,
+  )]
+  pub struct Basics {}
+  impl BasicsTrait for Basics {
+    type Time = i64;
+    type Globals = DataTimelineCell<Accumulator<i64, Steward>>;
+    type Types = (ListedType<Deposit>,);
+  }
+
+  /// Adds `amount`, but only if the total is already at least `only_if_at_least`.
+  #[derive(Clone, PartialEq, Eq, Serialize, Deserialize, Debug, PersistentlyIdentifiedType)]
+  #[persistent_id(0xd4a91c6f30e8275b)]
+  pub struct Deposit {
+    amount: i64,
+    only_if_at_least: Option<i64>,
+  }
+  #[time_steward::event]
+  impl Event for Deposit {
+    type Steward = Steward;
+    fn execute<Accessor: EventAccessor<Steward = Self::Steward>>(&self, accessor: &mut Accessor) {
+      if let Some(threshold) = self.only_if_at_least {
+        if !accumulator_timeline::tracking_at_least(accessor, accessor.globals(), threshold) {
+          return;
+        }
+      }
+      accumulator_timeline::add(accessor, accessor.globals(), self.amount);
+    }
+    fn undo<Accessor: FutureCleanupAccessor<Steward = Self::Steward>>(
+      &self,
+      accessor: &mut Accessor,
+      _: (),
+    ) {
+      accumulator_timeline::unset(accessor, accessor.globals());
+    }
+  }
+
+  pub struct AccumulatorScenario;
+  impl Scenario for AccumulatorScenario {
+    type Basics = Basics;
+    type Modification = Deposit;
+    type Query = i64;
+    type QueryResult = (i64, bool);
+
+    fn make_globals() -> <Basics as BasicsTrait>::Globals {
+      DataTimelineCell::new(Accumulator::new(0))
+    }
+    fn time_between(earlier: &i64, later: &i64) -> Option<i64> {
+      time_between(earlier, later)
+    }
+    fn query(
+      accessor: &<Steward as steward_module::TimeSteward>::SnapshotAccessor,
+      threshold: &i64,
+    ) -> Self::QueryResult {
+      let total = accessor.globals();
+      (
+        accumulator_timeline::total(accessor, total),
+        accumulator_timeline::at_least(accessor, total, *threshold),
+      )
+    }
+  }
+
+  pub fn deposits() -> BoxedStrategy<Deposit> {
+    (-5i64..10, proptest::option::of(-5i64..10))
+      .prop_map(|(amount, only_if_at_least)| Deposit {
+        amount,
+        only_if_at_least,
+      })
+      .boxed()
+  }
+}
+
+mod simple {
+  use super::*;
+  use crate::simple_timeline::{Projection, SimpleTimeline};
+
+  type Steward = steward_module::Steward<Basics>;
+  type Cell = DataTimelineCell<SimpleTimeline<i64, Steward>>;
+
+  pub const CELLS: u8 = 3;
+
+  pub struct Sign;
+  impl Projection<i64> for Sign {
+    type Projected = i64;
+    fn project(value: &i64) -> i64 {
+      value.signum()
+    }
+  }
+
+  #[derive(
+    Copy, Clone, Hash, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, Debug, Default,
+  )]
+  pub struct Basics {}
+  impl BasicsTrait for Basics {
+    type Time = i64;
+    type Globals = Vec<Cell>;
+    type Types = (ListedType<Write>,);
+  }
+
+  /// A SimpleTimeline can't be queried before it's set, so `Initialize` sets every cell first. After that, `Set` writes `amount` to `cell`, plus either the value of `copy_from`, or just its sign if `projected`.
+  #[derive(Clone, PartialEq, Eq, Serialize, Deserialize, Debug, PersistentlyIdentifiedType)]
+  #[persistent_id(0x2f9c6e1a85d04b73)]
+  pub enum Write {
+    Initialize,
+    Set {
+      cell: u8,
+      amount: i64,
+      copy_from: Option<u8>,
+      projected: bool,
+    },
+  }
+  #[time_steward::event]
+  impl Event for Write {
+    type Steward = Steward;
+    fn execute<Accessor: EventAccessor<Steward = Self::Steward>>(&self, accessor: &mut Accessor) {
+      let cells = accessor.globals();
+      match *self {
+        Write::Initialize => {
+          for cell in cells.iter() {
+            simple_timeline::set(accessor, cell, 0);
+          }
+        }
+        Write::Set {
+          cell,
+          amount,
+          copy_from,
+          projected,
+        } => {
+          let base = match copy_from {
+            Some(source) if projected => {
+              simple_timeline::tracking_query_projected(accessor, &cells[source as usize], &Sign)
+            }
+            Some(source) => simple_timeline::tracking_query(accessor, &cells[source as usize]),
+            None => 0,
+          };
+          simple_timeline::set(accessor, &cells[cell as usize], base + amount);
+        }
+      }
+    }
+    fn undo<Accessor: FutureCleanupAccessor<Steward = Self::Steward>>(
+      &self,
+      accessor: &mut Accessor,
+      _: (),
+    ) {
+      let cells = accessor.globals();
+      match *self {
+        Write::Initialize => {
+          for cell in cells.iter() {
+            simple_timeline::unset(accessor, cell);
+          }
+        }
+        Write::Set { cell, .. } => simple_timeline::unset(accessor, &cells[cell as usize]),
+      }
+    }
+  }
+
+  pub struct SimpleScenario;
+  impl Scenario for SimpleScenario {
+    type Basics = Basics;
+    type Modification = Write;
+    type Query = u8;
+    type QueryResult = i64;
+
+    fn make_globals() -> <Basics as BasicsTrait>::Globals {
+      (0..CELLS)
+        .map(|_| DataTimelineCell::new(SimpleTimeline::new()))
+        .collect()
+    }
+    fn initial_events() -> Vec<(i64, Write)> {
+      vec![(-1, Write::Initialize)]
+    }
+    fn time_between(earlier: &i64, later: &i64) -> Option<i64> {
+      time_between(earlier, later)
+    }
+    fn query(
+      accessor: &<Steward as steward_module::TimeSteward>::SnapshotAccessor,
+      cell: &u8,
+    ) -> Self::QueryResult {
+      simple_timeline::query(accessor, &accessor.globals()[*cell as usize])
+    }
+  }
+
+  pub fn writes() -> BoxedStrategy<Write> {
+    (
+      0..CELLS,
+      -3i64..4,
+      proptest::option::of(0..CELLS),
+      proptest::bool::ANY,
+    )
+      .prop_map(|(cell, amount, copy_from, projected)| Write::Set {
+        cell,
+        amount,
+        copy_from,
+        projected,
+      })
+      .boxed()
+  }
+}
+
+#[test]
+fn simple_timeline_conforms() {
+  check::<simple::SimpleScenario>(
+    config(),
+    times,
+    simple::writes,
+    || (0..simple::CELLS).boxed(),
+    24,
+  );
+}
+
+#[test]
+fn ordered_map_timeline_conforms() {
+  check::<ordered_map::MapScenario>(
+    config(),
+    times,
+    ordered_map::changes,
+    || (0u8..5).boxed(),
+    24,
+  );
+}
+
+#[test]
+fn accumulator_timeline_conforms() {
+  check::<accumulator::AccumulatorScenario>(
+    config(),
+    times,
+    accumulator::deposits,
+    || (-5i64..15).boxed(),
+    24,
+  );
+}
@@ -8,7 +8,7 @@ extern crate serde_derive;
 
 use crate::simple_timeline::{query, set, tracking_query, unset, SimpleTimeline};
 use crate::steward_module::{
-  simple_timeline, ConstructibleTimeSteward, DataTimelineCell, Event, EventAccessor,
+  simple_timeline, Accessor, ConstructibleTimeSteward, DataTimelineCell, Event, EventAccessor,
   FutureCleanupAccessor, SnapshotAccessor, TimeSteward,
 };
 use time_steward::stewards::simple_full as steward_module;
@@ -17,7 +17,7 @@ use time_steward::{
   Basics as BasicsTrait, DataTimelineCellTrait
 };
 use time_steward::type_utils::{PersistentTypeId, PersistentlyIdentifiedType};
-use time_steward::type_utils::list_of_types::{ListedType};
+use time_steward::type_utils::list_of_types::ListedType;
 
 type Time = i64;
 type Steward = steward_module::Steward<Basics>;
@@ -113,6 +113,7 @@ struct Shake {
 impl PersistentlyIdentifiedType for Shake {
   const ID: PersistentTypeId = PersistentTypeId(0x8987a0b8e7d3d624);
 }
+#[time_steward::event]
 impl Event for Shake {
   type Steward = Steward;
   type ExecutionData = ();
@@ -152,14 +153,12 @@ impl Event for Shake {
   }
 }
 
-#[derive(Clone, PartialEq, Eq, Serialize, Deserialize, Debug)]
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize, Debug, time_steward::PersistentlyIdentifiedType)]
+#[persistent_id(0xd5e73d8ba6ec59a2)]
 struct Initialize {}
-impl PersistentlyIdentifiedType for Initialize {
-  const ID: PersistentTypeId = PersistentTypeId(0xd5e73d8ba6ec59a2);
-}
+#[time_steward::event]
 impl Event for Initialize {
   type Steward = Steward;
-  type ExecutionData = ();
   fn execute<Accessor: EventAccessor<Steward = Self::Steward>>(&self, accessor: &mut Accessor) {
     println!("FIAT!!!!!");
     let philosophers = accessor.globals();
@@ -186,6 +185,7 @@ struct Tweak {}
 impl PersistentlyIdentifiedType for Tweak {
   const ID: PersistentTypeId = PersistentTypeId(0xfe9ff3047f9a9552);
 }
+#[time_steward::event]
 impl Event for Tweak {
   type Steward = Steward;
   type ExecutionData = ();
@@ -228,6 +228,7 @@ struct TweakUnsafe {}
 impl PersistentlyIdentifiedType for TweakUnsafe {
   const ID: PersistentTypeId = PersistentTypeId(0xa1618440808703da);
 }
+#[time_steward::event]
 impl Event for TweakUnsafe {
   type Steward = Steward;
   type ExecutionData = ();
@@ -268,7 +269,7 @@ fn make_globals() -> <Basics as BasicsTrait>::Globals {
 #[test]
 pub fn handshakes_simple() {
   //type Steward = crossverified::Steward<Basics, inefficient_flat::Steward<Basics>, memoized_flat::Steward<Basics>>;
-  let mut stew: Steward = Steward::from_globals(make_globals());
+  let mut stew: Steward = Steward::from_globals(make_globals()).unwrap();
 
   stew
     .insert_fiat_event(
@@ -287,7 +288,7 @@ pub fn handshakes_simple() {
 
 #[test]
 fn handshakes_retroactive() {
-  let mut stew: Steward = Steward::from_globals(make_globals());
+  let mut stew: Steward = Steward::from_globals(make_globals()).unwrap();
 
   stew
     .insert_fiat_event(
@@ -336,7 +337,7 @@ fn handshakes_retroactive() {
 
 #[test]
 fn handshakes_reloading() {
-  let mut stew: Steward = Steward::from_globals(make_globals());
+  let mut stew: Steward = Steward::from_globals(make_globals()).unwrap();
 
   stew
     .insert_fiat_event(
@@ -376,6 +377,146 @@ fn handshakes_reloading() {
   }
 }
 
+#[test]
+fn handshakes_partial_snapshot() {
+  use time_steward::serialization::MaxDepth;
+  let mut stew: Steward = Steward::from_globals(make_globals()).unwrap();
+
+  stew
+    .insert_fiat_event(
+      0,
+      DeterministicRandomId::new(&0x32e1570766e768a7u64),
+      Initialize {},
+    )
+    .unwrap();
+
+  let snapshot = stew.snapshot_before(&(1000i64)).unwrap();
+  let full_dump = dump_snapshot(&snapshot);
+  let mut serialized = Vec::new();
+  snapshot
+    .serialize_partial_into(&mut serialized, &snapshot.globals()[..3], MaxDepth(0))
+    .unwrap();
+  use std::io::Cursor;
+  let mut reader = Cursor::new(serialized);
+  let partial: steward_module::PartialSnapshot<Basics, Vec<PhilosopherCell>> =
+    steward_module::PartialSnapshot::deserialize_from(&mut reader).unwrap();
+  assert_eq!(partial.now(), snapshot.now());
+  let partial_dump: Vec<Time> = partial
+    .roots()
+    .iter()
+    .map(|handle| {
+      partial
+        .query(handle, &simple_timeline::GetVarying)
+        .time_when_next_initiates_handshake
+    })
+    .collect();
+  assert_eq!(&full_dump[..3], &partial_dump[..]);
+}
+
+#[test]
+fn handshakes_autosave() {
+  use std::fs::File;
+  use steward_module::autosave::{AutosaveSettings, Autosaver};
+  let mut stew: Steward = Steward::from_globals(make_globals()).unwrap();
+
+  stew
+    .insert_fiat_event(
+      0,
+      DeterministicRandomId::new(&0x32e1570766e768a7u64),
+      Initialize {},
+    )
+    .unwrap();
+
+  let directory = std::env::temp_dir().join(format!("time_steward_autosave_test_{}", std::process::id()));
+  let _ = std::fs::remove_dir_all(&directory);
+  let mut written = Vec::new();
+  {
+    let mut autosaver: Autosaver<Steward> = Autosaver::new(
+      AutosaveSettings {
+        directory: directory.clone(),
+        file_prefix: "handshakes_".to_string(),
+        interval: 300,
+        files_kept: 2,
+        serialization_steps_per_update: 2,
+      },
+      100,
+    )
+    .unwrap();
+    for now in 0..20 {
+      written.extend(autosaver.update(&mut stew, &(now * 100i64)).unwrap());
+    }
+    while autosaver.is_saving() {
+      written.extend(autosaver.update(&mut stew, &1900).unwrap());
+    }
+  }
+  let remaining = time_steward::support::autosave::numbered_files(&directory, "handshakes_").unwrap();
+  assert_eq!(remaining.len(), 2);
+
+  let first_dump = dump_snapshot(&stew.snapshot_before(&(2000i64)).unwrap());
+  let mut reloaded = Steward::deserialize_from(&mut File::open(&remaining[1].1).unwrap()).unwrap();
+  let dump = dump_snapshot(&reloaded.snapshot_before(&(2000i64)).unwrap());
+  assert_eq!(first_dump, dump);
+  std::fs::remove_dir_all(&directory).unwrap();
+}
+
+#[test]
+fn handshakes_journal_recovery() {
+  use std::fs::OpenOptions;
+  use std::io::Write;
+  use steward_module::journal::Journal;
+  let directory = std::env::temp_dir().join(format!("time_steward_journal_test_{}", std::process::id()));
+  let _ = std::fs::remove_dir_all(&directory);
+
+  let first_dump;
+  {
+    let mut journal: Journal<Steward> = Journal::open(&directory, 2, make_globals).unwrap();
+    journal
+      .insert_fiat_event(
+        0,
+        DeterministicRandomId::new(&0x32e1570766e768a7u64),
+        Initialize {},
+      )
+      .unwrap();
+    for increment in 1..21 {
+      journal
+        .insert_fiat_event(
+          increment * 100i64,
+          DeterministicRandomId::new(&increment),
+          Tweak {},
+        )
+        .unwrap();
+      if increment % 7 == 0 {
+        assert!(journal.checkpoint(&(increment * 50i64)).unwrap());
+      }
+    }
+    journal
+      .remove_fiat_event(&1500, DeterministicRandomId::new(&15i64))
+      .unwrap();
+    first_dump = dump_snapshot(&journal.steward_mut().snapshot_before(&(2000i64)).unwrap());
+  }
+
+  // simulate a crash in the middle of appending a record
+  let journal_path = directory.join(time_steward::support::autosave::numbered_file_name(
+    time_steward::support::journal::journal_prefix(),
+    2,
+  ));
+  OpenOptions::new()
+    .append(true)
+    .open(&journal_path)
+    .unwrap()
+    .write_all(&[7, 0, 0, 0, 1, 2, 3])
+    .unwrap();
+  let length_with_garbage = std::fs::metadata(&journal_path).unwrap().len();
+
+  let mut journal: Journal<Steward> = Journal::open(&directory, 2, make_globals).unwrap();
+  assert_eq!(journal.generation(), 2);
+  assert_eq!(std::fs::metadata(&journal_path).unwrap().len(), length_with_garbage - 7);
+  let dump = dump_snapshot(&journal.steward_mut().snapshot_before(&(2000i64)).unwrap());
+  assert_eq!(first_dump, dump);
+  drop(journal);
+  std::fs::remove_dir_all(&directory).unwrap();
+}
+
 /*
 
 #[test]
@@ -0,0 +1,187 @@
+extern crate time_steward;
+
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+
+use std::cell::Cell;
+
+use crate::ordered_map_timeline::{tracking_get, tracking_len, tracking_range, OrderedMapTimeline};
+use crate::steward_module::{
+  ordered_map_timeline, simple_timeline, Accessor, ConstructibleTimeSteward, DataTimelineCell,
+  Event, EventAccessor, FutureCleanupAccessor, TimeSteward,
+};
+use time_steward::stewards::simple_full as steward_module;
+use time_steward::type_utils::list_of_types::ListedType;
+use time_steward::DeterministicRandomId;
+use time_steward::{Basics as BasicsTrait, DataTimelineCellTrait, PersistentlyIdentifiedType};
+
+type Time = i64;
+type Steward = steward_module::Steward<Basics>;
+type Scores = DataTimelineCell<OrderedMapTimeline<u32, i64, Steward>>;
+type Summary = DataTimelineCell<simple_timeline::SimpleTimeline<(usize, i64), Steward>>;
+
+#[derive(
+  Copy, Clone, Hash, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, Debug, Default,
+)]
+struct Basics {}
+impl BasicsTrait for Basics {
+  type Time = Time;
+  type Globals = (Scores, Summary);
+  type Types = (
+    ListedType<SetScore>,
+    ListedType<CopyScore>,
+    ListedType<Summarize>,
+  );
+}
+
+thread_local! {
+  static COPY_EXECUTIONS: Cell<usize> = Cell::new(0);
+}
+
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize, Debug, PersistentlyIdentifiedType)]
+#[persistent_id(0x3c8e0f5a9b72d146)]
+struct SetScore {
+  player: u32,
+  score: Option<i64>,
+}
+#[time_steward::event]
+impl Event for SetScore {
+  type Steward = Steward;
+  fn execute<Accessor: EventAccessor<Steward = Self::Steward>>(&self, accessor: &mut Accessor) {
+    ordered_map_timeline::set(accessor, &accessor.globals().0, self.player, self.score);
+  }
+  fn undo<Accessor: FutureCleanupAccessor<Steward = Self::Steward>>(
+    &self,
+    accessor: &mut Accessor,
+    _: (),
+  ) {
+    ordered_map_timeline::unset(accessor, &accessor.globals().0, &self.player);
+  }
+}
+
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize, Debug, PersistentlyIdentifiedType)]
+#[persistent_id(0x9d14a7e2c05b38f1)]
+struct CopyScore {
+  from: u32,
+  to: u32,
+}
+#[time_steward::event]
+impl Event for CopyScore {
+  type Steward = Steward;
+  fn execute<Accessor: EventAccessor<Steward = Self::Steward>>(&self, accessor: &mut Accessor) {
+    COPY_EXECUTIONS.with(|count| count.set(count.get() + 1));
+    let score = tracking_get(accessor, &accessor.globals().0, &self.from);
+    ordered_map_timeline::set(accessor, &accessor.globals().0, self.to, score);
+  }
+  fn undo<Accessor: FutureCleanupAccessor<Steward = Self::Steward>>(
+    &self,
+    accessor: &mut Accessor,
+    _: (),
+  ) {
+    ordered_map_timeline::unset(accessor, &accessor.globals().0, &self.to);
+  }
+}
+
+/// Records how many players there are, and the total score of players below 100.
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize, Debug, PersistentlyIdentifiedType)]
+#[persistent_id(0x61f2b09d4e8a7c35)]
+struct Summarize {}
+#[time_steward::event]
+impl Event for Summarize {
+  type Steward = Steward;
+  fn execute<Accessor: EventAccessor<Steward = Self::Steward>>(&self, accessor: &mut Accessor) {
+    let scores = &accessor.globals().0;
+    let players = tracking_len(accessor, scores);
+    let total: i64 = tracking_range(accessor, scores, ..100)
+      .into_iter()
+      .map(|(_, score)| score)
+      .sum();
+    simple_timeline::set(accessor, &accessor.globals().1, (players, total));
+  }
+  fn undo<Accessor: FutureCleanupAccessor<Steward = Self::Steward>>(
+    &self,
+    accessor: &mut Accessor,
+    _: (),
+  ) {
+    simple_timeline::unset(accessor, &accessor.globals().1);
+  }
+}
+
+fn make_globals() -> <Basics as BasicsTrait>::Globals {
+  let summary = DataTimelineCell::new(simple_timeline::SimpleTimeline::new());
+  (DataTimelineCell::new(OrderedMapTimeline::new()), summary)
+}
+
+fn insert<E: Event<Steward = Steward>>(stew: &mut Steward, time: Time, event: E) {
+  stew
+    .insert_fiat_event(time, DeterministicRandomId::new(&(time, 0x7a2c)), event)
+    .unwrap();
+}
+
+fn observe(stew: &mut Steward) -> (Option<i64>, Option<i64>, (usize, i64)) {
+  let snapshot = stew.snapshot_before(&1000).unwrap();
+  let scores = &snapshot.globals().0;
+  (
+    ordered_map_timeline::get(&snapshot, scores, &1),
+    ordered_map_timeline::get(&snapshot, scores, &2),
+    simple_timeline::query(&snapshot, &snapshot.globals().1),
+  )
+}
+
+#[test]
+fn ordered_map_retroactive_changes() {
+  let mut stew: Steward = Steward::from_globals(make_globals()).unwrap();
+  insert(
+    &mut stew,
+    10,
+    SetScore {
+      player: 1,
+      score: Some(10),
+    },
+  );
+  insert(&mut stew, 20, CopyScore { from: 1, to: 2 });
+  insert(&mut stew, 30, Summarize {});
+
+  assert_eq!(observe(&mut stew), (Some(10), Some(10), (2, 20)));
+  let copies = COPY_EXECUTIONS.with(|count| count.get());
+
+  // a change to an unrelated key doesn't disturb the copy, but does change the summary
+  insert(
+    &mut stew,
+    15,
+    SetScore {
+      player: 150,
+      score: Some(5),
+    },
+  );
+  assert_eq!(observe(&mut stew), (Some(10), Some(10), (3, 20)));
+  assert_eq!(COPY_EXECUTIONS.with(|count| count.get()), copies);
+
+  // a change to the copied key does
+  insert(
+    &mut stew,
+    16,
+    SetScore {
+      player: 1,
+      score: Some(50),
+    },
+  );
+  assert_eq!(observe(&mut stew), (Some(50), Some(50), (3, 100)));
+  assert!(COPY_EXECUTIONS.with(|count| count.get()) > copies);
+
+  stew
+    .remove_fiat_event(&16, DeterministicRandomId::new(&(16i64, 0x7a2c)))
+    .unwrap();
+  assert_eq!(observe(&mut stew), (Some(10), Some(10), (3, 20)));
+
+  insert(
+    &mut stew,
+    25,
+    SetScore {
+      player: 1,
+      score: None,
+    },
+  );
+  assert_eq!(observe(&mut stew), (None, Some(10), (2, 10)));
+}
@@ -0,0 +1,179 @@
+extern crate time_steward;
+
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+
+use std::io::Cursor;
+
+use crate::simple_timeline::{query, set, unset, SimpleTimeline};
+use crate::steward_module::{
+  simple_timeline, Accessor, ConstructibleTimeSteward, DataHandle, DataTimelineCell, Event,
+  EventAccessor, FutureCleanupAccessor, PartialSnapshot, SnapshotAccessor, TimeSteward,
+};
+use time_steward::serialization::{MaxDepth, PartialSnapshotTarget, TypeRegistrationError};
+use time_steward::stewards::simple_full as steward_module;
+use time_steward::type_utils::list_of_types::{self, ListedType};
+use time_steward::type_utils::PersistentlyIdentifiedType;
+use time_steward::DeterministicRandomId;
+use time_steward::{
+  Basics as BasicsTrait, DataHandleTrait, DataTimelineCellTrait, Listable,
+  PersistentlyIdentifiedType,
+};
+
+type Time = i64;
+type Steward = steward_module::Steward<Basics>;
+
+#[derive(
+  Copy, Clone, Hash, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, Debug, Default,
+)]
+struct Basics {}
+impl BasicsTrait for Basics {
+  type Time = Time;
+  type Globals = DataHandle<Node>;
+  type Types = (ListedType<Node>,);
+}
+
+#[derive(
+  Clone, PartialEq, Eq, Serialize, Deserialize, Debug, Default, PersistentlyIdentifiedType, Listable,
+)]
+#[persistent_id(0x6c0e94d2b3a7f158)]
+#[listable(default)]
+struct Node {
+  value: u32,
+  next: Option<DataHandle<Node>>,
+}
+
+const CHAIN_LENGTH: u32 = 5;
+
+fn make_globals() -> <Basics as BasicsTrait>::Globals {
+  let mut next = None;
+  for value in (1..=CHAIN_LENGTH).rev() {
+    next = Some(DataHandle::new_for_globals(Node { value, next }));
+  }
+  next.unwrap()
+}
+
+fn chain(partial: &PartialSnapshot<Basics, DataHandle<Node>>) -> Vec<(u32, bool)> {
+  let mut result = Vec::new();
+  let mut next = Some(partial.roots().clone());
+  while let Some(node) = next {
+    result.push((node.value, partial.is_stub(&node)));
+    next = node.next.clone();
+  }
+  result
+}
+
+fn export<P: time_steward::serialization::PartialSnapshotPolicy + 'static>(
+  policy: P,
+) -> PartialSnapshot<Basics, DataHandle<Node>> {
+  let mut stew: Steward = Steward::from_globals(make_globals()).unwrap();
+  let snapshot = stew.snapshot_before(&0).unwrap();
+  let mut serialized = Vec::new();
+  snapshot
+    .serialize_partial_into(&mut serialized, snapshot.globals(), policy)
+    .unwrap();
+  PartialSnapshot::deserialize_from(&mut Cursor::new(serialized)).unwrap()
+}
+
+#[test]
+fn partial_snapshot_cuts_off_deep_handles() {
+  // The root is at depth 0, so MaxDepth(1) keeps two nodes in full. The third is a stub, which reads as Node::default(), so the chain ends there and nothing past it was exported.
+  let partial = export(MaxDepth(1));
+  assert_eq!(chain(&partial), vec![(1, false), (2, false), (0, true)]);
+
+  let partial = export(MaxDepth(0));
+  assert_eq!(chain(&partial), vec![(1, false), (0, true)]);
+}
+
+#[test]
+fn partial_snapshot_without_cutoff_has_no_stubs() {
+  let partial = export(MaxDepth(CHAIN_LENGTH as usize));
+  let expected: Vec<(u32, bool)> = (1..=CHAIN_LENGTH).map(|value| (value, false)).collect();
+  assert_eq!(chain(&partial), expected);
+}
+
+#[test]
+fn partial_snapshot_custom_policy() {
+  let partial = export(|target: &PartialSnapshotTarget| {
+    assert_eq!(target.type_id, Node::ID);
+    target.depth < 3
+  });
+  assert_eq!(
+    chain(&partial),
+    vec![(1, false), (2, false), (3, false), (0, true)]
+  );
+}
+
+#[derive(
+  Copy, Clone, Hash, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, Debug, Default,
+)]
+struct UnlistedBasics {}
+impl BasicsTrait for UnlistedBasics {
+  type Time = Time;
+  type Globals = DataHandle<Node>;
+  type Types = list_of_types::Nil;
+}
+
+#[test]
+fn unlisted_globals_are_rejected() {
+  let result = steward_module::Steward::<UnlistedBasics>::from_globals(make_globals());
+  match result {
+    Err(TypeRegistrationError::Unlisted { id, .. }) => assert_eq!(id, Node::ID),
+    other => panic!("expected an Unlisted error, got {:?}", other.err()),
+  }
+}
+
+#[derive(
+  Copy, Clone, Hash, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, Debug, Default,
+)]
+struct TimelineBasics {}
+impl BasicsTrait for TimelineBasics {
+  type Time = Time;
+  type Globals = DataTimelineCell<SimpleTimeline<u32, steward_module::Steward<TimelineBasics>>>;
+  type Types = (ListedType<SetValue>,);
+}
+
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize, Debug, PersistentlyIdentifiedType)]
+#[persistent_id(0x4b1f7c92e05da836)]
+struct SetValue {
+  value: u32,
+}
+#[time_steward::event]
+impl Event for SetValue {
+  type Steward = steward_module::Steward<TimelineBasics>;
+  fn execute<Accessor: EventAccessor<Steward = Self::Steward>>(&self, accessor: &mut Accessor) {
+    set(accessor, accessor.globals(), self.value);
+  }
+  fn undo<Accessor: FutureCleanupAccessor<Steward = Self::Steward>>(
+    &self,
+    accessor: &mut Accessor,
+    _: (),
+  ) {
+    unset(accessor, accessor.globals());
+  }
+}
+
+#[test]
+fn partial_snapshot_is_an_accessor() {
+  let mut stew = steward_module::Steward::<TimelineBasics>::from_globals(DataTimelineCell::new(
+    SimpleTimeline::new(),
+  ))
+  .unwrap();
+  stew
+    .insert_fiat_event(
+      1,
+      DeterministicRandomId::new(&0x5e7u32),
+      SetValue { value: 7 },
+    )
+    .unwrap();
+  let snapshot = stew.snapshot_before(&2).unwrap();
+  let mut serialized = Vec::new();
+  snapshot
+    .serialize_partial_into(&mut serialized, &(), MaxDepth(0))
+    .unwrap();
+  let partial: PartialSnapshot<TimelineBasics, ()> =
+    PartialSnapshot::deserialize_from(&mut Cursor::new(serialized)).unwrap();
+  assert_eq!(*partial.now(), 2);
+  assert_eq!(query(&partial, partial.globals()), 7);
+}
@@ -0,0 +1,141 @@
+extern crate time_steward;
+
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+
+use std::cell::Cell;
+
+use crate::priority_queue_timeline::{peek, pop, push, PriorityQueueTimeline};
+use crate::steward_module::{
+  priority_queue_timeline, simple_timeline, Accessor, ConstructibleTimeSteward, DataTimelineCell,
+  Event, EventAccessor, FutureCleanupAccessor, TimeSteward,
+};
+use time_steward::stewards::simple_full as steward_module;
+use time_steward::type_utils::list_of_types::ListedType;
+use time_steward::DeterministicRandomId;
+use time_steward::{Basics as BasicsTrait, DataTimelineCellTrait, PersistentlyIdentifiedType};
+
+type Time = i64;
+type Steward = steward_module::Steward<Basics>;
+type Queue = DataTimelineCell<PriorityQueueTimeline<i64, Steward>>;
+type Slot = DataTimelineCell<simple_timeline::SimpleTimeline<Option<i64>, Steward>>;
+
+#[derive(
+  Copy, Clone, Hash, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, Debug, Default,
+)]
+struct Basics {}
+impl BasicsTrait for Basics {
+  type Time = Time;
+  type Globals = (Queue, Vec<Slot>);
+  type Types = (ListedType<PushTask>, ListedType<Work>);
+}
+
+thread_local! {
+  static WORK_EXECUTIONS: Cell<usize> = Cell::new(0);
+}
+
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize, Debug, PersistentlyIdentifiedType)]
+#[persistent_id(0x4e19c07a85b2d3f6)]
+struct PushTask {
+  priority: i64,
+}
+#[time_steward::event]
+impl Event for PushTask {
+  type Steward = Steward;
+  fn execute<Accessor: EventAccessor<Steward = Self::Steward>>(&self, accessor: &mut Accessor) {
+    push(accessor, &accessor.globals().0, self.priority);
+  }
+  fn undo<Accessor: FutureCleanupAccessor<Steward = Self::Steward>>(
+    &self,
+    accessor: &mut Accessor,
+    _: (),
+  ) {
+    priority_queue_timeline::unset(accessor, &accessor.globals().0);
+  }
+}
+
+/// Pops the most urgent task and records it in a slot.
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize, Debug, PersistentlyIdentifiedType)]
+#[persistent_id(0xb8d2605f1ec94a73)]
+struct Work {
+  slot: usize,
+}
+#[time_steward::event]
+impl Event for Work {
+  type Steward = Steward;
+  fn execute<Accessor: EventAccessor<Steward = Self::Steward>>(&self, accessor: &mut Accessor) {
+    WORK_EXECUTIONS.with(|count| count.set(count.get() + 1));
+    let task = pop(accessor, &accessor.globals().0);
+    simple_timeline::set(accessor, &accessor.globals().1[self.slot], task);
+  }
+  fn undo<Accessor: FutureCleanupAccessor<Steward = Self::Steward>>(
+    &self,
+    accessor: &mut Accessor,
+    _: (),
+  ) {
+    priority_queue_timeline::unset(accessor, &accessor.globals().0);
+    simple_timeline::unset(accessor, &accessor.globals().1[self.slot]);
+  }
+}
+
+fn make_globals() -> <Basics as BasicsTrait>::Globals {
+  (
+    DataTimelineCell::new(PriorityQueueTimeline::new()),
+    (0..2)
+      .map(|_| DataTimelineCell::new(simple_timeline::SimpleTimeline::new()))
+      .collect(),
+  )
+}
+
+fn id(time: Time) -> DeterministicRandomId {
+  DeterministicRandomId::new(&(time, 0x3b7e))
+}
+
+fn observe(stew: &mut Steward) -> (Option<i64>, Vec<Option<i64>>, usize) {
+  let snapshot = stew.snapshot_before(&1000).unwrap();
+  (
+    peek(&snapshot, &snapshot.globals().0),
+    snapshot
+      .globals()
+      .1
+      .iter()
+      .map(|slot| simple_timeline::query(&snapshot, slot))
+      .collect(),
+    WORK_EXECUTIONS.with(|count| count.get()),
+  )
+}
+
+#[test]
+fn priority_queue_retroactive_changes() {
+  let mut stew: Steward = Steward::from_globals(make_globals()).unwrap();
+  stew
+    .insert_fiat_event(10, id(10), PushTask { priority: 5 })
+    .unwrap();
+  stew
+    .insert_fiat_event(20, id(20), PushTask { priority: 3 })
+    .unwrap();
+  stew
+    .insert_fiat_event(100, id(100), Work { slot: 0 })
+    .unwrap();
+  stew
+    .insert_fiat_event(200, id(200), Work { slot: 1 })
+    .unwrap();
+  assert_eq!(observe(&mut stew), (None, vec![Some(3), Some(5)], 2));
+
+  // doesn't become the smallest item before either pop, so neither is redone
+  stew
+    .insert_fiat_event(50, id(50), PushTask { priority: 7 })
+    .unwrap();
+  assert_eq!(observe(&mut stew), (Some(7), vec![Some(3), Some(5)], 2));
+
+  // cuts in line before the second pop only
+  stew
+    .insert_fiat_event(150, id(150), PushTask { priority: 1 })
+    .unwrap();
+  assert_eq!(observe(&mut stew), (Some(5), vec![Some(3), Some(1)], 3));
+
+  // the first pop takes 5 instead, which the second pop never saw
+  stew.remove_fiat_event(&20, id(20)).unwrap();
+  assert_eq!(observe(&mut stew), (Some(7), vec![Some(5), Some(1)], 4));
+}
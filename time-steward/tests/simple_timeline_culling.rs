@@ -0,0 +1,240 @@
+extern crate time_steward;
+
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+
+use std::cell::Cell;
+
+use crate::simple_timeline::{query, set, tracking_query, unset, SimpleTimeline};
+use crate::steward_module::{
+  simple_timeline, Accessor, ConstructibleTimeSteward, DataTimelineCell, Event, EventAccessor,
+  FutureCleanupAccessor, TimeSteward,
+};
+use time_steward::stewards::simple_full as steward_module;
+use time_steward::type_utils::list_of_types::ListedType;
+use time_steward::DeterministicRandomId;
+use time_steward::{Basics as BasicsTrait, DataTimelineCellTrait, PersistentlyIdentifiedType};
+
+type Time = i64;
+type Steward = steward_module::Steward<Basics>;
+type Slot = DataTimelineCell<SimpleTimeline<i64, Steward>>;
+type PredictionSlot =
+  DataTimelineCell<SimpleTimeline<Option<<Steward as TimeSteward>::EventHandle>, Steward>>;
+
+#[derive(
+  Copy, Clone, Hash, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, Debug, Default,
+)]
+struct Basics {}
+impl BasicsTrait for Basics {
+  type Time = Time;
+  type Globals = Globals;
+  type Types = (
+    ListedType<SetSource>,
+    ListedType<ComputeParity>,
+    ListedType<CopyParity>,
+    ListedType<Predict>,
+    ListedType<Fire>,
+  );
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct Globals {
+  slots: [Slot; 3],
+  prediction: PredictionSlot,
+  fired: Slot,
+}
+
+thread_local! {
+  static COPY_EXECUTIONS: Cell<usize> = Cell::new(0);
+}
+
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize, Debug, PersistentlyIdentifiedType)]
+#[persistent_id(0x2a6f93c1d8e04b75)]
+struct SetSource {
+  value: i64,
+}
+#[time_steward::event]
+impl Event for SetSource {
+  type Steward = Steward;
+  fn execute<Accessor: EventAccessor<Steward = Self::Steward>>(&self, accessor: &mut Accessor) {
+    set(accessor, &accessor.globals().slots[0], self.value);
+  }
+  fn undo<Accessor: FutureCleanupAccessor<Steward = Self::Steward>>(
+    &self,
+    accessor: &mut Accessor,
+    _: (),
+  ) {
+    unset(accessor, &accessor.globals().slots[0]);
+  }
+}
+
+/// Re-executes by simply executing again, since `set` overwrites its earlier output.
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize, Debug, PersistentlyIdentifiedType)]
+#[persistent_id(0xe5b0274d9c16f38a)]
+struct ComputeParity {}
+#[time_steward::event]
+impl Event for ComputeParity {
+  type Steward = Steward;
+  fn execute<Accessor: EventAccessor<Steward = Self::Steward>>(&self, accessor: &mut Accessor) {
+    let source = tracking_query(accessor, &accessor.globals().slots[0]);
+    set(accessor, &accessor.globals().slots[1], source.rem_euclid(2));
+  }
+  fn undo<Accessor: FutureCleanupAccessor<Steward = Self::Steward>>(
+    &self,
+    accessor: &mut Accessor,
+    _: (),
+  ) {
+    unset(accessor, &accessor.globals().slots[1]);
+  }
+  fn re_execute<Accessor: FutureCleanupAccessor<Steward = Self::Steward>>(
+    &self,
+    accessor: &mut Accessor,
+    _: (),
+  ) {
+    self.execute(accessor);
+  }
+}
+
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize, Debug, PersistentlyIdentifiedType)]
+#[persistent_id(0x47d1c8b3a9025e6f)]
+struct CopyParity {}
+#[time_steward::event]
+impl Event for CopyParity {
+  type Steward = Steward;
+  fn execute<Accessor: EventAccessor<Steward = Self::Steward>>(&self, accessor: &mut Accessor) {
+    COPY_EXECUTIONS.with(|count| count.set(count.get() + 1));
+    let parity = tracking_query(accessor, &accessor.globals().slots[1]);
+    set(accessor, &accessor.globals().slots[2], parity);
+  }
+  fn undo<Accessor: FutureCleanupAccessor<Steward = Self::Steward>>(
+    &self,
+    accessor: &mut Accessor,
+    _: (),
+  ) {
+    unset(accessor, &accessor.globals().slots[2]);
+  }
+}
+
+/// Predicts a Fire at a fixed time and ID, carrying the current source value. Re-executing it replaces the prediction with a new one at exactly the same time.
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize, Debug, PersistentlyIdentifiedType)]
+#[persistent_id(0x93e7a0b25c4fd168)]
+struct Predict {}
+#[time_steward::event]
+impl Event for Predict {
+  type Steward = Steward;
+  fn execute<Accessor: EventAccessor<Steward = Self::Steward>>(&self, accessor: &mut Accessor) {
+    let value = tracking_query(accessor, &accessor.globals().slots[0]);
+    let prediction =
+      accessor.create_prediction(500, DeterministicRandomId::new(&0x1f5c), Fire { value });
+    set(accessor, &accessor.globals().prediction, Some(prediction));
+  }
+  fn undo<Accessor: FutureCleanupAccessor<Steward = Self::Steward>>(
+    &self,
+    accessor: &mut Accessor,
+    _: (),
+  ) {
+    unset(accessor, &accessor.globals().prediction);
+  }
+  fn re_execute<Accessor: FutureCleanupAccessor<Steward = Self::Steward>>(
+    &self,
+    accessor: &mut Accessor,
+    _: (),
+  ) {
+    self.execute(accessor);
+  }
+}
+
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize, Debug, PersistentlyIdentifiedType)]
+#[persistent_id(0x6b2d58f1e0a9c374)]
+struct Fire {
+  value: i64,
+}
+#[time_steward::event]
+impl Event for Fire {
+  type Steward = Steward;
+  fn execute<Accessor: EventAccessor<Steward = Self::Steward>>(&self, accessor: &mut Accessor) {
+    set(accessor, &accessor.globals().fired, self.value);
+  }
+  fn undo<Accessor: FutureCleanupAccessor<Steward = Self::Steward>>(
+    &self,
+    accessor: &mut Accessor,
+    _: (),
+  ) {
+    unset(accessor, &accessor.globals().fired);
+  }
+}
+
+fn make_globals() -> <Basics as BasicsTrait>::Globals {
+  Globals {
+    slots: [
+      DataTimelineCell::new(SimpleTimeline::new()),
+      DataTimelineCell::new(SimpleTimeline::new()),
+      DataTimelineCell::new(SimpleTimeline::new()),
+    ],
+    prediction: DataTimelineCell::new(SimpleTimeline::new()),
+    fired: DataTimelineCell::new(SimpleTimeline::new()),
+  }
+}
+
+fn id(time: Time) -> DeterministicRandomId {
+  DeterministicRandomId::new(&(time, 0x6e05))
+}
+
+fn observe(stew: &mut Steward) -> (i64, usize) {
+  let snapshot = stew.snapshot_before(&1000).unwrap();
+  (
+    query(&snapshot, &snapshot.globals().slots[2]),
+    COPY_EXECUTIONS.with(|count| count.get()),
+  )
+}
+
+#[test]
+fn setting_identical_data_doesnt_invalidate_readers() {
+  let mut stew: Steward = Steward::from_globals(make_globals()).unwrap();
+  stew
+    .insert_fiat_event(10, id(10), SetSource { value: 2 })
+    .unwrap();
+  stew
+    .insert_fiat_event(100, id(100), ComputeParity {})
+    .unwrap();
+  stew.insert_fiat_event(200, id(200), CopyParity {}).unwrap();
+  assert_eq!(observe(&mut stew), (0, 1));
+
+  // the parity is re-computed, but comes out the same, so the copy isn't redone
+  stew
+    .insert_fiat_event(20, id(20), SetSource { value: 4 })
+    .unwrap();
+  assert_eq!(observe(&mut stew), (0, 1));
+
+  stew
+    .insert_fiat_event(30, id(30), SetSource { value: 7 })
+    .unwrap();
+  assert_eq!(observe(&mut stew), (1, 2));
+
+  stew.remove_fiat_event(&30, id(30)).unwrap();
+  assert_eq!(observe(&mut stew), (0, 3));
+}
+
+#[test]
+fn replacing_a_prediction_at_the_same_time_isnt_culled() {
+  let mut stew: Steward = Steward::from_globals(make_globals()).unwrap();
+  stew
+    .insert_fiat_event(10, id(10), SetSource { value: 2 })
+    .unwrap();
+  stew.insert_fiat_event(100, id(100), Predict {}).unwrap();
+  let fired = |stew: &mut Steward| {
+    let snapshot = stew.snapshot_before(&1000).unwrap();
+    query(&snapshot, &snapshot.globals().fired)
+  };
+  assert_eq!(fired(&mut stew), 2);
+
+  // The re-executed Predict sets a new prediction with the same time as the old one. EventHandles only compare equal to themselves, so this isn't mistaken for setting identical data, and the new prediction replaces the old one.
+  stew
+    .insert_fiat_event(20, id(20), SetSource { value: 5 })
+    .unwrap();
+  assert_eq!(fired(&mut stew), 5);
+
+  stew.remove_fiat_event(&20, id(20)).unwrap();
+  assert_eq!(fired(&mut stew), 2);
+}
@@ -0,0 +1,149 @@
+extern crate time_steward;
+
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+
+use std::cell::Cell;
+
+use crate::simple_timeline::{
+  query, set, tracking_query_projected, unset, Projection, SimpleTimeline,
+};
+use crate::steward_module::{
+  simple_timeline, Accessor, ConstructibleTimeSteward, DataTimelineCell, Event, EventAccessor,
+  FutureCleanupAccessor, TimeSteward,
+};
+use time_steward::stewards::simple_full as steward_module;
+use time_steward::type_utils::list_of_types::ListedType;
+use time_steward::DeterministicRandomId;
+use time_steward::{Basics as BasicsTrait, DataTimelineCellTrait, PersistentlyIdentifiedType};
+
+type Time = i64;
+type Steward = steward_module::Steward<Basics>;
+
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize, Debug)]
+struct Circle {
+  radius: i64,
+  position: i64,
+}
+
+struct Radius;
+impl Projection<Circle> for Radius {
+  type Projected = i64;
+  fn project(circle: &Circle) -> i64 {
+    circle.radius
+  }
+}
+
+#[derive(
+  Copy, Clone, Hash, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, Debug, Default,
+)]
+struct Basics {}
+impl BasicsTrait for Basics {
+  type Time = Time;
+  type Globals = (
+    DataTimelineCell<SimpleTimeline<Circle, Steward>>,
+    DataTimelineCell<SimpleTimeline<i64, Steward>>,
+  );
+  type Types = (ListedType<Change>, ListedType<MeasureArea>);
+}
+
+thread_local! {
+  static MEASURE_EXECUTIONS: Cell<usize> = Cell::new(0);
+}
+
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize, Debug, PersistentlyIdentifiedType)]
+#[persistent_id(0x93c0e5a17b4d28f6)]
+struct Change {
+  circle: Circle,
+}
+#[time_steward::event]
+impl Event for Change {
+  type Steward = Steward;
+  fn execute<Accessor: EventAccessor<Steward = Self::Steward>>(&self, accessor: &mut Accessor) {
+    set(accessor, &accessor.globals().0, self.circle.clone());
+  }
+  fn undo<Accessor: FutureCleanupAccessor<Steward = Self::Steward>>(
+    &self,
+    accessor: &mut Accessor,
+    _: (),
+  ) {
+    unset(accessor, &accessor.globals().0);
+  }
+}
+
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize, Debug, PersistentlyIdentifiedType)]
+#[persistent_id(0x0f5b28d4c7e9a163)]
+struct MeasureArea {}
+#[time_steward::event]
+impl Event for MeasureArea {
+  type Steward = Steward;
+  fn execute<Accessor: EventAccessor<Steward = Self::Steward>>(&self, accessor: &mut Accessor) {
+    MEASURE_EXECUTIONS.with(|count| count.set(count.get() + 1));
+    let radius = tracking_query_projected(accessor, &accessor.globals().0, &Radius);
+    set(accessor, &accessor.globals().1, radius * radius);
+  }
+  fn undo<Accessor: FutureCleanupAccessor<Steward = Self::Steward>>(
+    &self,
+    accessor: &mut Accessor,
+    _: (),
+  ) {
+    unset(accessor, &accessor.globals().1);
+  }
+}
+
+fn make_globals() -> <Basics as BasicsTrait>::Globals {
+  (
+    DataTimelineCell::new(SimpleTimeline::new()),
+    DataTimelineCell::new(SimpleTimeline::new()),
+  )
+}
+
+fn id(time: Time) -> DeterministicRandomId {
+  DeterministicRandomId::new(&(time, 0x2d81))
+}
+
+fn change(stew: &mut Steward, time: Time, radius: i64, position: i64) {
+  stew
+    .insert_fiat_event(
+      time,
+      id(time),
+      Change {
+        circle: Circle { radius, position },
+      },
+    )
+    .unwrap();
+}
+
+fn observe(stew: &mut Steward) -> (i64, usize) {
+  let snapshot = stew.snapshot_before(&1000).unwrap();
+  (
+    query(&snapshot, &snapshot.globals().1),
+    MEASURE_EXECUTIONS.with(|count| count.get()),
+  )
+}
+
+#[test]
+fn projected_readers_ignore_other_fields() {
+  let mut stew: Steward = Steward::from_globals(make_globals()).unwrap();
+  change(&mut stew, 10, 3, 0);
+  stew
+    .insert_fiat_event(100, id(100), MeasureArea {})
+    .unwrap();
+  assert_eq!(observe(&mut stew), (9, 1));
+
+  // only the position changes
+  change(&mut stew, 20, 3, 50);
+  assert_eq!(observe(&mut stew), (9, 1));
+
+  change(&mut stew, 30, 4, 50);
+  assert_eq!(observe(&mut stew), (16, 2));
+
+  // undoing a change that only moved the circle back doesn't matter either
+  change(&mut stew, 40, 4, 70);
+  stew.remove_fiat_event(&40, id(40)).unwrap();
+  assert_eq!(observe(&mut stew), (16, 2));
+
+  stew.remove_fiat_event(&30, id(30)).unwrap();
+  assert_eq!(observe(&mut stew), (9, 3));
+}
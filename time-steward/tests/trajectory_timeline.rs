@@ -0,0 +1,160 @@
+extern crate time_steward;
+
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+
+use std::cell::Cell;
+
+use crate::steward_module::{
+  simple_timeline, trajectory_timeline, Accessor, ConstructibleTimeSteward, DataTimelineCell,
+  Event, EventAccessor, FutureCleanupAccessor, TimeSteward,
+};
+use crate::trajectory_timeline::{
+  add_velocity, nth_coefficient, position, set_trajectory, set_velocity, tracking_position,
+  velocity, TrajectoryTimeline,
+};
+use time_steward::stewards::simple_full as steward_module;
+use time_steward::support::trajectories::QuadraticTrajectory;
+use time_steward::type_utils::list_of_types::ListedType;
+use time_steward::DeterministicRandomId;
+use time_steward::{Basics as BasicsTrait, DataTimelineCellTrait, PersistentlyIdentifiedType};
+
+type Time = i64;
+type Steward = steward_module::Steward<Basics>;
+type Ball = DataTimelineCell<TrajectoryTimeline<QuadraticTrajectory<i32>, Steward>>;
+type Record = DataTimelineCell<simple_timeline::SimpleTimeline<Option<i32>, Steward>>;
+
+#[derive(
+  Copy, Clone, Hash, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, Debug, Default,
+)]
+struct Basics {}
+impl BasicsTrait for Basics {
+  type Time = Time;
+  type Globals = (Ball, Record);
+  type Types = (ListedType<Launch>, ListedType<Push>, ListedType<Measure>);
+}
+
+thread_local! {
+  static MEASURE_EXECUTIONS: Cell<usize> = Cell::new(0);
+}
+
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize, Debug, PersistentlyIdentifiedType)]
+#[persistent_id(0x8f2d4a61c0b7e359)]
+struct Launch {
+  velocity: i32,
+}
+#[time_steward::event]
+impl Event for Launch {
+  type Steward = Steward;
+  fn execute<Accessor: EventAccessor<Steward = Self::Steward>>(&self, accessor: &mut Accessor) {
+    let ball = &accessor.globals().0;
+    set_trajectory(accessor, ball, QuadraticTrajectory::constant(0));
+    set_velocity(accessor, ball, self.velocity).unwrap();
+  }
+  fn undo<Accessor: FutureCleanupAccessor<Steward = Self::Steward>>(
+    &self,
+    accessor: &mut Accessor,
+    _: (),
+  ) {
+    trajectory_timeline::unset(accessor, &accessor.globals().0);
+  }
+}
+
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize, Debug, PersistentlyIdentifiedType)]
+#[persistent_id(0x16c9e07b3a5d42f8)]
+struct Push {
+  velocity: i32,
+}
+#[time_steward::event]
+impl Event for Push {
+  type Steward = Steward;
+  fn execute<Accessor: EventAccessor<Steward = Self::Steward>>(&self, accessor: &mut Accessor) {
+    // a push that would overflow is rejected, leaving the trajectory untouched
+    let _ = add_velocity(accessor, &accessor.globals().0, self.velocity);
+  }
+  fn undo<Accessor: FutureCleanupAccessor<Steward = Self::Steward>>(
+    &self,
+    accessor: &mut Accessor,
+    _: (),
+  ) {
+    trajectory_timeline::unset(accessor, &accessor.globals().0);
+  }
+}
+
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize, Debug, PersistentlyIdentifiedType)]
+#[persistent_id(0xd0a357e91b4c6f82)]
+struct Measure {}
+#[time_steward::event]
+impl Event for Measure {
+  type Steward = Steward;
+  fn execute<Accessor: EventAccessor<Steward = Self::Steward>>(&self, accessor: &mut Accessor) {
+    MEASURE_EXECUTIONS.with(|count| count.set(count.get() + 1));
+    let measured = tracking_position(accessor, &accessor.globals().0);
+    simple_timeline::set(accessor, &accessor.globals().1, measured);
+  }
+  fn undo<Accessor: FutureCleanupAccessor<Steward = Self::Steward>>(
+    &self,
+    accessor: &mut Accessor,
+    _: (),
+  ) {
+    simple_timeline::unset(accessor, &accessor.globals().1);
+  }
+}
+
+fn make_globals() -> <Basics as BasicsTrait>::Globals {
+  (
+    DataTimelineCell::new(TrajectoryTimeline::new(0, QuadraticTrajectory::constant(0))),
+    DataTimelineCell::new(simple_timeline::SimpleTimeline::new()),
+  )
+}
+
+fn id(time: Time) -> DeterministicRandomId {
+  DeterministicRandomId::new(&(time, 0x9c41))
+}
+
+fn observe(stew: &mut Steward, time: Time) -> (Option<i32>, Option<i32>, Option<i32>, usize) {
+  let snapshot = stew.snapshot_before(&time).unwrap();
+  (
+    position(&snapshot, &snapshot.globals().0),
+    velocity(&snapshot, &snapshot.globals().0),
+    simple_timeline::query(&snapshot, &snapshot.globals().1),
+    MEASURE_EXECUTIONS.with(|count| count.get()),
+  )
+}
+
+#[test]
+fn trajectory_timeline_retroactive_changes() {
+  let mut stew: Steward = Steward::from_globals(make_globals()).unwrap();
+  {
+    // the initial trajectory answers queries before anything sets one, and coefficients beyond its degree are None
+    let snapshot = stew.snapshot_before(&5).unwrap();
+    assert_eq!(position(&snapshot, &snapshot.globals().0), Some(0));
+    assert_eq!(nth_coefficient(&snapshot, &snapshot.globals().0, 3), None);
+  }
+  stew
+    .insert_fiat_event(10, id(10), Launch { velocity: 5 })
+    .unwrap();
+  stew.insert_fiat_event(100, id(100), Measure {}).unwrap();
+  assert_eq!(observe(&mut stew, 200), (Some(950), Some(5), Some(450), 1));
+
+  stew
+    .insert_fiat_event(50, id(50), Push { velocity: 1 })
+    .unwrap();
+  assert_eq!(observe(&mut stew, 200), (Some(1100), Some(6), Some(500), 2));
+
+  stew.remove_fiat_event(&50, id(50)).unwrap();
+  assert_eq!(observe(&mut stew, 200), (Some(950), Some(5), Some(450), 3));
+
+  // modify helpers report overflow rather than panicking, and leave the timeline untouched
+  stew
+    .insert_fiat_event(
+      150,
+      id(150),
+      Push {
+        velocity: i32::max_value() - 4,
+      },
+    )
+    .unwrap();
+  assert_eq!(observe(&mut stew, 200), (Some(950), Some(5), Some(450), 3));
+}
@@ -1,6 +1,5 @@
-#![feature(never_type, specialization)]
-
 use serde::{Serialize, Deserialize};
+use std::any::Any;
 
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, Debug)]
 pub struct PersistentTypeId(pub u64);
@@ -18,22 +17,12 @@ impl<T: PersistentlyIdentifiedType> DynamicPersistentlyIdentifiedType for T {
 
 
 
-trait StaticDowncast <T> {
-    fn static_downcast (self)->Option <T>;
-  }
-  impl <T> StaticDowncast <T> for T {
-    fn static_downcast (self)->Option <T> {Some (self)}
-  }
-  impl <T, U> StaticDowncast <T> for U {
-    default fn static_downcast (self)->Option <T> {None}
-  }
-  
-
 /// Convert between two types if they are actually the same type.
 ///
 /// 
-pub fn static_downcast <T, U> (input: T)->Option <U> {
-  StaticDowncast::<U>::static_downcast (input)
+pub fn static_downcast <T: Any, U: Any> (input: T)->Option <U> {
+  let mut input = Some (input);
+  (&mut input as &mut dyn Any).downcast_mut::<Option <U>>().and_then (Option::take)
 }
 
 #[cfg (test)]
@@ -48,3 +37,5 @@ mod tests {
 }
 
 pub mod list_of_types;
+pub mod registry;
+pub mod serde_array;
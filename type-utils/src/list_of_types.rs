@@ -1,17 +1,63 @@
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::any::{Any, TypeId};
+use std::fmt::Debug;
 use std::marker::PhantomData;
 
+use crate::PersistentlyIdentifiedType;
 
-pub struct ListedType<T>(PhantomData<T>, !);
+// Uninhabited, so that list types can't be constructed.
+enum Never {}
+
+pub struct ListedType<T>(PhantomData<T>, Never);
 pub trait ListOfTypesVisitor {
-  fn visit<T>(&mut self);
+  fn visit<T: Listable>(&mut self);
 }
 pub trait ListOfTypes {
   fn visit_all<Visitor: ListOfTypesVisitor>(visitor: &mut Visitor);
 }
 
+/// Shorthand for the traits a type needs in order to be serialized by PersistentTypeId.
+pub trait SerializableData: PersistentlyIdentifiedType + Any + Debug + Serialize + DeserializeOwned {}
+impl<T: PersistentlyIdentifiedType + Any + Debug + Serialize + DeserializeOwned> SerializableData for T {}
 
+/// Receives the capabilities of a listed type; see `Listable`.
+///
+/// Each method's default forwards to the next less specific one, so a registrar only needs to override the ones it cares about.
+pub trait Registrar {
+  /// The type has a PersistentTypeId, but can't necessarily be serialized.
+  fn persistently_identified<T: PersistentlyIdentifiedType + Any>(&mut self) {}
+  /// The type can be serialized by PersistentTypeId.
+  fn data<T: SerializableData>(&mut self) {
+    self.persistently_identified::<T>();
+  }
+  /// Like `data()`, but the type can also be filled in with its default value when it's missing from a partial snapshot.
+  fn data_with_default<T: SerializableData + Default>(&mut self) {
+    self.data::<T>();
+  }
+  /// Anything else a registrar might want to know about a type, such as the functions a TimeSteward needs for an event type. Registrars downcast the extensions they recognize and ignore the rest.
+  fn extension(&mut self, _extension: &dyn Any) {}
+}
 
-impl<T> ListOfTypes for ListedType<T> {
+/// A type that can be an element of a `ListOfTypes`.
+///
+/// Stable Rust can't ask a generic type which traits it implements, so each listed type reports its own capabilities: `register()` should call the `Registrar` method that best describes the type, plus any extensions. There's no default, so that a type can't be left out of the registry just by forgetting to write it; types that never need to be looked up by PersistentTypeId can register nothing.
+///
+/// Data types can use `#[derive(Listable)]`, and `#[event]` implements this for events.
+pub trait Listable: 'static {
+  fn register<R: Registrar>(registrar: &mut R);
+}
+
+macro_rules! impl_listable_without_registration {
+  ($($T: ty),*) => {$(impl Listable for $T {
+    fn register<R: Registrar>(_registrar: &mut R) {}
+  })*};
+}
+impl_listable_without_registration!(
+  (), bool, char, u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize, f32, f64, String
+);
+
+impl<T: Listable> ListOfTypes for ListedType<T> {
   fn visit_all<Visitor: ListOfTypesVisitor>(visitor: &mut Visitor) {
     visitor.visit::<T>();
   }
@@ -139,3 +185,141 @@ tuple_impls! {
     }
 }
 
+
+/// The empty type list; the end of a `Cons` list.
+pub enum Nil {}
+/// A type list with `Head` as its first element, followed by the elements of `Tail`.
+///
+/// Unlike tuples, cons lists can be any length. They're easiest to write using the `list_of_types!` macro.
+pub struct Cons<Head: Listable, Tail: ListOfTypes>(PhantomData<(Head, Tail)>, Never);
+
+impl ListOfTypes for Nil {
+  fn visit_all<Visitor: ListOfTypesVisitor>(_visitor: &mut Visitor) {}
+}
+impl<Head: Listable, Tail: ListOfTypes> ListOfTypes for Cons<Head, Tail> {
+  fn visit_all<Visitor: ListOfTypesVisitor>(visitor: &mut Visitor) {
+    visitor.visit::<Head>();
+    Tail::visit_all(visitor);
+  }
+}
+
+/// Builds a `Cons` list of the given types, in order.
+///
+/// `list_of_types![A, B, C]` is `Cons<A, Cons<B, Cons<C, Nil>>>`.
+#[macro_export]
+macro_rules! list_of_types {
+  () => {$crate::list_of_types::Nil};
+  ($Head: ty $(, $Tail: ty)*) => {$crate::list_of_types::Cons<$Head, $crate::list_of_types!($($Tail),*)>};
+}
+
+/// Index witness: `T` is the first element of the list.
+pub enum Here {}
+/// Index witness: `T` is somewhere in the tail of the list, at the position indicated by `Index`.
+pub struct There<Index>(PhantomData<Index>, Never);
+
+pub trait TypeIndex {
+  const VALUE: usize;
+}
+impl TypeIndex for Here {
+  const VALUE: usize = 0;
+}
+impl<Index: TypeIndex> TypeIndex for There<Index> {
+  const VALUE: usize = Index::VALUE + 1;
+}
+
+/// Implemented when `T` is an element of a `Cons` list. `Index` is inferred, so code can require `List: Contains<T, I>` for a generic `I` to check at compile time that `T` is listed.
+///
+/// If `T` appears in the list more than once, `Index` can't be inferred, and using this is a compile error.
+pub trait Contains<T, Index: TypeIndex> {
+  const INDEX: usize = Index::VALUE;
+}
+impl<T: Listable, Tail: ListOfTypes> Contains<T, Here> for Cons<T, Tail> {}
+impl<T, Head: Listable, Tail: ListOfTypes + Contains<T, Index>, Index: TypeIndex> Contains<T, There<Index>>
+  for Cons<Head, Tail>
+{
+}
+
+/// The position of `T` in a `Cons` list, checked at compile time.
+pub fn index_of<List: Contains<T, Index>, T, Index: TypeIndex>() -> usize {
+  <List as Contains<T, Index>>::INDEX
+}
+
+struct FindVisitor<T> {
+  position: usize,
+  found: Option<usize>,
+  _marker: PhantomData<T>,
+}
+impl<T: Any> ListOfTypesVisitor for FindVisitor<T> {
+  fn visit<U: Listable>(&mut self) {
+    if self.found.is_none() && TypeId::of::<T>() == TypeId::of::<U>() {
+      self.found = Some(self.position);
+    }
+    self.position += 1;
+  }
+}
+
+/// The position of the first occurrence of `T` in any list of types, counting elements in the order that `visit_all` visits them.
+///
+/// Unlike `index_of()`, this works for tuples and nested lists, but it's only checked at runtime.
+pub fn find<List: ListOfTypes, T: Any>() -> Option<usize> {
+  let mut visitor = FindVisitor::<T> {
+    position: 0,
+    found: None,
+    _marker: PhantomData,
+  };
+  List::visit_all(&mut visitor);
+  visitor.found
+}
+
+/// Whether `T` is an element of a list of types, at any depth.
+pub fn contains<List: ListOfTypes, T: Any>() -> bool {
+  find::<List, T>().is_some()
+}
+
+struct CountVisitor(usize);
+impl ListOfTypesVisitor for CountVisitor {
+  fn visit<U: Listable>(&mut self) {
+    self.0 += 1;
+  }
+}
+
+/// The number of elements in a list of types, including elements of nested lists.
+pub fn len<List: ListOfTypes>() -> usize {
+  let mut visitor = CountVisitor(0);
+  List::visit_all(&mut visitor);
+  visitor.0
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  type Long = list_of_types![
+    u8, u16, u32, u64, i8, i16, i32, i64, f32, f64, bool, char, (), String
+  ];
+
+  #[test]
+  fn cons_lists_visit_in_order() {
+    assert_eq!(len::<Long>(), 14);
+    assert_eq!(len::<list_of_types![]>(), 0);
+    assert_eq!(find::<Long, u8>(), Some(0));
+    assert_eq!(find::<Long, String>(), Some(13));
+    assert_eq!(find::<Long, usize>(), None);
+  }
+
+  #[test]
+  fn compile_time_index() {
+    assert_eq!(index_of::<Long, u8, _>(), 0);
+    assert_eq!(index_of::<Long, char, _>(), 11);
+    assert_eq!(index_of::<Long, String, _>(), 13);
+  }
+
+  #[test]
+  fn mixed_lists() {
+    type Mixed = (ListedType<u8>, list_of_types![u16, u32], ListedType<u64>);
+    assert_eq!(len::<Mixed>(), 4);
+    assert_eq!(find::<Mixed, u32>(), Some(2));
+    assert!(contains::<Mixed, u64>());
+    assert!(!contains::<Mixed, i8>());
+  }
+}
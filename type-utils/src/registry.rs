@@ -0,0 +1,321 @@
+//! Runtime information about the types in a list of types, looked up by PersistentTypeId.
+//!
+//! Serialized data only refers to types by ID, which isn't much help when something goes wrong. A TypeRegistry remembers, for each ID, the name of the Rust type it belongs to, and – when the type supports it – how to Debug-format a value of that type and how to decode one from bincode.
+
+use serde::de::DeserializeOwned;
+use std::any::Any;
+use std::collections::HashMap;
+use std::fmt::{self, Debug};
+
+use crate::list_of_types::{Listable, ListOfTypes, ListOfTypesVisitor, Registrar, SerializableData};
+use crate::{PersistentTypeId, PersistentlyIdentifiedType};
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum TypeRegistrationError {
+  /// Two different listed types have the same PersistentTypeId.
+  DuplicateId {
+    id: PersistentTypeId,
+    first: &'static str,
+    second: &'static str,
+  },
+  /// A type that needs to be serialized by PersistentTypeId isn't listed in `Basics::Types`.
+  Unlisted {
+    id: PersistentTypeId,
+    type_name: &'static str,
+  },
+}
+impl fmt::Display for TypeRegistrationError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      TypeRegistrationError::DuplicateId { id, first, second } => write!(
+        f,
+        "the types {} and {} both have PersistentTypeId({:#018x})",
+        first, second, id.0
+      ),
+      TypeRegistrationError::Unlisted { id, type_name } => write!(
+        f,
+        "the type {} (PersistentTypeId({:#018x})) is used in the simulation, but isn't listed in Basics::Types",
+        type_name, id.0
+      ),
+    }
+  }
+}
+impl ::std::error::Error for TypeRegistrationError {}
+
+/// The type name without its module path, e.g. `Shake` for `my_game::events::Shake`.
+///
+/// Generic arguments are kept, but their module paths are stripped as well.
+pub fn short_type_name(type_name: &str) -> String {
+  let mut result = String::with_capacity(type_name.len());
+  let mut segment_start = 0;
+  for (index, character) in type_name.char_indices() {
+    match character {
+      ':' => segment_start = index + 1,
+      '<' | '>' | ',' | ' ' | '(' | ')' | '[' | ']' | ';' | '&' | '*' => {
+        result.push_str(&type_name[segment_start..index]);
+        result.push(character);
+        segment_start = index + 1;
+      }
+      _ => {}
+    }
+  }
+  result.push_str(&type_name[segment_start..]);
+  result
+}
+
+type DebugFunction = fn(&dyn Any, &mut fmt::Formatter) -> fmt::Result;
+type DecodeFunction = fn(&[u8]) -> bincode::Result<Box<dyn Any>>;
+
+/// Everything the registry knows about one type.
+#[derive(Copy, Clone)]
+pub struct RegisteredType {
+  pub id: PersistentTypeId,
+  pub type_name: &'static str,
+  debug: Option<DebugFunction>,
+  decode: Option<DecodeFunction>,
+}
+impl Debug for RegisteredType {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{}(PersistentTypeId({:#018x}))", self.type_name, self.id.0)
+  }
+}
+
+fn debug_function<T: Any + Debug>(value: &dyn Any, f: &mut fmt::Formatter) -> fmt::Result {
+  match value.downcast_ref::<T>() {
+    Some(value) => value.fmt(f),
+    None => write!(f, "<not a {}>", ::std::any::type_name::<T>()),
+  }
+}
+fn decode_function<T: Any + DeserializeOwned>(bytes: &[u8]) -> bincode::Result<Box<dyn Any>> {
+  Ok(Box::new(bincode::deserialize::<T>(bytes)?))
+}
+
+impl RegisteredType {
+  pub fn short_name(&self) -> String {
+    short_type_name(self.type_name)
+  }
+  pub fn can_debug(&self) -> bool {
+    self.debug.is_some()
+  }
+  pub fn can_decode(&self) -> bool {
+    self.decode.is_some()
+  }
+  /// Formats `value`, which should be of this type, with its Debug implementation. Returns None unless the type was registered as data.
+  pub fn debug<'a>(&self, value: &'a dyn Any) -> Option<impl Debug + 'a> {
+    struct Formatted<'a>(&'a dyn Any, fn(&dyn Any, &mut fmt::Formatter) -> fmt::Result);
+    impl<'a> Debug for Formatted<'a> {
+      fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        (self.1)(self.0, f)
+      }
+    }
+    self.debug.map(|function| Formatted(value, function))
+  }
+  /// Decodes a bincode-serialized value of this type. Returns None unless the type was registered as data.
+  pub fn decode(&self, bytes: &[u8]) -> Option<bincode::Result<Box<dyn Any>>> {
+    self.decode.map(|function| function(bytes))
+  }
+}
+
+/// The PersistentTypeIds of all the types in a `ListOfTypes`, along with what we know about the types they belong to.
+///
+/// Types are only included if their `Listable` implementation registers them; listed types without a PersistentTypeId are ignored, since they can never be serialized by ID.
+#[derive(Clone, Debug)]
+pub struct TypeRegistry {
+  types: HashMap<PersistentTypeId, RegisteredType>,
+  duplicates: Vec<TypeRegistrationError>,
+}
+
+impl Registrar for TypeRegistry {
+  fn persistently_identified<T: PersistentlyIdentifiedType + Any>(&mut self) {
+    self.insert(RegisteredType {
+      id: T::ID,
+      type_name: ::std::any::type_name::<T>(),
+      debug: None,
+      decode: None,
+    });
+  }
+  fn data<T: SerializableData>(&mut self) {
+    self.insert(RegisteredType {
+      id: T::ID,
+      type_name: ::std::any::type_name::<T>(),
+      debug: Some(debug_function::<T>),
+      decode: Some(decode_function::<T>),
+    });
+  }
+}
+impl ListOfTypesVisitor for TypeRegistry {
+  fn visit<T: Listable>(&mut self) {
+    T::register(self);
+  }
+}
+
+impl TypeRegistry {
+  pub fn new<Types: ListOfTypes>() -> Self {
+    let mut result = TypeRegistry {
+      types: HashMap::new(),
+      duplicates: Vec::new(),
+    };
+    Types::visit_all(&mut result);
+    result
+  }
+  /// Like `new()`, but fails if any two listed types share an ID.
+  pub fn validated<Types: ListOfTypes>() -> Result<Self, TypeRegistrationError> {
+    let result = Self::new::<Types>();
+    match result.duplicates.first() {
+      Some(error) => Err(error.clone()),
+      None => Ok(result),
+    }
+  }
+  fn insert(&mut self, registered: RegisteredType) {
+    match self.types.get(&registered.id) {
+      // listing the same type twice is harmless
+      Some(existing) if existing.type_name == registered.type_name => {}
+      Some(existing) => self.duplicates.push(TypeRegistrationError::DuplicateId {
+        id: registered.id,
+        first: existing.type_name,
+        second: registered.type_name,
+      }),
+      None => {
+        self.types.insert(registered.id, registered);
+      }
+    }
+  }
+  pub fn get(&self, id: PersistentTypeId) -> Option<&RegisteredType> {
+    self.types.get(&id)
+  }
+  pub fn iter(&self) -> impl Iterator<Item = &RegisteredType> {
+    self.types.values()
+  }
+  pub fn type_name(&self, id: PersistentTypeId) -> Option<&'static str> {
+    self.get(id).map(|registered| registered.type_name)
+  }
+  /// A short human-readable name for an ID, for error messages and the like. Unknown IDs are shown as numbers.
+  pub fn describe(&self, id: PersistentTypeId) -> String {
+    match self.get(id) {
+      Some(registered) => registered.short_name(),
+      None => format!("<unknown type PersistentTypeId({:#018x})>", id.0),
+    }
+  }
+  pub fn duplicates(&self) -> &[TypeRegistrationError] {
+    &self.duplicates
+  }
+  /// Decodes a bincode-serialized value with the given type ID.
+  pub fn decode(&self, id: PersistentTypeId, bytes: &[u8]) -> bincode::Result<Box<dyn Any>> {
+    use serde::de::Error;
+    let registered = self
+      .get(id)
+      .ok_or_else(|| bincode::Error::custom(format!("can't decode {}, because it isn't listed", self.describe(id))))?;
+    registered.decode(bytes).unwrap_or_else(|| {
+      Err(bincode::Error::custom(format!(
+        "can't decode {}, because it wasn't registered as data",
+        registered.type_name
+      )))
+    })
+  }
+  /// Checks that a type with the given ID and name is listed.
+  pub fn check_listed_dynamic(&self, id: PersistentTypeId, type_name: &'static str) -> Result<(), TypeRegistrationError> {
+    match self.get(id) {
+      Some(listed) if listed.type_name == type_name => Ok(()),
+      Some(listed) => Err(TypeRegistrationError::DuplicateId {
+        id,
+        first: listed.type_name,
+        second: type_name,
+      }),
+      None => Err(TypeRegistrationError::Unlisted { id, type_name }),
+    }
+  }
+  pub fn check_listed<T: PersistentlyIdentifiedType + ?Sized>(&self) -> Result<(), TypeRegistrationError> {
+    self.check_listed_dynamic(T::ID, ::std::any::type_name::<T>())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::list_of_types::ListedType;
+  use serde::{Deserialize, Serialize};
+
+  #[derive(Serialize, Deserialize, PartialEq, Debug)]
+  struct First(u32);
+  impl PersistentlyIdentifiedType for First {
+    const ID: PersistentTypeId = PersistentTypeId(0x1dd6f1c4a0b2f5e3);
+  }
+  struct Second;
+  impl PersistentlyIdentifiedType for Second {
+    const ID: PersistentTypeId = PersistentTypeId(0x1dd6f1c4a0b2f5e3);
+  }
+  struct Third;
+  impl PersistentlyIdentifiedType for Third {
+    const ID: PersistentTypeId = PersistentTypeId(0x6a0c3e22d9a4b871);
+  }
+  struct Unidentified;
+
+  impl Listable for First {
+    fn register<R: Registrar>(registrar: &mut R) {
+      registrar.data::<Self>();
+    }
+  }
+  impl Listable for Second {
+    fn register<R: Registrar>(registrar: &mut R) {
+      registrar.persistently_identified::<Self>();
+    }
+  }
+  impl Listable for Third {
+    fn register<R: Registrar>(registrar: &mut R) {
+      registrar.persistently_identified::<Self>();
+    }
+  }
+  impl Listable for Unidentified {
+    fn register<R: Registrar>(_registrar: &mut R) {}
+  }
+
+  #[test]
+  fn type_registry_detects_duplicates() {
+    assert!(TypeRegistry::validated::<(ListedType<First>, ListedType<First>, ListedType<Unidentified>)>().is_ok());
+    match TypeRegistry::validated::<(ListedType<First>, ListedType<Second>)>() {
+      Err(TypeRegistrationError::DuplicateId { first, second, .. }) => {
+        assert!(first.ends_with("First"));
+        assert!(second.ends_with("Second"));
+      }
+      other => panic!("expected a duplicate ID error, got {:?}", other),
+    }
+  }
+
+  #[test]
+  fn type_registry_detects_unlisted() {
+    let registry = TypeRegistry::new::<(ListedType<First>,)>();
+    assert!(registry.check_listed::<First>().is_ok());
+    assert!(matches!(
+      registry.check_listed::<Second>(),
+      Err(TypeRegistrationError::DuplicateId { .. })
+    ));
+    let empty = TypeRegistry::new::<(ListedType<Unidentified>,)>();
+    assert!(match empty.check_listed::<First>() {
+      Err(TypeRegistrationError::Unlisted { type_name, .. }) => type_name.ends_with("First"),
+      _ => false,
+    });
+  }
+
+  #[test]
+  fn type_registry_formats_and_decodes() {
+    let registry = TypeRegistry::new::<(ListedType<First>, ListedType<Third>)>();
+    assert_eq!(registry.describe(First::ID), "First");
+    assert_eq!(registry.describe(PersistentTypeId(5)), "<unknown type PersistentTypeId(0x0000000000000005)>");
+
+    let bytes = bincode::serialize(&First(7), bincode::Infinite).unwrap();
+    let decoded = registry.decode(First::ID, &bytes).unwrap();
+    assert_eq!(decoded.downcast_ref::<First>(), Some(&First(7)));
+    let formatted = format!("{:?}", registry.get(First::ID).unwrap().debug(&*decoded).unwrap());
+    assert_eq!(formatted, "First(7)");
+
+    let third = registry.get(Third::ID).unwrap();
+    assert!(!third.can_debug() && !third.can_decode());
+    assert!(registry.decode(Third::ID, &bytes).is_err());
+  }
+
+  #[test]
+  fn short_type_names() {
+    assert_eq!(short_type_name("a::b::Shake"), "Shake");
+    assert_eq!(short_type_name("a::Grid<b::Space, u32>"), "Grid<Space, u32>");
+  }
+}
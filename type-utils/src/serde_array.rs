@@ -0,0 +1,43 @@
+//! Serialization for arrays of any length, for use with `#[serde(with = "serde_array")]`.
+//!
+//! Serde only implements its traits for arrays up to a fixed length, so arrays whose length is a const generic parameter need this instead. Arrays are serialized as tuples, the same way serde serializes the fixed-length ones.
+
+use serde::de::{Deserialize, Deserializer, Error, SeqAccess, Visitor};
+use serde::ser::{Serialize, SerializeTuple, Serializer};
+use std::convert::TryInto;
+use std::fmt;
+use std::marker::PhantomData;
+
+pub fn serialize<S: Serializer, T: Serialize, const N: usize>(
+  array: &[T; N],
+  serializer: S,
+) -> Result<S::Ok, S::Error> {
+  let mut tuple = serializer.serialize_tuple(N)?;
+  for element in array.iter() {
+    tuple.serialize_element(element)?;
+  }
+  tuple.end()
+}
+
+pub fn deserialize<'de, D: Deserializer<'de>, T: Deserialize<'de>, const N: usize>(
+  deserializer: D,
+) -> Result<[T; N], D::Error> {
+  struct ArrayVisitor<T, const N: usize>(PhantomData<T>);
+  impl<'de, T: Deserialize<'de>, const N: usize> Visitor<'de> for ArrayVisitor<T, N> {
+    type Value = [T; N];
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+      write!(formatter, "an array of length {}", N)
+    }
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<[T; N], A::Error> {
+      let mut elements = Vec::with_capacity(N);
+      while elements.len() < N {
+        match seq.next_element()? {
+          Some(element) => elements.push(element),
+          None => return Err(A::Error::invalid_length(elements.len(), &self)),
+        }
+      }
+      Ok(elements.try_into().ok().unwrap())
+    }
+  }
+  deserializer.deserialize_tuple(N, ArrayVisitor(PhantomData))
+}